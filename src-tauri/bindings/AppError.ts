@@ -0,0 +1,29 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CloudflareMessage } from "./CloudflareMessage";
+import type { ErrorCategory } from "./ErrorCategory";
+import type { FieldError } from "./FieldError";
+
+/**
+ * An error from talking to the Cloudflare API or from reading the cache.
+ */
+export type AppError = { 
+/**
+ * What kind of failure this is.
+ */
+category: ErrorCategory, 
+/**
+ * The HTTP status code of the response, if one was received.
+ */
+status: number | null, 
+/**
+ * The errors Cloudflare returned in the response body, if any.
+ */
+errors: Array<CloudflareMessage>, 
+/**
+ * A human-readable description of the failure.
+ */
+message: string, 
+/**
+ * The problems with each field of the input, for [`ErrorCategory::Validation`] errors.
+ */
+field_errors: Array<FieldError>, };
\ No newline at end of file
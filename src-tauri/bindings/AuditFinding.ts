@@ -0,0 +1,40 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Severity } from "./Severity";
+import type { TargetStatus } from "./TargetStatus";
+
+/**
+ * A record whose target needs looking at.
+ */
+export type AuditFinding = { 
+/**
+ * Identifier for the record.
+ */
+record_id: string, 
+/**
+ * The record name.
+ */
+name: string, 
+/**
+ * The record type, CNAME, NS or MX.
+ */
+record_type: string, 
+/**
+ * The name the record points at.
+ */
+target: string, 
+/**
+ * What looking up the target found.
+ */
+status: TargetStatus, 
+/**
+ * The provider hosting the target, if it is one where deleted resources can be claimed by others.
+ */
+provider: string | null, 
+/**
+ * How urgently this needs attention.
+ */
+severity: Severity, 
+/**
+ * What is wrong, and what could happen.
+ */
+problem: string, };
\ No newline at end of file
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of a CAA record.
+ */
+export type CAAData = { 
+/**
+ * Flags for the record. 128 marks the tag as critical.
+ */
+flags: number, 
+/**
+ * The property, e.g. `issue`, `issuewild` or `iodef`.
+ */
+tag: string, 
+/**
+ * The value of the property, e.g. `letsencrypt.org`.
+ */
+value: string, };
\ No newline at end of file
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of a CERT record.
+ */
+export type CERTData = { 
+/**
+ * The algorithm of the key.
+ */
+algorithm: number, 
+/**
+ * The certificate, base64 encoded.
+ */
+certificate: string, 
+/**
+ * The key tag.
+ */
+key_tag: number, 
+/**
+ * The certificate type.
+ */
+type: number, };
\ No newline at end of file
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AppError } from "./AppError";
+
+/**
+ * How fresh the cache is, so the UI can show when the data was last fetched.
+ */
+export type CacheStatus = { 
+/**
+ * When the zones were fetched from Cloudflare, or `None` if they haven't been yet.
+ */
+fetched_at: string | null, 
+/**
+ * Map of zone IDs to when their DNS records were fetched from Cloudflare
+ */
+zone_fetched_at: { [key: string]: string }, 
+/**
+ * Map of zone IDs to why their DNS records failed to load in the last refresh
+ */
+zone_errors: { [key: string]: AppError }, };
\ No newline at end of file
@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DNSRecord } from "./DNSRecord";
+import type { DNSRecordRequest } from "./DNSRecordRequest";
+
+export type Change = { "action": "create", 
+/**
+ * The record to create.
+ */
+record: DNSRecordRequest, } | { "action": "update", 
+/**
+ * The record as it is now.
+ */
+before: DNSRecord, 
+/**
+ * What it is overwritten with.
+ */
+after: DNSRecordRequest, } | { "action": "delete", 
+/**
+ * The record as it is now.
+ */
+before: DNSRecord, };
\ No newline at end of file
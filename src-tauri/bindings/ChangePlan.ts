@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Change } from "./Change";
+
+/**
+ * The changes needed to bring a zone to the wanted records: deletions first (so a CNAME can replace other
+ * records at its name), then updates, then creations.
+ */
+export type ChangePlan = { 
+/**
+ * The changes, in the order they are applied.
+ */
+changes: Array<Change>, 
+/**
+ * How many records already match.
+ */
+unchanged: number, 
+/**
+ * How many differing records are left alone, because of [`ConflictResolution::Keep`] or because
+ * `delete_missing` isn't set.
+ */
+kept: number, };
\ No newline at end of file
@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AppError } from "./AppError";
+import type { Change } from "./Change";
+import type { DNSRecord } from "./DNSRecord";
+
+/**
+ * How one change of a plan went.
+ */
+export type ChangeResult = { 
+/**
+ * The change.
+ */
+change: Change, 
+/**
+ * The record as Cloudflare returned it, for creations and updates that succeeded.
+ */
+record: DNSRecord | null, 
+/**
+ * Why the change failed, if it did.
+ */
+error: AppError | null, };
\ No newline at end of file
@@ -80,6 +80,12 @@ owner: CloudflareOwner,
  * Example: `["ns1.example.com","ns2.example.com"]`
  */
 vanity_name_servers: Array<string> | null, 
+/**
+ * The Cloudflare name servers assigned to the zone.
+ *
+ * Example: `["bob.ns.cloudflare.com","lola.ns.cloudflare.com"]`
+ */
+name_servers: Array<string>, 
 /**
  * What tenant the zone is in
  */
@@ -25,4 +25,4 @@ messages: Array<CloudflareMessage>,
 /**
  * Result information returned by the API. (Optional)
  */
-result_info: CloudflareResultInfo, };
\ No newline at end of file
+result_info: CloudflareResultInfo | null, };
\ No newline at end of file
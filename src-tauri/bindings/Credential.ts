@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Credential = { "type": "bearer_token", 
+/**
+ * The API token.
+ */
+token: string, } | { "type": "global_api_key", 
+/**
+ * The email address of the account the key belongs to.
+ */
+email: string, 
+/**
+ * The global API key.
+ */
+key: string, };
\ No newline at end of file
@@ -0,0 +1,106 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CAAData } from "./CAAData";
+import type { CERTData } from "./CERTData";
+import type { DNSKEYData } from "./DNSKEYData";
+import type { DSData } from "./DSData";
+import type { LOCData } from "./LOCData";
+import type { NAPTRData } from "./NAPTRData";
+import type { SRVData } from "./SRVData";
+import type { SSHFPData } from "./SSHFPData";
+import type { SVCBData } from "./SVCBData";
+import type { TLSAData } from "./TLSAData";
+import type { URIData } from "./URIData";
+
+export type DNSContent = { "type": "A", 
+/**
+ * The address.
+ */
+content: string, } | { "type": "AAAA", 
+/**
+ * The address.
+ */
+content: string, } | { "type": "CAA", 
+/**
+ * The CAA record fields.
+ */
+data: CAAData, } | { "type": "CERT", 
+/**
+ * The CERT record fields.
+ */
+data: CERTData, } | { "type": "CNAME", 
+/**
+ * The name this is an alias for.
+ */
+content: string, } | { "type": "DNSKEY", 
+/**
+ * The DNSKEY record fields.
+ */
+data: DNSKEYData, } | { "type": "DS", 
+/**
+ * The DS record fields.
+ */
+data: DSData, } | { "type": "HTTPS", 
+/**
+ * The HTTPS record fields.
+ */
+data: SVCBData, } | { "type": "LOC", 
+/**
+ * The LOC record fields.
+ */
+data: LOCData, } | { "type": "MX", 
+/**
+ * The mail server's name.
+ */
+content: string, 
+/**
+ * Lower values are preferred.
+ */
+priority: number, } | { "type": "NAPTR", 
+/**
+ * The NAPTR record fields.
+ */
+data: NAPTRData, } | { "type": "NS", 
+/**
+ * The name server's name.
+ */
+content: string, } | { "type": "OPENPGPKEY", 
+/**
+ * The key, base64 encoded.
+ */
+content: string, } | { "type": "PTR", 
+/**
+ * The name pointed to.
+ */
+content: string, } | { "type": "SMIMEA", 
+/**
+ * The SMIMEA record fields.
+ */
+data: TLSAData, } | { "type": "SRV", 
+/**
+ * The SRV record fields.
+ */
+data: SRVData, } | { "type": "SSHFP", 
+/**
+ * The SSHFP record fields.
+ */
+data: SSHFPData, } | { "type": "SVCB", 
+/**
+ * The SVCB record fields.
+ */
+data: SVCBData, } | { "type": "TLSA", 
+/**
+ * The TLSA record fields.
+ */
+data: TLSAData, } | { "type": "TXT", 
+/**
+ * The text.
+ */
+content: string, } | { "type": "URI", 
+/**
+ * The URI record fields.
+ */
+data: URIData, 
+/**
+ * Lower values are preferred.
+ */
+priority: number, };
\ No newline at end of file
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of a DNSKEY record.
+ */
+export type DNSKEYData = { 
+/**
+ * The algorithm of the key.
+ */
+algorithm: number, 
+/**
+ * Flags for the key. 257 marks a key-signing key, 256 a zone-signing key.
+ */
+flags: number, 
+/**
+ * The protocol. Always 3.
+ */
+protocol: number, 
+/**
+ * The public key, base64 encoded.
+ */
+public_key: string, };
\ No newline at end of file
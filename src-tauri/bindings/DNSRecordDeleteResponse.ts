@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Cloudflare endpoint response for Delete DNS Record
+ */
+export type DNSRecordDeleteResponse = { 
+/**
+ * Identifier of the deleted record
+ */
+id: string, };
\ No newline at end of file
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Cloudflare endpoint response for Import DNS Records
+ */
+export type DNSRecordImportResponse = { 
+/**
+ * Number of DNS records added
+ */
+recs_added: number, 
+/**
+ * Total number of DNS records parsed
+ */
+total_records_parsed: number, };
\ No newline at end of file
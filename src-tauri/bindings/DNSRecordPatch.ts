@@ -0,0 +1,34 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DNSContent } from "./DNSContent";
+
+/**
+ * A partial update (PATCH) of a DNS record. Only the fields that are set are changed.
+ *
+ * The frontend sends the new content as a nested `content` object, as the type and content have to change
+ * together. It is flattened into the request body sent to Cloudflare.
+ */
+export type DNSRecordPatch = { 
+/**
+ * The new record type and content.
+ */
+content?: DNSContent, 
+/**
+ * The DNS record name (or @ for the zone apex)
+ */
+name?: string, 
+/**
+ * Whether the record is proxied by Cloudflare
+ */
+proxied?: boolean, 
+/**
+ * Comments or notes about the DNS record. This field has no effect on DNS responses.
+ */
+comment?: string, 
+/**
+ * Custom tags for the record
+ */
+tags?: Array<string>, 
+/**
+ * Time to live for the record. To signify `auto ttl`, set this to `1`. Valid values: 60 - 86400,0,1
+ */
+ttl?: number, };
\ No newline at end of file
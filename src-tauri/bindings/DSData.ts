@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of a DS record.
+ */
+export type DSData = { 
+/**
+ * The algorithm of the key.
+ */
+algorithm: number, 
+/**
+ * The digest of the key, hex encoded.
+ */
+digest: string, 
+/**
+ * The digest algorithm.
+ */
+digest_type: number, 
+/**
+ * The key tag.
+ */
+key_tag: number, };
\ No newline at end of file
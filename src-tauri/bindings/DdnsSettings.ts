@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DynamicRecord } from "./DynamicRecord";
+import type { IpSource } from "./IpSource";
+
+/**
+ * The DDNS settings: where addresses come from, how often they're checked, and which records follow them.
+ */
+export type DdnsSettings = { 
+/**
+ * Where the public address is detected.
+ */
+source: IpSource, 
+/**
+ * How many minutes apart each record is checked.
+ */
+interval_minutes: number, 
+/**
+ * The dynamic records.
+ */
+records: Array<DynamicRecord>, };
\ No newline at end of file
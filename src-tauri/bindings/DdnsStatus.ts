@@ -0,0 +1,35 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AppError } from "./AppError";
+
+/**
+ * How the updates of a dynamic record have gone.
+ */
+export type DdnsStatus = { 
+/**
+ * When the record was last checked.
+ */
+last_run: string | null, 
+/**
+ * The address the record was last found or set to point at.
+ */
+address: string | null, 
+/**
+ * When the record was last changed to a new address.
+ */
+last_changed: string | null, 
+/**
+ * Why the last check failed, if it did.
+ */
+error: AppError | null, 
+/**
+ * How many checks in a row have failed.
+ */
+failures: number, 
+/**
+ * When the record is next due to be checked.
+ */
+next_run: string | null, 
+/**
+ * Whether the record is waiting for its profile to be switched to. It is checked as soon as it is.
+ */
+paused: boolean, };
\ No newline at end of file
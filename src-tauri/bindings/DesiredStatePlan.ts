@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChangePlan } from "./ChangePlan";
+
+/**
+ * The plan for a desired state file, with the zone it applies to.
+ */
+export type DesiredStatePlan = { 
+/**
+ * The ID of the zone.
+ */
+zone_id: string, 
+/**
+ * The name of the zone.
+ */
+zone_name: string, 
+/**
+ * The changes that make the zone match the file.
+ */
+plan: ChangePlan, };
\ No newline at end of file
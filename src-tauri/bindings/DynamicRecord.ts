@@ -0,0 +1,32 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DdnsStatus } from "./DdnsStatus";
+import type { IpFamily } from "./IpFamily";
+
+/**
+ * A record kept pointed at the current public address.
+ */
+export type DynamicRecord = { 
+/**
+ * The profile whose credentials update the record.
+ */
+profile: string, 
+/**
+ * The zone identifier.
+ */
+zone_id: string, 
+/**
+ * Identifier for the record.
+ */
+record_id: string, 
+/**
+ * The record name, for display.
+ */
+name: string, 
+/**
+ * Which address the record points at.
+ */
+family: IpFamily, 
+/**
+ * How the updates have gone.
+ */
+status: DdnsStatus, };
\ No newline at end of file
@@ -0,0 +1,32 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MailRecordCheck } from "./MailRecordCheck";
+import type { MailRecordKind } from "./MailRecordKind";
+
+/**
+ * How well a zone's mail is protected.
+ */
+export type EmailPosture = { 
+/**
+ * The zone identifier.
+ */
+zone_id: string, 
+/**
+ * The zone name.
+ */
+zone_name: string, 
+/**
+ * The mail authentication records in the zone.
+ */
+records: Array<MailRecordCheck>, 
+/**
+ * The kinds with no valid record.
+ */
+missing: Array<MailRecordKind>, 
+/**
+ * From 0, nothing set up, to 100, every record present and enforcing.
+ */
+score: number, 
+/**
+ * What to do next, most important first.
+ */
+recommendations: Array<string>, };
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ErrorCategory = "network" | "unauthorized" | "forbidden" | "not_found" | "rate_limited" | "server" | "api" | "parse" | "missing_credentials" | "cache" | "credential_storage" | "io" | "validation";
\ No newline at end of file
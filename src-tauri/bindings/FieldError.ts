@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A problem with one field of the input, so the frontend can highlight it.
+ */
+export type FieldError = { 
+/**
+ * The path of the field in the submitted JSON, e.g. `ttl` or `data.target`. `None` if the problem is
+ * with the input as a whole, e.g. a locked record.
+ */
+field: string | null, 
+/**
+ * A human-readable description of the problem.
+ */
+message: string, };
\ No newline at end of file
@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DNSRecordRequest } from "./DNSRecordRequest";
+import type { MailRecordKind } from "./MailRecordKind";
+
+/**
+ * A generated record, ready to create.
+ */
+export type GeneratedRecord = { 
+/**
+ * The kind of record.
+ */
+kind: MailRecordKind, 
+/**
+ * The request creating the record.
+ */
+record: DNSRecordRequest, 
+/**
+ * The record of the same kind at the name, which this should replace rather than be added next to.
+ */
+replaces: string | null, 
+/**
+ * For MTA-STS, the policy file to serve over HTTPS.
+ */
+policy: string | null, };
\ No newline at end of file
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChangePlan } from "./ChangePlan";
+
+/**
+ * What importing a zone file would change, shown before anything is applied.
+ */
+export type ImportPreview = { 
+/**
+ * The changes that make the zone match the file.
+ */
+plan: ChangePlan, 
+/**
+ * The records in the file that are left out, such as the SOA record, each with its line number.
+ */
+skipped: Array<string>, };
\ No newline at end of file
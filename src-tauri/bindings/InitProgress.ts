@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The payload of the `init-progress` event, emitted after each zone finishes loading.
+ */
+export type InitProgress = { 
+/**
+ * How many zones have loaded.
+ */
+loaded: number, 
+/**
+ * How many zones failed to load.
+ */
+failed: number, 
+/**
+ * How many zones there are.
+ */
+total: number, };
\ No newline at end of file
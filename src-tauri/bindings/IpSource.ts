@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type IpSource = { "kind": "echo", 
+/**
+ * The URL of the service to ask for the IPv4 address.
+ */
+ipv4_url: string, 
+/**
+ * The URL of the service to ask for the IPv6 address. It should only be reachable over IPv6.
+ */
+ipv6_url: string, } | { "kind": "interface" };
\ No newline at end of file
@@ -0,0 +1,48 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DNSRecord } from "./DNSRecord";
+import type { JournalAction } from "./JournalAction";
+
+/**
+ * One change made to a DNS record.
+ */
+export type JournalEntry = { 
+/**
+ * Identifier for the entry, used to undo it.
+ */
+id: string, 
+/**
+ * When the change was made.
+ */
+at: string, 
+/**
+ * The profile the change was made with.
+ */
+profile: string, 
+/**
+ * The zone identifier.
+ */
+zone_id: string, 
+/**
+ * The zone name.
+ */
+zone_name: string, 
+/**
+ * Identifier for the record that was changed.
+ */
+record_id: string, 
+/**
+ * What the change did.
+ */
+action: JournalAction, 
+/**
+ * The record before the change, unless it was created.
+ */
+before: DNSRecord | null, 
+/**
+ * The record after the change, unless it was deleted.
+ */
+after: DNSRecord | null, 
+/**
+ * The entry this change undid, if it was an undo.
+ */
+undoes: string | null, };
\ No newline at end of file
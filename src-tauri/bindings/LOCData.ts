@@ -0,0 +1,56 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LatitudeDirection } from "./LatitudeDirection";
+import type { LongitudeDirection } from "./LongitudeDirection";
+
+/**
+ * The fields of a LOC record.
+ */
+export type LOCData = { 
+/**
+ * Altitude in meters.
+ */
+altitude: number, 
+/**
+ * Degrees of latitude, 0 - 90.
+ */
+lat_degrees: number, 
+/**
+ * Hemisphere of the latitude.
+ */
+lat_direction: LatitudeDirection, 
+/**
+ * Minutes of latitude, 0 - 59.
+ */
+lat_minutes: number, 
+/**
+ * Seconds of latitude, 0 - 59.999.
+ */
+lat_seconds: number, 
+/**
+ * Degrees of longitude, 0 - 180.
+ */
+long_degrees: number, 
+/**
+ * Hemisphere of the longitude.
+ */
+long_direction: LongitudeDirection, 
+/**
+ * Minutes of longitude, 0 - 59.
+ */
+long_minutes: number, 
+/**
+ * Seconds of longitude, 0 - 59.999.
+ */
+long_seconds: number, 
+/**
+ * Horizontal precision in meters.
+ */
+precision_horz: number, 
+/**
+ * Vertical precision in meters.
+ */
+precision_vert: number, 
+/**
+ * Size of the location in meters.
+ */
+size: number, };
\ No newline at end of file
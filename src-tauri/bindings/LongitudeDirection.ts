@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The hemisphere of a LOC record's longitude.
+ */
+export type LongitudeDirection = "E" | "W";
\ No newline at end of file
@@ -0,0 +1,31 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MailRecordKind } from "./MailRecordKind";
+
+/**
+ * A mail authentication record found in the zone, and what is wrong with it.
+ */
+export type MailRecordCheck = { 
+/**
+ * The kind of record.
+ */
+kind: MailRecordKind, 
+/**
+ * Identifier for the record.
+ */
+record_id: string, 
+/**
+ * The record name.
+ */
+name: string, 
+/**
+ * The record's text, with its character strings joined.
+ */
+text: string, 
+/**
+ * Problems that make receivers ignore the record.
+ */
+errors: Array<string>, 
+/**
+ * Problems that weaken the record, though receivers use it.
+ */
+warnings: Array<string>, };
\ No newline at end of file
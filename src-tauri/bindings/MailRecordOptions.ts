@@ -0,0 +1,100 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DkimKeyType } from "./DkimKeyType";
+import type { DmarcPolicy } from "./DmarcPolicy";
+import type { MtaStsMode } from "./MtaStsMode";
+import type { SpfAll } from "./SpfAll";
+
+export type MailRecordOptions = { "kind": "spf", 
+/**
+ * Allow the addresses of the zone's mail servers.
+ */
+mx: boolean, 
+/**
+ * Allow the addresses of the zone apex.
+ */
+a: boolean, 
+/**
+ * IPv4 and IPv6 addresses or networks to allow.
+ */
+networks: Array<string>, 
+/**
+ * Domains whose SPF records to include, e.g. `_spf.google.com`.
+ */
+includes: Array<string>, 
+/**
+ * What to say about other senders.
+ */
+all: SpfAll, } | { "kind": "dmarc", 
+/**
+ * What to do with failing mail.
+ */
+policy: DmarcPolicy, 
+/**
+ * What to do with failing mail from subdomains, if different.
+ */
+subdomain_policy: DmarcPolicy | null, 
+/**
+ * The percentage of failing mail the policy applies to, if not all of it.
+ */
+percent: number | null, 
+/**
+ * Addresses to send daily aggregate reports to.
+ */
+aggregate_reports: Array<string>, 
+/**
+ * Addresses to send a report of each failing message to.
+ */
+failure_reports: Array<string>, 
+/**
+ * Require the DKIM signature's domain to match exactly, rather than share an organization.
+ */
+strict_dkim: boolean, 
+/**
+ * Require the SPF domain to match exactly, rather than share an organization.
+ */
+strict_spf: boolean, } | { "kind": "dkim", 
+/**
+ * The selector the mail provider signs with, e.g. `google` or `selector1`.
+ */
+selector: string, 
+/**
+ * The kind of key.
+ */
+key_type: DkimKeyType, 
+/**
+ * The public key, base64 encoded, as the mail provider gives it.
+ */
+public_key: string, 
+/**
+ * Mark the key as testing, so failures don't count against mail.
+ */
+testing: boolean, } | { "kind": "mta_sts", 
+/**
+ * Whether senders require TLS.
+ */
+mode: MtaStsMode, 
+/**
+ * The mail servers senders may deliver to, with `*.` for any subdomain. The zone's MX records if
+ * empty.
+ */
+mx: Array<string>, 
+/**
+ * How long senders keep the policy, in days.
+ */
+max_age_days: number, } | { "kind": "tls_rpt", 
+/**
+ * Addresses or HTTPS URLs to send reports to.
+ */
+reports: Array<string>, } | { "kind": "bimi", 
+/**
+ * The selector, `default` if not given.
+ */
+selector: string | null, 
+/**
+ * The HTTPS URL of the logo, an SVG Tiny PS image.
+ */
+logo_url: string, 
+/**
+ * The HTTPS URL of the mark certificate, if there is one.
+ */
+certificate_url: string | null, };
\ No newline at end of file
@@ -0,0 +1,30 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of a NAPTR record.
+ */
+export type NAPTRData = { 
+/**
+ * Flags controlling how the rule is applied, e.g. `U` or `S`.
+ */
+flags: string, 
+/**
+ * The order rules are processed in. Lower values come first.
+ */
+order: number, 
+/**
+ * The preference between rules with the same order. Lower values are preferred.
+ */
+preference: number, 
+/**
+ * The substitution expression applied to the name.
+ */
+regex: string, 
+/**
+ * The name to query next.
+ */
+replacement: string, 
+/**
+ * The service, e.g. `E2U+sip`.
+ */
+service: string, };
\ No newline at end of file
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A recursive resolver to ask.
+ */
+export type NamedResolver = { 
+/**
+ * The name to show, e.g. `Google`.
+ */
+name: string, 
+/**
+ * The address, with or without a port, e.g. `8.8.8.8` or `[2001:4860:4860::8888]:53`.
+ */
+address: string, };
\ No newline at end of file
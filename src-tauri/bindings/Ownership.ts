@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Ownership = { "mode": "listed" } | { "mode": "tagged", 
+/**
+ * The tag marking the records the file owns, e.g. `managed-by:cfdns`.
+ */
+tag: string, } | { "mode": "all" };
\ No newline at end of file
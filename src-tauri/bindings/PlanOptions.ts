@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ConflictResolution } from "./ConflictResolution";
+
+/**
+ * How a [`ChangePlan`] is worked out.
+ */
+export type PlanOptions = { 
+/**
+ * What to do with records that differ from the wanted ones.
+ */
+on_conflict: ConflictResolution, 
+/**
+ * Whether to delete records that aren't wanted at all. If not, they are left alone.
+ */
+delete_missing: boolean, };
\ No newline at end of file
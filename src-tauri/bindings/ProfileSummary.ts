@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A saved profile, for listing in the UI.
+ */
+export type ProfileSummary = { 
+/**
+ * The name the profile was saved under.
+ */
+name: string, 
+/**
+ * Whether this is the profile in use.
+ */
+active: boolean, 
+/**
+ * The email address of the account, if the profile's credentials have been checked.
+ */
+email: string | null, 
+/**
+ * How many zones are cached for the profile.
+ */
+zone_count: number, };
\ No newline at end of file
@@ -0,0 +1,44 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ResolverAnswer } from "./ResolverAnswer";
+
+/**
+ * Whether a record is live, and where.
+ */
+export type PropagationReport = { 
+/**
+ * The zone identifier.
+ */
+zone_id: string, 
+/**
+ * Identifier for the record.
+ */
+record_id: string, 
+/**
+ * The record name.
+ */
+name: string, 
+/**
+ * The record type that was asked for.
+ */
+record_type: string, 
+/**
+ * The cached content the answers were compared with. Proxied records resolve to Cloudflare's own
+ * addresses, so for those any answer agrees.
+ */
+expected: string, 
+/**
+ * When the servers were asked.
+ */
+checked_at: string, 
+/**
+ * Each server's answer, the zone's name servers first.
+ */
+answers: Array<ResolverAnswer>, 
+/**
+ * The percentage of the servers that answered whose answer includes the record, from 0 to 100.
+ */
+propagation: number, 
+/**
+ * Whether every one of the zone's name servers answers with the record.
+ */
+consistent: boolean, };
\ No newline at end of file
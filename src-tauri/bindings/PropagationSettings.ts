@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NamedResolver } from "./NamedResolver";
+
+/**
+ * The servers a propagation check asks.
+ */
+export type PropagationSettings = { 
+/**
+ * The recursive resolvers to ask. They also look up the addresses of the zone's name servers.
+ */
+resolvers: Array<NamedResolver>, 
+/**
+ * The port the zone's name servers are asked on. Only a local test server needs anything other than 53.
+ */
+name_server_port: number, };
\ No newline at end of file
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DNSRecord } from "./DNSRecord";
+
+/**
+ * A record that was changed outside the app.
+ */
+export type RecordChange = { 
+/**
+ * The record as it was cached.
+ */
+before: DNSRecord, 
+/**
+ * The record as it is on Cloudflare.
+ */
+after: DNSRecord, };
\ No newline at end of file
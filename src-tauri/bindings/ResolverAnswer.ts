@@ -0,0 +1,36 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AnswerStatus } from "./AnswerStatus";
+import type { ResolverKind } from "./ResolverKind";
+
+/**
+ * One server's answer.
+ */
+export type ResolverAnswer = { 
+/**
+ * The server's name, e.g. `Google` or `bob.ns.cloudflare.com`.
+ */
+resolver: string, 
+/**
+ * Whether the server answers for the zone or looks answers up.
+ */
+kind: ResolverKind, 
+/**
+ * The address the server was asked at, if it could be found.
+ */
+address: string | null, 
+/**
+ * How the answer compares with the cached record.
+ */
+status: AnswerStatus, 
+/**
+ * The records in the answer, written the way a zone file writes them.
+ */
+answers: Array<string>, 
+/**
+ * How many more seconds the server will give this answer, if it had records.
+ */
+ttl: number | null, 
+/**
+ * Why the server didn't answer.
+ */
+error: string | null, };
\ No newline at end of file
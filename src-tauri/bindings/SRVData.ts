@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of an SRV record.
+ */
+export type SRVData = { 
+/**
+ * The port the service listens on.
+ */
+port: number, 
+/**
+ * Lower values are preferred.
+ */
+priority: number, 
+/**
+ * The name of the host providing the service.
+ */
+target: string, 
+/**
+ * The relative weight of targets with the same priority.
+ */
+weight: number, };
\ No newline at end of file
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of an SSHFP record.
+ */
+export type SSHFPData = { 
+/**
+ * The algorithm of the key.
+ */
+algorithm: number, 
+/**
+ * The fingerprint, hex encoded.
+ */
+fingerprint: string, 
+/**
+ * The fingerprint type.
+ */
+type: number, };
\ No newline at end of file
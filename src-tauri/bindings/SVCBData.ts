@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of an HTTPS or SVCB record.
+ */
+export type SVCBData = { 
+/**
+ * 0 for alias mode, otherwise lower values are preferred.
+ */
+priority: number, 
+/**
+ * The target name, or `.` for the owner name.
+ */
+target: string, 
+/**
+ * The service parameters, e.g. `alpn="h3,h2"`.
+ */
+value: string, };
\ No newline at end of file
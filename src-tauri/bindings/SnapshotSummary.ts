@@ -0,0 +1,34 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A snapshot without its records, for listing.
+ */
+export type SnapshotSummary = { 
+/**
+ * Identifier for the snapshot.
+ */
+id: string, 
+/**
+ * The name the snapshot was given.
+ */
+name: string, 
+/**
+ * When the records were fetched.
+ */
+taken_at: string, 
+/**
+ * The profile the snapshot was taken with.
+ */
+profile: string, 
+/**
+ * The zone identifier.
+ */
+zone_id: string, 
+/**
+ * The zone name.
+ */
+zone_name: string, 
+/**
+ * How many records the snapshot holds.
+ */
+record_count: number, };
\ No newline at end of file
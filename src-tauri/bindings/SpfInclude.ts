@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A record followed to from another.
+ */
+export type SpfInclude = { 
+/**
+ * The domain followed to.
+ */
+domain: string, 
+/**
+ * How deep it is: 1 for the record's own includes, 2 for theirs, and so on.
+ */
+depth: number, 
+/**
+ * Whether it was followed by `include:` or `redirect=`.
+ */
+redirect: boolean, 
+/**
+ * The lookups evaluating its record takes, not counting the term that leads to it.
+ */
+lookups: number, };
\ No newline at end of file
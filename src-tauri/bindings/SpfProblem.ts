@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SpfProblemKind } from "./SpfProblemKind";
+
+/**
+ * A problem found in a domain's SPF record, or one it includes.
+ */
+export type SpfProblem = { 
+/**
+ * What kind of problem it is.
+ */
+kind: SpfProblemKind, 
+/**
+ * The domain whose record has the problem.
+ */
+domain: string, 
+/**
+ * What the problem is.
+ */
+message: string, };
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SpfProblemKind = "no_record" | "multiple_records" | "syntax" | "too_many_lookups" | "too_many_void_lookups" | "void_lookup" | "pass_all" | "loop" | "too_many_mx_names" | "ptr" | "lookup_failed" | "flattened_too_long";
\ No newline at end of file
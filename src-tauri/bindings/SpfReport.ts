@@ -0,0 +1,39 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SpfInclude } from "./SpfInclude";
+import type { SpfProblem } from "./SpfProblem";
+
+/**
+ * What evaluating a domain's SPF record involves.
+ */
+export type SpfReport = { 
+/**
+ * The domain checked.
+ */
+domain: string, 
+/**
+ * The SPF records at the domain. There should be exactly one.
+ */
+records: Array<string>, 
+/**
+ * The lookups evaluating the record takes, at most [`MAX_LOOKUPS`] for receivers to accept it.
+ */
+lookups: number, 
+/**
+ * The lookups finding nothing, at most [`MAX_VOID_LOOKUPS`] for receivers to accept it.
+ */
+void_lookups: number, 
+/**
+ * The records followed to, in the order they are evaluated.
+ */
+includes: Array<SpfInclude>, 
+/**
+ * The problems found.
+ */
+problems: Array<SpfProblem>, 
+/**
+ * The record with `include:`, `redirect=`, `a` and `mx` replaced by the addresses they currently
+ * resolve to, so it takes no lookups for them. The addresses need updating when the providers' change.
+ *
+ * `None` if a record couldn't be read or a lookup failed, since the addresses would be incomplete.
+ */
+flattened: string | null, };
\ No newline at end of file
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of a TLSA or SMIMEA record.
+ */
+export type TLSAData = { 
+/**
+ * The certificate association data, hex encoded.
+ */
+certificate: string, 
+/**
+ * How the data is matched against the certificate.
+ */
+matching_type: number, 
+/**
+ * Which part of the certificate is matched.
+ */
+selector: number, 
+/**
+ * How the certificate is used.
+ */
+usage: number, };
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TargetStatus = "resolves" | "no_addresses" | "nx_domain" | "serv_fail" | "failed";
\ No newline at end of file
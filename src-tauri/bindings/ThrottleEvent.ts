@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ThrottleReason } from "./ThrottleReason";
+
+/**
+ * The payload of the `api-throttled` event, emitted whenever a request has to wait before it is sent.
+ */
+export type ThrottleEvent = { 
+/**
+ * Why the request is waiting.
+ */
+reason: ThrottleReason, 
+/**
+ * Which retry this is, or 0 if the request hasn't been sent yet.
+ */
+attempt: number, 
+/**
+ * How long the request will wait, in milliseconds.
+ */
+retry_in_ms: number, };
\ No newline at end of file
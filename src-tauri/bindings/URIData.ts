@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The fields of a URI record. Its priority is kept alongside the data, as Cloudflare does.
+ */
+export type URIData = { 
+/**
+ * The URI.
+ */
+target: string, 
+/**
+ * The relative weight of URIs with the same priority.
+ */
+weight: number, };
\ No newline at end of file
@@ -0,0 +1,32 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditFinding } from "./AuditFinding";
+import type { Severity } from "./Severity";
+
+/**
+ * The findings for one zone.
+ */
+export type ZoneAudit = { 
+/**
+ * The zone identifier.
+ */
+zone_id: string, 
+/**
+ * The zone name.
+ */
+zone_name: string, 
+/**
+ * When the targets were looked up.
+ */
+checked_at: string, 
+/**
+ * How many CNAME, NS and MX records were checked.
+ */
+records_checked: number, 
+/**
+ * The records that need looking at, most severe first.
+ */
+findings: Array<AuditFinding>, 
+/**
+ * The severity of the most severe finding, if there are any.
+ */
+severity: Severity | null, };
\ No newline at end of file
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AppError } from "./AppError";
+import type { DNSRecord } from "./DNSRecord";
+
+/**
+ * The payload of the `zone-dns-loaded` event, emitted as each zone's DNS records finish loading.
+ */
+export type ZoneDnsLoaded = { 
+/**
+ * The zone ID.
+ */
+zone_id: string, 
+/**
+ * The zone's DNS records. Empty if they failed to load.
+ */
+records: Array<DNSRecord>, 
+/**
+ * Why the records failed to load, if they did.
+ */
+error: AppError | null, };
\ No newline at end of file
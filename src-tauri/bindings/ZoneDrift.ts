@@ -0,0 +1,29 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DNSRecord } from "./DNSRecord";
+import type { RecordChange } from "./RecordChange";
+
+/**
+ * How a zone's records on Cloudflare differ from the cached ones. This is the payload of the `zone-drift`
+ * event.
+ */
+export type ZoneDrift = { 
+/**
+ * The zone identifier.
+ */
+zone_id: string, 
+/**
+ * The zone name.
+ */
+zone_name: string, 
+/**
+ * Records that exist on Cloudflare but weren't cached.
+ */
+added: Array<DNSRecord>, 
+/**
+ * Records that were cached but no longer exist on Cloudflare.
+ */
+removed: Array<DNSRecord>, 
+/**
+ * Records whose content or settings changed.
+ */
+changed: Array<RecordChange>, };
\ No newline at end of file
@@ -0,0 +1,35 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DNSRecord } from "./DNSRecord";
+
+/**
+ * A zone's records as they were at one point in time.
+ */
+export type ZoneSnapshot = { 
+/**
+ * Identifier for the snapshot.
+ */
+id: string, 
+/**
+ * The name the snapshot was given, e.g. `before migration`.
+ */
+name: string, 
+/**
+ * When the records were fetched.
+ */
+taken_at: string, 
+/**
+ * The profile the snapshot was taken with.
+ */
+profile: string, 
+/**
+ * The zone identifier.
+ */
+zone_id: string, 
+/**
+ * The zone name.
+ */
+zone_name: string, 
+/**
+ * The zone's records.
+ */
+records: Array<DNSRecord>, };
\ No newline at end of file
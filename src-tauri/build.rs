@@ -1,5 +1,6 @@
 //! A Tauri app to provide a fast and responsive UI for changing cloudflare DNS.
 
 fn main() {
+    #[cfg(feature = "gui")]
     tauri_build::build();
 }
@@ -1,52 +1,71 @@
 //! This module contains the Tauri commands that are exposed to the frontend.
 
+use reqwest::{
+    multipart::{Form, Part},
+    Method,
+};
+use serde::de::DeserializeOwned;
+
 use crate::{
+    client::CloudflareClient,
     cloudflare::{
-        BearerAuthorizer, CloudflareAuthorizer, CloudflareListZonesResponse, CloudflareResponse,
-        CloudflareUserDetailsResponse, DNSRecord,
+        CloudflareListZonesResponse, CloudflareResponse, CloudflareResponseWithInfo,
+        CloudflareUserDetailsResponse, DNSRecord, DNSRecordDeleteResponse, DNSRecordImportResponse,
+        DNSRecordPatch, DNSRecordRequest,
     },
+    error::AppError,
     models::CustomUserDetails,
 };
 
-/// The base URL for the Cloudflare API.
-pub const CLOUDFLARE_API_BASE: &str = "https://api.cloudflare.com/client/v4";
+/// How many zones to request per page. 50 is the most the List Zones endpoint allows.
+const ZONES_PER_PAGE: u32 = 50;
 
-/// Get a list of zones the user has access to.
+/// How many DNS records to request per page.
+const DNS_RECORDS_PER_PAGE: u32 = 5000;
+
+/// Fetch every page of a list endpoint and collect the results.
 ///
-/// This command requires a token to be passed in, which is used to authenticate with the Cloudflare API.
+/// Pages are requested in order until `result_info.total_pages` is reached. If a response has no
+/// result information, it is treated as the only page.
 ///
 /// # Errors
 ///
-/// If the request fails, this function will return `Err(())`.
-pub async fn get_zones(
-    token: &str,
-) -> Result<CloudflareResponse<Vec<CloudflareListZonesResponse>>, ()> {
-    let authorizer = BearerAuthorizer {
-        token: token.to_string(),
-    };
-    let client = reqwest::Client::new();
-
-    let request_builder = client
-        .get(format!("{CLOUDFLARE_API_BASE}/zones"))
-        .header("Content-Type", "application/json");
-
-    let request_builder = authorizer.with_auth(request_builder);
-
-    let response: CloudflareResponse<Vec<CloudflareListZonesResponse>> = request_builder
-        .send()
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to send request");
-            tracing::error!("{:?}", e);
-        })?
-        .json()
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to parse response as JSON");
-            tracing::error!("{:?}", e);
-        })?;
+/// If any page fails to load, this function will return an [`AppError`] and the pages fetched so far are discarded.
+async fn get_paginated<T: DeserializeOwned + Send>(
+    client: &CloudflareClient,
+    path: &str,
+    per_page: u32,
+) -> Result<Vec<T>, AppError> {
+    let mut results = Vec::new();
+    let mut page: u32 = 1;
+    loop {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let response: CloudflareResponseWithInfo<Vec<T>> = client
+            .get(&format!("{path}{separator}page={page}&per_page={per_page}"))
+            .await?;
+        results.extend(response.result);
+
+        let Some(result_info) = response.result_info else {
+            break;
+        };
+        if page >= result_info.total_pages || result_info.count == 0 {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(results)
+}
 
-    Ok(response) // Return the response to the frontend
+/// Get a list of zones the user has access to.
+///
+/// # Errors
+///
+/// If the request fails or Cloudflare responds with an error, this function will return an [`AppError`].
+pub async fn get_zones(
+    client: &CloudflareClient,
+) -> Result<Vec<CloudflareListZonesResponse>, AppError> {
+    get_paginated(client, "/zones", ZONES_PER_PAGE).await
 }
 
 /// Check if the API key is valid, by making a request to the Cloudflare API.
@@ -60,32 +79,9 @@ pub async fn get_zones(
 ///
 /// # Errors
 ///
-/// If the request fails, this function will return `Err(())`.
-pub async fn check_api_key(token: &str) -> Result<CustomUserDetails, ()> {
-    let authorizer = BearerAuthorizer {
-        token: token.to_string(),
-    };
-    let client = reqwest::Client::new();
-
-    let request_builder = client
-        .get(format!("{CLOUDFLARE_API_BASE}/user"))
-        .header("Content-Type", "application/json");
-
-    let request_builder = authorizer.with_auth(request_builder);
-
-    let response: CloudflareResponse<CloudflareUserDetailsResponse> = request_builder
-        .send()
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to send request");
-            tracing::error!("{:?}", e);
-        })?
-        .json()
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to parse response as JSON");
-            tracing::error!("{:?}", e);
-        })?;
+/// If the request fails or Cloudflare responds with an error, this function will return an [`AppError`].
+pub async fn check_api_key(client: &CloudflareClient) -> Result<CustomUserDetails, AppError> {
+    let response: CloudflareResponse<CloudflareUserDetailsResponse> = client.get("/user").await?;
 
     Ok(CustomUserDetails {
         id: response.result.id.clone(),
@@ -104,37 +100,152 @@ pub async fn check_api_key(token: &str) -> Result<CustomUserDetails, ()> {
 ///
 /// # Errors
 ///
-/// This will return an error if the token is invalid.
+/// If the request fails or Cloudflare responds with an error, this function will return an [`AppError`].
 pub async fn get_zone_dns(
-    token: &str,
+    client: &CloudflareClient,
     zone_id: String,
-) -> Result<CloudflareResponse<Vec<DNSRecord>>, ()> {
-    let authorizer = BearerAuthorizer {
-        token: token.to_string(),
-    };
-    let client = reqwest::Client::new();
-
-    let request_builder = client
-        .get(format!(
-            "{CLOUDFLARE_API_BASE}/zones/{zone_id}/dns_records?per_page=1000"
-        ))
-        .header("Content-Type", "application/json");
-
-    let request_builder = authorizer.with_auth(request_builder);
-
-    let response: CloudflareResponse<Vec<DNSRecord>> = request_builder
-        .send()
+) -> Result<Vec<DNSRecord>, AppError> {
+    get_paginated(
+        client,
+        &format!("/zones/{zone_id}/dns_records"),
+        DNS_RECORDS_PER_PAGE,
+    )
+    .await
+}
+
+/// Get the DNS records of a zone with exactly the name `name`, as they are now.
+///
+/// # Errors
+///
+/// If the request fails or Cloudflare responds with an error, this function will return an [`AppError`].
+pub async fn get_dns_records_named(
+    client: &CloudflareClient,
+    zone_id: &str,
+    name: &str,
+) -> Result<Vec<DNSRecord>, AppError> {
+    get_paginated(
+        client,
+        &format!(
+            "/zones/{zone_id}/dns_records?name={}",
+            name.trim_end_matches('.')
+        ),
+        DNS_RECORDS_PER_PAGE,
+    )
+    .await
+}
+
+/// Get a single DNS record as it is now.
+///
+/// # Errors
+///
+/// If the request fails or Cloudflare responds with an error (e.g. the record doesn't exist), this function will
+/// return an [`AppError`].
+pub async fn get_dns_record(
+    client: &CloudflareClient,
+    zone_id: &str,
+    record_id: &str,
+) -> Result<CloudflareResponse<DNSRecord>, AppError> {
+    client
+        .get(&format!("/zones/{zone_id}/dns_records/{record_id}"))
+        .await
+}
+
+/// Create a new DNS record in a zone.
+///
+/// # Errors
+///
+/// If the request fails or Cloudflare responds with an error, this function will return an [`AppError`].
+pub async fn create_dns_record(
+    client: &CloudflareClient,
+    zone_id: &str,
+    record: &DNSRecordRequest,
+) -> Result<CloudflareResponse<DNSRecord>, AppError> {
+    client
+        .request(
+            Method::POST,
+            &format!("/zones/{zone_id}/dns_records"),
+            Some(record),
+        )
+        .await
+}
+
+/// Overwrite an existing DNS record (PUT). Every field of the record is replaced by the request.
+///
+/// # Errors
+///
+/// If the request fails or Cloudflare responds with an error, this function will return an [`AppError`].
+pub async fn update_dns_record(
+    client: &CloudflareClient,
+    zone_id: &str,
+    record_id: &str,
+    record: &DNSRecordRequest,
+) -> Result<CloudflareResponse<DNSRecord>, AppError> {
+    client
+        .request(
+            Method::PUT,
+            &format!("/zones/{zone_id}/dns_records/{record_id}"),
+            Some(record),
+        )
+        .await
+}
+
+/// Partially update an existing DNS record (PATCH). Only the fields set in the patch are changed.
+///
+/// # Errors
+///
+/// If the request fails or Cloudflare responds with an error, this function will return an [`AppError`].
+pub async fn patch_dns_record(
+    client: &CloudflareClient,
+    zone_id: &str,
+    record_id: &str,
+    patch: &DNSRecordPatch,
+) -> Result<CloudflareResponse<DNSRecord>, AppError> {
+    client
+        .request(
+            Method::PATCH,
+            &format!("/zones/{zone_id}/dns_records/{record_id}"),
+            Some(patch),
+        )
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to send request");
-            tracing::error!("{:?}", e);
-        })?
-        .json()
+}
+
+/// Delete a DNS record from a zone.
+///
+/// # Errors
+///
+/// If the request fails or Cloudflare responds with an error, this function will return an [`AppError`].
+pub async fn delete_dns_record(
+    client: &CloudflareClient,
+    zone_id: &str,
+    record_id: &str,
+) -> Result<CloudflareResponse<DNSRecordDeleteResponse>, AppError> {
+    client
+        .request(
+            Method::DELETE,
+            &format!("/zones/{zone_id}/dns_records/{record_id}"),
+            None::<&()>,
+        )
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to parse response as JSON");
-            tracing::error!("{:?}", e);
-        })?;
+}
 
-    Ok(response) // Return the response to the frontend
+/// Import a BIND zone file into a zone through Cloudflare's own importer. Records in the file that already
+/// exist are skipped by Cloudflare; nothing is overwritten or deleted.
+///
+/// `proxied` is used for proxiable records whose comment doesn't carry a `cf-proxied` tag.
+///
+/// # Errors
+///
+/// If the request fails or Cloudflare can't parse the file, this function will return an [`AppError`].
+pub async fn import_dns_records(
+    client: &CloudflareClient,
+    zone_id: &str,
+    zone_file: String,
+    proxied: bool,
+) -> Result<CloudflareResponse<DNSRecordImportResponse>, AppError> {
+    let form = Form::new()
+        .part("file", Part::text(zone_file).file_name("zone.txt"))
+        .text("proxied", proxied.to_string());
+    client
+        .post_multipart(&format!("/zones/{zone_id}/dns_records/import"), form)
+        .await
 }
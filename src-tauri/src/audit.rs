@@ -0,0 +1,577 @@
+//! Finding records that point at names nobody controls any more, which someone else could claim.
+//!
+//! [`audit`] walks the CNAME, NS and MX records of every cached zone and looks up the names they point at
+//! through a [`TargetResolver`]. A target that doesn't exist, or whose servers fail, is reported, and so is
+//! one hosted by a provider where a deleted resource can be claimed by anyone who creates one with the same
+//! name. The lookups go through the trait so the audit can be tested without a network.
+
+use std::{collections::HashMap, fmt, net::IpAddr};
+
+use chrono::{DateTime, Utc};
+use futures::{future::BoxFuture, stream, StreamExt};
+use hickory_resolver::{
+    error::ResolveErrorKind,
+    proto::{op::ResponseCode, rr::RData},
+    TokioAsyncResolver,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cloudflare::DNSRecord, models::ManagedCache, propagation::PropagationSettings,
+    records::DNSContent,
+};
+
+/// How many names are looked up at the same time.
+const CONCURRENT_LOOKUPS: usize = 8;
+
+/// What looking up the addresses of a name found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// The name has addresses.
+    Resolves {
+        /// The addresses.
+        addresses: Vec<IpAddr>,
+        /// The names CNAMEs were followed through on the way, without trailing dots.
+        aliases: Vec<String>,
+    },
+    /// The name exists, but has no addresses.
+    NoAddresses,
+    /// The name doesn't exist (NXDOMAIN).
+    NxDomain,
+    /// The servers for the name failed to answer (SERVFAIL).
+    ServFail,
+    /// The lookup itself failed, e.g. the resolvers didn't respond.
+    Failed(String),
+}
+
+/// Something that can look up the addresses of a name.
+pub trait TargetResolver: fmt::Debug + Send + Sync {
+    /// Look up the addresses of `name`, following CNAMEs.
+    fn resolve(&self, name: &str) -> BoxFuture<'_, Resolution>;
+}
+
+/// Looks names up through the recursive resolvers in the [`PropagationSettings`].
+#[derive(Debug)]
+pub struct DnsResolver {
+    /// The resolver asking them.
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsResolver {
+    /// A resolver asking the recursive resolvers in `settings`.
+    #[must_use]
+    pub fn new(settings: &PropagationSettings) -> Self {
+        Self {
+            resolver: settings.resolver(),
+        }
+    }
+}
+
+impl TargetResolver for DnsResolver {
+    fn resolve(&self, name: &str) -> BoxFuture<'_, Resolution> {
+        let name = format!("{}.", name.trim_end_matches('.'));
+        Box::pin(async move {
+            match self.resolver.lookup_ip(name).await {
+                Ok(lookup) => {
+                    let addresses: Vec<IpAddr> = lookup.iter().collect();
+                    if addresses.is_empty() {
+                        return Resolution::NoAddresses;
+                    }
+                    let aliases = lookup
+                        .as_lookup()
+                        .records()
+                        .iter()
+                        .filter_map(|r| r.data().and_then(RData::as_cname))
+                        .map(|cname| normalize(&cname.0.to_string()))
+                        .collect();
+                    Resolution::Resolves { addresses, aliases }
+                }
+                Err(e) => match e.kind() {
+                    ResolveErrorKind::NoRecordsFound { response_code, .. } => {
+                        match *response_code {
+                            ResponseCode::NXDomain => Resolution::NxDomain,
+                            ResponseCode::NoError => Resolution::NoAddresses,
+                            ResponseCode::ServFail => Resolution::ServFail,
+                            code => Resolution::Failed(format!("The resolvers answered {code}")),
+                        }
+                    }
+                    _ => Resolution::Failed(e.to_string()),
+                },
+            }
+        })
+    }
+}
+
+/// How urgently a finding needs attention, from least to most.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Worth knowing, but nothing is wrong.
+    Info,
+    /// Something is broken, but nobody else can take advantage of it.
+    Low,
+    /// Something is broken, and might be taken advantage of.
+    Medium,
+    /// Someone else could probably take control of the name.
+    High,
+    /// Someone else can take control of the name by creating a resource at a provider.
+    Critical,
+}
+
+/// What looking up a record's target found, as reported.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetStatus {
+    /// The target has addresses.
+    Resolves,
+    /// The target exists, but has no addresses.
+    NoAddresses,
+    /// The target doesn't exist.
+    NxDomain,
+    /// The servers for the target failed to answer.
+    ServFail,
+    /// The target couldn't be looked up.
+    Failed,
+}
+
+impl From<&Resolution> for TargetStatus {
+    fn from(resolution: &Resolution) -> Self {
+        match resolution {
+            Resolution::Resolves { .. } => Self::Resolves,
+            Resolution::NoAddresses => Self::NoAddresses,
+            Resolution::NxDomain => Self::NxDomain,
+            Resolution::ServFail => Self::ServFail,
+            Resolution::Failed(_) => Self::Failed,
+        }
+    }
+}
+
+/// A record whose target needs looking at.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct AuditFinding {
+    /// Identifier for the record.
+    pub record_id: String,
+    /// The record name.
+    pub name: String,
+    /// The record type, CNAME, NS or MX.
+    pub record_type: String,
+    /// The name the record points at.
+    pub target: String,
+    /// What looking up the target found.
+    pub status: TargetStatus,
+    /// The provider hosting the target, if it is one where deleted resources can be claimed by others.
+    pub provider: Option<String>,
+    /// How urgently this needs attention.
+    pub severity: Severity,
+    /// What is wrong, and what could happen.
+    pub problem: String,
+}
+
+/// The findings for one zone.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ZoneAudit {
+    /// The zone identifier.
+    pub zone_id: String,
+    /// The zone name.
+    pub zone_name: String,
+    /// When the targets were looked up.
+    pub checked_at: DateTime<Utc>,
+    /// How many CNAME, NS and MX records were checked.
+    pub records_checked: usize,
+    /// The records that need looking at, most severe first.
+    pub findings: Vec<AuditFinding>,
+    /// The severity of the most severe finding, if there are any.
+    pub severity: Option<Severity>,
+}
+
+/// A provider where a resource that has been deleted can be claimed by whoever creates one with its name.
+struct Fingerprint {
+    /// The provider's name.
+    provider: &'static str,
+    /// Whether a name is hosted by the provider.
+    matches: fn(&str) -> bool,
+}
+
+/// Whether `name` is `domain` or a name under it.
+fn within(name: &str, domain: &str) -> bool {
+    name == domain
+        || name
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Providers hosting the targets of CNAMEs.
+const CNAME_FINGERPRINTS: &[Fingerprint] = &[
+    Fingerprint {
+        provider: "Heroku",
+        matches: |name| {
+            ["herokuapp.com", "herokudns.com", "herokussl.com"]
+                .iter()
+                .any(|domain| within(name, domain))
+        },
+    },
+    Fingerprint {
+        provider: "Amazon S3",
+        matches: |name| {
+            within(name, "amazonaws.com")
+                && name
+                    .split('.')
+                    .any(|label| label == "s3" || label.starts_with("s3-"))
+        },
+    },
+    Fingerprint {
+        provider: "Microsoft Azure",
+        matches: |name| {
+            [
+                "azurewebsites.net",
+                "cloudapp.net",
+                "cloudapp.azure.com",
+                "trafficmanager.net",
+                "blob.core.windows.net",
+                "azureedge.net",
+                "azure-api.net",
+                "azurefd.net",
+            ]
+            .iter()
+            .any(|domain| within(name, domain))
+        },
+    },
+    Fingerprint {
+        provider: "GitHub Pages",
+        matches: |name| within(name, "github.io"),
+    },
+    Fingerprint {
+        provider: "Netlify",
+        matches: |name| within(name, "netlify.app"),
+    },
+    Fingerprint {
+        provider: "Shopify",
+        matches: |name| within(name, "myshopify.com"),
+    },
+    Fingerprint {
+        provider: "Surge",
+        matches: |name| within(name, "surge.sh"),
+    },
+    Fingerprint {
+        provider: "Bitbucket",
+        matches: |name| within(name, "bitbucket.io"),
+    },
+    Fingerprint {
+        provider: "Ghost",
+        matches: |name| within(name, "ghost.io"),
+    },
+    Fingerprint {
+        provider: "Pantheon",
+        matches: |name| within(name, "pantheonsite.io"),
+    },
+    Fingerprint {
+        provider: "Read the Docs",
+        matches: |name| within(name, "readthedocs.io"),
+    },
+];
+
+/// DNS hosts a subdomain can be delegated to, where a deleted zone can be created again by anyone.
+const NS_FINGERPRINTS: &[Fingerprint] = &[
+    Fingerprint {
+        provider: "Amazon Route 53",
+        matches: |name| {
+            name.split('.')
+                .next()
+                .is_some_and(|label| label.starts_with("ns-"))
+                && name.split('.').any(|label| label.starts_with("awsdns-"))
+        },
+    },
+    Fingerprint {
+        provider: "Azure DNS",
+        matches: |name| name.split('.').any(|label| label.starts_with("azure-dns")),
+    },
+    Fingerprint {
+        provider: "Google Cloud DNS",
+        matches: |name| within(name, "googledomains.com"),
+    },
+    Fingerprint {
+        provider: "DigitalOcean",
+        matches: |name| within(name, "digitalocean.com"),
+    },
+    Fingerprint {
+        provider: "Linode",
+        matches: |name| within(name, "linode.com"),
+    },
+    Fingerprint {
+        provider: "NS1",
+        matches: |name| within(name, "nsone.net"),
+    },
+];
+
+/// The provider in `fingerprints` hosting any of `names`.
+fn provider(fingerprints: &[Fingerprint], names: &[&str]) -> Option<&'static str> {
+    fingerprints
+        .iter()
+        .find(|f| names.iter().any(|name| (f.matches)(name)))
+        .map(|f| f.provider)
+}
+
+/// A name as compared: lowercase, without the trailing dot.
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// The name a CNAME, NS or MX record points at. `None` for other records, and for null MX records, which
+/// say the name receives no mail.
+fn target(record: &DNSRecord) -> Option<String> {
+    match &record.content {
+        DNSContent::Cname { content }
+        | DNSContent::Ns { content }
+        | DNSContent::Mx { content, .. } => {
+            Some(normalize(content)).filter(|target| !target.is_empty())
+        }
+        _ => None,
+    }
+}
+
+/// The severity of a problem and what it is.
+struct Verdict {
+    /// How urgently this needs attention.
+    severity: Severity,
+    /// What is wrong, and what could happen.
+    problem: String,
+}
+
+impl Verdict {
+    /// A verdict of `severity`.
+    fn new(severity: Severity, problem: impl Into<String>) -> Self {
+        Self {
+            severity,
+            problem: problem.into(),
+        }
+    }
+}
+
+/// What to make of a CNAME's target.
+fn cname_verdict(
+    resolution: &Resolution,
+    provider: Option<&str>,
+    in_zone: bool,
+) -> Option<Verdict> {
+    match (resolution, provider) {
+        (Resolution::NxDomain | Resolution::NoAddresses, Some(provider)) => Some(Verdict::new(
+            Severity::Critical,
+            format!("Points at a {provider} resource that no longer exists; anyone can create one with this name and serve content here"),
+        )),
+        (Resolution::ServFail, Some(provider)) => Some(Verdict::new(
+            Severity::High,
+            format!("Points at {provider}, whose servers fail to answer for the target; the resource may have been deleted"),
+        )),
+        (Resolution::Resolves { .. }, Some(provider)) => Some(Verdict::new(
+            Severity::Info,
+            format!("Points at {provider}; if the resource is deleted, remove this record first or anyone can claim it"),
+        )),
+        (Resolution::NxDomain, None) if in_zone => {
+            Some(Verdict::new(Severity::Low, "Points at a name in this zone that doesn't exist"))
+        }
+        (Resolution::NxDomain, None) => Some(Verdict::new(
+            Severity::High,
+            "Points at a name that doesn't exist; if its domain has lapsed, whoever registers it controls this name",
+        )),
+        (Resolution::ServFail, None) => Some(Verdict::new(
+            Severity::Medium,
+            "The servers for the target fail to answer; the domain may have been deleted from its DNS host",
+        )),
+        (Resolution::NoAddresses, None) => Some(Verdict::new(Severity::Low, "Points at a name with no addresses")),
+        (Resolution::Failed(error), _) => {
+            Some(Verdict::new(Severity::Info, format!("The target couldn't be looked up: {error}")))
+        }
+        (Resolution::Resolves { .. }, None) => None,
+    }
+}
+
+/// What to make of an NS record's target, and of the delegated name itself.
+fn ns_verdict(
+    resolution: &Resolution,
+    delegated: Option<&Resolution>,
+    provider: Option<&str>,
+    in_zone: bool,
+) -> Option<Verdict> {
+    match (resolution, delegated) {
+        (Resolution::NxDomain, _) if in_zone => Some(Verdict::new(
+            Severity::Medium,
+            "Delegates to a name server in this zone that doesn't exist",
+        )),
+        (Resolution::NxDomain, _) => Some(Verdict::new(
+            Severity::Critical,
+            "Delegates to a name server that doesn't exist; whoever registers its domain controls this subdomain",
+        )),
+        (Resolution::NoAddresses, _) => {
+            Some(Verdict::new(Severity::Medium, "Delegates to a name server with no addresses"))
+        }
+        (_, Some(Resolution::ServFail)) => Some(provider.map_or_else(
+            || {
+                Verdict::new(
+                    Severity::High,
+                    "The name servers don't serve this subdomain any more; whoever can create the zone on them controls it",
+                )
+            },
+            |provider| {
+                Verdict::new(
+                    Severity::Critical,
+                    format!("The {provider} name servers don't serve this subdomain any more; anyone can create the zone there and control it"),
+                )
+            },
+        )),
+        (Resolution::ServFail, _) => Some(Verdict::new(
+            Severity::Medium,
+            "The servers for the name server's name fail to answer",
+        )),
+        (Resolution::Failed(error), _) => Some(Verdict::new(
+            Severity::Info,
+            format!("The name server couldn't be looked up: {error}"),
+        )),
+        (Resolution::Resolves { .. }, _) => None,
+    }
+}
+
+/// What to make of an MX record's target.
+fn mx_verdict(resolution: &Resolution, in_zone: bool) -> Option<Verdict> {
+    match resolution {
+        Resolution::NxDomain if in_zone => Some(Verdict::new(
+            Severity::Medium,
+            "Mail goes to a name in this zone that doesn't exist, so it bounces",
+        )),
+        Resolution::NxDomain => Some(Verdict::new(
+            Severity::High,
+            "Mail goes to a name that doesn't exist; if its domain has lapsed, whoever registers it receives this name's mail",
+        )),
+        Resolution::NoAddresses => Some(Verdict::new(
+            Severity::Medium,
+            "Mail goes to a name with no addresses, so it bounces",
+        )),
+        Resolution::ServFail => Some(Verdict::new(
+            Severity::Medium,
+            "The servers for the mail server's name fail to answer",
+        )),
+        Resolution::Failed(error) => Some(Verdict::new(
+            Severity::Info,
+            format!("The mail server couldn't be looked up: {error}"),
+        )),
+        Resolution::Resolves { .. } => None,
+    }
+}
+
+/// Look up the targets of a zone's CNAME, NS and MX records, and report the ones that need looking at.
+///
+/// The zone's own NS records at the apex are Cloudflare's and aren't checked.
+pub async fn audit_zone(
+    resolver: &dyn TargetResolver,
+    zone_id: &str,
+    zone_name: &str,
+    records: &[DNSRecord],
+) -> ZoneAudit {
+    let zone = normalize(zone_name);
+    let checked: Vec<(&DNSRecord, String)> = records
+        .iter()
+        .filter(|r| !(matches!(r.content, DNSContent::Ns { .. }) && normalize(&r.name) == zone))
+        .filter_map(|r| target(r).map(|target| (r, target)))
+        .collect();
+
+    // Delegated names are looked up too: their servers fail when the zone there has been deleted.
+    let mut names: Vec<String> = checked
+        .iter()
+        .flat_map(|(record, target)| match record.content {
+            DNSContent::Ns { .. } => vec![target.clone(), normalize(&record.name)],
+            _ => vec![target.clone()],
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    let resolutions: HashMap<String, Resolution> = stream::iter(names)
+        .map(|name| async move {
+            let resolution = resolver.resolve(&name).await;
+            (name, resolution)
+        })
+        .buffer_unordered(CONCURRENT_LOOKUPS)
+        .collect()
+        .await;
+
+    let mut findings: Vec<AuditFinding> = checked
+        .iter()
+        .filter_map(|(record, target)| {
+            let resolution = &resolutions[target];
+            let in_zone = within(target, &zone);
+            let (provider, verdict) = match record.content {
+                DNSContent::Cname { .. } => {
+                    let mut names = vec![target.as_str()];
+                    if let Resolution::Resolves { aliases, .. } = resolution {
+                        names.extend(aliases.iter().map(String::as_str));
+                    }
+                    let provider = provider(CNAME_FINGERPRINTS, &names);
+                    (provider, cname_verdict(resolution, provider, in_zone))
+                }
+                DNSContent::Ns { .. } => {
+                    let provider = provider(NS_FINGERPRINTS, &[target]);
+                    let delegated = resolutions.get(&normalize(&record.name));
+                    (
+                        provider,
+                        ns_verdict(resolution, delegated, provider, in_zone),
+                    )
+                }
+                _ => (None, mx_verdict(resolution, in_zone)),
+            };
+            verdict.map(|verdict| AuditFinding {
+                record_id: record.id.clone(),
+                name: record.name.clone(),
+                record_type: record.content.record_type().to_string(),
+                target: target.clone(),
+                status: resolution.into(),
+                provider: provider.map(ToString::to_string),
+                severity: verdict.severity,
+                problem: verdict.problem,
+            })
+        })
+        .collect();
+    findings.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    ZoneAudit {
+        zone_id: zone_id.to_string(),
+        zone_name: zone_name.to_string(),
+        checked_at: Utc::now(),
+        records_checked: checked.len(),
+        severity: findings.first().map(|f| f.severity),
+        findings,
+    }
+}
+
+/// Audit every zone whose records are cached, in order of zone name.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+pub async fn audit(resolver: &dyn TargetResolver, cache: &ManagedCache) -> Vec<ZoneAudit> {
+    #[allow(clippy::unwrap_used)]
+    let mut zones: Vec<(String, String)> = cache
+        .zones
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|zone| (zone.id.clone(), zone.name.clone()))
+        .collect();
+    zones.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut audits = Vec::with_capacity(zones.len());
+    for (zone_id, zone_name) in zones {
+        #[allow(clippy::unwrap_used)]
+        let Some(records) = cache.zone_dns.lock().unwrap().get(&zone_id).cloned() else {
+            continue;
+        };
+        audits.push(audit_zone(resolver, &zone_id, &zone_name, &records).await);
+    }
+    audits
+}
@@ -0,0 +1,474 @@
+//! `cfdns`: manage Cloudflare DNS records from scripts and terminals, without the app.
+//!
+//! It talks to Cloudflare through the same client as the app, so requests are rate limited, retried and
+//! validated the same way, and it never opens a window, so it runs without a display server. The API token
+//! is read from `CLOUDFLARE_API_TOKEN`, or else from a YAML config file: `--config`, `CFDNS_CONFIG`, or
+//! `cfdns/config.yaml` in the user's config directory.
+//!
+//! ```yaml
+//! token: your-api-token
+//! ```
+//!
+//! Output is a table, or JSON with `--json`.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use chrono::Utc;
+use clap::{Args, Parser, Subcommand};
+use cloudflare_dns_gui::{
+    api,
+    client::{ClientConfig, CloudflareClient, CLOUDFLARE_API_BASE},
+    cloudflare::{
+        BearerAuthorizer, CloudflareListZonesResponse, DNSRecord, DNSRecordPatch, DNSRecordRequest,
+    },
+    error::{AppError, ErrorCategory},
+    validation::{ensure_valid, validate_deletion, validate_record, ZoneContext, AUTO_TTL},
+    zonefile,
+};
+use serde::{Deserialize, Serialize};
+
+/// The environment variable the API token is read from.
+const TOKEN_VAR: &str = "CLOUDFLARE_API_TOKEN";
+
+/// Manage Cloudflare DNS records from the command line.
+#[derive(Debug, Parser)]
+#[command(name = "cfdns", version)]
+struct Cli {
+    /// Print JSON instead of a table.
+    #[arg(long, global = true)]
+    json: bool,
+    /// The config file to read the API token from.
+    #[arg(long, global = true, env = "CFDNS_CONFIG")]
+    config: Option<PathBuf>,
+    /// What to do.
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// The top-level commands.
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List the zones the token can access.
+    Zones,
+    /// List, create, update and delete a zone's DNS records.
+    #[command(subcommand)]
+    Records(RecordsCommand),
+    /// Export a zone's records as a zone file.
+    Export {
+        /// The zone's name or ID.
+        zone: String,
+        /// The file to write the zone file to, instead of standard output.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// The commands about DNS records.
+#[derive(Debug, Subcommand)]
+enum RecordsCommand {
+    /// List a zone's records, optionally filtered.
+    List {
+        /// The zone's name or ID.
+        zone: String,
+        /// Only records of this type, e.g. `MX`.
+        #[arg(long = "type", value_name = "TYPE")]
+        record_type: Option<String>,
+        /// Only records with this name, relative to the zone unless it ends with a dot or the zone's name.
+        #[arg(long)]
+        name: Option<String>,
+        /// Only records whose content contains this text.
+        #[arg(long)]
+        content: Option<String>,
+    },
+    /// Create a record.
+    Create {
+        /// The zone's name or ID.
+        zone: String,
+        /// The record's name, relative to the zone unless it ends with a dot or the zone's name. `@` is the
+        /// zone apex.
+        name: String,
+        /// The record type, e.g. `A`.
+        #[arg(value_name = "TYPE")]
+        record_type: String,
+        /// The record data, the way a zone file writes it, e.g. `10 mail.example.com` for an MX record.
+        #[arg(required = true, num_args = 1..)]
+        content: Vec<String>,
+        /// The record's settings.
+        #[command(flatten)]
+        settings: RecordSettings,
+        /// Tags to add to the record, e.g. `owner:web`.
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Change a record. Only the given settings change.
+    Update {
+        /// The zone's name or ID.
+        zone: String,
+        /// The record's ID.
+        record: String,
+        /// The new name, relative to the zone unless it ends with a dot or the zone's name.
+        #[arg(long)]
+        name: Option<String>,
+        /// The new record data, of the record's current type.
+        #[arg(long, allow_hyphen_values = true)]
+        content: Option<String>,
+        /// The record's settings.
+        #[command(flatten)]
+        settings: RecordSettings,
+    },
+    /// Delete a record.
+    Delete {
+        /// The zone's name or ID.
+        zone: String,
+        /// The record's ID.
+        record: String,
+    },
+}
+
+/// Settings shared by creating and updating a record.
+#[derive(Debug, Args)]
+struct RecordSettings {
+    /// The time to live in seconds, or 1 for automatic.
+    #[arg(long)]
+    ttl: Option<u32>,
+    /// Whether Cloudflare proxies the record.
+    #[arg(long)]
+    proxied: Option<bool>,
+    /// A note about the record.
+    #[arg(long)]
+    comment: Option<String>,
+}
+
+/// The config file.
+#[derive(Debug, Default, Deserialize)]
+struct Config {
+    /// The API token. `CLOUDFLARE_API_TOKEN` takes precedence.
+    token: Option<String>,
+    /// The API to talk to, if not Cloudflare's.
+    base_url: Option<String>,
+}
+
+impl Config {
+    /// Read the config file at `path`, or at the default location if there is none. A missing default file is
+    /// an empty config.
+    fn load(path: Option<&Path>) -> Result<Self, AppError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_config_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => serde_yaml::from_str(&text).map_err(|e| {
+                AppError::new(
+                    ErrorCategory::Parse,
+                    format!("{} isn't a valid config file: {e}", path.display()),
+                )
+            }),
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(AppError::new(
+                ErrorCategory::Io,
+                format!("Can't read {}: {e}", path.display()),
+            )),
+        }
+    }
+}
+
+/// `cfdns/config.yaml` in the user's config directory.
+fn default_config_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(dir.join("cfdns").join("config.yaml"))
+}
+
+/// A client authorized with the token from the environment or the config file.
+fn client(config: &Config) -> Result<CloudflareClient, AppError> {
+    let token = env::var(TOKEN_VAR)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .or_else(|| config.token.clone())
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCategory::MissingCredentials,
+                format!("No API token: set {TOKEN_VAR}, or `token` in the config file"),
+            )
+        })?;
+    let client = CloudflareClient::new(ClientConfig {
+        base_url: config
+            .base_url
+            .clone()
+            .unwrap_or_else(|| CLOUDFLARE_API_BASE.to_string()),
+        ..ClientConfig::default()
+    })?;
+    client.set_authorizer(Box::new(BearerAuthorizer { token }));
+    Ok(client)
+}
+
+/// Find a zone by its name or ID.
+async fn zone(
+    client: &CloudflareClient,
+    zone: &str,
+) -> Result<CloudflareListZonesResponse, AppError> {
+    let wanted = zone.trim_end_matches('.');
+    api::get_zones(client)
+        .await?
+        .into_iter()
+        .find(|z| z.id == wanted || z.name.eq_ignore_ascii_case(wanted))
+        .ok_or_else(|| AppError::new(ErrorCategory::NotFound, format!("There is no zone {zone}")))
+}
+
+/// Find a record by its ID.
+fn record<'a>(records: &'a [DNSRecord], record_id: &str) -> Result<&'a DNSRecord, AppError> {
+    records.iter().find(|r| r.id == record_id).ok_or_else(|| {
+        AppError::new(
+            ErrorCategory::NotFound,
+            format!("There is no record {record_id} in the zone"),
+        )
+    })
+}
+
+/// Print rows as a table with a header, each column as wide as its widest cell.
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    for row in rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+/// Print a value as JSON.
+fn print_json(value: &impl Serialize) -> Result<(), AppError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Print records, as JSON or as a table.
+fn print_records(records: &[DNSRecord], json: bool) -> Result<(), AppError> {
+    if json {
+        return print_json(&records);
+    }
+    let rows: Vec<Vec<String>> = records
+        .iter()
+        .map(|r| {
+            vec![
+                r.id.clone(),
+                r.content.record_type().to_string(),
+                r.name.clone(),
+                zonefile::rdata(&r.content),
+                if r.ttl == AUTO_TTL {
+                    "auto".to_string()
+                } else {
+                    r.ttl.to_string()
+                },
+                r.proxied.unwrap_or_default().to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "TYPE", "NAME", "CONTENT", "TTL", "PROXIED"], &rows);
+    Ok(())
+}
+
+/// Run a command.
+async fn run(cli: Cli) -> Result<(), AppError> {
+    let config = Config::load(cli.config.as_deref())?;
+    let client = client(&config)?;
+    match cli.command {
+        Command::Zones => {
+            let zones = api::get_zones(&client).await?;
+            if cli.json {
+                return print_json(&zones);
+            }
+            let rows: Vec<Vec<String>> = zones
+                .iter()
+                .map(|z| vec![z.id.clone(), z.name.clone(), z.status.clone()])
+                .collect();
+            print_table(&["ID", "NAME", "STATUS"], &rows);
+        }
+        Command::Records(command) => records(&client, command, cli.json).await?,
+        Command::Export { zone: name, output } => {
+            let zone = zone(&client, &name).await?;
+            let records = api::get_zone_dns(&client, zone.id).await?;
+            if cli.json {
+                return print_json(&records);
+            }
+            let text = zonefile::render(&zone.name, &records, Utc::now());
+            match output {
+                Some(path) => fs::write(path, text)?,
+                None => print!("{text}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run a records command.
+async fn records(
+    client: &CloudflareClient,
+    command: RecordsCommand,
+    json: bool,
+) -> Result<(), AppError> {
+    match command {
+        RecordsCommand::List {
+            zone: name,
+            record_type,
+            name: record_name,
+            content,
+        } => {
+            let zone = zone(client, &name).await?;
+            let context = ZoneContext {
+                zone_name: &zone.name,
+                records: &[],
+            };
+            let record_name = record_name.map(|n| context.qualify_relative(&n));
+            let records: Vec<DNSRecord> = api::get_zone_dns(client, zone.id.clone())
+                .await?
+                .into_iter()
+                .filter(|r| {
+                    record_type
+                        .as_ref()
+                        .is_none_or(|t| r.content.record_type().eq_ignore_ascii_case(t))
+                })
+                .filter(|r| {
+                    record_name
+                        .as_ref()
+                        .is_none_or(|n| r.name.eq_ignore_ascii_case(n))
+                })
+                .filter(|r| {
+                    content
+                        .as_ref()
+                        .is_none_or(|c| zonefile::rdata(&r.content).contains(c.as_str()))
+                })
+                .collect();
+            print_records(&records, json)?;
+        }
+        RecordsCommand::Create {
+            zone: name,
+            name: record_name,
+            record_type,
+            content,
+            settings,
+            tags,
+        } => {
+            let zone = zone(client, &name).await?;
+            let records = api::get_zone_dns(client, zone.id.clone()).await?;
+            let context = ZoneContext {
+                zone_name: &zone.name,
+                records: &records,
+            };
+            let request = DNSRecordRequest {
+                content: zonefile::parse_rdata(&record_type, &content.join(" "), &zone.name)?,
+                name: context.qualify_relative(&record_name),
+                proxied: settings.proxied,
+                comment: settings.comment,
+                tags,
+                ttl: settings.ttl.unwrap_or(AUTO_TTL),
+            };
+            ensure_valid(validate_record(&request, &context, None))?;
+            let created = api::create_dns_record(client, &zone.id, &request).await?;
+            print_records(&[created.result], json)?;
+        }
+        RecordsCommand::Update {
+            zone: name,
+            record: record_id,
+            name: record_name,
+            content,
+            settings,
+        } => {
+            let zone = zone(client, &name).await?;
+            let records = api::get_zone_dns(client, zone.id.clone()).await?;
+            let context = ZoneContext {
+                zone_name: &zone.name,
+                records: &records,
+            };
+            let existing = record(&records, &record_id)?;
+            let patch = update_patch(existing, &context, record_name, content, settings)?;
+            ensure_valid(validate_record(
+                &patch.apply_to(existing),
+                &context,
+                Some(existing),
+            ))?;
+            let updated = api::patch_dns_record(client, &zone.id, &record_id, &patch).await?;
+            print_records(&[updated.result], json)?;
+        }
+        RecordsCommand::Delete {
+            zone: name,
+            record: record_id,
+        } => {
+            let zone = zone(client, &name).await?;
+            let records = api::get_zone_dns(client, zone.id.clone()).await?;
+            ensure_valid(validate_deletion(record(&records, &record_id)?))?;
+            let deleted = api::delete_dns_record(client, &zone.id, &record_id).await?;
+            if json {
+                return print_json(&deleted.result);
+            }
+            println!("Deleted {}", deleted.result.id);
+        }
+    }
+    Ok(())
+}
+
+/// The patch that makes the changes given to `records update` to `existing`.
+fn update_patch(
+    existing: &DNSRecord,
+    zone: &ZoneContext<'_>,
+    name: Option<String>,
+    content: Option<String>,
+    settings: RecordSettings,
+) -> Result<DNSRecordPatch, AppError> {
+    let patch = DNSRecordPatch {
+        content: content
+            .map(|c| zonefile::parse_rdata(existing.content.record_type(), &c, zone.zone_name))
+            .transpose()?,
+        name: name.map(|n| zone.qualify_relative(&n)),
+        proxied: settings.proxied,
+        comment: settings.comment,
+        tags: None,
+        ttl: settings.ttl,
+    };
+    if patch == DNSRecordPatch::default() {
+        return Err(AppError::new(
+            ErrorCategory::Validation,
+            "Nothing to change: give at least one of --name, --content, --ttl, --proxied or --comment",
+        ));
+    }
+    Ok(patch)
+}
+
+/// Entry point for the command-line tool.
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("cfdns: Failed to start the async runtime: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match runtime.block_on(run(cli)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("cfdns: {}", e.message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,427 @@
+//! The shared client every request to the Cloudflare API goes through.
+//!
+//! A single [`CloudflareClient`] is held in Tauri managed state. It owns one pooled `reqwest::Client`,
+//! the authorizer for the configured credentials, and the settings from [`ClientConfig`].
+
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use reqwest::Method;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    cloudflare::{
+        CloudflareAuthorizer, CloudflareMessage, CloudflareResponse, CloudflareResponseWithInfo,
+    },
+    error::AppError,
+    rate_limit::{
+        retry_after, RateLimit, RetryPolicy, ThrottleEvent, ThrottleListener, ThrottleReason,
+        TokenBucket,
+    },
+};
+
+/// The base URL for the Cloudflare API.
+pub const CLOUDFLARE_API_BASE: &str = "https://api.cloudflare.com/client/v4";
+
+/// The user agent sent with every request.
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Settings for a [`CloudflareClient`].
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// The base URL requests are made against, without a trailing slash.
+    ///
+    /// Defaults to [`CLOUDFLARE_API_BASE`]; tests point it at a local mock server.
+    pub base_url: String,
+    /// How long a whole request (connecting, sending and reading the response) may take.
+    pub timeout: Duration,
+    /// How long connecting to the API may take.
+    pub connect_timeout: Duration,
+    /// The user agent sent with every request.
+    pub user_agent: String,
+    /// How many requests may be in flight at once when fetching the DNS records of many zones.
+    pub max_concurrent_requests: usize,
+    /// The client-side request budget. Defaults to Cloudflare's limit.
+    pub rate_limit: RateLimit,
+    /// How failed `GET` requests are retried.
+    pub retry: RetryPolicy,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            base_url: CLOUDFLARE_API_BASE.to_string(),
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            user_agent: USER_AGENT.to_string(),
+            max_concurrent_requests: 8,
+            rate_limit: RateLimit::default(),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// The envelope every Cloudflare response is wrapped in, so [`CloudflareClient::request`] can check it
+/// regardless of whether the response includes result information.
+pub trait Envelope: DeserializeOwned + Send {
+    /// Whether the API call was successful.
+    fn success(&self) -> bool;
+    /// Take the errors returned by the API.
+    fn into_errors(self) -> Vec<CloudflareMessage>;
+}
+
+impl<T: DeserializeOwned + Send> Envelope for CloudflareResponse<T> {
+    fn success(&self) -> bool {
+        self.success
+    }
+    fn into_errors(self) -> Vec<CloudflareMessage> {
+        self.errors
+    }
+}
+
+impl<T: DeserializeOwned + Send> Envelope for CloudflareResponseWithInfo<T> {
+    fn success(&self) -> bool {
+        self.success
+    }
+    fn into_errors(self) -> Vec<CloudflareMessage> {
+        self.errors
+    }
+}
+
+/// The body Cloudflare responds with when a request fails. The `result` is usually `null`, so it
+/// can't be parsed as a [`CloudflareResponse`] of the expected type.
+#[derive(Debug, Deserialize)]
+struct CloudflareErrorBody {
+    /// Errors returned by the API.
+    #[serde(default)]
+    errors: Vec<CloudflareMessage>,
+}
+
+/// A client for the Cloudflare API, shared by every command.
+#[derive(Debug)]
+pub struct CloudflareClient {
+    /// The pooled HTTP client.
+    http: reqwest::Client,
+    /// The base URL requests are made against, without a trailing slash.
+    base_url: String,
+    /// Adds the configured credentials to each request. `None` until credentials are set.
+    authorizer: RwLock<Option<Box<dyn CloudflareAuthorizer>>>,
+    /// How many requests may be in flight at once when fetching many zones.
+    max_concurrent_requests: usize,
+    /// The request budget, shared with clients made by [`CloudflareClient::with_authorizer`].
+    limiter: Arc<TokenBucket>,
+    /// How failed `GET` requests are retried.
+    retry: RetryPolicy,
+    /// Told about every request that has to wait.
+    throttle_listener: Arc<RwLock<Option<ThrottleListener>>>,
+}
+
+impl CloudflareClient {
+    /// Create a client with the given settings and no credentials.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if the HTTP client can't be built (e.g. the TLS backend fails to initialize).
+    pub fn new(config: ClientConfig) -> Result<Self, AppError> {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .user_agent(config.user_agent)
+            .build()?;
+        Ok(Self {
+            http,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            authorizer: RwLock::new(None),
+            max_concurrent_requests: config.max_concurrent_requests.max(1),
+            limiter: Arc::new(TokenBucket::new(config.rate_limit)),
+            retry: config.retry,
+            throttle_listener: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    /// The base URL requests are made against.
+    #[must_use]
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// A client sharing this one's connection pool and settings, but using other credentials.
+    ///
+    /// Used to check credentials without replacing the ones in use.
+    #[must_use]
+    pub fn with_authorizer(&self, authorizer: Box<dyn CloudflareAuthorizer>) -> Self {
+        Self {
+            http: self.http.clone(),
+            base_url: self.base_url.clone(),
+            authorizer: RwLock::new(Some(authorizer)),
+            max_concurrent_requests: self.max_concurrent_requests,
+            limiter: Arc::clone(&self.limiter),
+            retry: self.retry,
+            throttle_listener: Arc::clone(&self.throttle_listener),
+        }
+    }
+
+    /// How many requests may be in flight at once when fetching many zones. Always at least 1.
+    #[must_use]
+    pub const fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests
+    }
+
+    /// Use these credentials for every following request.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the authorizer lock is poisoned.
+    pub fn set_authorizer(&self, authorizer: Box<dyn CloudflareAuthorizer>) {
+        #[allow(clippy::unwrap_used)]
+        {
+            *self.authorizer.write().unwrap() = Some(authorizer);
+        }
+    }
+
+    /// Call `listener` whenever a request has to wait: for the request budget to refill, or before a retry.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the listener lock is poisoned.
+    pub fn set_throttle_listener(&self, listener: ThrottleListener) {
+        #[allow(clippy::unwrap_used)]
+        {
+            *self.throttle_listener.write().unwrap() = Some(listener);
+        }
+    }
+
+    /// Forget the credentials. Requests fail with [`ErrorCategory::MissingCredentials`] until new ones are set.
+    ///
+    /// [`ErrorCategory::MissingCredentials`]: crate::error::ErrorCategory::MissingCredentials
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the authorizer lock is poisoned.
+    pub fn clear_authorizer(&self) {
+        #[allow(clippy::unwrap_used)]
+        {
+            *self.authorizer.write().unwrap() = None;
+        }
+    }
+
+    /// Make a `GET` request to `path` (relative to the base URL, including any query string).
+    ///
+    /// # Errors
+    ///
+    /// See [`CloudflareClient::request`].
+    pub async fn get<R: Envelope>(&self, path: &str) -> Result<R, AppError> {
+        self.request(Method::GET, path, None::<&()>).await
+    }
+
+    /// Make a request to `path` (relative to the base URL, including any query string), with an optional
+    /// JSON body, and parse the response as `R`.
+    ///
+    /// Every request waits for the client-side budget first. If Cloudflare responds with `Retry-After`, every
+    /// request waits that long. `GET` requests that are rate limited, hit a server error or fail to connect are
+    /// retried as set by [`ClientConfig::retry`]; other methods aren't, since they may not be idempotent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if no credentials are set, if the request can't be sent, if the response
+    /// has an error status, if the body can't be parsed as `R`, or if Cloudflare reports `success: false`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the authorizer lock is poisoned.
+    pub async fn request<R: Envelope, B: Serialize + Sync + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<R, AppError> {
+        let max_retries = if method == Method::GET {
+            self.retry.max_retries
+        } else {
+            0
+        };
+        let mut attempt = 0;
+        loop {
+            self.wait_for_budget(attempt).await;
+            let (error, retry_after) = match self.send(method.clone(), path, body).await {
+                Ok(response) => return Ok(response),
+                Err(failure) => failure,
+            };
+            if let Some(delay) = retry_after {
+                self.limiter.pause_for(delay);
+            }
+            let Some(reason) = ThrottleEvent::reason_for(&error) else {
+                return Err(error);
+            };
+            if attempt >= max_retries {
+                return Err(error);
+            }
+
+            attempt += 1;
+            let delay = retry_after.unwrap_or_else(|| self.retry.backoff(attempt));
+            tracing::warn!("Retrying {path} in {delay:?} (attempt {attempt}): {error}");
+            self.notify(&ThrottleEvent::new(reason, attempt, delay));
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Make a `POST` request to `path` with a multipart form body, and parse the response as `R`.
+    ///
+    /// Like any other `POST`, the request waits for the budget and honours `Retry-After`, but isn't retried.
+    ///
+    /// # Errors
+    ///
+    /// See [`CloudflareClient::request`].
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the authorizer lock is poisoned.
+    pub async fn post_multipart<R: Envelope>(
+        &self,
+        path: &str,
+        form: reqwest::multipart::Form,
+    ) -> Result<R, AppError> {
+        self.wait_for_budget(0).await;
+        let request_builder = self
+            .http
+            .post(format!("{}{path}", self.base_url))
+            .multipart(form);
+        self.dispatch(request_builder)
+            .await
+            .map_err(|(error, retry_after)| {
+                if let Some(delay) = retry_after {
+                    self.limiter.pause_for(delay);
+                }
+                error
+            })
+    }
+
+    /// Wait until the request budget has a token to spend.
+    async fn wait_for_budget(&self, attempt: u32) {
+        while let Err(delay) = self.limiter.try_acquire() {
+            self.notify(&ThrottleEvent::new(ThrottleReason::Budget, attempt, delay));
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Tell the throttle listener, if there is one, that a request is waiting.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the listener lock is poisoned.
+    fn notify(&self, event: &ThrottleEvent) {
+        #[allow(clippy::unwrap_used)]
+        if let Some(listener) = self.throttle_listener.read().unwrap().as_ref() {
+            listener.notify(event);
+        }
+    }
+
+    /// Send a request once and parse the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`AppError`], along with how long Cloudflare asked to wait (from `Retry-After`), if any.
+    async fn send<R: Envelope, B: Serialize + Sync + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<R, (AppError, Option<Duration>)> {
+        let mut request_builder = self
+            .http
+            .request(method, format!("{}{path}", self.base_url))
+            .header("Content-Type", "application/json");
+        if let Some(body) = body {
+            request_builder = request_builder.json(body);
+        }
+        self.dispatch(request_builder).await
+    }
+
+    /// Authorize and send a built request, and parse the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`AppError`], along with how long Cloudflare asked to wait (from `Retry-After`), if any.
+    async fn dispatch<R: Envelope>(
+        &self,
+        request_builder: reqwest::RequestBuilder,
+    ) -> Result<R, (AppError, Option<Duration>)> {
+        let request_builder = self.authorize(request_builder).map_err(|e| (e, None))?;
+
+        let response = request_builder.send().await.map_err(|e| {
+            tracing::error!("Failed to send request");
+            tracing::error!("{:?}", e);
+            (AppError::from(e), None)
+        })?;
+
+        let retry_after = retry_after(response.headers());
+        Self::parse_response(response)
+            .await
+            .map_err(|e| (e, retry_after))
+    }
+
+    /// Add the configured credentials to a request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if no credentials are set.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the authorizer lock is poisoned.
+    fn authorize(
+        &self,
+        request_builder: reqwest::RequestBuilder,
+    ) -> Result<reqwest::RequestBuilder, AppError> {
+        #[allow(clippy::unwrap_used)]
+        self.authorizer
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|authorizer| authorizer.with_auth(request_builder))
+            .ok_or_else(AppError::missing_credentials)
+    }
+
+    /// Read a response from the Cloudflare API and parse it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the response has an error status, if the body can't be parsed
+    /// as the expected type, or if Cloudflare reports `success: false`.
+    async fn parse_response<R: Envelope>(response: reqwest::Response) -> Result<R, AppError> {
+        let status = response.status();
+        let body = response.bytes().await.map_err(|e| {
+            tracing::error!("Failed to read response body");
+            tracing::error!("{:?}", e);
+            AppError::from(e)
+        })?;
+
+        if !status.is_success() {
+            let errors = serde_json::from_slice::<CloudflareErrorBody>(&body)
+                .map(|body| body.errors)
+                .unwrap_or_default();
+            let error = AppError::from_response(status, errors);
+            tracing::error!("Request failed: {error}");
+            return Err(error);
+        }
+
+        let response: R = serde_json::from_slice(&body).map_err(|e| {
+            tracing::error!("Failed to parse response as JSON");
+            tracing::error!("{:?}", e);
+            AppError {
+                status: Some(status.as_u16()),
+                ..AppError::from(e)
+            }
+        })?;
+
+        if !response.success() {
+            let error = AppError::from_response(status, response.into_errors());
+            tracing::error!("Request failed: {error}");
+            return Err(error);
+        }
+
+        Ok(response)
+    }
+}
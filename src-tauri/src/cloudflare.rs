@@ -2,24 +2,34 @@
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
+
+use crate::records::DNSContent;
 use ts_rs::TS;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 /// A trait for authorizing requests to the Cloudflare API.
-pub trait CloudflareAuthorizer: fmt::Debug {
+pub trait CloudflareAuthorizer: fmt::Debug + Send + Sync {
     /// Authorize a request to the Cloudflare API.
     fn with_auth(&self, request_builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder;
 }
 
 /// An authorizer that uses a bearer token (in the header as "Auth: Bearer xxx").
-#[derive(Debug)]
+///
+/// The token is wiped from memory when the authorizer is dropped (e.g. replaced by new credentials).
+#[derive(Debug, Zeroize, ZeroizeOnDrop)]
 pub struct BearerAuthorizer {
     /// The bearer token.
     pub token: String,
 }
 
-/// An authorizer that uses an API key (in the header under X-Auth-Key).
-#[derive(Debug)]
+/// An authorizer that uses the legacy global API key (in the header under X-Auth-Key), which Cloudflare
+/// only accepts along with the account's email address (in the header under X-Auth-Email).
+///
+/// The key is wiped from memory when the authorizer is dropped (e.g. replaced by new credentials).
+#[derive(Debug, Zeroize, ZeroizeOnDrop)]
 pub struct ApiKeyAuthorizer {
+    /// The email address of the account the key belongs to.
+    pub email: String,
     /// The API key.
     pub key: String,
 }
@@ -32,7 +42,52 @@ impl CloudflareAuthorizer for BearerAuthorizer {
 
 impl CloudflareAuthorizer for ApiKeyAuthorizer {
     fn with_auth(&self, request_builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
-        request_builder.header("X-Auth-Key", &self.key)
+        let request_builder = request_builder.header("X-Auth-Email", &self.email);
+        // Mark the key as sensitive so it is left out of debug output, as `bearer_auth` does for tokens.
+        match reqwest::header::HeaderValue::from_str(&self.key) {
+            Ok(mut key) => {
+                key.set_sensitive(true);
+                request_builder.header("X-Auth-Key", key)
+            }
+            Err(_) => request_builder.header("X-Auth-Key", &self.key),
+        }
+    }
+}
+
+/// Credentials for the Cloudflare API, as entered by the user.
+///
+/// The secrets are wiped from memory when the credentials are dropped.
+#[derive(Debug, Deserialize, Serialize, Clone, TS, Zeroize, ZeroizeOnDrop)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Credential {
+    /// An API token, sent as a bearer token. This is what Cloudflare recommends.
+    BearerToken {
+        /// The API token.
+        token: String,
+    },
+    /// The legacy global API key, sent along with the email address of the account.
+    GlobalApiKey {
+        /// The email address of the account the key belongs to.
+        email: String,
+        /// The global API key.
+        key: String,
+    },
+}
+
+impl Credential {
+    /// The authorizer that sends these credentials with each request.
+    #[must_use]
+    pub fn authorizer(&self) -> Box<dyn CloudflareAuthorizer> {
+        match self {
+            Self::BearerToken { token } => Box::new(BearerAuthorizer {
+                token: token.clone(),
+            }),
+            Self::GlobalApiKey { email, key } => Box::new(ApiKeyAuthorizer {
+                email: email.clone(),
+                key: key.clone(),
+            }),
+        }
     }
 }
 
@@ -92,7 +147,7 @@ pub struct CloudflareResponseWithInfo<T> {
     /// Messages returned by the API.
     pub messages: Vec<CloudflareMessage>,
     /// Result information returned by the API. (Optional)
-    pub result_info: CloudflareResultInfo,
+    pub result_info: Option<CloudflareResultInfo>,
 }
 
 /// Cloudflare account information.
@@ -197,6 +252,11 @@ pub struct CloudflareListZonesResponse {
     ///
     /// Example: `["ns1.example.com","ns2.example.com"]`
     pub vanity_name_servers: Option<Vec<String>>,
+    /// The Cloudflare name servers assigned to the zone.
+    ///
+    /// Example: `["bob.ns.cloudflare.com","lola.ns.cloudflare.com"]`
+    #[serde(default)]
+    pub name_servers: Vec<String>,
     /// What tenant the zone is in
     pub tenant: CloudflareTenant,
     /// What tenant unit the zone is in
@@ -325,46 +385,199 @@ pub struct CloudflareUserDetailsResponse {
 #[derive(Debug, Deserialize, Serialize, Clone, TS)]
 #[ts(export)]
 pub struct DNSRecord {
-    /// The stored DNS record.
-    /// For A or AAAA records, this is the IP address.
-    /// For most others, this is the content of the record (string)
-    content: String,
+    /// The record type and its content: the IP address for A and AAAA records, the target for CNAME
+    /// records, the structured data for SRV records, etc.
+    #[serde(flatten)]
+    pub content: DNSContent,
     /// The DNS record identifier. @ for root record
-    name: String,
+    pub name: String,
     /// Whether the record is proxied by Cloudflare
-    proxied: Option<bool>,
-    /// The record type (A, AAAA, CNAME, etc.)
-    r#type: String,
+    pub proxied: Option<bool>,
     /// Comments or notes about the DNS record. This field has no effect on DNS responses.
-    comment: Option<String>,
+    pub comment: Option<String>,
     /// When the record was created. Example: `2014-01-01T05:20:00.12345Z`
-    created_on: String,
+    pub created_on: String,
     /// Identifier for the record (used with the API)
-    id: String,
+    pub id: String,
     /// Whether this record can be modified/deleted (true means it's managed by Cloudflare)
-    locked: bool,
+    pub locked: bool,
     /// Extra Cloudflare-specific information about the record.
-    meta: Meta,
+    pub meta: Meta,
     /// When the record was last modified. Example: `2014-01-01T05:20:00.12345Z`
-    modified_on: Option<String>,
+    pub modified_on: Option<String>,
     /// Whether the record can be proxied by Cloudflare or not
-    proxiable: bool,
+    pub proxiable: bool,
     /// Custom tags for the record
-    tags: Vec<String>,
+    pub tags: Vec<String>,
     /// Time to live for the record. To signify `auto ttl`, set this to `1`. Valid values: 60 - 86400,0,1
-    ttl: u32,
+    pub ttl: u32,
     /// The zone identifier
-    zone_id: Option<String>,
+    pub zone_id: Option<String>,
     /// The zone name
-    zone_name: String,
+    pub zone_name: String,
+}
+
+impl DNSRecord {
+    /// Whether two versions of a record have the same name, content and settings. Timestamps are ignored,
+    /// an empty comment is the same as none, and tags can be in any order.
+    #[must_use]
+    pub fn same_as(&self, other: &Self) -> bool {
+        let mut tags = self.tags.clone();
+        let mut other_tags = other.tags.clone();
+        tags.sort();
+        other_tags.sort();
+        self.name.eq_ignore_ascii_case(&other.name)
+            && self.content == other.content
+            && self.ttl == other.ttl
+            && self.proxied == other.proxied
+            && self.comment.as_deref().filter(|c| !c.is_empty())
+                == other.comment.as_deref().filter(|c| !c.is_empty())
+            && tags == other_tags
+    }
+}
+
+/// The body of a request to create or overwrite (PUT) a DNS record.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct DNSRecordRequest {
+    /// The record type and its content.
+    #[serde(flatten)]
+    pub content: DNSContent,
+    /// The DNS record name (or @ for the zone apex)
+    pub name: String,
+    /// Whether the record is proxied by Cloudflare
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    /// Comments or notes about the DNS record. This field has no effect on DNS responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Custom tags for the record
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Time to live for the record. To signify `auto ttl`, set this to `1`. Valid values: 60 - 86400,0,1
+    pub ttl: u32,
+}
+
+impl From<&DNSRecord> for DNSRecordRequest {
+    /// The request that creates or overwrites a record to be the same as `record`.
+    fn from(record: &DNSRecord) -> Self {
+        Self {
+            content: record.content.clone(),
+            name: record.name.clone(),
+            proxied: record.proxied,
+            comment: record.comment.clone(),
+            tags: record.tags.clone(),
+            ttl: record.ttl,
+        }
+    }
+}
+
+/// A partial update (PATCH) of a DNS record. Only the fields that are set are changed.
+///
+/// The frontend sends the new content as a nested `content` object, as the type and content have to change
+/// together. It is flattened into the request body sent to Cloudflare.
+#[derive(Debug, Default, Deserialize, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct DNSRecordPatch {
+    /// The new record type and content.
+    #[ts(optional)]
+    pub content: Option<DNSContent>,
+    /// The DNS record name (or @ for the zone apex)
+    #[ts(optional)]
+    pub name: Option<String>,
+    /// Whether the record is proxied by Cloudflare
+    #[ts(optional)]
+    pub proxied: Option<bool>,
+    /// Comments or notes about the DNS record. This field has no effect on DNS responses.
+    #[ts(optional)]
+    pub comment: Option<String>,
+    /// Custom tags for the record
+    #[ts(optional)]
+    pub tags: Option<Vec<String>>,
+    /// Time to live for the record. To signify `auto ttl`, set this to `1`. Valid values: 60 - 86400,0,1
+    #[ts(optional)]
+    pub ttl: Option<u32>,
+}
+
+impl DNSRecordPatch {
+    /// The full record that results from applying this patch to `record`.
+    #[must_use]
+    pub fn apply_to(&self, record: &DNSRecord) -> DNSRecordRequest {
+        DNSRecordRequest {
+            content: self
+                .content
+                .clone()
+                .unwrap_or_else(|| record.content.clone()),
+            name: self.name.clone().unwrap_or_else(|| record.name.clone()),
+            proxied: self.proxied.or(record.proxied),
+            comment: self.comment.clone().or_else(|| record.comment.clone()),
+            tags: self.tags.clone().unwrap_or_else(|| record.tags.clone()),
+            ttl: self.ttl.unwrap_or(record.ttl),
+        }
+    }
+}
+
+/// The body of a PATCH request, as Cloudflare expects it.
+#[derive(Serialize)]
+struct DNSRecordPatchBody<'a> {
+    /// The new record type and content.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    content: Option<&'a DNSContent>,
+    /// The new name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a String>,
+    /// Whether the record is proxied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxied: Option<bool>,
+    /// The new comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<&'a String>,
+    /// The new tags.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<&'a Vec<String>>,
+    /// The new time to live.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u32>,
+}
+
+impl Serialize for DNSRecordPatch {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DNSRecordPatchBody {
+            content: self.content.as_ref(),
+            name: self.name.as_ref(),
+            proxied: self.proxied,
+            comment: self.comment.as_ref(),
+            tags: self.tags.as_ref(),
+            ttl: self.ttl,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Cloudflare endpoint response for Delete DNS Record
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct DNSRecordDeleteResponse {
+    /// Identifier of the deleted record
+    pub id: String,
+}
+
+/// Cloudflare endpoint response for Import DNS Records
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct DNSRecordImportResponse {
+    /// Number of DNS records added
+    pub recs_added: u32,
+    /// Total number of DNS records parsed
+    pub total_records_parsed: u32,
 }
 
 /// Extra Cloudflare-specific information about the record.
 #[derive(Debug, Deserialize, Serialize, Clone, TS)]
 #[ts(export)]
-struct Meta {
+pub struct Meta {
     /// Whether the record was automatically added by Cloudflare during initial setup
-    auto_added: Option<bool>,
+    pub auto_added: Option<bool>,
     /// The source of the record. This is typically the name of the service that the record was imported from.
-    source: Option<String>,
+    pub source: Option<String>,
 }
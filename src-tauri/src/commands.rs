@@ -1,20 +1,46 @@
 //! This module contains the Tauri commands that are exposed to the JavaScript side of the application.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
-use tauri::State;
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime, State};
 
 use crate::{
     api,
-    cloudflare::{CloudflareListZonesResponse, DNSRecord},
-    models::{CustomUserDetails, ManagedCache},
+    audit::{self, DnsResolver, ZoneAudit},
+    client::CloudflareClient,
+    cloudflare::{
+        CloudflareListZonesResponse, Credential, DNSRecord, DNSRecordImportResponse,
+        DNSRecordPatch, DNSRecordRequest,
+    },
+    credentials::{CredentialStore, SecretStorage},
+    ddns::{self, Ddns, DdnsSettings, DynamicRecord, IpSource},
+    desired::{DesiredState, DesiredStatePlan},
+    drift::{self, ZoneDrift},
+    email_auth::{self, EmailPosture, GeneratedRecord, MailRecordOptions},
+    error::{AppError, ErrorCategory, FieldError},
+    journal::{self, Journal, JournalEntry},
+    models::{
+        CacheStatus, CustomUserDetails, InitProgress, ManagedCache, ProfileCache, ProfileSummary,
+        ZoneDnsLoaded,
+    },
+    plan::{self, find_record, ChangePlan, ChangeResult, PlanOptions, Writer},
+    profiles::{validate_name, Profiles},
+    propagation::{self, Propagation, PropagationReport, PropagationSettings},
+    snapshots::{changes_between, SnapshotStore, SnapshotSummary, ZoneSnapshot},
+    spf::{self, CachedLookup, DnsLookup, SpfReport},
+    validation::{validate_record, ZoneContext},
+    zonefile::{self, ImportPreview},
 };
 
-/// Set the api_token
+/// Set the credentials used for every request to Cloudflare: either an API token or the
+/// global API key and its email address.
 ///
 /// # Errors
 ///
-/// This will return an error if the token is invalid.
+/// This will return an [`AppError`] if the credentials are invalid or a request to Cloudflare fails.
 ///
 /// It will also error if there's an issue accessing the cache.
 ///
@@ -24,103 +50,500 @@ use crate::{
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
 pub async fn set_api_token(
-    token: String,
+    credential: Credential,
     managed_cache: State<'_, ManagedCache>,
-) -> Result<(), ()> {
-    // Clear the cache
-    #[allow(clippy::unwrap_used)]
-    {
-        managed_cache.zones.lock().unwrap().clear();
-        managed_cache.zone_dns.lock().unwrap().clear();
-        *managed_cache.user_details.lock().unwrap() = None;
-        *managed_cache.api_token.lock().unwrap() = String::default();
+    client: State<'_, CloudflareClient>,
+) -> Result<(), AppError> {
+    use_credential(&credential, &managed_cache, &client).await
+}
+
+/// Save credentials for the active profile, so they can be restored with [`load_credentials`] the next time
+/// the app starts. The profile is added to the profile list if it wasn't there yet.
+///
+/// They are kept in the OS keyring, or in an encrypted file in the app data directory if no keyring is available.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the credentials or the profile list can't be saved.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn save_credentials(
+    credential: Credential,
+    storage: State<'_, SecretStorage>,
+    profiles: State<'_, Profiles>,
+) -> Result<(), AppError> {
+    let name = profiles.active();
+    storage.save(&name, &credential)?;
+    profiles.add(&name, ProfileCache::default())
+}
+
+/// Restore the saved credentials of the active profile and use them, as [`set_api_token`] does.
+/// The credentials themselves are never sent back to the frontend.
+///
+/// Returns `false` if no credentials are saved.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the saved credentials can't be read, are invalid, or a request to
+/// Cloudflare fails.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn load_credentials(
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    storage: State<'_, SecretStorage>,
+    profiles: State<'_, Profiles>,
+) -> Result<bool, AppError> {
+    let Some(credential) = storage.load(&profiles.active())? else {
+        return Ok(false);
+    };
+    use_credential(&credential, &managed_cache, &client).await?;
+    Ok(true)
+}
+
+/// Delete the saved credentials of the active profile and remove it from the profile list, then stop using
+/// the current credentials and clear the cache.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the saved credentials can't be deleted.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn forget_credentials(
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    storage: State<'_, SecretStorage>,
+    profiles: State<'_, Profiles>,
+) -> Result<(), AppError> {
+    client.clear_authorizer();
+    clear_cache(&managed_cache);
+    let name = profiles.active();
+    storage.forget(&name)?;
+    match profiles.remove(&name, &managed_cache) {
+        Ok(_) => Ok(()),
+        // The credentials were never saved, so the profile isn't listed.
+        Err(e) if e.category == ErrorCategory::NotFound => Ok(()),
+        Err(e) => Err(e),
     }
-    // Set the token
-    #[allow(clippy::unwrap_used)]
-    {
-        *managed_cache.api_token.lock().unwrap() = token.clone();
+}
+
+/// List the saved profiles.
+///
+/// # Errors
+///
+/// This doesn't fail, but returns a `Result` like every other command.
+///
+/// # Panics
+///
+/// This will panic if the profile list or the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn list_profiles(
+    managed_cache: State<'_, ManagedCache>,
+    profiles: State<'_, Profiles>,
+) -> Result<Vec<ProfileSummary>, AppError> {
+    Ok(profiles.summaries(&managed_cache))
+}
+
+/// Add a profile, or replace the credentials of an existing one. The credentials are checked with Cloudflare
+/// and saved, but the active profile doesn't change; use [`switch_profile`] for that.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the name is empty, the credentials are invalid, a request to Cloudflare
+/// fails, or the credentials or profile list can't be saved.
+///
+/// # Panics
+///
+/// This will panic if the profile list or the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn add_profile(
+    name: String,
+    credential: Credential,
+    client: State<'_, CloudflareClient>,
+    storage: State<'_, SecretStorage>,
+    profiles: State<'_, Profiles>,
+) -> Result<ProfileSummary, AppError> {
+    let name = validate_name(&name)?;
+    let user_details = api::check_api_key(&client.with_authorizer(credential.authorizer())).await?;
+    storage.save(name, &credential)?;
+    let email = user_details.email.clone();
+    profiles.add(
+        name,
+        ProfileCache {
+            user_details: Some(user_details),
+            ..ProfileCache::default()
+        },
+    )?;
+    Ok(ProfileSummary {
+        name: name.to_string(),
+        active: profiles.active() == name,
+        email: Some(email),
+        zone_count: 0,
+    })
+}
+
+/// Remove a profile and delete its saved credentials. If it was the active profile, its credentials stop
+/// being used and the cache is cleared.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if there is no such profile or it can't be deleted.
+///
+/// # Panics
+///
+/// This will panic if the profile list or the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn remove_profile(
+    name: String,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    storage: State<'_, SecretStorage>,
+    profiles: State<'_, Profiles>,
+) -> Result<(), AppError> {
+    if profiles.remove(&name, &managed_cache)? {
+        client.clear_authorizer();
     }
+    storage.forget(&name)
+}
 
-    // Check the token is valid
-    let user_details = api::check_api_key(&token).await;
-    if let Ok(user_details) = user_details {
-        // Update the user details and re-lock the cache
-        #[allow(clippy::unwrap_used)]
-        {
-            *managed_cache.user_details.lock().unwrap() = Some(user_details);
-        }
+/// Switch to another profile, using its saved credentials from now on.
+///
+/// The cache of the profile being left is kept, and the cache of the profile being switched to is put back.
+/// Returns `false` if the profile hasn't been loaded yet, in which case [`initialize_cf`] needs to be called.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if there is no such profile or its credentials can't be loaded.
+///
+/// # Panics
+///
+/// This will panic if the profile list or the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn switch_profile(
+    name: String,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    storage: State<'_, SecretStorage>,
+    profiles: State<'_, Profiles>,
+) -> Result<bool, AppError> {
+    let credential = storage
+        .load(&name)?
+        .ok_or_else(AppError::missing_credentials)?;
+    let cached = profiles.switch(&name, &managed_cache)?;
+    // The previous authorizer is dropped, which wipes its secret from memory.
+    client.set_authorizer(credential.authorizer());
+    Ok(cached)
+}
+
+/// Clear the cache, then use the credentials for every following request and check they are valid.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the credentials are invalid or a request to Cloudflare fails.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+async fn use_credential(
+    credential: &Credential,
+    managed_cache: &ManagedCache,
+    client: &CloudflareClient,
+) -> Result<(), AppError> {
+    clear_cache(managed_cache);
+    // Set the credentials. The previous authorizer is dropped, which wipes its secret from memory.
+    client.set_authorizer(credential.authorizer());
+
+    // Check the credentials are valid
+    let user_details = api::check_api_key(client).await?;
+    // Update the user details and re-lock the cache
+    #[allow(clippy::unwrap_used)]
+    {
+        *managed_cache.user_details.lock().unwrap() = Some(user_details);
     }
     Ok(())
 }
 
-/// Initialize the Cloudflare API with the provided token.
-/// This will clear the cache and set the token, for use in subsequent requests.
+/// Empty the cached user details, zones and DNS records.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+fn clear_cache(managed_cache: &ManagedCache) {
+    managed_cache.restore(ProfileCache::default());
+}
+
+/// Initialize the Cloudflare API with the credentials set by [`set_api_token`].
+///
+/// This will fetch the user details, zones and DNS records, replace the cache with them once everything
+/// has loaded, and save the cache to disk. See [`refresh`] for the events emitted along the way.
 ///
 /// # Errors
 ///
-/// This will return an error if the token is invalid.
+/// This will return an [`AppError`] if the token is invalid or the user details or zones can't be fetched.
+/// The cache is left as it was. Zones whose DNS records fail to load don't stop the others; their errors are
+/// in the [`CacheStatus`].
 ///
-/// It will also error if there's an issue accessing the cache.
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn initialize_cf<R: Runtime>(
+    app: AppHandle<R>,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    profiles: State<'_, Profiles>,
+) -> Result<bool, AppError> {
+    refresh(&app, &managed_cache, &client, &profiles).await?;
+    Ok(true)
+}
+
+/// Refresh the cache in the background, e.g. after showing the cache loaded from disk at startup.
+///
+/// Returns straight away. When the refresh finishes, a `cache-refreshed` event is emitted with the time the
+/// data was fetched, or a `cache-refresh-failed` event with the [`AppError`].
+///
+/// # Errors
+///
+/// This doesn't fail, but returns a `Result` like every other command.
+#[tauri::command]
+pub async fn refresh_cache<R: Runtime>(app: AppHandle<R>) -> Result<(), AppError> {
+    spawn_refresh(app);
+    Ok(())
+}
+
+/// Get when the cached data was fetched from Cloudflare.
+///
+/// # Errors
+///
+/// This doesn't fail, but returns a `Result` like every other command.
 ///
 /// # Panics
 ///
 /// This will panic if the cache is poisoned.
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-pub async fn initialize_cf(managed_cache: State<'_, ManagedCache>) -> Result<bool, ()> {
-    // Clear the cache
-    #[allow(clippy::unwrap_used)]
-    {
-        managed_cache.zones.lock().unwrap().clear();
-        managed_cache.zone_dns.lock().unwrap().clear();
-        *managed_cache.user_details.lock().unwrap() = None;
-    }
-    // Attempt to set the token
+pub async fn get_cache_status(
+    managed_cache: State<'_, ManagedCache>,
+) -> Result<CacheStatus, AppError> {
     #[allow(clippy::unwrap_used)]
-    let new_token = managed_cache.api_token.lock().unwrap().clone();
-    // Check the token is valid
-    let user_details = api::check_api_key(&new_token).await;
-    if let Ok(user_details) = user_details {
-        // Update the user details and re-lock the cache
-        #[allow(clippy::unwrap_used)]
-        {
-            *managed_cache.user_details.lock().unwrap() = Some(user_details);
+    Ok(CacheStatus {
+        fetched_at: *managed_cache.fetched_at.lock().unwrap(),
+        zone_fetched_at: managed_cache.zone_fetched_at.lock().unwrap().clone(),
+        zone_errors: managed_cache.zone_errors.lock().unwrap().clone(),
+    })
+}
+
+/// Start refreshing the cache on the async runtime, emitting `cache-refreshed` or `cache-refresh-failed`
+/// when done. See [`refresh_cache`].
+pub fn spawn_refresh<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let result = refresh(&app, &app.state(), &app.state(), &app.state()).await;
+        match result {
+            Ok(fetched_at) => emit(&app, "cache-refreshed", fetched_at),
+            Err(e) => {
+                tracing::warn!("Background refresh failed: {e}");
+                emit(&app, "cache-refresh-failed", e);
+            }
         }
-        // Get the zones and DNS records
-        let zones = match api::get_zones(&new_token).await {
-            Ok(zones) => {
-                if zones.success {
-                    zones.result
-                } else {
-                    return Err(());
+    });
+}
+
+/// Fetch the user details, zones and every zone's DNS records for the active profile, then store them all at
+/// once, so the cache keeps showing the previous data until the new data is complete.
+///
+/// Zones are fetched concurrently, up to [`CloudflareClient::max_concurrent_requests`] at a time. As each
+/// finishes, a `zone-dns-loaded` event ([`ZoneDnsLoaded`]) and an `init-progress` event ([`InitProgress`])
+/// are emitted. A zone that fails to load keeps its previously cached records, and the error is recorded
+/// in the cache's `zone_errors`.
+///
+/// Returns when the zones were fetched.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the credentials are invalid or the user details or zones can't be fetched.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+async fn refresh<R: Runtime>(
+    app: &AppHandle<R>,
+    managed_cache: &ManagedCache,
+    client: &CloudflareClient,
+    profiles: &Profiles,
+) -> Result<DateTime<Utc>, AppError> {
+    let profile = profiles.active();
+    let writes = managed_cache.zone_writes();
+    // Taken before anything is awaited, so it is the profile's own even if another is switched to meanwhile.
+    let previous = managed_cache.snapshot();
+    // Check the token is valid
+    let user_details = api::check_api_key(client).await?;
+    // Get the zones and DNS records
+    let fetched_at = Utc::now();
+    let zones = api::get_zones(client).await?;
+
+    let mut progress = InitProgress {
+        loaded: 0,
+        failed: 0,
+        total: zones.len(),
+    };
+    emit(app, "init-progress", progress);
+    let mut zone_dns = HashMap::new();
+    let mut zone_fetched_at = HashMap::new();
+    let mut zone_errors = HashMap::new();
+    let zone_ids: Vec<String> = zones.iter().map(|zone| zone.id.clone()).collect();
+    let mut results = stream::iter(zone_ids)
+        .map(|zone_id| async move {
+            let result = api::get_zone_dns(client, zone_id.clone()).await;
+            (zone_id, Utc::now(), result)
+        })
+        .buffer_unordered(client.max_concurrent_requests());
+    while let Some((zone_id, at, result)) = results.next().await {
+        match result {
+            Ok(records) => {
+                progress.loaded += 1;
+                emit(
+                    app,
+                    "zone-dns-loaded",
+                    ZoneDnsLoaded {
+                        zone_id: zone_id.clone(),
+                        records: records.clone(),
+                        error: None,
+                    },
+                );
+                zone_fetched_at.insert(zone_id.clone(), at);
+                zone_dns.insert(zone_id, records);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load the DNS records of zone {zone_id}: {e}");
+                progress.failed += 1;
+                emit(
+                    app,
+                    "zone-dns-loaded",
+                    ZoneDnsLoaded {
+                        zone_id: zone_id.clone(),
+                        records: Vec::new(),
+                        error: Some(e.clone()),
+                    },
+                );
+                // Keep showing what was cached before, along with when it was fetched.
+                if let Some(records) = previous.zone_dns.get(&zone_id) {
+                    zone_dns.insert(zone_id.clone(), records.clone());
+                }
+                if let Some(at) = previous.zone_fetched_at.get(&zone_id) {
+                    zone_fetched_at.insert(zone_id.clone(), *at);
                 }
+                zone_errors.insert(zone_id, e);
             }
-            Err(()) => return Err(()),
-        };
-        // Update the cache with the zone details
-        #[allow(clippy::unwrap_used)]
-        {
-            *managed_cache.zones.lock().unwrap() = zones.clone();
         }
-        let zone_ids: Vec<String> = zones.iter().map(|z| z.id.clone()).collect();
-
-        {
-            for zone_id in &zone_ids {
-                let Ok(dns_records) = api::get_zone_dns(&new_token, zone_id.clone()).await else {
-                    return Err(());
-                };
-                if !dns_records.success {
-                    return Err(());
-                }
-                let dns_records = dns_records.result;
-                #[allow(clippy::unwrap_used)]
-                let mut zone_dns = managed_cache.zone_dns.lock().unwrap();
+        emit(app, "init-progress", progress);
+    }
+    drop(results);
+
+    profiles.store_refresh(
+        &profile,
+        ProfileCache {
+            zones,
+            zone_dns,
+            user_details: Some(user_details),
+            fetched_at: Some(fetched_at),
+            zone_fetched_at,
+            zone_errors,
+        },
+        &writes,
+        managed_cache,
+    );
+    Ok(fetched_at)
+}
+
+/// Check the active profile's zones for changes made outside the app, e.g. in the Cloudflare dashboard.
+///
+/// The cache is brought up to date, and a `zone-drift` event ([`ZoneDrift`]) is emitted for each zone whose
+/// records changed. Returns the zones that drifted. The same check runs in the background every
+/// [`drift::CHECK_INTERVAL`].
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zones can't be listed.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn check_drift<R: Runtime>(
+    app: AppHandle<R>,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    profiles: State<'_, Profiles>,
+) -> Result<Vec<ZoneDrift>, AppError> {
+    detect_drift(&app, &managed_cache, &client, &profiles).await
+}
 
-                zone_dns.insert(zone_id.clone(), dns_records);
+/// Start checking for drift on the async runtime every [`drift::CHECK_INTERVAL`]. See [`check_drift`].
+pub fn spawn_drift_watch<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(drift::CHECK_INTERVAL).await;
+            if let Err(e) = detect_drift(&app, &app.state(), &app.state(), &app.state()).await {
+                tracing::warn!("Drift check failed: {e}");
             }
         }
+    });
+}
+
+/// Check for drift and store what was found, unless the profile was switched meanwhile. See [`check_drift`].
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zones can't be listed.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+async fn detect_drift<R: Runtime>(
+    app: &AppHandle<R>,
+    managed_cache: &ManagedCache,
+    client: &CloudflareClient,
+    profiles: &Profiles,
+) -> Result<Vec<ZoneDrift>, AppError> {
+    let profile = profiles.active();
+    // Taken first, so a write made while the snapshot is copied counts as made during the check.
+    let writes = managed_cache.zone_writes();
+    let cache = managed_cache.snapshot();
+    // Nothing has been loaded yet, so there is nothing to drift from.
+    if cache.zones.is_empty() {
+        return Ok(Vec::new());
+    }
+    let found = drift::check(client, &cache).await?;
+    let Some(drifted) = profiles.while_active(&profile, || found.store(managed_cache, &writes))
+    else {
+        return Ok(Vec::new());
+    };
+    profiles.persist(&profile, managed_cache);
+    for drift in &drifted {
+        emit(app, "zone-drift", drift.clone());
+    }
+    Ok(drifted)
+}
+
+/// Emit an event to the frontend, logging rather than failing if it can't be sent.
+fn emit<R: Runtime, S: Serialize + Clone>(app: &AppHandle<R>, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        tracing::error!("Failed to emit {event}: {e}");
     }
-    Ok(true)
 }
 
 /// Check if the API key was valid.
@@ -128,7 +551,7 @@ pub async fn initialize_cf(managed_cache: State<'_, ManagedCache>) -> Result<boo
 ///
 /// # Errors
 ///
-/// This will return an error if the token is invalid.
+/// This will return an [`AppError`] if the token is invalid or a request to Cloudflare fails.
 ///
 /// It will also error if there's an issue accessing the cache.
 ///
@@ -139,18 +562,21 @@ pub async fn initialize_cf(managed_cache: State<'_, ManagedCache>) -> Result<boo
 #[allow(clippy::needless_pass_by_value)]
 pub async fn get_user_details(
     managed_cache: State<'_, ManagedCache>,
-) -> Result<CustomUserDetails, ()> {
+) -> Result<CustomUserDetails, AppError> {
     #[allow(clippy::unwrap_used)]
     (*managed_cache.user_details.lock().unwrap())
         .as_ref()
-        .map_or(Err(()), |user_details| Ok(user_details.clone()))
+        .map_or_else(
+            || Err(AppError::missing_credentials()),
+            |user_details| Ok(user_details.clone()),
+        )
 }
 
 /// Get the zones for the current user. This is pulled from the cache.
 ///
 /// # Errors
 ///
-/// This will return an error if the token is invalid.
+/// This will return an [`AppError`] if the token is invalid or a request to Cloudflare fails.
 ///
 /// It will also error if there's an issue accessing the cache.
 ///
@@ -161,18 +587,18 @@ pub async fn get_user_details(
 #[allow(clippy::needless_pass_by_value)]
 pub async fn get_zones(
     managed_cache: State<'_, ManagedCache>,
-) -> Result<Vec<CloudflareListZonesResponse>, ()> {
+) -> Result<Vec<CloudflareListZonesResponse>, AppError> {
     if let Ok(zones) = managed_cache.zones.lock() {
         return Ok(zones.clone());
     }
-    Err(())
+    Err(AppError::cache())
 }
 
 /// Get the DNS records for a zone. This is pulled from the cache.
 ///
 /// # Errors
 ///
-/// This will return an error if the token is invalid.
+/// This will return an [`AppError`] if the token is invalid or a request to Cloudflare fails.
 ///
 /// It will also error if there's an issue accessing the cache.
 ///
@@ -183,9 +609,827 @@ pub async fn get_zones(
 #[allow(clippy::needless_pass_by_value)]
 pub async fn get_zone_dns(
     managed_cache: State<'_, ManagedCache>,
-) -> Result<HashMap<String, Vec<DNSRecord>>, ()> {
+) -> Result<HashMap<String, Vec<DNSRecord>>, AppError> {
     if let Ok(zone_dns) = managed_cache.zone_dns.lock() {
         return Ok(zone_dns.clone());
     }
-    Err(())
+    Err(AppError::cache())
+}
+
+/// Check a record against the cached zone without sending it, returning the problem with each field.
+/// Pass `record_id` when checking a change to an existing record.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone or the record isn't cached.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn validate_dns_record(
+    zone_id: String,
+    record: DNSRecordRequest,
+    record_id: Option<String>,
+    managed_cache: State<'_, ManagedCache>,
+) -> Result<Vec<FieldError>, AppError> {
+    let (zone_name, records) = managed_cache.zone(&zone_id)?;
+    let existing = record_id.map(|id| find_record(&records, &id)).transpose()?;
+    let zone = ZoneContext {
+        zone_name: &zone_name,
+        records: &records,
+    };
+    Ok(validate_record(&record, &zone, existing))
+}
+
+/// Export a cached zone's records as an RFC 1035 zone file, written to `path`.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone isn't cached or the file can't be written.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn export_zone_file(
+    zone_id: String,
+    path: String,
+    managed_cache: State<'_, ManagedCache>,
+) -> Result<(), AppError> {
+    let (zone_name, records) = managed_cache.zone(&zone_id)?;
+    std::fs::write(path, zonefile::render(&zone_name, &records, Utc::now()))?;
+    Ok(())
+}
+
+/// Read the zone file at `path` and work out the changes that would make the cached zone match it.
+/// Nothing is sent to Cloudflare; pass the plan to [`apply_zone_plan`] to make the changes.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone isn't cached, or the file can't be read or parsed.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn preview_zone_import(
+    zone_id: String,
+    path: String,
+    proxied_default: bool,
+    options: PlanOptions,
+    managed_cache: State<'_, ManagedCache>,
+) -> Result<ImportPreview, AppError> {
+    let (zone_name, records) = managed_cache.zone(&zone_id)?;
+    let parsed = zonefile::parse(&std::fs::read_to_string(path)?, &zone_name, proxied_default)?;
+    let zone = ZoneContext {
+        zone_name: &zone_name,
+        records: &records,
+    };
+    Ok(ImportPreview {
+        plan: plan::diff(&zone, &parsed.records, options),
+        skipped: parsed.skipped,
+    })
+}
+
+/// Apply the changes of a plan to a zone, in order. Each change is checked against the cache first, and one
+/// that fails doesn't stop the rest; the result of every change is returned.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone isn't cached.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn apply_zone_plan(
+    zone_id: String,
+    plan: ChangePlan,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    profiles: State<'_, Profiles>,
+) -> Result<Vec<ChangeResult>, AppError> {
+    managed_cache.zone(&zone_id)?;
+    let writer = Writer::new(&client, &managed_cache, &journal, &profiles);
+    let results = writer.apply(&zone_id, plan).await;
+    profiles.persist(&writer.profile, &managed_cache);
+    Ok(results)
+}
+
+/// Read the desired state file at `path` and work out the changes that would make its zone match it.
+/// Nothing is sent to Cloudflare; pass the plan to [`apply_zone_plan`] to make the changes.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the file can't be read, or its zone isn't cached.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn plan_desired_state(
+    path: String,
+    managed_cache: State<'_, ManagedCache>,
+) -> Result<DesiredStatePlan, AppError> {
+    let desired = DesiredState::load(Path::new(&path))?;
+    let zone_id = managed_cache.zone_id(&desired.zone)?;
+    let (zone_name, records) = managed_cache.zone(&zone_id)?;
+    Ok(DesiredStatePlan {
+        plan: desired.plan(&zone_name, &records),
+        zone_id,
+        zone_name,
+    })
+}
+
+/// Read the desired state file at `path`, work out the changes that make its zone match it, and apply them
+/// straight away. The result of every change is returned.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the file can't be read, or its zone isn't cached.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn apply_desired_state(
+    path: String,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    profiles: State<'_, Profiles>,
+) -> Result<Vec<ChangeResult>, AppError> {
+    let desired = DesiredState::load(Path::new(&path))?;
+    let zone_id = managed_cache.zone_id(&desired.zone)?;
+    let (zone_name, records) = managed_cache.zone(&zone_id)?;
+    let writer = Writer::new(&client, &managed_cache, &journal, &profiles);
+    let results = writer
+        .apply(&zone_id, desired.plan(&zone_name, &records))
+        .await;
+    profiles.persist(&writer.profile, &managed_cache);
+    Ok(results)
+}
+
+/// Upload the zone file at `path` to Cloudflare's own importer, which adds the records that don't exist yet.
+///
+/// `proxied` is used for proxiable records without a `cf-proxied` tag. The zone's records are fetched before
+/// and after the import; the records that weren't there before are journaled as created, and the cache is
+/// replaced with the records afterwards.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone isn't cached, the file can't be read, or Cloudflare rejects it.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn import_zone_file_via_cloudflare(
+    zone_id: String,
+    path: String,
+    proxied: bool,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    profiles: State<'_, Profiles>,
+) -> Result<DNSRecordImportResponse, AppError> {
+    let profile = profiles.active();
+    let (zone_name, _) = managed_cache.zone(&zone_id)?;
+    let zone_file = std::fs::read_to_string(path)?;
+    // The cache may be missing records made elsewhere, which mustn't be journaled as imported.
+    let existing = api::get_zone_dns(&client, zone_id.clone()).await?;
+    let imported = api::import_dns_records(&client, &zone_id, zone_file, proxied)
+        .await?
+        .result;
+
+    let records = api::get_zone_dns(&client, zone_id.clone()).await?;
+    for record in records
+        .iter()
+        .filter(|record| !existing.iter().any(|r| r.id == record.id))
+    {
+        let entry = JournalEntry::new(&profile, &zone_id, &zone_name, None, Some(record.clone()));
+        if let Err(e) = journal.append(&entry) {
+            tracing::warn!("Failed to record an imported record in the journal: {e}");
+        }
+    }
+    profiles.while_active(&profile, || {
+        managed_cache.put_written_records(&zone_id, records);
+    });
+    profiles.persist(&profile, &managed_cache);
+    Ok(imported)
+}
+
+/// Create a DNS record in a zone. On success the new record is added to the cached records for the zone.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the record fails validation, the request fails or Cloudflare rejects
+/// the record.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn create_dns_record(
+    zone_id: String,
+    record: DNSRecordRequest,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    profiles: State<'_, Profiles>,
+) -> Result<DNSRecord, AppError> {
+    let writer = Writer::new(&client, &managed_cache, &journal, &profiles);
+    let created = writer.create_record(&zone_id, &record).await?;
+    profiles.persist(&writer.profile, &managed_cache);
+    Ok(created)
+}
+
+/// Overwrite a DNS record (PUT). On success the record is replaced in the cached records for the zone.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the record isn't cached or fails validation, the request fails or
+/// Cloudflare rejects the record.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn update_dns_record(
+    zone_id: String,
+    record_id: String,
+    record: DNSRecordRequest,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    profiles: State<'_, Profiles>,
+) -> Result<DNSRecord, AppError> {
+    let writer = Writer::new(&client, &managed_cache, &journal, &profiles);
+    let updated = writer.update_record(&zone_id, &record_id, &record).await?;
+    profiles.persist(&writer.profile, &managed_cache);
+    Ok(updated)
+}
+
+/// Partially update a DNS record (PATCH). On success the record is replaced in the cached records for the zone.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the record isn't cached, the patched record fails validation, the
+/// request fails or Cloudflare rejects the change.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn patch_dns_record(
+    zone_id: String,
+    record_id: String,
+    patch: DNSRecordPatch,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    profiles: State<'_, Profiles>,
+) -> Result<DNSRecord, AppError> {
+    let writer = Writer::new(&client, &managed_cache, &journal, &profiles);
+    let updated = writer.patch_record(&zone_id, &record_id, &patch).await?;
+    profiles.persist(&writer.profile, &managed_cache);
+    Ok(updated)
+}
+
+/// Delete a DNS record. On success the record is removed from the cached records for the zone.
+///
+/// Returns the identifier of the deleted record.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the record isn't cached or is locked, the request fails or Cloudflare
+/// refuses the deletion.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn delete_dns_record(
+    zone_id: String,
+    record_id: String,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    profiles: State<'_, Profiles>,
+) -> Result<String, AppError> {
+    let writer = Writer::new(&client, &managed_cache, &journal, &profiles);
+    let deleted = writer.delete_record(&zone_id, &record_id).await?;
+    profiles.persist(&writer.profile, &managed_cache);
+    Ok(deleted)
+}
+
+/// List the changes made through the app to a zone, or to one of its records, newest first.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the journal can't be read.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn list_history(
+    zone_id: String,
+    record_id: Option<String>,
+    journal: State<'_, Journal>,
+) -> Result<Vec<JournalEntry>, AppError> {
+    journal.history(&zone_id, record_id.as_deref())
+}
+
+/// Undo a change from the history by making the opposite change, which is journaled in turn. Returns the
+/// record as it is afterwards, unless the undo deleted it.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if there is no such change, it was made with another profile, it has
+/// already been undone, the record has been changed since, or the request fails.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn undo_change(
+    entry_id: String,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    profiles: State<'_, Profiles>,
+) -> Result<Option<DNSRecord>, AppError> {
+    let entry = journal.find(&entry_id)?;
+    let mut writer = Writer::new(&client, &managed_cache, &journal, &profiles);
+    if entry.profile != writer.profile {
+        return Err(AppError::new(
+            ErrorCategory::Validation,
+            format!(
+                "This change was made with the profile \"{}\"; switch to it to undo the change",
+                entry.profile
+            ),
+        ));
+    }
+    writer.undoes = Some(entry_id);
+    let record = journal::undo(&writer, &entry).await?;
+    profiles.persist(&writer.profile, &managed_cache);
+    Ok(record)
+}
+
+/// Fetch a zone's records from Cloudflare and save them as a named snapshot. The zone's cached records are
+/// replaced with the fetched ones too.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone isn't cached, the name is empty, the request fails, or the
+/// snapshot can't be saved.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn take_snapshot(
+    zone_id: String,
+    name: String,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    snapshots: State<'_, SnapshotStore>,
+    profiles: State<'_, Profiles>,
+) -> Result<SnapshotSummary, AppError> {
+    let profile = profiles.active();
+    let (zone_name, _) = managed_cache.zone(&zone_id)?;
+    let records = api::get_zone_dns(&client, zone_id.clone()).await?;
+    profiles.while_active(&profile, || {
+        managed_cache.put_zone_records(&zone_id, records.clone());
+    });
+    profiles.persist(&profile, &managed_cache);
+
+    let snapshot = ZoneSnapshot::new(&name, &profile, &zone_id, &zone_name, records)?;
+    snapshots.save(&snapshot)?;
+    Ok(snapshot.summary())
+}
+
+/// List the saved snapshots, of one zone or of every zone, newest first.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the snapshots directory can't be read.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn list_snapshots(
+    zone_id: Option<String>,
+    snapshots: State<'_, SnapshotStore>,
+) -> Result<Vec<SnapshotSummary>, AppError> {
+    snapshots.list(zone_id.as_deref())
+}
+
+/// Delete a saved snapshot.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if there is no such snapshot or it can't be deleted.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn delete_snapshot(
+    snapshot_id: String,
+    snapshots: State<'_, SnapshotStore>,
+) -> Result<(), AppError> {
+    snapshots.remove(&snapshot_id)
+}
+
+/// Compare a snapshot with a later snapshot of the same zone, or with the zone's live records if `to_id`
+/// isn't given. Returns the changes that were made between the two.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if a snapshot doesn't exist, the snapshots are of different zones, or the
+/// live records can't be fetched.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn compare_snapshots(
+    from_id: String,
+    to_id: Option<String>,
+    client: State<'_, CloudflareClient>,
+    snapshots: State<'_, SnapshotStore>,
+) -> Result<ChangePlan, AppError> {
+    let from = snapshots.load(&from_id)?;
+    let to = match to_id {
+        Some(to_id) => {
+            let to = snapshots.load(&to_id)?;
+            if to.zone_id != from.zone_id {
+                return Err(AppError::new(
+                    ErrorCategory::Validation,
+                    format!(
+                        "Snapshot {} is of {}, not {}",
+                        to.name, to.zone_name, from.zone_name
+                    ),
+                ));
+            }
+            to.records
+        }
+        None => api::get_zone_dns(&client, from.zone_id.clone()).await?,
+    };
+    Ok(changes_between(&from.zone_name, &from.records, &to))
+}
+
+/// Work out the fewest changes that return a zone to a snapshot, from its live records.
+///
+/// Nothing is changed on Cloudflare; pass the plan to [`apply_zone_plan`] to restore the snapshot. The zone's
+/// cached records are replaced with the live ones, so the plan is checked against them when it's applied.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if there is no such snapshot or the live records can't be fetched.
+///
+/// # Panics
+///
+/// This will panic if the cache is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn plan_snapshot_restore(
+    snapshot_id: String,
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    snapshots: State<'_, SnapshotStore>,
+    profiles: State<'_, Profiles>,
+) -> Result<ChangePlan, AppError> {
+    let profile = profiles.active();
+    let snapshot = snapshots.load(&snapshot_id)?;
+    let live = api::get_zone_dns(&client, snapshot.zone_id.clone()).await?;
+    let plan = snapshot.restore_plan(&live);
+    profiles.while_active(&profile, || {
+        managed_cache.put_zone_records(&snapshot.zone_id, live);
+    });
+    profiles.persist(&profile, &managed_cache);
+    Ok(plan)
+}
+
+/// Get the DDNS settings, with the dynamic records and how their last updates went.
+///
+/// # Errors
+///
+/// This doesn't fail, but returns a `Result` like every other command.
+///
+/// # Panics
+///
+/// This will panic if the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn get_ddns_settings(ddns: State<'_, Ddns>) -> Result<DdnsSettings, AppError> {
+    Ok(ddns.settings())
+}
+
+/// Set where the public address is detected and how many minutes apart dynamic records are checked.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the interval is zero or the settings can't be saved.
+///
+/// # Panics
+///
+/// This will panic if the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn set_ddns_source(
+    source: IpSource,
+    interval_minutes: u32,
+    ddns: State<'_, Ddns>,
+) -> Result<DdnsSettings, AppError> {
+    ddns.set_source(source, interval_minutes)
+}
+
+/// Mark an A or AAAA record as dynamic, so it follows the public address, or stop it following it.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the record isn't cached or isn't an A or AAAA record, or the settings
+/// can't be saved.
+///
+/// # Panics
+///
+/// This will panic if the cache or the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn set_record_dynamic(
+    zone_id: String,
+    record_id: String,
+    dynamic: bool,
+    managed_cache: State<'_, ManagedCache>,
+    ddns: State<'_, Ddns>,
+    profiles: State<'_, Profiles>,
+) -> Result<DdnsSettings, AppError> {
+    let (_, records) = managed_cache.zone(&zone_id)?;
+    let record = find_record(&records, &record_id)?;
+    ddns.set_dynamic(&profiles.active(), &zone_id, record, dynamic)
+}
+
+/// Update every dynamic record of the active profile now, whether or not it is due. Returns every dynamic
+/// record with how its last update went.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the settings can't be saved. Failures of single records are in their
+/// status instead.
+///
+/// # Panics
+///
+/// This will panic if the cache or the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn run_ddns(
+    managed_cache: State<'_, ManagedCache>,
+    client: State<'_, CloudflareClient>,
+    journal: State<'_, Journal>,
+    ddns: State<'_, Ddns>,
+    profiles: State<'_, Profiles>,
+) -> Result<Vec<DynamicRecord>, AppError> {
+    let records =
+        update_dynamic_records(&managed_cache, &client, &journal, &ddns, &profiles, true).await?;
+    Ok(records.unwrap_or_else(|| ddns.settings().records))
+}
+
+/// Start updating the dynamic records on the async runtime as they fall due, emitting a `ddns-updated` event
+/// with every dynamic record and its status after each run that changed a status.
+pub fn spawn_ddns<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(ddns::TICK).await;
+            let result = update_dynamic_records(
+                &app.state(),
+                &app.state(),
+                &app.state(),
+                &app.state(),
+                &app.state(),
+                false,
+            )
+            .await;
+            match result {
+                Ok(Some(records)) => emit(&app, "ddns-updated", records),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to update the dynamic records: {e}"),
+            }
+        }
+    });
+}
+
+/// Update the active profile's dynamic records that are due, or all of them if `force` is set, and save the
+/// cache if any of them changed. Returns the records as [`ddns::run`] does.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the settings can't be saved.
+///
+/// # Panics
+///
+/// This will panic if the cache or the settings lock is poisoned.
+async fn update_dynamic_records(
+    managed_cache: &ManagedCache,
+    client: &CloudflareClient,
+    journal: &Journal,
+    ddns: &Ddns,
+    profiles: &Profiles,
+    force: bool,
+) -> Result<Option<Vec<DynamicRecord>>, AppError> {
+    let started = Utc::now();
+    let detector = ddns.detector()?;
+    let writer = Writer::new(client, managed_cache, journal, profiles);
+    let records = ddns::run(&writer, detector.as_ref(), ddns, force).await?;
+    if records
+        .iter()
+        .flatten()
+        .any(|r| r.status.last_changed.is_some_and(|at| at >= started))
+    {
+        profiles.persist(&writer.profile, managed_cache);
+    }
+    Ok(records)
+}
+
+/// Get the recursive resolvers propagation checks ask.
+///
+/// # Errors
+///
+/// This doesn't fail, but returns a `Result` like every other command.
+///
+/// # Panics
+///
+/// This will panic if the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn get_resolvers(
+    propagation: State<'_, Propagation>,
+) -> Result<PropagationSettings, AppError> {
+    Ok(propagation.settings())
+}
+
+/// Set the recursive resolvers propagation checks ask.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if a resolver's address can't be read, or the settings can't be saved.
+///
+/// # Panics
+///
+/// This will panic if the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn set_resolvers(
+    settings: PropagationSettings,
+    propagation: State<'_, Propagation>,
+) -> Result<PropagationSettings, AppError> {
+    propagation.set(settings)
+}
+
+/// Ask the zone's Cloudflare name servers and the recursive resolvers for a cached record, and report which
+/// of them answer with it.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone or the record isn't cached.
+///
+/// # Panics
+///
+/// This will panic if the cache or the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn check_propagation(
+    zone_id: String,
+    record_id: String,
+    managed_cache: State<'_, ManagedCache>,
+    propagation: State<'_, Propagation>,
+) -> Result<PropagationReport, AppError> {
+    let (_, records) = managed_cache.zone(&zone_id)?;
+    let record = find_record(&records, &record_id)?;
+    #[allow(clippy::unwrap_used)]
+    let zone = managed_cache
+        .zones
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|zone| zone.id == zone_id)
+        .cloned()
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCategory::NotFound,
+                format!("Zone {zone_id} isn't loaded"),
+            )
+        })?;
+    propagation::check(&propagation.settings(), &zone, record).await
+}
+
+/// Audit the CNAME, NS and MX records of every cached zone for targets that are gone.
+///
+/// The targets are looked up through the recursive resolvers, and the records pointing at names that don't
+/// exist or could be claimed by someone else are reported.
+///
+/// # Errors
+///
+/// This doesn't return errors: a target that can't be looked up is reported as a finding.
+///
+/// # Panics
+///
+/// This will panic if the cache or the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn audit_zones(
+    managed_cache: State<'_, ManagedCache>,
+    propagation: State<'_, Propagation>,
+) -> Result<Vec<ZoneAudit>, AppError> {
+    let resolver = DnsResolver::new(&propagation.settings());
+    Ok(audit::audit(&resolver, &managed_cache).await)
+}
+
+/// Follow the SPF record at a zone's apex, or at `name` in the zone, counting the DNS lookups receivers make
+/// to evaluate it.
+///
+/// Names in cached zones are answered from the cache, and others are looked up through the recursive
+/// resolvers. The report includes a flattened record that takes no lookups for the zone's includes.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone isn't cached, or `name` isn't in it.
+///
+/// # Panics
+///
+/// This will panic if the cache or the settings lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn analyze_spf(
+    zone_id: String,
+    name: Option<String>,
+    managed_cache: State<'_, ManagedCache>,
+    propagation: State<'_, Propagation>,
+) -> Result<SpfReport, AppError> {
+    let (zone_name, records) = managed_cache.zone(&zone_id)?;
+    let context = ZoneContext {
+        zone_name: &zone_name,
+        records: &records,
+    };
+    let domain = match name {
+        Some(name) => context.qualify(&name).ok_or_else(|| {
+            AppError::validation(vec![FieldError::new(
+                "name",
+                format!("{name} isn't in {zone_name}"),
+            )])
+        })?,
+        None => zone_name.clone(),
+    };
+    let fallback = DnsLookup::new(&propagation.settings());
+    let lookup = CachedLookup {
+        cache: &managed_cache,
+        fallback: &fallback,
+    };
+    Ok(spf::analyze(&lookup, &domain).await)
+}
+
+/// Check a zone's SPF, DMARC, DKIM, MTA-STS, TLS-RPT and BIMI records, and score how well its mail is
+/// protected.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone isn't cached.
+///
+/// # Panics
+///
+/// This will panic if the cache lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn check_email_auth(
+    zone_id: String,
+    managed_cache: State<'_, ManagedCache>,
+) -> Result<EmailPosture, AppError> {
+    let (zone_name, records) = managed_cache.zone(&zone_id)?;
+    Ok(email_auth::check_zone(&zone_id, &zone_name, &records))
+}
+
+/// Write a mail authentication record for a zone from `options`, ready to create with [`create_dns_record`].
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zone isn't cached, or an option is invalid.
+///
+/// # Panics
+///
+/// This will panic if the cache lock is poisoned.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn generate_email_record(
+    zone_id: String,
+    options: MailRecordOptions,
+    managed_cache: State<'_, ManagedCache>,
+) -> Result<GeneratedRecord, AppError> {
+    let (zone_name, records) = managed_cache.zone(&zone_id)?;
+    email_auth::generate(&zone_name, &records, &options)
 }
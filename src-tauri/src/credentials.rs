@@ -0,0 +1,391 @@
+//! Persistent storage for Cloudflare credentials.
+//!
+//! Credentials are kept in the operating system's secret store: the Secret Service (GNOME Keyring, `KWallet`)
+//! on Linux, the Keychain on macOS and the Credential Manager on Windows. When no secret store can be reached,
+//! as on a headless machine or in CI, they are kept in a file in the app data directory instead, encrypted
+//! with ChaCha20-Poly1305 under a key that is stored next to it and readable only by the current user.
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng},
+    AeadCore, ChaCha20Poly1305, Key, Nonce,
+};
+use zeroize::Zeroizing;
+
+use crate::{
+    cloudflare::Credential,
+    error::{AppError, ErrorCategory},
+};
+
+/// The service name credentials are stored under in the OS secret store (the app identifier).
+pub const KEYRING_SERVICE: &str = "one.nwest.apps.cloudflare-dns-gui";
+
+/// The account the credentials are stored under, and the name of the profile in use, until another profile is chosen.
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Set this environment variable to `file` to skip the OS secret store and always use the encrypted file.
+pub const CREDENTIAL_STORE_ENV: &str = "CLOUDFLARE_DNS_GUI_CREDENTIAL_STORE";
+
+/// The file the encryption key for [`EncryptedFileStore`] is kept in.
+const KEY_FILE: &str = "credentials.key";
+
+/// The file [`EncryptedFileStore`] keeps the encrypted credentials in.
+const DATA_FILE: &str = "credentials.enc";
+
+/// The length of a ChaCha20-Poly1305 nonce, which is stored in front of the ciphertext.
+const NONCE_LENGTH: usize = 12;
+
+/// Somewhere credentials can be saved between runs of the app.
+pub trait CredentialStore: fmt::Debug + Send + Sync {
+    /// Save the credentials for `account`, replacing any saved before.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the store can't be written.
+    fn save(&self, account: &str, credential: &Credential) -> Result<(), AppError>;
+
+    /// Load the credentials saved for `account`, if there are any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the store can't be read.
+    fn load(&self, account: &str) -> Result<Option<Credential>, AppError>;
+
+    /// Delete the credentials saved for `account`. Does nothing if there are none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the store can't be written.
+    fn forget(&self, account: &str) -> Result<(), AppError>;
+}
+
+/// Create an error for a failure to access a credential store.
+fn storage_error(context: &str, error: impl fmt::Display) -> AppError {
+    AppError::new(
+        ErrorCategory::CredentialStorage,
+        format!("{context}: {error}"),
+    )
+}
+
+/// Credentials stored in the OS secret store, as JSON.
+#[derive(Debug)]
+pub struct KeyringStore {
+    /// The service name the entries are stored under.
+    service: String,
+}
+
+impl KeyringStore {
+    /// A store keeping entries under the given service name.
+    #[must_use]
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    /// Save the credentials, keeping the keyring error so callers can tell whether the store is reachable.
+    fn try_save(&self, account: &str, credential: &Credential) -> Result<(), keyring::Error> {
+        let secret = Zeroizing::new(
+            serde_json::to_string(credential)
+                .map_err(|e| keyring::Error::PlatformFailure(Box::new(e)))?,
+        );
+        keyring::Entry::new(&self.service, account)?.set_password(&secret)
+    }
+
+    /// Load the credentials, keeping the keyring error so callers can tell whether the store is reachable.
+    fn try_load(&self, account: &str) -> Result<Option<Credential>, keyring::Error> {
+        match keyring::Entry::new(&self.service, account)?.get_password() {
+            Ok(secret) => {
+                let secret = Zeroizing::new(secret);
+                serde_json::from_str(&secret)
+                    .map(Some)
+                    .map_err(|e| keyring::Error::BadEncoding(e.to_string().into_bytes()))
+            }
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Delete the credentials, keeping the keyring error so callers can tell whether the store is reachable.
+    fn try_forget(&self, account: &str) -> Result<(), keyring::Error> {
+        match keyring::Entry::new(&self.service, account)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl CredentialStore for KeyringStore {
+    fn save(&self, account: &str, credential: &Credential) -> Result<(), AppError> {
+        self.try_save(account, credential)
+            .map_err(|e| storage_error("Failed to save credentials to the keyring", e))
+    }
+
+    fn load(&self, account: &str) -> Result<Option<Credential>, AppError> {
+        self.try_load(account)
+            .map_err(|e| storage_error("Failed to load credentials from the keyring", e))
+    }
+
+    fn forget(&self, account: &str) -> Result<(), AppError> {
+        self.try_forget(account)
+            .map_err(|e| storage_error("Failed to delete credentials from the keyring", e))
+    }
+}
+
+/// Credentials stored in an encrypted file, for when there is no OS secret store.
+///
+/// All accounts are kept together as a JSON map, encrypted with ChaCha20-Poly1305. The file holds the
+/// random nonce followed by the ciphertext. The key is generated on first use and kept in a separate
+/// file that only the current user can read.
+#[derive(Debug)]
+pub struct EncryptedFileStore {
+    /// The directory the key and data files are kept in.
+    dir: PathBuf,
+    /// Held while the files are read and written, so concurrent changes don't overwrite each other.
+    lock: Mutex<()>,
+}
+
+impl EncryptedFileStore {
+    /// A store keeping its files in `dir`, which is created when first written to.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Load the encryption key, generating and saving a new one if there is none yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the key file can't be read or written, or holds a key of the wrong length.
+    fn cipher(&self) -> Result<ChaCha20Poly1305, AppError> {
+        let key_path = self.dir.join(KEY_FILE);
+        if key_path.exists() {
+            let key = Zeroizing::new(
+                fs::read(&key_path)
+                    .map_err(|e| storage_error("Failed to read the credentials key", e))?,
+            );
+            if key.len() != 32 {
+                return Err(storage_error(
+                    "Failed to read the credentials key",
+                    format!("expected 32 bytes, found {}", key.len()),
+                ));
+            }
+            return Ok(ChaCha20Poly1305::new(Key::from_slice(&key)));
+        }
+
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| storage_error("Failed to create the credentials directory", e))?;
+        write_private(&key_path, &key)
+            .map_err(|e| storage_error("Failed to save the credentials key", e))?;
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    /// Decrypt every saved account.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file can't be read or decrypted.
+    fn read_all(&self) -> Result<HashMap<String, Credential>, AppError> {
+        let data_path = self.dir.join(DATA_FILE);
+        if !data_path.exists() {
+            return Ok(HashMap::new());
+        }
+        let data =
+            fs::read(&data_path).map_err(|e| storage_error("Failed to read credentials", e))?;
+        if data.len() < NONCE_LENGTH {
+            return Err(storage_error(
+                "Failed to read credentials",
+                "the file is truncated",
+            ));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+        let plaintext = Zeroizing::new(
+            self.cipher()?
+                .decrypt(Nonce::from_slice(nonce), ciphertext)
+                .map_err(|e| storage_error("Failed to decrypt credentials", e))?,
+        );
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| storage_error("Failed to read credentials", e))
+    }
+
+    /// Encrypt and save every account, replacing the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file can't be encrypted or written.
+    fn write_all(&self, credentials: &HashMap<String, Credential>) -> Result<(), AppError> {
+        let cipher = self.cipher()?;
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(credentials)
+                .map_err(|e| storage_error("Failed to save credentials", e))?,
+        );
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|e| storage_error("Failed to encrypt credentials", e))?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        // A file of its own, so another process saving at the same time can't write into it.
+        let temp_path = self
+            .dir
+            .join(format!("{DATA_FILE}.{:016x}.tmp", rand::random::<u64>()));
+        write_private(&temp_path, &data)
+            .and_then(|()| fs::rename(&temp_path, self.dir.join(DATA_FILE)))
+            .map_err(|e| {
+                let _ = fs::remove_file(&temp_path);
+                storage_error("Failed to save credentials", e)
+            })
+    }
+}
+
+impl CredentialStore for EncryptedFileStore {
+    fn save(&self, account: &str, credential: &Credential) -> Result<(), AppError> {
+        #[allow(clippy::unwrap_used)]
+        let _guard = self.lock.lock().unwrap();
+        let mut credentials = self.read_all()?;
+        credentials.insert(account.to_string(), credential.clone());
+        self.write_all(&credentials)
+    }
+
+    fn load(&self, account: &str) -> Result<Option<Credential>, AppError> {
+        #[allow(clippy::unwrap_used)]
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.read_all()?.remove(account))
+    }
+
+    fn forget(&self, account: &str) -> Result<(), AppError> {
+        #[allow(clippy::unwrap_used)]
+        let _guard = self.lock.lock().unwrap();
+        let mut credentials = self.read_all()?;
+        if credentials.remove(account).is_some() {
+            self.write_all(&credentials)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a file that only the current user can read.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/// Whether a keyring error means there is no usable secret store (rather than a problem with one entry).
+const fn keyring_unavailable(error: &keyring::Error) -> bool {
+    matches!(
+        error,
+        keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_)
+    )
+}
+
+/// The credential store used by the app: the OS secret store, falling back to an encrypted file when
+/// the secret store can't be reached. This is held in Tauri managed state.
+#[derive(Debug)]
+pub struct SecretStorage {
+    /// The OS secret store, unless [`CREDENTIAL_STORE_ENV`] turned it off.
+    keyring: Option<KeyringStore>,
+    /// The encrypted file in the app data directory.
+    file: EncryptedFileStore,
+}
+
+impl SecretStorage {
+    /// Storage using the OS secret store, with the encrypted fallback file kept in `data_dir`.
+    ///
+    /// If [`CREDENTIAL_STORE_ENV`] is set to `file`, only the encrypted file is used.
+    #[must_use]
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        let file_only = std::env::var(CREDENTIAL_STORE_ENV).is_ok_and(|store| store == "file");
+        Self {
+            keyring: (!file_only).then(|| KeyringStore::new(KEYRING_SERVICE)),
+            file: EncryptedFileStore::new(data_dir),
+        }
+    }
+
+    /// Storage that only uses the encrypted file kept in `data_dir`.
+    #[must_use]
+    pub fn file_only(data_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            keyring: None,
+            file: EncryptedFileStore::new(data_dir),
+        }
+    }
+
+    /// Log that the secret store is unavailable and the encrypted file is used instead.
+    fn warn_fallback(error: &keyring::Error) {
+        tracing::warn!(
+            "OS secret store unavailable, using the encrypted credentials file: {error}"
+        );
+    }
+}
+
+impl CredentialStore for SecretStorage {
+    fn save(&self, account: &str, credential: &Credential) -> Result<(), AppError> {
+        if let Some(keyring) = &self.keyring {
+            match keyring.try_save(account, credential) {
+                Ok(()) => return Ok(()),
+                Err(e) if keyring_unavailable(&e) => Self::warn_fallback(&e),
+                Err(e) => {
+                    return Err(storage_error(
+                        "Failed to save credentials to the keyring",
+                        e,
+                    ))
+                }
+            }
+        }
+        self.file.save(account, credential)
+    }
+
+    fn load(&self, account: &str) -> Result<Option<Credential>, AppError> {
+        if let Some(keyring) = &self.keyring {
+            match keyring.try_load(account) {
+                Ok(Some(credential)) => return Ok(Some(credential)),
+                Ok(None) => {}
+                Err(e) if keyring_unavailable(&e) => Self::warn_fallback(&e),
+                Err(e) => {
+                    return Err(storage_error(
+                        "Failed to load credentials from the keyring",
+                        e,
+                    ))
+                }
+            }
+        }
+        self.file.load(account)
+    }
+
+    fn forget(&self, account: &str) -> Result<(), AppError> {
+        if let Some(keyring) = &self.keyring {
+            match keyring.try_forget(account) {
+                Ok(()) => {}
+                Err(e) if keyring_unavailable(&e) => Self::warn_fallback(&e),
+                Err(e) => {
+                    return Err(storage_error(
+                        "Failed to delete credentials from the keyring",
+                        e,
+                    ))
+                }
+            }
+        }
+        // Also clear the fallback file, in case the credentials were saved there while the keyring was unavailable.
+        self.file.forget(account)
+    }
+}
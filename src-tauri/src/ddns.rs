@@ -0,0 +1,548 @@
+//! Dynamic DNS: keeping A and AAAA records pointed at the public address of this machine's network.
+//!
+//! Records are marked as dynamic in [`DdnsSettings`], saved in `ddns.json` in the app data directory along
+//! with how each record's last update went. On a schedule, [`run`] detects the current addresses with an
+//! [`IpDetector`] and patches the dynamic records whose content differs. A record whose update fails is
+//! retried with a growing backoff instead of on every run.
+//!
+//! Records are updated with the credentials of the profile in use, so the records of other profiles are
+//! paused until their profile is switched to.
+
+use std::{
+    fmt, fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+use ts_rs::TS;
+
+use crate::{
+    cloudflare::{DNSRecord, DNSRecordPatch},
+    error::{AppError, ErrorCategory, FieldError},
+    plan::{find_record, Writer},
+    records::DNSContent,
+};
+
+/// The file the DDNS settings are saved in.
+const DDNS_FILE: &str = "ddns.json";
+
+/// How often the background updater looks for records that are due.
+pub const TICK: Duration = Duration::from_secs(60);
+
+/// How long to wait before retrying a record whose update failed once. The wait doubles with each failure in
+/// a row, up to [`MAX_FAILURE_BACKOFF`].
+const FAILURE_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The longest wait before retrying a record whose updates keep failing.
+const MAX_FAILURE_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// An IP version.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum IpFamily {
+    /// IPv4, for A records.
+    V4,
+    /// IPv6, for AAAA records.
+    V6,
+}
+
+impl IpFamily {
+    /// The family of the address an A or AAAA record points at, or `None` for other record types.
+    #[must_use]
+    pub const fn of(content: &DNSContent) -> Option<Self> {
+        match content {
+            DNSContent::A { .. } => Some(Self::V4),
+            DNSContent::Aaaa { .. } => Some(Self::V6),
+            _ => None,
+        }
+    }
+
+    /// Whether `address` is of this family.
+    #[must_use]
+    pub const fn matches(self, address: &IpAddr) -> bool {
+        matches!(
+            (self, address),
+            (Self::V4, IpAddr::V4(_)) | (Self::V6, IpAddr::V6(_))
+        )
+    }
+}
+
+/// Something that can find out the current public address.
+pub trait IpDetector: fmt::Debug + Send + Sync {
+    /// Detect the current address of `family`.
+    fn detect(&self, family: IpFamily) -> BoxFuture<'_, Result<IpAddr, AppError>>;
+}
+
+/// Where the public address is detected. Saved with the DDNS settings.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IpSource {
+    /// Ask an echo service, which responds with the address the request came from as plain text.
+    Echo {
+        /// The URL of the service to ask for the IPv4 address.
+        ipv4_url: String,
+        /// The URL of the service to ask for the IPv6 address. It should only be reachable over IPv6.
+        ipv6_url: String,
+    },
+    /// Use the address of the local interface that routes to the internet. Only useful if this machine has a
+    /// public address of its own, rather than being behind NAT.
+    Interface,
+}
+
+impl Default for IpSource {
+    fn default() -> Self {
+        Self::Echo {
+            ipv4_url: "https://api.ipify.org".to_string(),
+            ipv6_url: "https://api6.ipify.org".to_string(),
+        }
+    }
+}
+
+impl IpSource {
+    /// The detector for this source.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the HTTP client can't be created.
+    pub fn detector(&self) -> Result<Box<dyn IpDetector>, AppError> {
+        Ok(match self {
+            Self::Echo { ipv4_url, ipv6_url } => Box::new(EchoDetector {
+                http: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(10))
+                    .build()?,
+                ipv4_url: ipv4_url.clone(),
+                ipv6_url: ipv6_url.clone(),
+            }),
+            Self::Interface => Box::new(InterfaceDetector),
+        })
+    }
+}
+
+/// Detects the public address by asking an echo service.
+#[derive(Debug)]
+pub struct EchoDetector {
+    /// The client the services are asked with.
+    http: reqwest::Client,
+    /// The service to ask for the IPv4 address.
+    ipv4_url: String,
+    /// The service to ask for the IPv6 address.
+    ipv6_url: String,
+}
+
+impl IpDetector for EchoDetector {
+    fn detect(&self, family: IpFamily) -> BoxFuture<'_, Result<IpAddr, AppError>> {
+        Box::pin(async move {
+            let url = match family {
+                IpFamily::V4 => &self.ipv4_url,
+                IpFamily::V6 => &self.ipv6_url,
+            };
+            let text = self
+                .http
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let address: IpAddr = text.trim().parse().map_err(|_| {
+                AppError::new(
+                    ErrorCategory::Parse,
+                    format!("{url} didn't respond with an IP address"),
+                )
+            })?;
+            if !family.matches(&address) {
+                return Err(AppError::new(
+                    ErrorCategory::Parse,
+                    format!("{url} responded with {address}, which is the wrong IP version"),
+                ));
+            }
+            Ok(address)
+        })
+    }
+}
+
+/// Detects the address of the local interface that routes to the internet.
+///
+/// Connecting a UDP socket only picks the route; nothing is sent.
+#[derive(Debug)]
+pub struct InterfaceDetector;
+
+impl IpDetector for InterfaceDetector {
+    fn detect(&self, family: IpFamily) -> BoxFuture<'_, Result<IpAddr, AppError>> {
+        Box::pin(async move {
+            // Cloudflare's public resolvers stand in for "the internet".
+            let (bind, remote): (IpAddr, IpAddr) = match family {
+                IpFamily::V4 => (
+                    Ipv4Addr::UNSPECIFIED.into(),
+                    Ipv4Addr::new(1, 1, 1, 1).into(),
+                ),
+                IpFamily::V6 => (
+                    Ipv6Addr::UNSPECIFIED.into(),
+                    Ipv6Addr::new(0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111).into(),
+                ),
+            };
+            let socket = UdpSocket::bind((bind, 0)).await?;
+            socket.connect((remote, 53)).await?;
+            Ok(socket.local_addr()?.ip())
+        })
+    }
+}
+
+/// How the updates of a dynamic record have gone.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, TS)]
+#[ts(export)]
+pub struct DdnsStatus {
+    /// When the record was last checked.
+    pub last_run: Option<DateTime<Utc>>,
+    /// The address the record was last found or set to point at.
+    pub address: Option<IpAddr>,
+    /// When the record was last changed to a new address.
+    pub last_changed: Option<DateTime<Utc>>,
+    /// Why the last check failed, if it did.
+    pub error: Option<AppError>,
+    /// How many checks in a row have failed.
+    pub failures: u32,
+    /// When the record is next due to be checked.
+    pub next_run: Option<DateTime<Utc>>,
+    /// Whether the record is waiting for its profile to be switched to. It is checked as soon as it is.
+    #[serde(default)]
+    pub paused: bool,
+}
+
+/// A record kept pointed at the current public address.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct DynamicRecord {
+    /// The profile whose credentials update the record.
+    pub profile: String,
+    /// The zone identifier.
+    pub zone_id: String,
+    /// Identifier for the record.
+    pub record_id: String,
+    /// The record name, for display.
+    pub name: String,
+    /// Which address the record points at.
+    pub family: IpFamily,
+    /// How the updates have gone.
+    #[serde(default)]
+    pub status: DdnsStatus,
+}
+
+/// The DDNS settings: where addresses come from, how often they're checked, and which records follow them.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct DdnsSettings {
+    /// Where the public address is detected.
+    #[serde(default)]
+    pub source: IpSource,
+    /// How many minutes apart each record is checked.
+    pub interval_minutes: u32,
+    /// The dynamic records.
+    #[serde(default)]
+    pub records: Vec<DynamicRecord>,
+}
+
+impl Default for DdnsSettings {
+    fn default() -> Self {
+        Self {
+            source: IpSource::default(),
+            interval_minutes: 5,
+            records: Vec::new(),
+        }
+    }
+}
+
+/// The DDNS settings file in the app data directory. This is held in Tauri managed state.
+#[derive(Debug)]
+pub struct Ddns {
+    /// Where the settings are saved.
+    path: PathBuf,
+    /// The settings, as last saved.
+    settings: Mutex<DdnsSettings>,
+    /// The detector for the source in the settings, kept so its HTTP client is reused between runs.
+    detector: Mutex<Option<(IpSource, Arc<dyn IpDetector>)>>,
+}
+
+impl Ddns {
+    /// The settings saved in `data_dir`. A missing or unreadable file gives the default settings.
+    #[must_use]
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(DDNS_FILE);
+        let settings = fs::read(&path)
+            .ok()
+            .and_then(|data| {
+                serde_json::from_slice(&data)
+                    .map_err(|e| tracing::warn!("Ignoring unreadable DDNS settings: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            settings: Mutex::new(settings),
+            detector: Mutex::new(None),
+        }
+    }
+
+    /// The detector for the source in the settings. It is only created again when the source changes.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the HTTP client can't be created.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the settings or detector lock is poisoned.
+    pub fn detector(&self) -> Result<Arc<dyn IpDetector>, AppError> {
+        let source = self.settings().source;
+        #[allow(clippy::unwrap_used)]
+        let mut detector = self.detector.lock().unwrap();
+        if let Some((cached, detector)) = detector.as_ref() {
+            if *cached == source {
+                return Ok(Arc::clone(detector));
+            }
+        }
+        let created: Arc<dyn IpDetector> = Arc::from(source.detector()?);
+        *detector = Some((source, Arc::clone(&created)));
+        drop(detector);
+        Ok(created)
+    }
+
+    /// A copy of the settings.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the settings lock is poisoned.
+    #[must_use]
+    pub fn settings(&self) -> DdnsSettings {
+        #[allow(clippy::unwrap_used)]
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Change the settings and save them, returning them as they are afterwards. The file is replaced in one
+    /// step, so a crash can't leave half of it behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if `change` fails, or the file can't be written.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the settings lock is poisoned.
+    pub fn update(
+        &self,
+        change: impl FnOnce(&mut DdnsSettings) -> Result<(), AppError>,
+    ) -> Result<DdnsSettings, AppError> {
+        #[allow(clippy::unwrap_used)]
+        let mut settings = self.settings.lock().unwrap();
+        let mut changed = settings.clone();
+        change(&mut changed)?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(&changed)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        settings.clone_from(&changed);
+        drop(settings);
+        Ok(changed)
+    }
+
+    /// Set where addresses are detected and how often records are checked. Every record is due straight away.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the interval is zero, or the file can't be written.
+    pub fn set_source(
+        &self,
+        source: IpSource,
+        interval_minutes: u32,
+    ) -> Result<DdnsSettings, AppError> {
+        if interval_minutes == 0 {
+            return Err(AppError::validation(vec![FieldError::new(
+                "interval_minutes",
+                "The interval must be at least a minute",
+            )]));
+        }
+        self.update(|settings| {
+            settings.source = source;
+            settings.interval_minutes = interval_minutes;
+            for record in &mut settings.records {
+                record.status.next_run = None;
+            }
+            Ok(())
+        })
+    }
+
+    /// Mark a record as dynamic, or stop updating it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the record isn't an A or AAAA record, or the file can't be written.
+    pub fn set_dynamic(
+        &self,
+        profile: &str,
+        zone_id: &str,
+        record: &DNSRecord,
+        dynamic: bool,
+    ) -> Result<DdnsSettings, AppError> {
+        let Some(family) = IpFamily::of(&record.content) else {
+            return Err(AppError::validation(vec![FieldError::new(
+                "type",
+                "Only A and AAAA records can be dynamic",
+            )]));
+        };
+        self.update(|settings| {
+            settings
+                .records
+                .retain(|r| !(r.zone_id == zone_id && r.record_id == record.id));
+            if dynamic {
+                settings.records.push(DynamicRecord {
+                    profile: profile.to_string(),
+                    zone_id: zone_id.to_string(),
+                    record_id: record.id.clone(),
+                    name: record.name.clone(),
+                    family,
+                    status: DdnsStatus::default(),
+                });
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Point one dynamic record at `address`, unless it already does. Returns whether it was changed.
+async fn update_record(
+    writer: &Writer<'_>,
+    record: &DynamicRecord,
+    address: IpAddr,
+) -> Result<bool, AppError> {
+    let (_, records) = writer.zone(&record.zone_id)?;
+    let current = find_record(&records, &record.record_id)?;
+    let content = match address {
+        IpAddr::V4(content) => DNSContent::A { content },
+        IpAddr::V6(content) => DNSContent::Aaaa { content },
+    };
+    if current.content == content {
+        return Ok(false);
+    }
+    let patch = DNSRecordPatch {
+        content: Some(content),
+        ..DNSRecordPatch::default()
+    };
+    writer
+        .patch_record(&record.zone_id, &record.record_id, &patch)
+        .await?;
+    Ok(true)
+}
+
+/// Update the dynamic records of the writer's profile that are due, or all of them if `force` is set, and
+/// record how each went.
+///
+/// The records of other profiles are marked as paused, and are due once their profile is in use again.
+/// Returns every dynamic record with its status, or `None` if no status changed, in which case the settings
+/// aren't saved. Each address family is detected at most once per run, and only if a record needs it.
+///
+/// # Errors
+///
+/// Returns an [`AppError`] if the statuses can't be saved. Failures to detect an address or update a record
+/// are recorded in the record's status instead.
+pub async fn run(
+    writer: &Writer<'_>,
+    detector: &dyn IpDetector,
+    ddns: &Ddns,
+    force: bool,
+) -> Result<Option<Vec<DynamicRecord>>, AppError> {
+    let settings = ddns.settings();
+    let interval = chrono::Duration::minutes(i64::from(settings.interval_minutes));
+    let mut detected: Vec<(IpFamily, Result<IpAddr, AppError>)> = Vec::new();
+    let mut statuses = Vec::new();
+    for record in settings.records {
+        let now = Utc::now();
+        if record.profile != writer.profile {
+            if !record.status.paused {
+                let status = DdnsStatus {
+                    paused: true,
+                    ..record.status
+                };
+                statuses.push((record.zone_id, record.record_id, status));
+            }
+            continue;
+        }
+        let due = record.status.paused || record.status.next_run.is_none_or(|at| at <= now);
+        if !(force || due) {
+            continue;
+        }
+        let address = if let Some((_, address)) = detected.iter().find(|(f, _)| *f == record.family)
+        {
+            address.clone()
+        } else {
+            let address = detector.detect(record.family).await;
+            detected.push((record.family, address.clone()));
+            address
+        };
+
+        let mut status = record.status.clone();
+        status.last_run = Some(now);
+        status.paused = false;
+        let result = match address {
+            Ok(address) => update_record(writer, &record, address)
+                .await
+                .map(|changed| (address, changed)),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok((address, changed)) => {
+                status.address = Some(address);
+                if changed {
+                    status.last_changed = Some(now);
+                }
+                status.error = None;
+                status.failures = 0;
+                status.next_run = Some(now + interval);
+            }
+            Err(e) => fail(&mut status, &record, e, now),
+        }
+        statuses.push((record.zone_id, record.record_id, status));
+    }
+
+    if statuses.is_empty() {
+        return Ok(None);
+    }
+    let settings = ddns.update(|settings| {
+        for (zone_id, record_id, status) in statuses {
+            if let Some(record) = settings
+                .records
+                .iter_mut()
+                .find(|r| r.zone_id == zone_id && r.record_id == record_id)
+            {
+                record.status = status;
+            }
+        }
+        Ok(())
+    })?;
+    Ok(Some(settings.records))
+}
+
+/// Record a failed check in a record's status and back off before the next one.
+fn fail(status: &mut DdnsStatus, record: &DynamicRecord, error: AppError, now: DateTime<Utc>) {
+    tracing::warn!(
+        "Failed to update the dynamic record {}: {error}",
+        record.name
+    );
+    status.failures = status.failures.saturating_add(1);
+    status.error = Some(error);
+    let backoff = FAILURE_BACKOFF
+        .saturating_mul(2_u32.saturating_pow(status.failures - 1))
+        .min(MAX_FAILURE_BACKOFF);
+    status.next_run = Some(
+        now + chrono::Duration::from_std(backoff).unwrap_or_else(|_| chrono::Duration::hours(1)),
+    );
+}
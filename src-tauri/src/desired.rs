@@ -0,0 +1,323 @@
+//! Desired state files: the records a zone should have, kept alongside the rest of a project in git.
+//!
+//! A file names its zone, says which of the zone's records it owns, and lists the records the zone should
+//! have. [`DesiredState::plan`] compares it with the cached zone and produces a [`ChangePlan`], which is
+//! applied like any other plan. Files are YAML, or JSON if the file name ends in `.json`:
+//!
+//! ```yaml
+//! zone: example.com
+//! ownership:
+//!   mode: tagged
+//!   tag: managed-by:cfdns
+//! ignore:
+//!   - _acme-challenge.*
+//! defaults:
+//!   ttl: 3600
+//! records:
+//!   - name: "@"
+//!     type: A
+//!     content: 198.51.100.4
+//!     proxied: true
+//!   - name: www
+//!     type: CNAME
+//!     content: example.com
+//!   - name: "@"
+//!     type: MX
+//!     content: mail.example.com
+//!     priority: 10
+//! ```
+//!
+//! Record names are relative to the zone unless they end with a dot or already end with the zone's name.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cloudflare::{DNSRecord, DNSRecordRequest},
+    error::{AppError, FieldError},
+    plan::{diff, Change, ChangePlan, ConflictResolution, PlanOptions},
+    records::DNSContent,
+    validation::{ZoneContext, AUTO_TTL},
+};
+
+/// Which of the zone's records a desired state file owns.
+///
+/// Records that aren't in the file are deleted if the file owns them, and left alone if it doesn't. Records
+/// in the file are always created or updated.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default, TS)]
+#[ts(export)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Ownership {
+    /// The file owns only the records it lists, so nothing is ever deleted.
+    #[default]
+    Listed,
+    /// The file owns the records with `tag`. The tag is added to every record in the file.
+    Tagged {
+        /// The tag marking the records the file owns, e.g. `managed-by:cfdns`.
+        tag: String,
+    },
+    /// The file owns every record in the zone, so the zone ends up with exactly the records in it.
+    All,
+}
+
+/// Settings for the records in a desired state file that don't give their own.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RecordDefaults {
+    /// The TTL, automatic if not set.
+    pub ttl: Option<u32>,
+    /// Whether proxiable records are proxied. If not set, proxying isn't compared, and Cloudflare's default
+    /// applies when a record is created or overwritten.
+    pub proxied: Option<bool>,
+}
+
+/// One record in a desired state file.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DesiredRecord {
+    /// The record name, relative to the zone. `@` is the zone apex.
+    pub name: String,
+    /// The type and content of the record.
+    #[serde(flatten)]
+    pub content: DNSContent,
+    /// Whether the record is proxied through Cloudflare.
+    pub proxied: Option<bool>,
+    /// Time to live, in seconds. 1 is automatic.
+    pub ttl: Option<u32>,
+    /// A comment on the record.
+    pub comment: Option<String>,
+    /// Tags on the record.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// The contents of a desired state file.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DesiredState {
+    /// The name of the zone, e.g. `example.com`.
+    pub zone: String,
+    /// Which of the zone's records the file owns.
+    #[serde(default)]
+    pub ownership: Ownership,
+    /// Names of records that are never touched, even if they are in the file, fully qualified. `*` matches
+    /// any run of characters, so `_acme-challenge.*` covers every ACME challenge in the zone.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Settings for records that don't give their own.
+    #[serde(default)]
+    pub defaults: RecordDefaults,
+    /// The records the zone should have.
+    #[serde(default)]
+    pub records: Vec<DesiredRecord>,
+}
+
+/// The plan for a desired state file, with the zone it applies to.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct DesiredStatePlan {
+    /// The ID of the zone.
+    pub zone_id: String,
+    /// The name of the zone.
+    pub zone_name: String,
+    /// The changes that make the zone match the file.
+    pub plan: ChangePlan,
+}
+
+impl DesiredState {
+    /// Read a desired state file: JSON if the name ends in `.json`, YAML otherwise.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the file can't be read or doesn't describe a desired state.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let text = std::fs::read_to_string(path)?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+        {
+            Self::from_json(&text)
+        } else {
+            Self::from_yaml(&text)
+        }
+    }
+
+    /// Read a desired state from YAML.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] pointing at the line that doesn't describe a desired state.
+    pub fn from_yaml(text: &str) -> Result<Self, AppError> {
+        serde_yaml::from_str(text).map_err(|e| {
+            let field = e
+                .location()
+                .map_or_else(|| "file".to_string(), |l| format!("line {}", l.line()));
+            AppError::validation(vec![FieldError::new(&field, e.to_string())])
+        })
+    }
+
+    /// Read a desired state from JSON.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] pointing at the line that doesn't describe a desired state.
+    pub fn from_json(text: &str) -> Result<Self, AppError> {
+        serde_json::from_str(text).map_err(|e| {
+            AppError::validation(vec![FieldError::new(
+                &format!("line {}", e.line()),
+                e.to_string(),
+            )])
+        })
+    }
+
+    /// The fully qualified, lowercase name of a record in the file.
+    fn qualify(&self, name: &str) -> String {
+        ZoneContext {
+            zone_name: &self.zone,
+            records: &[],
+        }
+        .qualify_relative(name)
+    }
+
+    /// The records in the file as requests, with the defaults and the ownership tag filled in.
+    #[must_use]
+    pub fn requests(&self) -> Vec<DNSRecordRequest> {
+        self.records
+            .iter()
+            .map(|record| {
+                let mut tags = record.tags.clone();
+                if let Ownership::Tagged { tag } = &self.ownership {
+                    if !tags.contains(tag) {
+                        tags.push(tag.clone());
+                    }
+                }
+                DNSRecordRequest {
+                    content: record.content.clone(),
+                    name: self.qualify(&record.name),
+                    proxied: record.proxied.or_else(|| {
+                        self.defaults
+                            .proxied
+                            .filter(|_| record.content.is_proxiable())
+                    }),
+                    comment: record.comment.clone(),
+                    tags,
+                    ttl: record.ttl.or(self.defaults.ttl).unwrap_or(AUTO_TTL),
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the file owns `record`, so it is deleted if it isn't in the file.
+    #[must_use]
+    pub fn owns(&self, record: &DNSRecord) -> bool {
+        match &self.ownership {
+            Ownership::Listed => false,
+            Ownership::Tagged { tag } => record.tags.contains(tag),
+            Ownership::All => true,
+        }
+    }
+
+    /// Whether `name` (fully qualified) matches one of the `ignore` patterns.
+    #[must_use]
+    pub fn ignores(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        self.ignore.iter().any(|pattern| {
+            glob_matches(
+                pattern
+                    .trim_end_matches('.')
+                    .to_ascii_lowercase()
+                    .as_bytes(),
+                name.as_bytes(),
+            )
+        })
+    }
+
+    /// Work out the changes that make the zone's records match the file.
+    ///
+    /// Records with ignored names are left out on both sides. Records the file doesn't own are never deleted.
+    /// With [`Ownership::Tagged`], a record in the file only takes over an untagged record with the same
+    /// content, so a hand-made record is never overwritten; otherwise they are updated if the file has a
+    /// record with the same name and type.
+    #[must_use]
+    pub fn plan(&self, zone_name: &str, records: &[DNSRecord]) -> ChangePlan {
+        let wanted: Vec<DNSRecordRequest> = self
+            .requests()
+            .into_iter()
+            .filter(|record| !self.ignores(&record.name))
+            .collect();
+        let considered: Vec<&DNSRecord> = records
+            .iter()
+            .filter(|record| !self.ignores(&record.name))
+            .collect();
+        let pool: Vec<DNSRecord> = if matches!(self.ownership, Ownership::Tagged { .. }) {
+            self.adoptable(&considered, &wanted)
+        } else {
+            considered.iter().map(|&record| record.clone()).collect()
+        };
+        let zone = ZoneContext {
+            zone_name,
+            records: &pool,
+        };
+        let mut plan = diff(
+            &zone,
+            &wanted,
+            PlanOptions {
+                on_conflict: ConflictResolution::Overwrite,
+                delete_missing: self.ownership != Ownership::Listed,
+            },
+        );
+        // Unowned records left out of the pool are left alone.
+        plan.kept += considered.len() - pool.len();
+
+        let before = plan.changes.len();
+        plan.changes.retain(|change| match change {
+            Change::Delete { before } => self.owns(before),
+            Change::Create { .. } | Change::Update { .. } => true,
+        });
+        plan.kept += before - plan.changes.len();
+        plan
+    }
+
+    /// The records a tagged file may change: the ones it owns, and for each record in the file with no owned
+    /// record of the same content, one unowned record with that content, which it takes over.
+    fn adoptable(&self, considered: &[&DNSRecord], wanted: &[DNSRecordRequest]) -> Vec<DNSRecord> {
+        let same = |record: &DNSRecord, request: &DNSRecordRequest| {
+            record.content == request.content
+                && record
+                    .name
+                    .trim_end_matches('.')
+                    .eq_ignore_ascii_case(&request.name)
+        };
+        let mut claimed = vec![false; considered.len()];
+        for request in wanted {
+            let owned = considered
+                .iter()
+                .enumerate()
+                .position(|(i, &record)| !claimed[i] && self.owns(record) && same(record, request));
+            let unowned = || {
+                considered.iter().enumerate().position(|(i, &record)| {
+                    !claimed[i] && !self.owns(record) && same(record, request)
+                })
+            };
+            if let Some(i) = owned.or_else(unowned) {
+                claimed[i] = true;
+            }
+        }
+        considered
+            .iter()
+            .zip(claimed)
+            .filter(|&(&record, claimed)| claimed || self.owns(record))
+            .map(|(&record, _)| record.clone())
+            .collect()
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of bytes.
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| glob_matches(rest, &name[skip..])),
+        Some((c, rest)) => name
+            .split_first()
+            .is_some_and(|(n, name)| n == c && glob_matches(rest, name)),
+    }
+}
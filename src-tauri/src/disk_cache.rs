@@ -0,0 +1,139 @@
+//! The on-disk copy of the cache, so the app can show zones and records as soon as it starts.
+//!
+//! Every profile's [`ProfileCache`] is kept in `cache.json` in the app data directory, along with a format
+//! version. A file written by a different version is ignored and replaced on the next save, rather than
+//! migrated: everything in it can be fetched from Cloudflare again.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AppError, models::ProfileCache};
+
+/// The version of the cache file format. Bump this whenever a cached type changes incompatibly.
+pub const CACHE_VERSION: u32 = 2;
+
+/// The file the cache is saved in.
+const CACHE_FILE: &str = "cache.json";
+
+/// The contents of the cache file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CacheFile {
+    /// The format version the file was written with.
+    version: u32,
+    /// Map of profile names to their cached data.
+    profiles: HashMap<String, ProfileCache>,
+}
+
+/// The cache file in the app data directory.
+#[derive(Debug)]
+pub struct DiskCache {
+    /// Where the cache is saved.
+    path: PathBuf,
+    /// Held while the file is read and rewritten, so concurrent saves don't lose each other's changes.
+    lock: Mutex<()>,
+}
+
+impl DiskCache {
+    /// The cache file in `data_dir`.
+    #[must_use]
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(CACHE_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Read the cache file. A missing file, or one from another version, counts as empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file exists but can't be read or parsed.
+    fn read(&self) -> Result<CacheFile, AppError> {
+        if !self.path.exists() {
+            return Ok(CacheFile::default());
+        }
+        let data = fs::read(&self.path)?;
+        let version = serde_json::from_slice::<serde_json::Value>(&data)?
+            .get("version")
+            .and_then(serde_json::Value::as_u64);
+        if version != Some(u64::from(CACHE_VERSION)) {
+            tracing::info!("Ignoring cache file with version {version:?}");
+            return Ok(CacheFile::default());
+        }
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Write the cache file, replacing it in one step so a crash can't leave half a file behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file can't be written.
+    fn write(&self, file: &CacheFile) -> Result<(), AppError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec(file)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+
+    /// Load the cached data of a profile, if there is any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file exists but can't be read or parsed.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the file lock is poisoned.
+    pub fn load(&self, profile: &str) -> Result<Option<ProfileCache>, AppError> {
+        #[allow(clippy::unwrap_used)]
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.read()?.profiles.remove(profile))
+    }
+
+    /// Save the cached data of a profile, replacing what was saved before.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file can't be written.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the file lock is poisoned.
+    pub fn save(&self, profile: &str, cache: ProfileCache) -> Result<(), AppError> {
+        #[allow(clippy::unwrap_used)]
+        let _guard = self.lock.lock().unwrap();
+        // An unreadable file is replaced rather than blocking every later save.
+        let mut file = self.read().unwrap_or_default();
+        file.version = CACHE_VERSION;
+        file.profiles.insert(profile.to_string(), cache);
+        self.write(&file)
+    }
+
+    /// Delete the cached data of a profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file can't be read or written.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the file lock is poisoned.
+    pub fn remove(&self, profile: &str) -> Result<(), AppError> {
+        #[allow(clippy::unwrap_used)]
+        let _guard = self.lock.lock().unwrap();
+        let mut file = self.read()?;
+        if file.profiles.remove(profile).is_some() {
+            self.write(&file)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+//! Noticing changes made to zones outside the app, e.g. in the Cloudflare dashboard or by automation.
+//!
+//! [`check`] lists the zones again and re-fetches the records of the ones that may have changed: those whose
+//! `modified_on` has moved since they were cached, and those whose records haven't been fetched for
+//! [`FULL_CHECK_AGE`], as editing a record doesn't always touch the zone's timestamp. The differences from
+//! the cached records are returned as a [`ZoneDrift`] per zone, and the fetched records then replace them.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    api,
+    client::CloudflareClient,
+    cloudflare::{CloudflareListZonesResponse, DNSRecord},
+    error::AppError,
+    models::{ManagedCache, ProfileCache},
+};
+
+/// How often the background refresher checks for drift.
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// How long a zone's records are trusted without being fetched again, even if the zone's `modified_on` hasn't
+/// moved.
+pub const FULL_CHECK_AGE: Duration = Duration::minutes(30);
+
+/// A record that was changed outside the app.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct RecordChange {
+    /// The record as it was cached.
+    pub before: DNSRecord,
+    /// The record as it is on Cloudflare.
+    pub after: DNSRecord,
+}
+
+/// How a zone's records on Cloudflare differ from the cached ones. This is the payload of the `zone-drift`
+/// event.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ZoneDrift {
+    /// The zone identifier.
+    pub zone_id: String,
+    /// The zone name.
+    pub zone_name: String,
+    /// Records that exist on Cloudflare but weren't cached.
+    pub added: Vec<DNSRecord>,
+    /// Records that were cached but no longer exist on Cloudflare.
+    pub removed: Vec<DNSRecord>,
+    /// Records whose content or settings changed.
+    pub changed: Vec<RecordChange>,
+}
+
+impl ZoneDrift {
+    /// Whether nothing changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare a zone's cached records with its records on Cloudflare, matching them by ID.
+#[must_use]
+pub fn compare(
+    zone_id: &str,
+    zone_name: &str,
+    cached: &[DNSRecord],
+    live: &[DNSRecord],
+) -> ZoneDrift {
+    let cached_by_id: HashMap<&str, &DNSRecord> =
+        cached.iter().map(|r| (r.id.as_str(), r)).collect();
+    let mut drift = ZoneDrift {
+        zone_id: zone_id.to_string(),
+        zone_name: zone_name.to_string(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+    for record in live {
+        match cached_by_id.get(record.id.as_str()) {
+            None => drift.added.push(record.clone()),
+            Some(before) if !before.same_as(record) => drift.changed.push(RecordChange {
+                before: (*before).clone(),
+                after: record.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    drift.removed = cached
+        .iter()
+        .filter(|r| !live.iter().any(|l| l.id == r.id))
+        .cloned()
+        .collect();
+    drift
+}
+
+/// Whether a zone's records need fetching again to find out if they drifted.
+fn needs_check(
+    modified_on: &str,
+    cached_modified_on: Option<&str>,
+    fetched_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> bool {
+    cached_modified_on != Some(modified_on)
+        || fetched_at.is_none_or(|at| now - at >= FULL_CHECK_AGE)
+}
+
+/// What a drift check found: the zones as listed now, the records that were fetched again, and the zones
+/// that drifted.
+#[derive(Debug, Default)]
+pub struct DriftCheck {
+    /// Every zone, as listed now. Zones whose records failed to load keep their cached listing, so they are
+    /// checked again next time.
+    pub zones: Vec<CloudflareListZonesResponse>,
+    /// Map of zone IDs to the records fetched for them.
+    pub zone_dns: HashMap<String, Vec<DNSRecord>>,
+    /// The zones whose records changed outside the app.
+    pub drifted: Vec<ZoneDrift>,
+}
+
+impl DriftCheck {
+    /// Bring the cache up to date with what the check found, and return the zones that drifted. Zones that are
+    /// gone are dropped from it.
+    ///
+    /// Zones the app wrote to since `writes` was taken with [`ManagedCache::zone_writes`] are left as they
+    /// are, since the records fetched for them may predate the write, and their drift is discarded. They
+    /// keep their cached listing, so they are checked again next time.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn store(
+        mut self,
+        managed_cache: &ManagedCache,
+        writes: &HashMap<String, u64>,
+    ) -> Vec<ZoneDrift> {
+        let mut written = Vec::new();
+        for (zone_id, records) in self.zone_dns {
+            if !managed_cache.put_fetched_records(&zone_id, records, writes) {
+                written.push(zone_id);
+            }
+        }
+        self.drifted
+            .retain(|drift| !written.contains(&drift.zone_id));
+        #[allow(clippy::unwrap_used)]
+        let cached_zones = managed_cache.zones.lock().unwrap().clone();
+        for zone in self
+            .zones
+            .iter_mut()
+            .filter(|zone| written.contains(&zone.id))
+        {
+            if let Some(cached) = cached_zones.iter().find(|z| z.id == zone.id) {
+                zone.modified_on.clone_from(&cached.modified_on);
+            }
+        }
+        managed_cache.retain_zones(&self.zones);
+        #[allow(clippy::unwrap_used)]
+        {
+            *managed_cache.zones.lock().unwrap() = self.zones;
+        }
+        self.drifted
+    }
+}
+
+/// Check the zones in `cache` for changes made outside the app. Nothing is stored; see [`DriftCheck::store`].
+///
+/// Zones that are new on Cloudflare are fetched, but don't count as drift. Zones whose records fail to load
+/// are skipped until the next check.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the zones can't be listed.
+pub async fn check(
+    client: &CloudflareClient,
+    cache: &ProfileCache,
+) -> Result<DriftCheck, AppError> {
+    let mut zones = api::get_zones(client).await?;
+    let now = Utc::now();
+    let stale: Vec<(String, String)> = zones
+        .iter()
+        .filter(|zone| {
+            let cached = cache.zones.iter().find(|z| z.id == zone.id);
+            needs_check(
+                &zone.modified_on,
+                cached.map(|z| z.modified_on.as_str()),
+                cache.zone_fetched_at.get(&zone.id).copied(),
+                now,
+            )
+        })
+        .map(|zone| (zone.id.clone(), zone.name.clone()))
+        .collect();
+
+    let mut results = stream::iter(stale)
+        .map(|(zone_id, zone_name)| async move {
+            let result = api::get_zone_dns(client, zone_id.clone()).await;
+            (zone_id, zone_name, result)
+        })
+        .buffer_unordered(client.max_concurrent_requests());
+    let mut found = DriftCheck::default();
+    while let Some((zone_id, zone_name, result)) = results.next().await {
+        match result {
+            Ok(live) => {
+                if let Some(cached) = cache.zone_dns.get(&zone_id) {
+                    let drift = compare(&zone_id, &zone_name, cached, &live);
+                    if !drift.is_empty() {
+                        found.drifted.push(drift);
+                    }
+                }
+                found.zone_dns.insert(zone_id, live);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to check zone {zone_id} for drift: {e}");
+                if let (Some(zone), Some(cached)) = (
+                    zones.iter_mut().find(|z| z.id == zone_id),
+                    cache.zones.iter().find(|z| z.id == zone_id),
+                ) {
+                    zone.modified_on.clone_from(&cached.modified_on);
+                }
+            }
+        }
+    }
+    drop(results);
+    found.zones = zones;
+    Ok(found)
+}
@@ -0,0 +1,1131 @@
+//! Checking and writing the TXT records that authenticate a domain's mail.
+//!
+//! [`check_zone`] reads the SPF, DMARC, DKIM, MTA-STS, TLS-RPT and BIMI records in a cached zone, checks
+//! each against its specification and scores how well the domain's mail is protected. [`generate`] writes
+//! one of those records from structured options, ready to be created through the API.
+//!
+//! Apart from SPF, these records are lists of `tag=value` pairs separated by semicolons, with the version
+//! tag first.
+
+use std::fmt::Write as _;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cloudflare::{DNSRecord, DNSRecordRequest},
+    error::{AppError, FieldError},
+    records::DNSContent,
+    spf::{is_spf, Mechanism, Qualifier, SpfRecord, Term},
+    validation::{txt_chunks, ZoneContext},
+    zonefile::quote_txt,
+};
+
+/// The longest a single TXT character string may be, in bytes.
+const MAX_CHARACTER_STRING: usize = 255;
+
+/// RSA public keys that decode to fewer bytes than this hold keys shorter than 2048 bits.
+const MIN_RSA_KEY_BYTES: usize = 256;
+
+/// The length of an Ed25519 public key, in bytes.
+const ED25519_KEY_BYTES: usize = 32;
+
+/// A kind of mail authentication record.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum MailRecordKind {
+    /// The addresses allowed to send the domain's mail, at the domain itself.
+    Spf,
+    /// What receivers should do with mail that fails SPF and DKIM, at `_dmarc`.
+    Dmarc,
+    /// A key signing the domain's mail, at `<selector>._domainkey`.
+    Dkim,
+    /// That the domain's mail servers require TLS, at `_mta-sts`.
+    MtaSts,
+    /// Where to report TLS failures delivering to the domain, at `_smtp._tls`.
+    TlsRpt,
+    /// The logo shown next to the domain's mail, at `<selector>._bimi`.
+    Bimi,
+}
+
+impl MailRecordKind {
+    /// Every kind, in the order they are reported.
+    pub const ALL: [Self; 6] = [
+        Self::Spf,
+        Self::Dmarc,
+        Self::Dkim,
+        Self::MtaSts,
+        Self::TlsRpt,
+        Self::Bimi,
+    ];
+
+    /// The name the kind is known by.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Spf => "SPF",
+            Self::Dmarc => "DMARC",
+            Self::Dkim => "DKIM",
+            Self::MtaSts => "MTA-STS",
+            Self::TlsRpt => "TLS-RPT",
+            Self::Bimi => "BIMI",
+        }
+    }
+
+    /// The most this kind adds to the score.
+    const fn weight(self) -> u8 {
+        match self {
+            Self::Spf => 25,
+            Self::Dmarc => 35,
+            Self::Dkim => 20,
+            Self::MtaSts => 10,
+            Self::TlsRpt | Self::Bimi => 5,
+        }
+    }
+
+    /// What to do when the zone has no valid record of this kind.
+    const fn recommendation(self) -> &'static str {
+        match self {
+            Self::Spf => "Add an SPF record listing the servers that send the domain's mail",
+            Self::Dmarc => "Add a DMARC record, starting with p=none and a report address",
+            Self::Dkim => "Publish the DKIM key your mail provider signs with",
+            Self::MtaSts => "Add an MTA-STS record and policy so senders require TLS",
+            Self::TlsRpt => "Add a TLS-RPT record to hear about TLS delivery failures",
+            Self::Bimi => "Add a BIMI record to show the domain's logo in inboxes",
+        }
+    }
+
+    /// The kind of record at `relative`, a name relative to the zone with `@` for the apex, with `text`.
+    fn of(relative: &str, text: &str) -> Option<Self> {
+        let labels: Vec<&str> = relative.split('.').collect();
+        match labels.as_slice() {
+            ["@"] if is_spf(text) => Some(Self::Spf),
+            ["_dmarc"] => Some(Self::Dmarc),
+            [.., "_domainkey"] if labels.len() > 1 => Some(Self::Dkim),
+            ["_mta-sts"] => Some(Self::MtaSts),
+            ["_smtp", "_tls"] => Some(Self::TlsRpt),
+            [_, "_bimi"] => Some(Self::Bimi),
+            _ => None,
+        }
+    }
+}
+
+/// A mail authentication record found in the zone, and what is wrong with it.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct MailRecordCheck {
+    /// The kind of record.
+    pub kind: MailRecordKind,
+    /// Identifier for the record.
+    pub record_id: String,
+    /// The record name.
+    pub name: String,
+    /// The record's text, with its character strings joined.
+    pub text: String,
+    /// Problems that make receivers ignore the record.
+    pub errors: Vec<String>,
+    /// Problems that weaken the record, though receivers use it.
+    pub warnings: Vec<String>,
+}
+
+impl MailRecordCheck {
+    /// Whether receivers use the record.
+    #[must_use]
+    pub fn valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// How well a zone's mail is protected.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct EmailPosture {
+    /// The zone identifier.
+    pub zone_id: String,
+    /// The zone name.
+    pub zone_name: String,
+    /// The mail authentication records in the zone.
+    pub records: Vec<MailRecordCheck>,
+    /// The kinds with no valid record.
+    pub missing: Vec<MailRecordKind>,
+    /// From 0, nothing set up, to 100, every record present and enforcing.
+    pub score: u8,
+    /// What to do next, most important first.
+    pub recommendations: Vec<String>,
+}
+
+/// The `tag=value` pairs of a record, in order, with lowercase tags.
+fn tags(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut tags: Vec<(String, String)> = Vec::new();
+    for part in text
+        .split(';')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (tag, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("{part} isn't a tag=value pair"))?;
+        let tag = tag.trim().to_ascii_lowercase();
+        if tags.iter().any(|(t, _)| *t == tag) {
+            return Err(format!("{tag} is given more than once"));
+        }
+        tags.push((tag, value.trim().to_string()));
+    }
+    Ok(tags)
+}
+
+/// The value of `tag`, if it is given.
+fn tag<'a>(tags: &'a [(String, String)], tag: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|(t, _)| t == tag)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Check that the first tag is the version tag with `version`.
+fn check_version(tags: &[(String, String)], version: &str, errors: &mut Vec<String>) {
+    match tags.first() {
+        Some((tag, value)) if tag == "v" && value == version => {}
+        _ => errors.push(format!("The record must start with v={version}")),
+    }
+}
+
+/// Whether `address` looks like an email address.
+fn is_email(address: &str) -> bool {
+    address.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty()
+            && !domain.contains('@')
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && !address.contains(char::is_whitespace)
+    })
+}
+
+/// Whether `url` is an HTTPS URL.
+fn is_https(url: &str) -> bool {
+    url.strip_prefix("https://")
+        .is_some_and(|rest| !rest.is_empty() && !rest.contains(char::is_whitespace))
+}
+
+/// Check a comma-separated list of report URIs, allowing `mailto:` and, if `https` is set, HTTPS URLs.
+fn check_report_uris(tag: &str, value: &str, https: bool, errors: &mut Vec<String>) {
+    for uri in value.split(',').map(str::trim) {
+        let valid = uri.strip_prefix("mailto:").map_or_else(
+            || https && is_https(uri),
+            // DMARC allows a size limit after the address, e.g. `mailto:dmarc@example.com!10m`.
+            |address| is_email(address.split('!').next().unwrap_or_default()),
+        );
+        if !valid {
+            let allowed = if https {
+                "mailto: addresses or https: URLs"
+            } else {
+                "mailto: addresses"
+            };
+            errors.push(format!("{tag}: {uri} isn't one of the {allowed} it takes"));
+        }
+    }
+}
+
+/// Whether `text` is base64.
+fn is_base64(text: &str) -> bool {
+    let data = text.trim_end_matches('=');
+    text.len() - data.len() <= 2
+        && data
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/')
+        && text.len() % 4 == 0
+}
+
+/// The DMARC policies.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum DmarcPolicy {
+    /// Only report failing mail.
+    None,
+    /// Treat failing mail as suspicious, usually by putting it in spam.
+    Quarantine,
+    /// Reject failing mail.
+    Reject,
+}
+
+impl DmarcPolicy {
+    /// The policy as written in the record.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Quarantine => "quarantine",
+            Self::Reject => "reject",
+        }
+    }
+
+    /// The policy written as `text`.
+    fn parse(text: &str) -> Option<Self> {
+        [Self::None, Self::Quarantine, Self::Reject]
+            .into_iter()
+            .find(|policy| policy.as_str() == text)
+    }
+}
+
+/// The policy a DMARC record gives, and the percentage of mail it applies to. `None` if it is invalid.
+fn dmarc_policy(text: &str) -> Option<(DmarcPolicy, u8)> {
+    let tags = tags(text).ok()?;
+    let policy = DmarcPolicy::parse(tag(&tags, "p")?)?;
+    let percent = tag(&tags, "pct").map_or(Some(100), |pct| pct.parse().ok())?;
+    Some((policy, percent))
+}
+
+/// Check a DMARC record (RFC 7489).
+fn check_dmarc(text: &str, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let tags = match tags(text) {
+        Ok(tags) => tags,
+        Err(e) => return errors.push(e),
+    };
+    check_version(&tags, "DMARC1", errors);
+    match tag(&tags, "p") {
+        None => errors.push("p= (the policy) is required".to_string()),
+        Some(p) if DmarcPolicy::parse(p).is_none() => {
+            errors.push(format!("p={p} isn't none, quarantine or reject"));
+        }
+        Some("none") => warnings.push(
+            "p=none only reports failing mail; move to quarantine or reject once the reports look right"
+                .to_string(),
+        ),
+        Some(_) => {}
+    }
+    for name in ["sp", "np"] {
+        if let Some(policy) = tag(&tags, name).filter(|p| DmarcPolicy::parse(p).is_none()) {
+            errors.push(format!("{name}={policy} isn't none, quarantine or reject"));
+        }
+    }
+    match tag(&tags, "pct").map(str::parse::<u8>) {
+        Some(Ok(pct)) if pct > 100 => errors.push(format!("pct={pct} isn't 0 to 100")),
+        Some(Ok(pct)) if pct < 100 => warnings.push(format!(
+            "pct={pct} applies the policy to only some failing mail"
+        )),
+        Some(Err(_)) => errors.push("pct isn't a number from 0 to 100".to_string()),
+        _ => {}
+    }
+    for name in ["adkim", "aspf"] {
+        if let Some(mode) = tag(&tags, name).filter(|mode| *mode != "r" && *mode != "s") {
+            errors.push(format!("{name}={mode} isn't r (relaxed) or s (strict)"));
+        }
+    }
+    match tag(&tags, "rua") {
+        Some(rua) => check_report_uris("rua", rua, false, errors),
+        None => warnings.push(
+            "Without rua= (where to send aggregate reports), you won't see who sends mail as the domain"
+                .to_string(),
+        ),
+    }
+    if let Some(ruf) = tag(&tags, "ruf") {
+        check_report_uris("ruf", ruf, false, errors);
+    }
+    if let Some(fo) = tag(&tags, "fo").filter(|fo| {
+        !fo.split(':')
+            .all(|option| ["0", "1", "d", "s"].contains(&option))
+    }) {
+        errors.push(format!("fo={fo} isn't a list of 0, 1, d and s"));
+    }
+    if let Some(ri) = tag(&tags, "ri").filter(|ri| ri.parse::<u32>().is_err()) {
+        errors.push(format!("ri={ri} isn't a number of seconds"));
+    }
+}
+
+/// Check a DKIM key record (RFC 6376).
+fn check_dkim(text: &str, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let tags = match tags(text) {
+        Ok(tags) => tags,
+        Err(e) => return errors.push(e),
+    };
+    if tag(&tags, "v").is_some() {
+        check_version(&tags, "DKIM1", errors);
+    }
+    let key_type = tag(&tags, "k").unwrap_or("rsa");
+    if key_type != "rsa" && key_type != "ed25519" {
+        errors.push(format!("k={key_type} isn't rsa or ed25519"));
+    }
+    match tag(&tags, "p").map(|p| p.replace(char::is_whitespace, "")) {
+        None => errors.push("p= (the public key) is required".to_string()),
+        Some(key) if key.is_empty() => {
+            warnings.push("The key has been revoked (p= is empty)".to_string());
+        }
+        Some(key) if !is_base64(&key) => errors.push("p= isn't base64".to_string()),
+        Some(key) => {
+            let bytes = key.len() / 4 * 3 - (key.len() - key.trim_end_matches('=').len());
+            if key_type == "rsa" && bytes < MIN_RSA_KEY_BYTES {
+                warnings.push(
+                    "The RSA key is shorter than 2048 bits, which receivers may not trust"
+                        .to_string(),
+                );
+            }
+            if key_type == "ed25519" && bytes != ED25519_KEY_BYTES {
+                errors.push(format!(
+                    "An Ed25519 key is {ED25519_KEY_BYTES} bytes, not {bytes}"
+                ));
+            }
+        }
+    }
+    if tag(&tags, "t").is_some_and(|flags| flags.split(':').any(|flag| flag.trim() == "y")) {
+        warnings.push(
+            "t=y marks the key as testing, so receivers treat signed mail as unsigned".to_string(),
+        );
+    }
+}
+
+/// Check an MTA-STS record (RFC 8461).
+fn check_mta_sts(text: &str, errors: &mut Vec<String>) {
+    let tags = match tags(text) {
+        Ok(tags) => tags,
+        Err(e) => return errors.push(e),
+    };
+    check_version(&tags, "STSv1", errors);
+    match tag(&tags, "id") {
+        None => errors.push("id= (the policy's version) is required".to_string()),
+        Some(id)
+            if id.is_empty() || id.len() > 32 || !id.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            errors.push(format!("id={id} isn't 1 to 32 letters and digits"));
+        }
+        Some(_) => {}
+    }
+}
+
+/// Check a TLS-RPT record (RFC 8460).
+fn check_tls_rpt(text: &str, errors: &mut Vec<String>) {
+    let tags = match tags(text) {
+        Ok(tags) => tags,
+        Err(e) => return errors.push(e),
+    };
+    check_version(&tags, "TLSRPTv1", errors);
+    match tag(&tags, "rua") {
+        Some(rua) => check_report_uris("rua", rua, true, errors),
+        None => errors.push("rua= (where to send reports) is required".to_string()),
+    }
+}
+
+/// Check a BIMI record.
+fn check_bimi(text: &str, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let tags = match tags(text) {
+        Ok(tags) => tags,
+        Err(e) => return errors.push(e),
+    };
+    check_version(&tags, "BIMI1", errors);
+    match tag(&tags, "l") {
+        None => errors.push("l= (the logo's URL) is required".to_string()),
+        Some("") => {}
+        Some(logo) if !is_https(logo) => errors.push(format!("l={logo} isn't an https: URL")),
+        Some(logo) if !logo.to_ascii_lowercase().ends_with(".svg") => {
+            warnings.push("The logo must be an SVG Tiny PS image".to_string());
+        }
+        Some(_) => {}
+    }
+    match tag(&tags, "a") {
+        Some(certificate) if !certificate.is_empty() && !is_https(certificate) => {
+            errors.push(format!("a={certificate} isn't an https: URL"));
+        }
+        None | Some("") => warnings.push(
+            "Most mailbox providers only show the logo with a mark certificate (a=)".to_string(),
+        ),
+        Some(_) => {}
+    }
+}
+
+/// Check an SPF record, leaving the lookup budget to [`crate::spf::analyze`].
+fn check_spf(text: &str, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+    let record = match SpfRecord::parse(text) {
+        Ok(record) => record,
+        Err(e) => return errors.push(e.message),
+    };
+    let all = record.terms.iter().find_map(|term| match term {
+        Term::Mechanism {
+            qualifier,
+            mechanism: Mechanism::All,
+        } => Some(*qualifier),
+        _ => None,
+    });
+    match all {
+        Some(Qualifier::Pass) => {
+            errors.push("+all lets anyone send mail as the domain".to_string());
+        }
+        Some(Qualifier::Neutral) => {
+            warnings
+                .push("?all says nothing about other senders; end with -all or ~all".to_string());
+        }
+        None if record.redirect().is_none() => warnings.push(
+            "The record doesn't end with -all or ~all, so it says nothing about other senders"
+                .to_string(),
+        ),
+        _ => {}
+    }
+}
+
+/// Check mail authentication record text of `kind`.
+#[must_use]
+pub fn check_record(kind: MailRecordKind, text: &str) -> (Vec<String>, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    match kind {
+        MailRecordKind::Spf => check_spf(text, &mut errors, &mut warnings),
+        MailRecordKind::Dmarc => check_dmarc(text, &mut errors, &mut warnings),
+        MailRecordKind::Dkim => check_dkim(text, &mut errors, &mut warnings),
+        MailRecordKind::MtaSts => check_mta_sts(text, &mut errors),
+        MailRecordKind::TlsRpt => check_tls_rpt(text, &mut errors),
+        MailRecordKind::Bimi => check_bimi(text, &mut errors, &mut warnings),
+    }
+    (errors, warnings)
+}
+
+/// The name of `record` relative to `zone_name`, with `@` for the apex. `None` if it isn't in the zone.
+fn relative_name(record: &DNSRecord, zone_name: &str) -> Option<String> {
+    let zone = zone_name.trim_end_matches('.').to_ascii_lowercase();
+    let name = ZoneContext {
+        zone_name,
+        records: &[],
+    }
+    .qualify(&record.name)?;
+    Some(
+        name.strip_suffix(&zone)
+            .and_then(|prefix| prefix.strip_suffix('.'))
+            .unwrap_or("@")
+            .to_string(),
+    )
+}
+
+/// The mail authentication records in a zone, checked.
+fn mail_records(zone_name: &str, records: &[DNSRecord]) -> Vec<MailRecordCheck> {
+    let mut checks: Vec<MailRecordCheck> = records
+        .iter()
+        .filter_map(|record| {
+            let DNSContent::Txt { content } = &record.content else {
+                return None;
+            };
+            let text = txt_chunks(content).concat();
+            let kind = MailRecordKind::of(&relative_name(record, zone_name)?, &text)?;
+            let (errors, warnings) = check_record(kind, &text);
+            Some(MailRecordCheck {
+                kind,
+                record_id: record.id.clone(),
+                name: record.name.clone(),
+                text,
+                errors,
+                warnings,
+            })
+        })
+        .collect();
+
+    // Receivers ignore every record of a kind at a name when there is more than one.
+    let duplicated: Vec<(MailRecordKind, String)> = checks
+        .iter()
+        .filter(|c| {
+            c.kind != MailRecordKind::Dkim
+                && checks
+                    .iter()
+                    .filter(|other| other.kind == c.kind && other.name == c.name)
+                    .count()
+                    > 1
+        })
+        .map(|c| (c.kind, c.name.clone()))
+        .collect();
+    for check in &mut checks {
+        if duplicated.contains(&(check.kind, check.name.clone())) {
+            check.errors.push(format!(
+                "{} has more than one {} record, so receivers ignore them all",
+                check.name,
+                check.kind.label()
+            ));
+        }
+    }
+    checks.sort_by_key(|c| MailRecordKind::ALL.iter().position(|k| *k == c.kind));
+    checks
+}
+
+/// What a valid record of `kind` adds to the score, given the zone's DMARC policy.
+fn points(check: &MailRecordCheck, dmarc: Option<(DmarcPolicy, u8)>) -> u8 {
+    let weight = check.kind.weight();
+    match check.kind {
+        MailRecordKind::Dmarc => match dmarc {
+            Some((DmarcPolicy::Reject, 100)) => weight,
+            Some((DmarcPolicy::Reject | DmarcPolicy::Quarantine, _)) => weight - 10,
+            _ => weight - 15,
+        },
+        MailRecordKind::Spf | MailRecordKind::Dkim if !check.warnings.is_empty() => weight / 2,
+        MailRecordKind::Bimi
+            if !matches!(
+                dmarc,
+                Some((DmarcPolicy::Quarantine | DmarcPolicy::Reject, 100))
+            ) =>
+        {
+            0
+        }
+        _ => weight,
+    }
+}
+
+/// Check the mail authentication records of a cached zone, and score how well its mail is protected.
+#[must_use]
+pub fn check_zone(zone_id: &str, zone_name: &str, records: &[DNSRecord]) -> EmailPosture {
+    let checks = mail_records(zone_name, records);
+    let dmarc = checks
+        .iter()
+        .find(|c| c.kind == MailRecordKind::Dmarc && c.valid())
+        .and_then(|c| dmarc_policy(&c.text));
+
+    let mut score = 0;
+    let mut missing = Vec::new();
+    let mut recommendations = Vec::new();
+    for kind in MailRecordKind::ALL {
+        let best = checks
+            .iter()
+            .filter(|c| c.kind == kind && c.valid())
+            .map(|c| points(c, dmarc))
+            .max();
+        if let Some(points) = best {
+            score += points;
+        } else {
+            missing.push(kind);
+            recommendations.push(kind.recommendation().to_string());
+        }
+    }
+    match dmarc {
+        Some((DmarcPolicy::None, _)) => recommendations.insert(
+            usize::from(missing.first() == Some(&MailRecordKind::Spf)),
+            "Move the DMARC policy to quarantine, then reject, once its reports show only your own mail".to_string(),
+        ),
+        Some((_, percent)) if percent < 100 => recommendations.push("Apply the DMARC policy to all mail (pct=100)".to_string()),
+        _ => {}
+    }
+    if checks
+        .iter()
+        .any(|c| c.kind == MailRecordKind::Bimi && c.valid())
+        && !matches!(
+            dmarc,
+            Some((DmarcPolicy::Quarantine | DmarcPolicy::Reject, 100))
+        )
+    {
+        recommendations.push(
+            "BIMI logos are only shown when DMARC quarantines or rejects all failing mail"
+                .to_string(),
+        );
+    }
+
+    EmailPosture {
+        zone_id: zone_id.to_string(),
+        zone_name: zone_name.to_string(),
+        records: checks,
+        missing,
+        score,
+        recommendations,
+    }
+}
+
+/// What `all` says about senders an SPF record doesn't list.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum SpfAll {
+    /// `-all`: reject them.
+    Fail,
+    /// `~all`: accept them, but as suspicious.
+    SoftFail,
+}
+
+/// The kinds of DKIM key.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum DkimKeyType {
+    /// An RSA key, which every receiver supports.
+    Rsa,
+    /// An Ed25519 key, usually published alongside an RSA key.
+    Ed25519,
+}
+
+/// Whether senders delivering to the domain require TLS.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum MtaStsMode {
+    /// Report failures, but deliver anyway.
+    Testing,
+    /// Don't deliver without TLS to a listed server.
+    Enforce,
+    /// Stop using MTA-STS.
+    None,
+}
+
+impl MtaStsMode {
+    /// The mode as written in the policy.
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Testing => "testing",
+            Self::Enforce => "enforce",
+            Self::None => "none",
+        }
+    }
+}
+
+/// What a generated record should say.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MailRecordOptions {
+    /// An SPF record at the zone apex.
+    Spf {
+        /// Allow the addresses of the zone's mail servers.
+        mx: bool,
+        /// Allow the addresses of the zone apex.
+        a: bool,
+        /// IPv4 and IPv6 addresses or networks to allow.
+        networks: Vec<String>,
+        /// Domains whose SPF records to include, e.g. `_spf.google.com`.
+        includes: Vec<String>,
+        /// What to say about other senders.
+        all: SpfAll,
+    },
+    /// A DMARC record at `_dmarc`.
+    Dmarc {
+        /// What to do with failing mail.
+        policy: DmarcPolicy,
+        /// What to do with failing mail from subdomains, if different.
+        subdomain_policy: Option<DmarcPolicy>,
+        /// The percentage of failing mail the policy applies to, if not all of it.
+        percent: Option<u8>,
+        /// Addresses to send daily aggregate reports to.
+        aggregate_reports: Vec<String>,
+        /// Addresses to send a report of each failing message to.
+        failure_reports: Vec<String>,
+        /// Require the DKIM signature's domain to match exactly, rather than share an organization.
+        strict_dkim: bool,
+        /// Require the SPF domain to match exactly, rather than share an organization.
+        strict_spf: bool,
+    },
+    /// A DKIM key record at `<selector>._domainkey`.
+    Dkim {
+        /// The selector the mail provider signs with, e.g. `google` or `selector1`.
+        selector: String,
+        /// The kind of key.
+        key_type: DkimKeyType,
+        /// The public key, base64 encoded, as the mail provider gives it.
+        public_key: String,
+        /// Mark the key as testing, so failures don't count against mail.
+        testing: bool,
+    },
+    /// An MTA-STS record at `_mta-sts`, and the policy to serve at
+    /// `https://mta-sts.<zone>/.well-known/mta-sts.txt`.
+    MtaSts {
+        /// Whether senders require TLS.
+        mode: MtaStsMode,
+        /// The mail servers senders may deliver to, with `*.` for any subdomain. The zone's MX records if
+        /// empty.
+        mx: Vec<String>,
+        /// How long senders keep the policy, in days.
+        max_age_days: u32,
+    },
+    /// A TLS-RPT record at `_smtp._tls`.
+    TlsRpt {
+        /// Addresses or HTTPS URLs to send reports to.
+        reports: Vec<String>,
+    },
+    /// A BIMI record at `<selector>._bimi`.
+    Bimi {
+        /// The selector, `default` if not given.
+        selector: Option<String>,
+        /// The HTTPS URL of the logo, an SVG Tiny PS image.
+        logo_url: String,
+        /// The HTTPS URL of the mark certificate, if there is one.
+        certificate_url: Option<String>,
+    },
+}
+
+/// A generated record, ready to create.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct GeneratedRecord {
+    /// The kind of record.
+    pub kind: MailRecordKind,
+    /// The request creating the record.
+    pub record: DNSRecordRequest,
+    /// The record of the same kind at the name, which this should replace rather than be added next to.
+    pub replaces: Option<String>,
+    /// For MTA-STS, the policy file to serve over HTTPS.
+    pub policy: Option<String>,
+}
+
+/// Check that every address in `addresses`, given as `field`, is an email address.
+fn check_emails(field: &str, addresses: &[String], errors: &mut Vec<FieldError>) {
+    for (i, address) in addresses.iter().enumerate() {
+        if !is_email(address.trim()) {
+            errors.push(FieldError::new(
+                &format!("{field}[{i}]"),
+                format!("{address} isn't an email address"),
+            ));
+        }
+    }
+}
+
+/// Whether `label` can be a DNS label.
+fn is_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The `mailto:` URIs of `addresses`.
+fn mailto(addresses: &[String]) -> String {
+    addresses
+        .iter()
+        .map(|address| format!("mailto:{}", address.trim()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// An SPF record's text.
+fn spf_text(
+    mx: bool,
+    a: bool,
+    networks: &[String],
+    includes: &[String],
+    all: SpfAll,
+    errors: &mut Vec<FieldError>,
+) -> String {
+    let mut text = "v=spf1".to_string();
+    if mx {
+        text.push_str(" mx");
+    }
+    if a {
+        text.push_str(" a");
+    }
+    for (i, network) in networks.iter().enumerate() {
+        let network = network.trim();
+        let address = network.split('/').next().unwrap_or_default();
+        if address.parse::<std::net::Ipv4Addr>().is_ok() {
+            let _ = write!(text, " ip4:{network}");
+        } else if address.parse::<std::net::Ipv6Addr>().is_ok() {
+            let _ = write!(text, " ip6:{network}");
+        } else {
+            errors.push(FieldError::new(
+                &format!("networks[{i}]"),
+                format!("{network} isn't an IP address or network"),
+            ));
+        }
+    }
+    for include in includes {
+        let _ = write!(text, " include:{}", include.trim().trim_end_matches('.'));
+    }
+    text.push_str(match all {
+        SpfAll::Fail => " -all",
+        SpfAll::SoftFail => " ~all",
+    });
+    text
+}
+
+/// A DMARC record's text.
+#[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)]
+fn dmarc_text(
+    policy: DmarcPolicy,
+    subdomain_policy: Option<DmarcPolicy>,
+    percent: Option<u8>,
+    aggregate_reports: &[String],
+    failure_reports: &[String],
+    strict_dkim: bool,
+    strict_spf: bool,
+    errors: &mut Vec<FieldError>,
+) -> String {
+    let mut text = format!("v=DMARC1; p={}", policy.as_str());
+    if let Some(policy) = subdomain_policy {
+        let _ = write!(text, "; sp={}", policy.as_str());
+    }
+    match percent {
+        Some(percent) if percent > 100 => errors.push(FieldError::new(
+            "percent",
+            "The percentage must be from 0 to 100",
+        )),
+        Some(percent) if percent < 100 => {
+            let _ = write!(text, "; pct={percent}");
+        }
+        _ => {}
+    }
+    check_emails("aggregate_reports", aggregate_reports, errors);
+    check_emails("failure_reports", failure_reports, errors);
+    if !aggregate_reports.is_empty() {
+        let _ = write!(text, "; rua={}", mailto(aggregate_reports));
+    }
+    if !failure_reports.is_empty() {
+        let _ = write!(text, "; ruf={}; fo=1", mailto(failure_reports));
+    }
+    if strict_dkim {
+        text.push_str("; adkim=s");
+    }
+    if strict_spf {
+        text.push_str("; aspf=s");
+    }
+    text
+}
+
+/// A DKIM key record's name relative to the zone, and its text.
+fn dkim_text(
+    selector: &str,
+    key_type: DkimKeyType,
+    public_key: &str,
+    testing: bool,
+    errors: &mut Vec<FieldError>,
+) -> (String, String) {
+    let selector = selector.trim().to_ascii_lowercase();
+    if !selector.split('.').all(is_label) {
+        errors.push(FieldError::new(
+            "selector",
+            format!("{selector} isn't a valid selector"),
+        ));
+    }
+    let key_type = match key_type {
+        DkimKeyType::Rsa => "rsa",
+        DkimKeyType::Ed25519 => "ed25519",
+    };
+    // Providers hand out keys wrapped over several lines, sometimes with the PEM armour.
+    let key: String = public_key
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect::<String>()
+        .replace(char::is_whitespace, "");
+    let mut text = format!("v=DKIM1; k={key_type}; p={key}");
+    if testing {
+        text.push_str("; t=y");
+    }
+    (format!("{selector}._domainkey"), text)
+}
+
+/// The MTA-STS policy file, listing `mx` or, if it is empty, the zone's MX records.
+fn mta_sts_policy(
+    zone_name: &str,
+    records: &[DNSRecord],
+    mode: MtaStsMode,
+    mx: &[String],
+    max_age_days: u32,
+    errors: &mut Vec<FieldError>,
+) -> String {
+    let mut servers: Vec<String> = mx
+        .iter()
+        .map(|server| server.trim().trim_end_matches('.').to_ascii_lowercase())
+        .collect();
+    if servers.is_empty() {
+        servers = records
+            .iter()
+            .filter_map(|record| match &record.content {
+                DNSContent::Mx { content, .. } => {
+                    Some(content.trim_end_matches('.').to_ascii_lowercase())
+                }
+                _ => None,
+            })
+            .collect();
+        servers.sort();
+        servers.dedup();
+    }
+    if servers.is_empty() {
+        errors.push(FieldError::new(
+            "mx",
+            format!("{zone_name} has no MX records; list the mail servers"),
+        ));
+    }
+    if max_age_days == 0 || max_age_days > 365 {
+        errors.push(FieldError::new(
+            "max_age_days",
+            "The maximum age must be from 1 to 365 days",
+        ));
+    }
+    let mut policy = format!("version: STSv1\nmode: {}\n", mode.as_str());
+    for server in &servers {
+        let _ = writeln!(policy, "mx: {server}");
+    }
+    let _ = writeln!(policy, "max_age: {}", u64::from(max_age_days) * 86_400);
+    policy
+}
+
+/// A TLS-RPT record's text, sending reports to `reports`, addresses or HTTPS URLs.
+fn tls_rpt_text(reports: &[String], errors: &mut Vec<FieldError>) -> String {
+    if reports.is_empty() {
+        errors.push(FieldError::new(
+            "reports",
+            "Give at least one report address",
+        ));
+    }
+    let uris: Vec<String> = reports
+        .iter()
+        .map(|report| {
+            let report = report.trim();
+            if is_https(report) || report.starts_with("mailto:") {
+                report.to_string()
+            } else {
+                format!("mailto:{report}")
+            }
+        })
+        .collect();
+    format!("v=TLSRPTv1; rua={}", uris.join(","))
+}
+
+/// The name, text and MTA-STS policy of the record `options` describe, relative to the zone.
+fn record_text(
+    zone_name: &str,
+    records: &[DNSRecord],
+    options: &MailRecordOptions,
+    errors: &mut Vec<FieldError>,
+) -> (String, String, Option<String>) {
+    match options {
+        MailRecordOptions::Spf {
+            mx,
+            a,
+            networks,
+            includes,
+            all,
+        } => (
+            "@".to_string(),
+            spf_text(*mx, *a, networks, includes, *all, errors),
+            None,
+        ),
+        MailRecordOptions::Dmarc {
+            policy,
+            subdomain_policy,
+            percent,
+            aggregate_reports,
+            failure_reports,
+            strict_dkim,
+            strict_spf,
+        } => (
+            "_dmarc".to_string(),
+            dmarc_text(
+                *policy,
+                *subdomain_policy,
+                *percent,
+                aggregate_reports,
+                failure_reports,
+                *strict_dkim,
+                *strict_spf,
+                errors,
+            ),
+            None,
+        ),
+        MailRecordOptions::Dkim {
+            selector,
+            key_type,
+            public_key,
+            testing,
+        } => {
+            let (name, text) = dkim_text(selector, *key_type, public_key, *testing, errors);
+            (name, text, None)
+        }
+        MailRecordOptions::MtaSts {
+            mode,
+            mx,
+            max_age_days,
+        } => {
+            let policy = mta_sts_policy(zone_name, records, *mode, mx, *max_age_days, errors);
+            // The ID changes whenever the policy does, so senders fetch it again.
+            let id = Utc::now().format("%Y%m%d%H%M%S");
+            (
+                "_mta-sts".to_string(),
+                format!("v=STSv1; id={id}"),
+                Some(policy),
+            )
+        }
+        MailRecordOptions::TlsRpt { reports } => (
+            "_smtp._tls".to_string(),
+            tls_rpt_text(reports, errors),
+            None,
+        ),
+        MailRecordOptions::Bimi {
+            selector,
+            logo_url,
+            certificate_url,
+        } => {
+            let selector = selector
+                .as_deref()
+                .map_or("default", str::trim)
+                .to_ascii_lowercase();
+            if !is_label(&selector) {
+                errors.push(FieldError::new(
+                    "selector",
+                    format!("{selector} isn't a valid selector"),
+                ));
+            }
+            let mut text = format!("v=BIMI1; l={}", logo_url.trim());
+            if let Some(certificate) = certificate_url {
+                let _ = write!(text, "; a={}", certificate.trim());
+            }
+            (format!("{selector}._bimi"), text, None)
+        }
+    }
+}
+
+/// The kind of record `options` describe.
+const fn kind(options: &MailRecordOptions) -> MailRecordKind {
+    match options {
+        MailRecordOptions::Spf { .. } => MailRecordKind::Spf,
+        MailRecordOptions::Dmarc { .. } => MailRecordKind::Dmarc,
+        MailRecordOptions::Dkim { .. } => MailRecordKind::Dkim,
+        MailRecordOptions::MtaSts { .. } => MailRecordKind::MtaSts,
+        MailRecordOptions::TlsRpt { .. } => MailRecordKind::TlsRpt,
+        MailRecordOptions::Bimi { .. } => MailRecordKind::Bimi,
+    }
+}
+
+/// Write the record `options` describe for a zone, checked as the zone's own records are.
+///
+/// The record has automatic TTL. Text longer than a TXT character string is split into quoted strings.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if an option is invalid, or the record written from them has errors.
+pub fn generate(
+    zone_name: &str,
+    records: &[DNSRecord],
+    options: &MailRecordOptions,
+) -> Result<GeneratedRecord, AppError> {
+    let kind = kind(options);
+    let mut errors = Vec::new();
+    let (relative, text, policy) = record_text(zone_name, records, options, &mut errors);
+    if errors.is_empty() {
+        errors.extend(
+            check_record(kind, &text)
+                .0
+                .into_iter()
+                .map(|error| FieldError::new("content", error)),
+        );
+    }
+    if !errors.is_empty() {
+        return Err(AppError::validation(errors));
+    }
+
+    let zone = zone_name.trim_end_matches('.').to_ascii_lowercase();
+    let name = if relative == "@" {
+        zone
+    } else {
+        format!("{relative}.{zone}")
+    };
+    let replaces = mail_records(zone_name, records)
+        .into_iter()
+        .find(|c| c.kind == kind && c.name.trim_end_matches('.').eq_ignore_ascii_case(&name))
+        .map(|c| c.record_id);
+    let content = if text.len() > MAX_CHARACTER_STRING {
+        quote_txt(&text)
+    } else {
+        text
+    };
+    Ok(GeneratedRecord {
+        kind,
+        record: DNSRecordRequest {
+            content: DNSContent::Txt { content },
+            name,
+            proxied: None,
+            comment: None,
+            tags: Vec::new(),
+            ttl: 1,
+        },
+        replaces,
+        policy,
+    })
+}
@@ -0,0 +1,229 @@
+//! The error type returned by the API helpers and the Tauri commands.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use ts_rs::TS;
+
+use crate::cloudflare::CloudflareMessage;
+
+/// Cloudflare error codes that mean the credentials themselves are bad (rather than lacking a permission).
+///
+/// - 6003: Invalid request headers
+/// - 6111: Invalid format for Authorization header
+/// - 9103: Unknown X-Auth-Key or X-Auth-Email
+/// - 9106: Missing X-Auth-Key, X-Auth-Email or Authorization headers
+/// - 9109: Invalid access token
+/// - 10000: Authentication error
+const AUTHENTICATION_ERROR_CODES: [u32; 6] = [6003, 6111, 9103, 9106, 9109, 10000];
+
+/// What kind of failure an [`AppError`] represents, so the frontend can decide how to react.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// The request never got a response (DNS failure, refused connection, TLS error, timeout).
+    Network,
+    /// The credentials are invalid or expired.
+    Unauthorized,
+    /// The credentials are valid but lack the permission needed for the request.
+    Forbidden,
+    /// The zone or record does not exist (or isn't visible to these credentials).
+    NotFound,
+    /// Cloudflare is rate limiting the requests.
+    RateLimited,
+    /// Cloudflare failed to handle the request (HTTP 5xx).
+    Server,
+    /// Cloudflare rejected the request for another reason. See the error codes for details.
+    Api,
+    /// The response did not match the expected JSON schema.
+    Parse,
+    /// No credentials have been configured yet, or they haven't been verified.
+    MissingCredentials,
+    /// The in-memory cache could not be accessed.
+    Cache,
+    /// The saved credentials could not be read from or written to the keyring or the encrypted file.
+    CredentialStorage,
+    /// A file in the app data directory could not be read or written.
+    Io,
+    /// The input was rejected before anything was sent to Cloudflare.
+    Validation,
+}
+
+/// A problem with one field of the input, so the frontend can highlight it.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct FieldError {
+    /// The path of the field in the submitted JSON, e.g. `ttl` or `data.target`. `None` if the problem is
+    /// with the input as a whole, e.g. a locked record.
+    pub field: Option<String>,
+    /// A human-readable description of the problem.
+    pub message: String,
+}
+
+impl FieldError {
+    /// A problem with the field at `field`.
+    #[must_use]
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: Some(field.to_string()),
+            message: message.into(),
+        }
+    }
+
+    /// A problem with the input as a whole.
+    #[must_use]
+    pub fn input(message: impl Into<String>) -> Self {
+        Self {
+            field: None,
+            message: message.into(),
+        }
+    }
+}
+
+/// An error from talking to the Cloudflare API or from reading the cache.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct AppError {
+    /// What kind of failure this is.
+    pub category: ErrorCategory,
+    /// The HTTP status code of the response, if one was received.
+    pub status: Option<u16>,
+    /// The errors Cloudflare returned in the response body, if any.
+    pub errors: Vec<CloudflareMessage>,
+    /// A human-readable description of the failure.
+    pub message: String,
+    /// The problems with each field of the input, for [`ErrorCategory::Validation`] errors.
+    #[serde(default)]
+    pub field_errors: Vec<FieldError>,
+}
+
+impl AppError {
+    /// Create an error without an HTTP status or Cloudflare error codes.
+    #[must_use]
+    pub fn new(category: ErrorCategory, message: impl Into<String>) -> Self {
+        Self {
+            category,
+            status: None,
+            errors: Vec::new(),
+            message: message.into(),
+            field_errors: Vec::new(),
+        }
+    }
+
+    /// Create an error for a response Cloudflare answered with an error status or with `success: false`.
+    ///
+    /// The category is picked from the status code, except that authentication error codes always
+    /// count as [`ErrorCategory::Unauthorized`] (Cloudflare reports some of those as 400 or 403).
+    #[must_use]
+    pub fn from_response(status: reqwest::StatusCode, errors: Vec<CloudflareMessage>) -> Self {
+        let category = if errors
+            .iter()
+            .any(|e| AUTHENTICATION_ERROR_CODES.contains(&e.code))
+        {
+            ErrorCategory::Unauthorized
+        } else {
+            match status.as_u16() {
+                401 => ErrorCategory::Unauthorized,
+                403 => ErrorCategory::Forbidden,
+                404 => ErrorCategory::NotFound,
+                429 => ErrorCategory::RateLimited,
+                500..=599 => ErrorCategory::Server,
+                _ => ErrorCategory::Api,
+            }
+        };
+        let message = if errors.is_empty() {
+            format!("Cloudflare responded with {status}")
+        } else {
+            errors
+                .iter()
+                .map(|e| format!("{} ({})", e.message, e.code))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        Self {
+            category,
+            status: Some(status.as_u16()),
+            errors,
+            message,
+            field_errors: Vec::new(),
+        }
+    }
+
+    /// Create an error for a cache mutex that could not be locked.
+    #[must_use]
+    pub fn cache() -> Self {
+        Self::new(ErrorCategory::Cache, "Failed to access the cache")
+    }
+
+    /// Create an error for input that failed validation, listing the problem with each field.
+    #[must_use]
+    pub fn validation(field_errors: Vec<FieldError>) -> Self {
+        let message = field_errors
+            .iter()
+            .map(|e| {
+                e.field.as_ref().map_or_else(
+                    || e.message.clone(),
+                    |field| format!("{field}: {}", e.message),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        Self {
+            field_errors,
+            ..Self::new(ErrorCategory::Validation, message)
+        }
+    }
+
+    /// Create an error for a command that needs verified credentials when there are none.
+    #[must_use]
+    pub fn missing_credentials() -> Self {
+        Self::new(
+            ErrorCategory::MissingCredentials,
+            "No valid Cloudflare credentials have been set",
+        )
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(
+                f,
+                "{:?} error (HTTP {status}): {}",
+                self.category, self.message
+            ),
+            None => write!(f, "{:?} error: {}", self.category, self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        let category = if e.is_decode() {
+            ErrorCategory::Parse
+        } else {
+            ErrorCategory::Network
+        };
+        Self {
+            category,
+            status: e.status().map(|s| s.as_u16()),
+            errors: Vec::new(),
+            message: e.to_string(),
+            field_errors: Vec::new(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::new(ErrorCategory::Parse, e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        Self::new(ErrorCategory::Io, e.to_string())
+    }
+}
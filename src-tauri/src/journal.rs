@@ -0,0 +1,322 @@
+//! The history of every change made to DNS records through the app, and undoing those changes.
+//!
+//! Each change is appended as one line of JSON to `journal.jsonl` in the app data directory, with the record
+//! as it was before and after. Lines are never rewritten: undoing a change makes a new change, which is
+//! journaled like any other and points back at the entry it undoes.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    api,
+    cloudflare::{DNSRecord, DNSRecordRequest},
+    error::{AppError, ErrorCategory},
+    plan::Writer,
+};
+
+/// The file the journal is kept in.
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// What a journaled change did to a record.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalAction {
+    /// The record was created.
+    Create,
+    /// The record was overwritten or patched.
+    Update,
+    /// The record was deleted.
+    Delete,
+}
+
+/// One change made to a DNS record.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct JournalEntry {
+    /// Identifier for the entry, used to undo it.
+    pub id: String,
+    /// When the change was made.
+    pub at: DateTime<Utc>,
+    /// The profile the change was made with.
+    pub profile: String,
+    /// The zone identifier.
+    pub zone_id: String,
+    /// The zone name.
+    pub zone_name: String,
+    /// Identifier for the record that was changed.
+    pub record_id: String,
+    /// What the change did.
+    pub action: JournalAction,
+    /// The record before the change, unless it was created.
+    pub before: Option<DNSRecord>,
+    /// The record after the change, unless it was deleted.
+    pub after: Option<DNSRecord>,
+    /// The entry this change undid, if it was an undo.
+    pub undoes: Option<String>,
+}
+
+impl JournalEntry {
+    /// A new entry for a change made just now.
+    #[must_use]
+    pub fn new(
+        profile: &str,
+        zone_id: &str,
+        zone_name: &str,
+        before: Option<DNSRecord>,
+        after: Option<DNSRecord>,
+    ) -> Self {
+        let action = match (&before, &after) {
+            (None, _) => JournalAction::Create,
+            (Some(_), Some(_)) => JournalAction::Update,
+            (Some(_), None) => JournalAction::Delete,
+        };
+        let record_id = after
+            .as_ref()
+            .or(before.as_ref())
+            .map(|r| r.id.clone())
+            .unwrap_or_default();
+        Self {
+            id: format!("{:032x}", rand::random::<u128>()),
+            at: Utc::now(),
+            profile: profile.to_string(),
+            zone_id: zone_id.to_string(),
+            zone_name: zone_name.to_string(),
+            record_id,
+            action,
+            before,
+            after,
+            undoes: None,
+        }
+    }
+
+    /// Whether the entry is about the record `record_id`, whether it was created, changed or deleted.
+    fn concerns(&self, record_id: &str) -> bool {
+        self.record_id == record_id
+            || self.before.as_ref().is_some_and(|r| r.id == record_id)
+            || self.after.as_ref().is_some_and(|r| r.id == record_id)
+    }
+}
+
+/// The journal file in the app data directory. This is held in Tauri managed state.
+#[derive(Debug)]
+pub struct Journal {
+    /// Where the journal is kept.
+    path: PathBuf,
+    /// Held while the file is appended to, so concurrent changes don't interleave their lines.
+    lock: Mutex<()>,
+}
+
+impl Journal {
+    /// The journal file in `data_dir`.
+    #[must_use]
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(JOURNAL_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Add an entry to the end of the journal.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file can't be written.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the file lock is poisoned.
+    pub fn append(&self, entry: &JournalEntry) -> Result<(), AppError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        #[allow(clippy::unwrap_used)]
+        let _guard = self.lock.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Every entry in the journal, oldest first. Lines that can't be read are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file exists but can't be read.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the file lock is poisoned.
+    pub fn entries(&self) -> Result<Vec<JournalEntry>, AppError> {
+        #[allow(clippy::unwrap_used)]
+        let _guard = self.lock.lock().unwrap();
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(&self.path)?;
+        Ok(text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!("Skipping line {} of the journal: {e}", i + 1);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// The changes made to a zone, or to one record of it, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file exists but can't be read.
+    pub fn history(
+        &self,
+        zone_id: &str,
+        record_id: Option<&str>,
+    ) -> Result<Vec<JournalEntry>, AppError> {
+        let mut entries: Vec<JournalEntry> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.zone_id == zone_id)
+            .filter(|entry| record_id.is_none_or(|id| entry.concerns(id)))
+            .collect();
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Find an entry by its identifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if there is no such entry, or the file can't be read.
+    pub fn find(&self, entry_id: &str) -> Result<JournalEntry, AppError> {
+        self.entries()?
+            .into_iter()
+            .find(|entry| entry.id == entry_id)
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorCategory::NotFound,
+                    format!("There is no change {entry_id} in the history"),
+                )
+            })
+    }
+}
+
+/// What happens to an undo whose record has been changed since.
+const UNDO_REFUSED: &str = "so the change can't be undone";
+
+/// Fetch the records now at the name of `deleted` and check none has taken its place since it was deleted:
+/// the same record created again, or a record that can't share a name with it, like a CNAME.
+///
+/// The zone's cached records at that name are replaced with the fetched ones, so validation sees them.
+async fn free_since(
+    writer: &Writer<'_>,
+    zone_id: &str,
+    deleted: &DNSRecord,
+) -> Result<(), AppError> {
+    let live = api::get_dns_records_named(writer.client, zone_id, &deleted.name).await?;
+    let (_, cached) = writer.zone(zone_id)?;
+    for record in cached
+        .iter()
+        .filter(|r| r.name.eq_ignore_ascii_case(&deleted.name))
+        .filter(|r| !live.iter().any(|l| l.id == r.id))
+    {
+        writer.remove_record(zone_id, &record.id);
+    }
+    for record in &live {
+        writer.put_record(zone_id, record.clone());
+    }
+
+    let cname = |record: &DNSRecord| record.content.record_type() == "CNAME";
+    if let Some(conflict) = live
+        .iter()
+        .find(|r| r.content == deleted.content || cname(r) || cname(deleted))
+    {
+        let message = if conflict.content == deleted.content {
+            format!(
+                "{} has been created again since, so the deletion can't be undone",
+                deleted.name
+            )
+        } else {
+            format!(
+                "{} has a {} record since, so the deletion can't be undone",
+                deleted.name,
+                conflict.content.record_type()
+            )
+        };
+        return Err(AppError::new(ErrorCategory::Validation, message));
+    }
+    Ok(())
+}
+
+/// Undo a journaled change by making the opposite change.
+///
+/// A created record is deleted, an updated record is put back as it was, and a deleted record is created
+/// again. Returns the record as it is afterwards, unless it was deleted.
+///
+/// The change is refused if the record has been changed, deleted or recreated since, or, for a deletion, if
+/// a record that conflicts with it has been created at its name, so an undo never overwrites someone else's
+/// work.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the change has already been undone, the record has drifted since,
+/// or the opposite change fails.
+pub async fn undo(
+    writer: &Writer<'_>,
+    entry: &JournalEntry,
+) -> Result<Option<DNSRecord>, AppError> {
+    if writer
+        .journal
+        .entries()?
+        .iter()
+        .any(|e| e.undoes.as_deref() == Some(entry.id.as_str()))
+    {
+        return Err(AppError::new(
+            ErrorCategory::Validation,
+            "This change has already been undone",
+        ));
+    }
+    let zone_id = entry.zone_id.as_str();
+    match (&entry.before, &entry.after) {
+        (None, Some(after)) => {
+            let live = writer.unchanged_since(zone_id, after, UNDO_REFUSED).await?;
+            writer.delete_record(zone_id, &live.id).await?;
+            Ok(None)
+        }
+        (Some(before), Some(after)) => {
+            let live = writer.unchanged_since(zone_id, after, UNDO_REFUSED).await?;
+            writer
+                .update_record(zone_id, &live.id, &DNSRecordRequest::from(before))
+                .await
+                .map(Some)
+        }
+        (Some(before), None) => {
+            free_since(writer, zone_id, before).await?;
+            writer
+                .create_record(zone_id, &DNSRecordRequest::from(before))
+                .await
+                .map(Some)
+        }
+        (None, None) => Err(AppError::new(
+            ErrorCategory::Validation,
+            "This change has no record to restore",
+        )),
+    }
+}
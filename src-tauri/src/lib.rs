@@ -1,20 +1,63 @@
 //! A Tauri app to provide a fast and responsive UI for changing cloudflare DNS.
+//!
+//! The Tauri app is behind the default `gui` feature. Without it, only the modules the `cfdns` command-line
+//! tool needs are built.
 
-use std::sync::Mutex;
-
+#[cfg(feature = "gui")]
+use client::{ClientConfig, CloudflareClient};
+#[cfg(feature = "gui")]
+use credentials::{CredentialStore, SecretStorage};
+#[cfg(feature = "gui")]
+use ddns::Ddns;
+#[cfg(feature = "gui")]
+use journal::Journal;
+#[cfg(feature = "gui")]
 use models::ManagedCache;
+#[cfg(feature = "gui")]
+use profiles::Profiles;
+#[cfg(feature = "gui")]
+use propagation::Propagation;
+#[cfg(feature = "gui")]
+use rate_limit::ThrottleListener;
+#[cfg(feature = "gui")]
+use snapshots::SnapshotStore;
+#[cfg(feature = "gui")]
+use tauri::Manager;
 
 #[allow(clippy::used_underscore_binding)]
 pub mod api;
+pub mod audit;
+pub mod client;
 pub mod cloudflare;
+#[cfg(feature = "gui")]
 pub mod commands;
+pub mod credentials;
+pub mod ddns;
+pub mod desired;
+pub mod disk_cache;
+pub mod drift;
+pub mod email_auth;
+pub mod error;
+pub mod journal;
+#[cfg(feature = "mock-server")]
+pub mod mock;
 pub mod models;
+pub mod plan;
+pub mod profiles;
+pub mod propagation;
+pub mod rate_limit;
+pub mod records;
+pub mod snapshots;
+pub mod spf;
+pub mod validation;
+pub mod zonefile;
 
 /// Entry point for the Tauri application.
 ///
 /// # Panics
 ///
 /// This will panic if the app fails to run
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tracing_subscriber::fmt::init();
@@ -26,13 +69,90 @@ pub fn run() {
             commands::get_user_details,
             commands::get_zone_dns,
             commands::initialize_cf,
+            commands::refresh_cache,
+            commands::get_cache_status,
+            commands::check_drift,
             commands::set_api_token,
+            commands::save_credentials,
+            commands::load_credentials,
+            commands::forget_credentials,
+            commands::list_profiles,
+            commands::add_profile,
+            commands::remove_profile,
+            commands::switch_profile,
+            commands::validate_dns_record,
+            commands::export_zone_file,
+            commands::preview_zone_import,
+            commands::apply_zone_plan,
+            commands::plan_desired_state,
+            commands::apply_desired_state,
+            commands::import_zone_file_via_cloudflare,
+            commands::create_dns_record,
+            commands::update_dns_record,
+            commands::patch_dns_record,
+            commands::delete_dns_record,
+            commands::list_history,
+            commands::undo_change,
+            commands::take_snapshot,
+            commands::list_snapshots,
+            commands::delete_snapshot,
+            commands::compare_snapshots,
+            commands::plan_snapshot_restore,
+            commands::get_ddns_settings,
+            commands::set_ddns_source,
+            commands::set_record_dynamic,
+            commands::run_ddns,
+            commands::get_resolvers,
+            commands::set_resolvers,
+            commands::check_propagation,
+            commands::audit_zones,
+            commands::analyze_spf,
+            commands::check_email_auth,
+            commands::generate_email_record,
         ])
-        .manage(ManagedCache {
-            zones: Mutex::default(),
-            zone_dns: Mutex::default(),
-            api_token: Mutex::default(),
-            user_details: Mutex::default(),
+        .manage(ManagedCache::default())
+        .manage(
+            CloudflareClient::new(ClientConfig::default())
+                .expect("error while creating the Cloudflare API client"),
+        )
+        .setup(|app| {
+            // The encrypted fallback for credentials, the profile list, the cache, the change journal, the zone
+            // snapshots, the DDNS settings and the resolvers live in the app data directory.
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(Journal::new(&data_dir));
+            app.manage(SnapshotStore::new(&data_dir));
+            app.manage(Ddns::load(&data_dir));
+            app.manage(Propagation::load(&data_dir));
+            let profiles = Profiles::load(&data_dir);
+            let storage = SecretStorage::new(data_dir);
+
+            // Show the cache from the last run straight away, then refresh it in the background.
+            let _ = profiles.restore_from_disk(&app.state::<ManagedCache>());
+            let credential = storage.load(&profiles.active()).unwrap_or_else(|e| {
+                tracing::warn!("Failed to load the saved credentials: {e}");
+                None
+            });
+            app.manage(profiles);
+            app.manage(storage);
+
+            // Let the frontend show when requests are being throttled and retried.
+            let handle = app.handle().clone();
+            app.state::<CloudflareClient>()
+                .set_throttle_listener(ThrottleListener::new(move |event| {
+                    if let Err(e) = handle.emit("api-throttled", event.clone()) {
+                        tracing::error!("Failed to emit api-throttled: {e}");
+                    }
+                }));
+            if let Some(credential) = credential {
+                app.state::<CloudflareClient>()
+                    .set_authorizer(credential.authorizer());
+                commands::spawn_refresh(app.handle().clone());
+            }
+            // Notice changes made outside the app, e.g. in the Cloudflare dashboard.
+            commands::spawn_drift_watch(app.handle().clone());
+            // Keep dynamic records pointed at the public address.
+            commands::spawn_ddns(app.handle().clone());
+            Ok(())
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
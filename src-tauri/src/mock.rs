@@ -0,0 +1,748 @@
+//! An in-process stand-in for the Cloudflare API, for tests. Only built with the `mock-server` feature.
+//!
+//! The server listens on a random local port and serves the JSON fixtures in `fixtures/` from the
+//! same paths the real API uses, and keeps the DNS records created, changed and deleted through it, so pointing [`ClientConfig::base_url`] at [`MockCloudflare::base_url`]
+//! is all it takes to run the app against it.
+//!
+//! [`ClientConfig::base_url`]: crate::client::ClientConfig::base_url
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::header::RETRY_AFTER,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use hickory_resolver::proto::{
+    op::{Message, MessageType, ResponseCode},
+    rr::Record,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{
+    net::{TcpListener, UdpSocket},
+    task::JoinHandle,
+};
+
+use crate::ddns::IpSource;
+
+/// The bearer token the mock server accepts.
+pub const MOCK_TOKEN: &str = "mock-api-token";
+
+/// The global API key the mock server accepts, along with the email address of the fixture user.
+pub const MOCK_GLOBAL_API_KEY: &str = "144c9defac04969c7bfad8efaa8ea194";
+
+/// The data served by the mock server. Tests can change it between requests through [`MockCloudflare::state`].
+#[derive(Debug, Clone)]
+pub struct MockState {
+    /// The bearer token requests can be authorized with.
+    pub token: String,
+    /// The global API key requests can be authorized with, along with the email address in [`MockState::user`].
+    pub global_api_key: String,
+    /// The response to `GET /user`.
+    pub user: Value,
+    /// The zones listed by `GET /zones`.
+    pub zones: Vec<Value>,
+    /// The DNS records listed by `GET /zones/{zone_id}/dns_records`, keyed by zone ID.
+    pub dns_records: HashMap<String, Vec<Value>>,
+    /// Failures to answer the next requests with, in order, before serving anything.
+    pub failures: VecDeque<MockFailure>,
+    /// How many requests have been received.
+    pub requests: usize,
+    /// The address `GET /echo/ipv4` responds with, standing in for the public IPv4 address.
+    pub public_ipv4: String,
+    /// The address `GET /echo/ipv6` responds with, standing in for the public IPv6 address.
+    pub public_ipv6: String,
+}
+
+/// A failure the mock server answers a request with, e.g. to test retries.
+#[derive(Debug, Clone, Copy)]
+pub struct MockFailure {
+    /// The HTTP status code to respond with.
+    pub status: u16,
+    /// The value of the `Retry-After` header, in seconds, if one should be sent.
+    pub retry_after: Option<u64>,
+}
+
+impl MockFailure {
+    /// A 429 response, as Cloudflare sends when the rate limit is exceeded.
+    #[must_use]
+    pub const fn rate_limited(retry_after: Option<u64>) -> Self {
+        Self {
+            status: 429,
+            retry_after,
+        }
+    }
+
+    /// A 5xx response with the given status.
+    #[must_use]
+    pub const fn server_error(status: u16) -> Self {
+        Self {
+            status,
+            retry_after: None,
+        }
+    }
+}
+
+impl IntoResponse for MockFailure {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = if status == StatusCode::TOO_MANY_REQUESTS {
+            error_response(
+                status,
+                971,
+                "Please wait and consider throttling your request speed",
+            )
+        } else {
+            error_response(status, 10001, "Internal server error")
+        };
+        if let Some(retry_after) = self.retry_after {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after.into());
+        }
+        response
+    }
+}
+
+impl MockState {
+    /// The state described by the JSON files in `fixtures/`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the fixtures are not valid JSON (they are compiled in, so this is a bug).
+    #[must_use]
+    pub fn from_fixtures() -> Self {
+        Self {
+            token: MOCK_TOKEN.to_string(),
+            global_api_key: MOCK_GLOBAL_API_KEY.to_string(),
+            user: serde_json::from_str(include_str!("../fixtures/user.json"))
+                .expect("fixtures/user.json is valid JSON"),
+            zones: serde_json::from_str(include_str!("../fixtures/zones.json"))
+                .expect("fixtures/zones.json is valid JSON"),
+            dns_records: serde_json::from_str(include_str!("../fixtures/dns_records.json"))
+                .expect("fixtures/dns_records.json is valid JSON"),
+            failures: VecDeque::new(),
+            requests: 0,
+            public_ipv4: "203.0.113.77".to_string(),
+            public_ipv6: "2001:db8::77".to_string(),
+        }
+    }
+}
+
+/// A running mock Cloudflare API. The server is stopped when this is dropped.
+#[derive(Debug)]
+pub struct MockCloudflare {
+    /// The base URL to point the client at.
+    base_url: String,
+    /// The echo service answering with [`MockState::public_ipv4`] and [`MockState::public_ipv6`].
+    ip_source: IpSource,
+    /// The data being served.
+    state: Arc<Mutex<MockState>>,
+    /// The task running the server.
+    server: JoinHandle<()>,
+}
+
+impl MockCloudflare {
+    /// Start a mock server serving the fixtures.
+    ///
+    /// This must be called from within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if a local port can't be bound.
+    pub async fn start() -> std::io::Result<Self> {
+        Self::start_with(MockState::from_fixtures()).await
+    }
+
+    /// Start a mock server serving the given state.
+    ///
+    /// This must be called from within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if a local port can't be bound.
+    pub async fn start_with(state: MockState) -> std::io::Result<Self> {
+        let state = Arc::new(Mutex::new(state));
+        let app = Router::new()
+            .route("/client/v4/user", get(get_user))
+            .route("/client/v4/zones", get(list_zones))
+            .route(
+                "/client/v4/zones/:zone_id/dns_records",
+                get(list_dns_records).post(create_dns_record),
+            )
+            .route(
+                "/client/v4/zones/:zone_id/dns_records/import",
+                post(import_dns_records),
+            )
+            .route(
+                "/client/v4/zones/:zone_id/dns_records/:record_id",
+                get(get_dns_record)
+                    .put(update_dns_record)
+                    .patch(patch_dns_record)
+                    .delete(delete_dns_record),
+            )
+            .route("/echo/ipv4", get(echo_ipv4))
+            .route("/echo/ipv6", get(echo_ipv6))
+            .with_state(Arc::clone(&state));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let origin = format!("http://{}", listener.local_addr()?);
+        let base_url = format!("{origin}/client/v4");
+        let ip_source = IpSource::Echo {
+            ipv4_url: format!("{origin}/echo/ipv4"),
+            ipv6_url: format!("{origin}/echo/ipv6"),
+        };
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("Mock Cloudflare server stopped: {e}");
+            }
+        });
+
+        Ok(Self {
+            base_url,
+            ip_source,
+            state,
+            server,
+        })
+    }
+
+    /// The base URL of the mock API, e.g. `http://127.0.0.1:49152/client/v4`.
+    #[must_use]
+    pub fn base_url(&self) -> String {
+        self.base_url.clone()
+    }
+
+    /// An echo service on the mock server, to detect the public addresses in [`MockState`] with.
+    #[must_use]
+    pub fn ip_source(&self) -> IpSource {
+        self.ip_source.clone()
+    }
+
+    /// The data being served, so tests can change it.
+    #[must_use]
+    pub fn state(&self) -> Arc<Mutex<MockState>> {
+        Arc::clone(&self.state)
+    }
+}
+
+impl Drop for MockCloudflare {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Shared state handed to the route handlers.
+type SharedState = Arc<Mutex<MockState>>;
+
+/// Pagination query parameters accepted by the list endpoints.
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    /// The page to return, starting at 1.
+    page: Option<usize>,
+    /// How many items to return per page.
+    per_page: Option<usize>,
+    /// Only list the DNS records with exactly this name.
+    name: Option<String>,
+}
+
+/// A Cloudflare error response with a single error.
+fn error_response(status: StatusCode, code: u32, message: &str) -> Response {
+    let body = json!({
+        "result": null,
+        "success": false,
+        "errors": [{ "code": code, "message": message }],
+        "messages": [],
+    });
+    (status, Json(body)).into_response()
+}
+
+/// Check the request is authorized with the expected bearer token or global API key and email,
+/// returning the error response if it isn't.
+///
+/// Like the real API, missing headers are a 400, a wrong token is a 403 and a wrong key or email is a 403.
+fn reject_unauthorized(headers: &HeaderMap, state: &MockState) -> Option<Response> {
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok());
+
+    if let Some(authorization) = header("Authorization") {
+        return if authorization.strip_prefix("Bearer ") == Some(state.token.as_str()) {
+            None
+        } else {
+            Some(error_response(
+                StatusCode::FORBIDDEN,
+                9109,
+                "Invalid access token",
+            ))
+        };
+    }
+
+    match (header("X-Auth-Email"), header("X-Auth-Key")) {
+        (Some(email), Some(key)) => {
+            if Some(email) == state.user["email"].as_str() && key == state.global_api_key {
+                None
+            } else {
+                Some(error_response(
+                    StatusCode::FORBIDDEN,
+                    9103,
+                    "Unknown X-Auth-Key or X-Auth-Email",
+                ))
+            }
+        }
+        _ => Some(error_response(
+            StatusCode::BAD_REQUEST,
+            9106,
+            "Missing X-Auth-Key, X-Auth-Email or Authorization headers",
+        )),
+    }
+}
+
+/// Count a request and take a copy of the state to answer it from, or the next queued failure to answer it with.
+#[allow(clippy::unwrap_used)]
+fn begin_request(state: &SharedState) -> Result<MockState, MockFailure> {
+    let mut state = state.lock().unwrap();
+    state.requests += 1;
+    if let Some(failure) = state.failures.pop_front() {
+        return Err(failure);
+    }
+    Ok(state.clone())
+}
+
+/// A successful response with pagination information.
+fn paginated_response(items: &[Value], query: &PageQuery) -> Response {
+    let per_page = query.per_page.unwrap_or(20).max(1);
+    let page = query.page.unwrap_or(1).max(1);
+    let total_pages = items.len().div_ceil(per_page);
+    let result: Vec<Value> = items
+        .iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .cloned()
+        .collect();
+    let body = json!({
+        "result_info": {
+            "page": page,
+            "per_page": per_page,
+            "total_pages": total_pages,
+            "count": result.len(),
+            "total_count": items.len(),
+        },
+        "result": result,
+        "success": true,
+        "errors": [],
+        "messages": [],
+    });
+    Json(body).into_response()
+}
+
+/// `GET /user`
+async fn get_user(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    let state = match begin_request(&state) {
+        Ok(state) => state,
+        Err(failure) => return failure.into_response(),
+    };
+    if let Some(response) = reject_unauthorized(&headers, &state) {
+        return response;
+    }
+    Json(json!({
+        "result": state.user,
+        "success": true,
+        "errors": [],
+        "messages": [],
+    }))
+    .into_response()
+}
+
+/// `GET /zones`
+async fn list_zones(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<PageQuery>,
+) -> Response {
+    let state = match begin_request(&state) {
+        Ok(state) => state,
+        Err(failure) => return failure.into_response(),
+    };
+    if let Some(response) = reject_unauthorized(&headers, &state) {
+        return response;
+    }
+    paginated_response(&state.zones, &query)
+}
+
+/// `GET /zones/{zone_id}/dns_records`
+async fn list_dns_records(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(zone_id): Path<String>,
+    Query(query): Query<PageQuery>,
+) -> Response {
+    let state = match begin_request(&state) {
+        Ok(state) => state,
+        Err(failure) => return failure.into_response(),
+    };
+    if let Some(response) = reject_unauthorized(&headers, &state) {
+        return response;
+    }
+    state.dns_records.get(&zone_id).map_or_else(
+        || {
+            error_response(
+                StatusCode::NOT_FOUND,
+                7003,
+                "Could not route to /zones/dns_records, perhaps your object identifier is invalid?",
+            )
+        },
+        |records| {
+            let named: Vec<Value> = records
+                .iter()
+                .filter(|record| {
+                    query.name.as_deref().is_none_or(|name| {
+                        record["name"]
+                            .as_str()
+                            .is_some_and(|n| n.eq_ignore_ascii_case(name))
+                    })
+                })
+                .cloned()
+                .collect();
+            paginated_response(&named, &query)
+        },
+    )
+}
+
+/// A successful response with a single result.
+fn result_response(result: Value) -> Response {
+    Json(json!({
+        "result": result,
+        "success": true,
+        "errors": [],
+        "messages": [],
+    }))
+    .into_response()
+}
+
+/// The response when a record doesn't exist.
+fn record_not_found() -> Response {
+    error_response(StatusCode::NOT_FOUND, 81044, "Record does not exist.")
+}
+
+/// Fill in the fields Cloudflare sets on a record it stores, keeping the ones sent.
+fn stored_record(state: &MockState, zone_id: &str, id: &str, mut record: Value) -> Value {
+    let zone_name = state
+        .zones
+        .iter()
+        .find(|zone| zone["id"] == zone_id)
+        .map_or(Value::Null, |zone| zone["name"].clone());
+    let proxiable = matches!(record["type"].as_str(), Some("A" | "AAAA" | "CNAME"));
+    let defaults = json!({
+        "id": id,
+        "zone_id": zone_id,
+        "zone_name": zone_name,
+        "proxiable": proxiable,
+        "proxied": false,
+        "locked": false,
+        "comment": null,
+        "tags": [],
+        "meta": { "auto_added": false, "source": "primary" },
+        "created_on": "2024-01-01T00:00:00Z",
+        "modified_on": "2024-01-01T00:00:00Z",
+    });
+    if let (Some(record), Some(defaults)) = (record.as_object_mut(), defaults.as_object()) {
+        for (key, value) in defaults {
+            if record.get(key).is_none_or(Value::is_null) || key == "id" || key == "proxiable" {
+                record.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    record
+}
+
+/// Answer a request that changes a zone's records: count it, check it's authorized, and run `change` on the
+/// zone's records if the zone exists.
+#[allow(clippy::unwrap_used)]
+fn change_records(
+    state: &SharedState,
+    headers: &HeaderMap,
+    zone_id: &str,
+    change: impl FnOnce(&MockState, &mut Vec<Value>) -> Response,
+) -> Response {
+    let snapshot = match begin_request(state) {
+        Ok(state) => state,
+        Err(failure) => return failure.into_response(),
+    };
+    if let Some(response) = reject_unauthorized(headers, &snapshot) {
+        return response;
+    }
+    let mut state = state.lock().unwrap();
+    let response = state.dns_records.get_mut(zone_id).map_or_else(
+        || {
+            error_response(
+                StatusCode::NOT_FOUND,
+                7003,
+                "Could not route to /zones/dns_records, perhaps your object identifier is invalid?",
+            )
+        },
+        |records| change(&snapshot, records),
+    );
+    drop(state);
+    response
+}
+
+/// A new record ID.
+fn new_record_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// `POST /zones/{zone_id}/dns_records`
+async fn create_dns_record(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(zone_id): Path<String>,
+    Json(body): Json<Value>,
+) -> Response {
+    change_records(&state, &headers, &zone_id, |snapshot, records| {
+        let record = stored_record(snapshot, &zone_id, &new_record_id(), body);
+        records.push(record.clone());
+        result_response(record)
+    })
+}
+
+/// `GET /zones/{zone_id}/dns_records/{record_id}`
+async fn get_dns_record(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path((zone_id, record_id)): Path<(String, String)>,
+) -> Response {
+    change_records(&state, &headers, &zone_id, |_, records| {
+        records
+            .iter()
+            .find(|r| r["id"] == record_id.as_str())
+            .map_or_else(record_not_found, |record| result_response(record.clone()))
+    })
+}
+
+/// `PUT /zones/{zone_id}/dns_records/{record_id}`
+async fn update_dns_record(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path((zone_id, record_id)): Path<(String, String)>,
+    Json(body): Json<Value>,
+) -> Response {
+    change_records(&state, &headers, &zone_id, |snapshot, records| {
+        let Some(record) = records.iter_mut().find(|r| r["id"] == record_id.as_str()) else {
+            return record_not_found();
+        };
+        *record = stored_record(snapshot, &zone_id, &record_id, body);
+        result_response(record.clone())
+    })
+}
+
+/// `PATCH /zones/{zone_id}/dns_records/{record_id}`
+async fn patch_dns_record(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path((zone_id, record_id)): Path<(String, String)>,
+    Json(body): Json<Value>,
+) -> Response {
+    change_records(&state, &headers, &zone_id, |snapshot, records| {
+        let Some(record) = records.iter_mut().find(|r| r["id"] == record_id.as_str()) else {
+            return record_not_found();
+        };
+        let mut patched = record.clone();
+        if let (Some(patched), Some(body)) = (patched.as_object_mut(), body.as_object()) {
+            patched.extend(body.clone());
+        }
+        *record = stored_record(snapshot, &zone_id, &record_id, patched);
+        result_response(record.clone())
+    })
+}
+
+/// `DELETE /zones/{zone_id}/dns_records/{record_id}`
+async fn delete_dns_record(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path((zone_id, record_id)): Path<(String, String)>,
+) -> Response {
+    change_records(&state, &headers, &zone_id, |_, records| {
+        let Some(index) = records.iter().position(|r| r["id"] == record_id.as_str()) else {
+            return record_not_found();
+        };
+        records.remove(index);
+        result_response(json!({ "id": record_id }))
+    })
+}
+
+/// `POST /zones/{zone_id}/dns_records/import`, reading the zone file with the app's own parser. Like the real
+/// API, records that already exist are skipped.
+async fn import_dns_records(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(zone_id): Path<String>,
+    mut multipart: Multipart,
+) -> Response {
+    let mut file = None;
+    let mut proxied = false;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name() {
+            Some("file") => file = field.text().await.ok(),
+            Some("proxied") => proxied = field.text().await.is_ok_and(|p| p == "true"),
+            _ => {}
+        }
+    }
+    let Some(file) = file else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            1004,
+            "DNS Validation Error: missing file",
+        );
+    };
+
+    change_records(&state, &headers, &zone_id, |snapshot, records| {
+        let zone_name = snapshot
+            .zones
+            .iter()
+            .find(|zone| zone["id"] == zone_id.as_str())
+            .and_then(|zone| zone["name"].as_str())
+            .unwrap_or_default();
+        let parsed = match crate::zonefile::parse(&file, zone_name, proxied) {
+            Ok(parsed) => parsed,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, 1004, &e.message),
+        };
+        let total = parsed.records.len();
+        let mut added = 0;
+        for request in parsed.records {
+            let Ok(request) = serde_json::to_value(request) else {
+                continue;
+            };
+            let exists = records.iter().any(|r| {
+                r["type"] == request["type"]
+                    && r["name"] == request["name"]
+                    && r["content"] == request["content"]
+                    && r["data"] == request["data"]
+            });
+            if !exists {
+                records.push(stored_record(snapshot, &zone_id, &new_record_id(), request));
+                added += 1;
+            }
+        }
+        result_response(json!({ "recs_added": added, "total_records_parsed": total }))
+    })
+}
+
+/// Respond with the public IPv4 address as plain text, like an echo service. Not counted as an API request.
+#[allow(clippy::unwrap_used)]
+async fn echo_ipv4(State(state): State<SharedState>) -> String {
+    state.lock().unwrap().public_ipv4.clone()
+}
+
+/// Respond with the public IPv6 address as plain text, like an echo service. Not counted as an API request.
+#[allow(clippy::unwrap_used)]
+async fn echo_ipv6(State(state): State<SharedState>) -> String {
+    state.lock().unwrap().public_ipv6.clone()
+}
+
+/// A running stand-in DNS server, answering queries over UDP from a list of records. The server is stopped
+/// when this is dropped.
+///
+/// It answers every query authoritatively: with the records of the name and type asked for, with no records
+/// if the name has records of other types, and with `NXDOMAIN` otherwise.
+#[derive(Debug)]
+pub struct MockDns {
+    /// The address the server listens on.
+    address: SocketAddr,
+    /// The records being served.
+    records: Arc<Mutex<Vec<Record>>>,
+    /// The task running the server.
+    server: JoinHandle<()>,
+}
+
+impl MockDns {
+    /// Start a DNS server serving `records`.
+    ///
+    /// This must be called from within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// This will return an error if a local port can't be bound.
+    pub async fn start(records: Vec<Record>) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind("127.0.0.1:0").await?;
+        let address = socket.local_addr()?;
+        let records = Arc::new(Mutex::new(records));
+        let answering = Arc::clone(&records);
+        let server = tokio::spawn(async move {
+            let mut buffer = [0_u8; 4096];
+            loop {
+                let Ok((length, from)) = socket.recv_from(&mut buffer).await else {
+                    continue;
+                };
+                let Some(response) = dns_response(&buffer[..length], &answering) else {
+                    continue;
+                };
+                if let Err(e) = socket.send_to(&response, from).await {
+                    tracing::error!("Mock DNS server failed to respond: {e}");
+                }
+            }
+        });
+        Ok(Self {
+            address,
+            records,
+            server,
+        })
+    }
+
+    /// The address the server listens on.
+    #[must_use]
+    pub const fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The records being served, so tests can change them.
+    #[must_use]
+    pub fn records(&self) -> Arc<Mutex<Vec<Record>>> {
+        Arc::clone(&self.records)
+    }
+}
+
+impl Drop for MockDns {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// The response to a DNS query, or `None` if it can't be read.
+#[allow(clippy::unwrap_used)]
+fn dns_response(query: &[u8], records: &Mutex<Vec<Record>>) -> Option<Vec<u8>> {
+    let query = Message::from_vec(query).ok()?;
+    let question = query.queries().first()?.clone();
+    let records = records.lock().unwrap();
+    let at_name: Vec<&Record> = records
+        .iter()
+        .filter(|r| r.name() == question.name())
+        .collect();
+    let mut response = Message::new();
+    response
+        .set_id(query.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(query.op_code())
+        .set_authoritative(true)
+        .set_recursion_desired(query.recursion_desired())
+        .set_recursion_available(true)
+        .set_response_code(if at_name.is_empty() {
+            ResponseCode::NXDomain
+        } else {
+            ResponseCode::NoError
+        })
+        .add_query(question.clone())
+        .add_answers(
+            at_name
+                .into_iter()
+                .filter(|r| r.record_type() == question.query_type())
+                .cloned(),
+        );
+    drop(records);
+    response.to_vec().ok()
+}
@@ -1,6 +1,10 @@
 //! Data models for the application.
 
-use crate::cloudflare::{CloudflareListZonesResponse, DNSRecord};
+use crate::{
+    cloudflare::{CloudflareListZonesResponse, DNSRecord},
+    error::{AppError, ErrorCategory},
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::Mutex};
 use ts_rs::TS;
@@ -20,14 +24,416 @@ pub struct CustomUserDetails {
 }
 
 /// Managed cache of the queries that are supported
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct ManagedCache {
-    /// Cloudflare API token (bearer token)
-    pub api_token: Mutex<String>,
     /// Zone IDs
     pub zones: Mutex<Vec<CloudflareListZonesResponse>>,
     /// Map of zone IDs to DNS records vectors
     pub zone_dns: Mutex<HashMap<String, Vec<DNSRecord>>>,
     /// User details (verify API key)
     pub user_details: Mutex<Option<CustomUserDetails>>,
+    /// When the zones were fetched from Cloudflare
+    pub fetched_at: Mutex<Option<DateTime<Utc>>>,
+    /// Map of zone IDs to when their DNS records were fetched from Cloudflare
+    pub zone_fetched_at: Mutex<HashMap<String, DateTime<Utc>>>,
+    /// Map of zone IDs to why their DNS records failed to load in the last refresh
+    pub zone_errors: Mutex<HashMap<String, AppError>>,
+    /// Map of zone IDs to how many times the app has written one of their cached records, so records
+    /// fetched before a write can be told apart from ones fetched after it
+    #[serde(skip)]
+    pub zone_writes: Mutex<HashMap<String, u64>>,
+}
+
+impl ManagedCache {
+    /// Take everything out of the cache, leaving it empty.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    #[must_use]
+    pub fn take(&self) -> ProfileCache {
+        #[allow(clippy::unwrap_used)]
+        ProfileCache {
+            zones: std::mem::take(&mut *self.zones.lock().unwrap()),
+            zone_dns: std::mem::take(&mut *self.zone_dns.lock().unwrap()),
+            user_details: self.user_details.lock().unwrap().take(),
+            fetched_at: self.fetched_at.lock().unwrap().take(),
+            zone_fetched_at: std::mem::take(&mut *self.zone_fetched_at.lock().unwrap()),
+            zone_errors: std::mem::take(&mut *self.zone_errors.lock().unwrap()),
+        }
+    }
+
+    /// Copy everything in the cache.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    #[must_use]
+    pub fn snapshot(&self) -> ProfileCache {
+        #[allow(clippy::unwrap_used)]
+        ProfileCache {
+            zones: self.zones.lock().unwrap().clone(),
+            zone_dns: self.zone_dns.lock().unwrap().clone(),
+            user_details: self.user_details.lock().unwrap().clone(),
+            fetched_at: *self.fetched_at.lock().unwrap(),
+            zone_fetched_at: self.zone_fetched_at.lock().unwrap().clone(),
+            zone_errors: self.zone_errors.lock().unwrap().clone(),
+        }
+    }
+
+    /// Replace the contents of the cache.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn restore(&self, cache: ProfileCache) {
+        #[allow(clippy::unwrap_used)]
+        {
+            *self.zones.lock().unwrap() = cache.zones;
+            *self.zone_dns.lock().unwrap() = cache.zone_dns;
+            *self.user_details.lock().unwrap() = cache.user_details;
+            *self.fetched_at.lock().unwrap() = cache.fetched_at;
+            *self.zone_fetched_at.lock().unwrap() = cache.zone_fetched_at;
+            *self.zone_errors.lock().unwrap() = cache.zone_errors;
+        }
+    }
+
+    /// Replace the contents of the cache with freshly fetched data, keeping the cached records of zones the
+    /// app wrote to since `writes` was taken with [`Self::zone_writes`], as the fetched ones may predate the
+    /// write.
+    ///
+    /// Returns what the cache now holds.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn restore_fetched(
+        &self,
+        mut cache: ProfileCache,
+        writes: &HashMap<String, u64>,
+    ) -> ProfileCache {
+        #[allow(clippy::unwrap_used)]
+        let mut zone_dns = self.zone_dns.lock().unwrap();
+        for (zone_id, records) in zone_dns.iter() {
+            if self.written_since(zone_id, writes) && cache.zones.iter().any(|z| &z.id == zone_id) {
+                cache.zone_dns.insert(zone_id.clone(), records.clone());
+            }
+        }
+        zone_dns.clone_from(&cache.zone_dns);
+        drop(zone_dns);
+        #[allow(clippy::unwrap_used)]
+        {
+            self.zones.lock().unwrap().clone_from(&cache.zones);
+            self.user_details
+                .lock()
+                .unwrap()
+                .clone_from(&cache.user_details);
+            *self.fetched_at.lock().unwrap() = cache.fetched_at;
+            self.zone_fetched_at
+                .lock()
+                .unwrap()
+                .clone_from(&cache.zone_fetched_at);
+            self.zone_errors
+                .lock()
+                .unwrap()
+                .clone_from(&cache.zone_errors);
+        }
+        cache
+    }
+
+    /// The name and cached records of a zone.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the zone isn't cached.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn zone(&self, zone_id: &str) -> Result<(String, Vec<DNSRecord>), AppError> {
+        #[allow(clippy::unwrap_used)]
+        let zone_name = self
+            .zones
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|zone| zone.id == zone_id)
+            .map(|zone| zone.name.clone())
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorCategory::NotFound,
+                    format!("Zone {zone_id} isn't loaded; refresh the cache and try again"),
+                )
+            })?;
+        #[allow(clippy::unwrap_used)]
+        let records = self
+            .zone_dns
+            .lock()
+            .unwrap()
+            .get(zone_id)
+            .cloned()
+            .unwrap_or_default();
+        Ok((zone_name, records))
+    }
+
+    /// The ID of the cached zone named `zone_name` (with or without a trailing dot, in any case).
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if no such zone is cached.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn zone_id(&self, zone_name: &str) -> Result<String, AppError> {
+        let zone_name = zone_name.trim_end_matches('.');
+        #[allow(clippy::unwrap_used)]
+        self.zones
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|zone| zone.name.eq_ignore_ascii_case(zone_name))
+            .map(|zone| zone.id.clone())
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorCategory::NotFound,
+                    format!("Zone {zone_name} isn't loaded; refresh the cache and try again"),
+                )
+            })
+    }
+
+    /// Add a record to a zone's cached records, replacing the cached version with the same ID if there is one.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn put_record(&self, zone_id: &str, record: DNSRecord) {
+        #[allow(clippy::unwrap_used)]
+        let mut zone_dns = self.zone_dns.lock().unwrap();
+        let records = zone_dns.entry(zone_id.to_string()).or_default();
+        match records.iter_mut().find(|r| r.id == record.id) {
+            Some(cached) => *cached = record,
+            None => records.push(record),
+        }
+        self.count_write(zone_id);
+        drop(zone_dns);
+    }
+
+    /// Note a write to a zone's cached records. Called with the `zone_dns` lock held, so a fetch can't be
+    /// stored between the write and the count.
+    fn count_write(&self, zone_id: &str) {
+        #[allow(clippy::unwrap_used)]
+        {
+            *self
+                .zone_writes
+                .lock()
+                .unwrap()
+                .entry(zone_id.to_string())
+                .or_default() += 1;
+        }
+    }
+
+    /// How many times the app has written each zone's cached records. Take this before fetching records, and
+    /// pass it to [`Self::put_fetched_records`] to store them.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    #[must_use]
+    pub fn zone_writes(&self) -> HashMap<String, u64> {
+        #[allow(clippy::unwrap_used)]
+        self.zone_writes.lock().unwrap().clone()
+    }
+
+    /// Whether the app has written a zone's cached records since `writes` was taken with
+    /// [`Self::zone_writes`]. Call with the `zone_dns` lock held.
+    fn written_since(&self, zone_id: &str, writes: &HashMap<String, u64>) -> bool {
+        #[allow(clippy::unwrap_used)]
+        let now = self.zone_writes.lock().unwrap().get(zone_id).copied();
+        now != writes.get(zone_id).copied()
+    }
+
+    /// Replace a zone's cached records with ones fetched from Cloudflare, unless the app has written to the
+    /// zone since `writes` was taken with [`Self::zone_writes`], in which case they may be missing the write.
+    ///
+    /// Returns whether the records were stored.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn put_fetched_records(
+        &self,
+        zone_id: &str,
+        records: Vec<DNSRecord>,
+        writes: &HashMap<String, u64>,
+    ) -> bool {
+        #[allow(clippy::unwrap_used)]
+        let mut zone_dns = self.zone_dns.lock().unwrap();
+        if self.written_since(zone_id, writes) {
+            return false;
+        }
+        zone_dns.insert(zone_id.to_string(), records);
+        drop(zone_dns);
+        #[allow(clippy::unwrap_used)]
+        {
+            self.zone_fetched_at
+                .lock()
+                .unwrap()
+                .insert(zone_id.to_string(), Utc::now());
+            self.zone_errors.lock().unwrap().remove(zone_id);
+        }
+        true
+    }
+
+    /// Replace a zone's cached records with ones just fetched from Cloudflare.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn put_zone_records(&self, zone_id: &str, records: Vec<DNSRecord>) {
+        #[allow(clippy::unwrap_used)]
+        {
+            self.zone_dns
+                .lock()
+                .unwrap()
+                .insert(zone_id.to_string(), records);
+            self.zone_fetched_at
+                .lock()
+                .unwrap()
+                .insert(zone_id.to_string(), Utc::now());
+            self.zone_errors.lock().unwrap().remove(zone_id);
+        }
+    }
+
+    /// Replace a zone's cached records with ones fetched after the app changed them on Cloudflare in one go,
+    /// such as with Cloudflare's importer. Like [`Self::put_record`], this counts as a write to the zone.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn put_written_records(&self, zone_id: &str, records: Vec<DNSRecord>) {
+        #[allow(clippy::unwrap_used)]
+        let mut zone_dns = self.zone_dns.lock().unwrap();
+        zone_dns.insert(zone_id.to_string(), records);
+        self.count_write(zone_id);
+        drop(zone_dns);
+        #[allow(clippy::unwrap_used)]
+        {
+            self.zone_fetched_at
+                .lock()
+                .unwrap()
+                .insert(zone_id.to_string(), Utc::now());
+            self.zone_errors.lock().unwrap().remove(zone_id);
+        }
+    }
+
+    /// Remove a record from a zone's cached records.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn remove_record(&self, zone_id: &str, record_id: &str) {
+        #[allow(clippy::unwrap_used)]
+        let mut zone_dns = self.zone_dns.lock().unwrap();
+        if let Some(records) = zone_dns.get_mut(zone_id) {
+            records.retain(|r| r.id != record_id);
+        }
+        self.count_write(zone_id);
+        drop(zone_dns);
+    }
+
+    /// Drop the cached records, fetch times and errors of zones that aren't in `zones`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    pub fn retain_zones(&self, zones: &[CloudflareListZonesResponse]) {
+        let listed = |zone_id: &String| zones.iter().any(|zone| &zone.id == zone_id);
+        #[allow(clippy::unwrap_used)]
+        {
+            self.zone_dns
+                .lock()
+                .unwrap()
+                .retain(|zone_id, _| listed(zone_id));
+            self.zone_fetched_at
+                .lock()
+                .unwrap()
+                .retain(|zone_id, _| listed(zone_id));
+            self.zone_errors
+                .lock()
+                .unwrap()
+                .retain(|zone_id, _| listed(zone_id));
+        }
+    }
+}
+
+/// The cached data of one profile, outside of the managed state: either stashed while another profile is
+/// active, or saved to disk.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ProfileCache {
+    /// Zone IDs
+    pub zones: Vec<CloudflareListZonesResponse>,
+    /// Map of zone IDs to DNS records vectors
+    pub zone_dns: HashMap<String, Vec<DNSRecord>>,
+    /// User details (verify API key)
+    pub user_details: Option<CustomUserDetails>,
+    /// When the zones were fetched from Cloudflare
+    #[serde(default)]
+    pub fetched_at: Option<DateTime<Utc>>,
+    /// Map of zone IDs to when their DNS records were fetched from Cloudflare
+    #[serde(default)]
+    pub zone_fetched_at: HashMap<String, DateTime<Utc>>,
+    /// Map of zone IDs to why their DNS records failed to load in the last refresh
+    #[serde(default)]
+    pub zone_errors: HashMap<String, AppError>,
+}
+
+/// How fresh the cache is, so the UI can show when the data was last fetched.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct CacheStatus {
+    /// When the zones were fetched from Cloudflare, or `None` if they haven't been yet.
+    pub fetched_at: Option<DateTime<Utc>>,
+    /// Map of zone IDs to when their DNS records were fetched from Cloudflare
+    pub zone_fetched_at: HashMap<String, DateTime<Utc>>,
+    /// Map of zone IDs to why their DNS records failed to load in the last refresh
+    pub zone_errors: HashMap<String, AppError>,
+}
+
+/// The payload of the `zone-dns-loaded` event, emitted as each zone's DNS records finish loading.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ZoneDnsLoaded {
+    /// The zone ID.
+    pub zone_id: String,
+    /// The zone's DNS records. Empty if they failed to load.
+    pub records: Vec<DNSRecord>,
+    /// Why the records failed to load, if they did.
+    pub error: Option<AppError>,
+}
+
+/// The payload of the `init-progress` event, emitted after each zone finishes loading.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, TS)]
+#[ts(export)]
+pub struct InitProgress {
+    /// How many zones have loaded.
+    pub loaded: usize,
+    /// How many zones failed to load.
+    pub failed: usize,
+    /// How many zones there are.
+    pub total: usize,
+}
+
+/// A saved profile, for listing in the UI.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ProfileSummary {
+    /// The name the profile was saved under.
+    pub name: String,
+    /// Whether this is the profile in use.
+    pub active: bool,
+    /// The email address of the account, if the profile's credentials have been checked.
+    pub email: Option<String>,
+    /// How many zones are cached for the profile.
+    pub zone_count: usize,
 }
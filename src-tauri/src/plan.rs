@@ -0,0 +1,505 @@
+//! Planning and applying a set of changes to a zone's records.
+//!
+//! [`diff`] compares the records a zone should have with its cached records and produces a [`ChangePlan`].
+//! The plan is shown to the user before anything is sent; [`Writer::apply`] then makes each change in order,
+//! checking it against the cache as it stands after the changes before it, and reports how each one went. A
+//! record is fetched again before it is overwritten or deleted, and left alone if it changed after the plan
+//! was made.
+//! Every change made through a [`Writer`] is recorded in the [`Journal`], so it can be undone.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    api,
+    client::CloudflareClient,
+    cloudflare::{DNSRecord, DNSRecordPatch, DNSRecordRequest},
+    error::{AppError, ErrorCategory},
+    journal::{Journal, JournalEntry},
+    models::ManagedCache,
+    profiles::Profiles,
+    validation::{ensure_valid, validate_deletion, validate_record, ZoneContext},
+};
+
+/// One change to a zone's records.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Change {
+    /// Create a record.
+    Create {
+        /// The record to create.
+        record: DNSRecordRequest,
+    },
+    /// Overwrite a record.
+    Update {
+        /// The record as it is now.
+        before: DNSRecord,
+        /// What it is overwritten with.
+        after: DNSRecordRequest,
+    },
+    /// Delete a record.
+    Delete {
+        /// The record as it is now.
+        before: DNSRecord,
+    },
+}
+
+/// What to do with a record that exists with different content or settings than wanted.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Update the record to match.
+    #[default]
+    Overwrite,
+    /// Leave the record as it is.
+    Keep,
+}
+
+/// How a [`ChangePlan`] is worked out.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, TS)]
+#[ts(export)]
+pub struct PlanOptions {
+    /// What to do with records that differ from the wanted ones.
+    pub on_conflict: ConflictResolution,
+    /// Whether to delete records that aren't wanted at all. If not, they are left alone.
+    pub delete_missing: bool,
+}
+
+/// The changes needed to bring a zone to the wanted records: deletions first (so a CNAME can replace other
+/// records at its name), then updates, then creations.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, TS)]
+#[ts(export)]
+pub struct ChangePlan {
+    /// The changes, in the order they are applied.
+    pub changes: Vec<Change>,
+    /// How many records already match.
+    pub unchanged: usize,
+    /// How many differing records are left alone, because of [`ConflictResolution::Keep`] or because
+    /// `delete_missing` isn't set.
+    pub kept: usize,
+}
+
+/// How one change of a plan went.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ChangeResult {
+    /// The change.
+    pub change: Change,
+    /// The record as Cloudflare returned it, for creations and updates that succeeded.
+    pub record: Option<DNSRecord>,
+    /// Why the change failed, if it did.
+    pub error: Option<AppError>,
+}
+
+/// Whether a record's settings already match the wanted ones. Settings that aren't given are left as they are.
+fn settings_match(current: &DNSRecord, wanted: &DNSRecordRequest) -> bool {
+    let mut current_tags = current.tags.clone();
+    let mut wanted_tags = wanted.tags.clone();
+    current_tags.sort();
+    wanted_tags.sort();
+    current.ttl == wanted.ttl
+        && wanted.proxied.is_none_or(|p| current.proxied == Some(p))
+        && current.comment.as_deref().filter(|c| !c.is_empty())
+            == wanted.comment.as_deref().filter(|c| !c.is_empty())
+        && current_tags == wanted_tags
+}
+
+/// Work out the changes that turn a zone's records into `wanted`.
+///
+/// Records are compared by name and type. Records with the same content are matched first; the remaining
+/// records of a name and type are then paired up in order as updates. Whatever is left is created, or
+/// deleted if `delete_missing` is set.
+#[must_use]
+pub fn diff(
+    zone: &ZoneContext<'_>,
+    wanted: &[DNSRecordRequest],
+    options: PlanOptions,
+) -> ChangePlan {
+    /// The wanted and current records of one name and type.
+    #[derive(Default)]
+    struct Group<'a> {
+        wanted: Vec<&'a DNSRecordRequest>,
+        current: Vec<&'a DNSRecord>,
+    }
+
+    let key = |name: &str, record_type: &'static str| {
+        (
+            zone.qualify(name)
+                .unwrap_or_else(|| name.trim_end_matches('.').to_ascii_lowercase()),
+            record_type,
+        )
+    };
+    let mut groups: BTreeMap<(String, &'static str), Group<'_>> = BTreeMap::new();
+    for record in wanted {
+        groups
+            .entry(key(&record.name, record.content.record_type()))
+            .or_default()
+            .wanted
+            .push(record);
+    }
+    for record in zone.records {
+        groups
+            .entry(key(&record.name, record.content.record_type()))
+            .or_default()
+            .current
+            .push(record);
+    }
+
+    let mut plan = ChangePlan::default();
+    let (mut deletes, mut updates, mut creates) = (Vec::new(), Vec::new(), Vec::new());
+    for group in groups.into_values() {
+        let mut current = group.current;
+        let mut unmatched = Vec::new();
+        for wanted in group.wanted {
+            match current.iter().position(|r| r.content == wanted.content) {
+                Some(i) => {
+                    let before = current.remove(i);
+                    if settings_match(before, wanted) {
+                        plan.unchanged += 1;
+                    } else if options.on_conflict == ConflictResolution::Keep {
+                        plan.kept += 1;
+                    } else {
+                        updates.push(Change::Update {
+                            before: before.clone(),
+                            after: wanted.clone(),
+                        });
+                    }
+                }
+                None => unmatched.push(wanted),
+            }
+        }
+
+        let paired = unmatched.len().min(current.len());
+        for (wanted, before) in unmatched.drain(..paired).zip(current.drain(..paired)) {
+            if options.on_conflict == ConflictResolution::Keep {
+                plan.kept += 1;
+            } else {
+                updates.push(Change::Update {
+                    before: before.clone(),
+                    after: wanted.clone(),
+                });
+            }
+        }
+        creates.extend(unmatched.into_iter().map(|record| Change::Create {
+            record: record.clone(),
+        }));
+        if options.delete_missing {
+            deletes.extend(current.into_iter().map(|before| Change::Delete {
+                before: before.clone(),
+            }));
+        } else {
+            plan.kept += current.len();
+        }
+    }
+    plan.changes = deletes.into_iter().chain(updates).chain(creates).collect();
+    plan
+}
+
+/// Find a record among a zone's cached records.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the record isn't cached.
+pub fn find_record<'a>(
+    records: &'a [DNSRecord],
+    record_id: &str,
+) -> Result<&'a DNSRecord, AppError> {
+    records.iter().find(|r| r.id == record_id).ok_or_else(|| {
+        AppError::new(
+            ErrorCategory::NotFound,
+            format!("Record {record_id} isn't loaded; refresh the cache and try again"),
+        )
+    })
+}
+
+/// Makes changes to a zone's records: checks each one against the cache, sends it, updates the cache, and
+/// records it in the [`Journal`].
+///
+/// The writer belongs to the profile active when it was made. Once another profile is switched to, it makes
+/// no more changes and leaves the cache, which then holds the other profile's records, alone.
+#[derive(Debug)]
+pub struct Writer<'a> {
+    /// The client the changes are sent with.
+    pub client: &'a CloudflareClient,
+    /// The cache the changes are checked against and written to.
+    pub managed_cache: &'a ManagedCache,
+    /// The journal the changes are recorded in.
+    pub journal: &'a Journal,
+    /// The profiles, to check the writer's profile is still active.
+    pub profiles: &'a Profiles,
+    /// The profile the changes are made with.
+    pub profile: String,
+    /// The journal entry the changes undo, if they are an undo.
+    pub undoes: Option<String>,
+}
+
+impl<'a> Writer<'a> {
+    /// A writer for changes made with the active profile.
+    #[must_use]
+    pub fn new(
+        client: &'a CloudflareClient,
+        managed_cache: &'a ManagedCache,
+        journal: &'a Journal,
+        profiles: &'a Profiles,
+    ) -> Self {
+        Self {
+            client,
+            managed_cache,
+            journal,
+            profiles,
+            profile: profiles.active(),
+            undoes: None,
+        }
+    }
+
+    /// Get a zone's name and cached records, to check a change against.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the writer's profile is no longer active or the zone isn't cached.
+    pub fn zone(&self, zone_id: &str) -> Result<(String, Vec<DNSRecord>), AppError> {
+        self.profiles
+            .while_active(&self.profile, || self.managed_cache.zone(zone_id))
+            .unwrap_or_else(|| {
+                Err(AppError::new(
+                    ErrorCategory::Validation,
+                    format!(
+                        "Another profile was switched to, so no more changes are made with \"{}\"",
+                        self.profile
+                    ),
+                ))
+            })
+    }
+
+    /// Add or replace a record in the cache, unless the writer's profile is no longer active.
+    pub fn put_record(&self, zone_id: &str, record: DNSRecord) {
+        self.profiles.while_active(&self.profile, || {
+            self.managed_cache.put_record(zone_id, record);
+        });
+    }
+
+    /// Remove a record from the cache, unless the writer's profile is no longer active.
+    pub fn remove_record(&self, zone_id: &str, record_id: &str) {
+        self.profiles.while_active(&self.profile, || {
+            self.managed_cache.remove_record(zone_id, record_id);
+        });
+    }
+
+    /// Record a change in the journal. The change has already been made, so failing to record it is only
+    /// logged.
+    fn record(
+        &self,
+        zone_id: &str,
+        zone_name: &str,
+        before: Option<DNSRecord>,
+        after: Option<DNSRecord>,
+    ) {
+        let mut entry = JournalEntry::new(&self.profile, zone_id, zone_name, before, after);
+        entry.undoes.clone_from(&self.undoes);
+        if let Err(e) = self.journal.append(&entry) {
+            tracing::warn!("Failed to record a change to zone {zone_id} in the journal: {e}");
+        }
+    }
+
+    /// Check a new record against the zone's cached records, create it, and add it to the cache.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the zone isn't cached, the record fails validation, or the
+    /// request fails.
+    pub async fn create_record(
+        &self,
+        zone_id: &str,
+        record: &DNSRecordRequest,
+    ) -> Result<DNSRecord, AppError> {
+        let (zone_name, records) = self.zone(zone_id)?;
+        let zone = ZoneContext {
+            zone_name: &zone_name,
+            records: &records,
+        };
+        ensure_valid(validate_record(record, &zone, None))?;
+        let created = api::create_dns_record(self.client, zone_id, record)
+            .await?
+            .result;
+        self.put_record(zone_id, created.clone());
+        self.record(zone_id, &zone_name, None, Some(created.clone()));
+        Ok(created)
+    }
+
+    /// Check a change to a record against the zone's cached records, overwrite the record, and replace it
+    /// in the cache.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the zone or the record isn't cached, the change fails validation,
+    /// or the request fails.
+    pub async fn update_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        record: &DNSRecordRequest,
+    ) -> Result<DNSRecord, AppError> {
+        let (zone_name, records) = self.zone(zone_id)?;
+        let existing = find_record(&records, record_id)?;
+        let zone = ZoneContext {
+            zone_name: &zone_name,
+            records: &records,
+        };
+        ensure_valid(validate_record(record, &zone, Some(existing)))?;
+        let updated = api::update_dns_record(self.client, zone_id, record_id, record)
+            .await?
+            .result;
+        self.put_record(zone_id, updated.clone());
+        self.record(
+            zone_id,
+            &zone_name,
+            Some(existing.clone()),
+            Some(updated.clone()),
+        );
+        Ok(updated)
+    }
+
+    /// Check a partial change to a record against the zone's cached records, patch the record, and replace
+    /// it in the cache.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the zone or the record isn't cached, the patched record fails
+    /// validation, or the request fails.
+    pub async fn patch_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        patch: &DNSRecordPatch,
+    ) -> Result<DNSRecord, AppError> {
+        let (zone_name, records) = self.zone(zone_id)?;
+        let existing = find_record(&records, record_id)?;
+        let zone = ZoneContext {
+            zone_name: &zone_name,
+            records: &records,
+        };
+        ensure_valid(validate_record(
+            &patch.apply_to(existing),
+            &zone,
+            Some(existing),
+        ))?;
+        let updated = api::patch_dns_record(self.client, zone_id, record_id, patch)
+            .await?
+            .result;
+        self.put_record(zone_id, updated.clone());
+        self.record(
+            zone_id,
+            &zone_name,
+            Some(existing.clone()),
+            Some(updated.clone()),
+        );
+        Ok(updated)
+    }
+
+    /// Check a record can be deleted, delete it, and remove it from the cache. Returns the deleted record's
+    /// ID.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the zone or the record isn't cached, the record is locked, or the
+    /// request fails.
+    pub async fn delete_record(&self, zone_id: &str, record_id: &str) -> Result<String, AppError> {
+        let (zone_name, records) = self.zone(zone_id)?;
+        let existing = find_record(&records, record_id)?;
+        ensure_valid(validate_deletion(existing))?;
+        let deleted = api::delete_dns_record(self.client, zone_id, record_id)
+            .await?
+            .result;
+        self.remove_record(zone_id, record_id);
+        self.record(zone_id, &zone_name, Some(existing.clone()), None);
+        Ok(deleted.id)
+    }
+
+    /// Fetch a record as it is now, refresh it in the cache, and check it is still `expected`. `consequence`
+    /// ends the error message, e.g. "so the change can't be undone".
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the record has been changed or deleted since, or the request fails.
+    pub async fn unchanged_since(
+        &self,
+        zone_id: &str,
+        expected: &DNSRecord,
+        consequence: &str,
+    ) -> Result<DNSRecord, AppError> {
+        let live = match api::get_dns_record(self.client, zone_id, &expected.id).await {
+            Ok(response) => response.result,
+            Err(e) if e.category == ErrorCategory::NotFound => {
+                self.remove_record(zone_id, &expected.id);
+                return Err(AppError::new(
+                    ErrorCategory::Validation,
+                    format!("{} has been deleted since, {consequence}", expected.name),
+                ));
+            }
+            Err(e) => return Err(e),
+        };
+        // The cache may be older than the live record; validation needs the record as it is now.
+        self.put_record(zone_id, live.clone());
+        if !live.same_as(expected) {
+            return Err(AppError::new(
+                ErrorCategory::Validation,
+                format!("{} has been changed since, {consequence}", expected.name),
+            ));
+        }
+        Ok(live)
+    }
+
+    /// Make one change of a plan. Returns the record as Cloudflare returned it, for creations and updates.
+    ///
+    /// A record is only overwritten or deleted if it is still as it was when the plan was made.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the record has been changed or deleted since the plan was made.
+    /// Otherwise, see [`Self::create_record`], [`Self::update_record`] and [`Self::delete_record`].
+    pub async fn apply_change(
+        &self,
+        zone_id: &str,
+        change: &Change,
+    ) -> Result<Option<DNSRecord>, AppError> {
+        /// What happens to a change whose record has been changed since it was planned.
+        const LEFT_ALONE: &str = "so it was left alone";
+        match change {
+            Change::Create { record } => self.create_record(zone_id, record).await.map(Some),
+            Change::Update { before, after } => {
+                self.unchanged_since(zone_id, before, LEFT_ALONE).await?;
+                self.update_record(zone_id, &before.id, after)
+                    .await
+                    .map(Some)
+            }
+            Change::Delete { before } => {
+                self.unchanged_since(zone_id, before, LEFT_ALONE).await?;
+                self.delete_record(zone_id, &before.id).await.map(|_| None)
+            }
+        }
+    }
+
+    /// Apply every change of a plan in order. A change that fails doesn't stop the ones after it.
+    pub async fn apply(&self, zone_id: &str, plan: ChangePlan) -> Vec<ChangeResult> {
+        let mut results = Vec::with_capacity(plan.changes.len());
+        for change in plan.changes {
+            let (record, error) = match self.apply_change(zone_id, &change).await {
+                Ok(record) => (record, None),
+                Err(e) => {
+                    tracing::warn!("Failed to apply a change to zone {zone_id}: {e}");
+                    (None, Some(e))
+                }
+            };
+            results.push(ChangeResult {
+                change,
+                record,
+                error,
+            });
+        }
+        results
+    }
+}
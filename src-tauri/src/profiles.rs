@@ -0,0 +1,348 @@
+//! Named profiles, each with its own Cloudflare credentials and cache.
+//!
+//! The active profile's data lives in [`ManagedCache`], so the other commands don't need to know about
+//! profiles. When switching, the active profile's cache is stashed here and the new profile's stashed
+//! cache (if it has been loaded before) is put back, so switching doesn't fetch every zone again.
+//!
+//! Each profile's credentials are saved in the [`SecretStorage`](crate::credentials::SecretStorage)
+//! under the profile's name. The list of names and which one is active are saved in `profiles.json`
+//! in the app data directory; that file holds no secrets. Each profile's cache is also saved to the
+//! [`DiskCache`], so it can be shown straight away after a restart.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    credentials::DEFAULT_ACCOUNT,
+    disk_cache::DiskCache,
+    error::{AppError, ErrorCategory},
+    models::{ManagedCache, ProfileCache, ProfileSummary},
+};
+
+/// The file the profile list is saved in.
+const PROFILES_FILE: &str = "profiles.json";
+
+/// The saved profile list, plus the caches of the inactive profiles.
+#[derive(Debug, Deserialize, Serialize)]
+struct ProfileState {
+    /// The name of the profile in use.
+    active: String,
+    /// The names of every saved profile.
+    names: BTreeSet<String>,
+    /// The caches of the profiles that aren't active. Never saved.
+    #[serde(skip)]
+    caches: HashMap<String, ProfileCache>,
+}
+
+impl Default for ProfileState {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_ACCOUNT.to_string(),
+            names: BTreeSet::new(),
+            caches: HashMap::new(),
+        }
+    }
+}
+
+/// The saved profiles. This is held in Tauri managed state.
+#[derive(Debug)]
+pub struct Profiles {
+    /// Where the profile list is saved.
+    path: PathBuf,
+    /// The on-disk copy of every profile's cache.
+    disk_cache: DiskCache,
+    /// The profile list and stashed caches.
+    state: Mutex<ProfileState>,
+}
+
+/// Check a profile name is usable, returning it without surrounding whitespace.
+///
+/// # Errors
+///
+/// Returns an [`AppError`] if the name is empty.
+pub fn validate_name(name: &str) -> Result<&str, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::new(
+            ErrorCategory::Validation,
+            "A profile name can't be empty",
+        ));
+    }
+    Ok(name)
+}
+
+/// Create an error for a profile that doesn't exist.
+fn unknown_profile(name: &str) -> AppError {
+    AppError::new(
+        ErrorCategory::NotFound,
+        format!("There is no profile named \"{name}\""),
+    )
+}
+
+impl Profiles {
+    /// Load the profile list saved in `data_dir`. If there is none, or it can't be read, start with no profiles.
+    #[must_use]
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(PROFILES_FILE);
+        let state = fs::read(&path).map_or_else(
+            |_| ProfileState::default(),
+            |data| {
+                serde_json::from_slice(&data).unwrap_or_else(|e| {
+                    tracing::warn!("Ignoring unreadable profile list: {e}");
+                    ProfileState::default()
+                })
+            },
+        );
+        Self {
+            path,
+            disk_cache: DiskCache::new(data_dir),
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Save the profile list.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file can't be written.
+    fn save(&self, state: &ProfileState) -> Result<(), AppError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Written to a temporary file first, so a crash while writing doesn't lose the list.
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec_pretty(state)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+
+    /// The name of the profile in use.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the profile lock is poisoned.
+    #[must_use]
+    pub fn active(&self) -> String {
+        #[allow(clippy::unwrap_used)]
+        self.state.lock().unwrap().active.clone()
+    }
+
+    /// Summaries of every saved profile, in name order.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the profile lock or the cache is poisoned.
+    #[must_use]
+    pub fn summaries(&self, managed_cache: &ManagedCache) -> Vec<ProfileSummary> {
+        #[allow(clippy::unwrap_used)]
+        let state = self.state.lock().unwrap();
+        state
+            .names
+            .iter()
+            .map(|name| {
+                if *name == state.active {
+                    #[allow(clippy::unwrap_used)]
+                    ProfileSummary {
+                        name: name.clone(),
+                        active: true,
+                        email: managed_cache
+                            .user_details
+                            .lock()
+                            .unwrap()
+                            .as_ref()
+                            .map(|user| user.email.clone()),
+                        zone_count: managed_cache.zones.lock().unwrap().len(),
+                    }
+                } else {
+                    let cache = state.caches.get(name);
+                    ProfileSummary {
+                        name: name.clone(),
+                        active: false,
+                        email: cache
+                            .and_then(|c| c.user_details.as_ref())
+                            .map(|user| user.email.clone()),
+                        zone_count: cache.map_or(0, |c| c.zones.len()),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Add a profile to the list, or replace the stashed cache of an existing one. The profile isn't switched to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the profile list can't be saved.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the profile lock is poisoned.
+    pub fn add(&self, name: &str, cache: ProfileCache) -> Result<(), AppError> {
+        #[allow(clippy::unwrap_used)]
+        let mut state = self.state.lock().unwrap();
+        state.names.insert(name.to_string());
+        if state.active != name {
+            state.caches.insert(name.to_string(), cache);
+        }
+        let result = self.save(&state);
+        drop(state);
+        result
+    }
+
+    /// Remove a profile from the list. If it was active, the default profile becomes active and the cache is cleared.
+    ///
+    /// Returns whether the profile was the active one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if there is no such profile or the profile list can't be saved.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the profile lock or the cache is poisoned.
+    pub fn remove(&self, name: &str, managed_cache: &ManagedCache) -> Result<bool, AppError> {
+        #[allow(clippy::unwrap_used)]
+        let mut state = self.state.lock().unwrap();
+        if !state.names.remove(name) {
+            return Err(unknown_profile(name));
+        }
+        state.caches.remove(name);
+        let was_active = state.active == name;
+        if was_active {
+            state.active = DEFAULT_ACCOUNT.to_string();
+            let _ = managed_cache.take();
+        }
+        let result = self.save(&state);
+        drop(state);
+        result?;
+        self.disk_cache.remove(name)?;
+        Ok(was_active)
+    }
+
+    /// Make `name` the active profile: stash the active profile's cache and put back the stashed cache of `name`,
+    /// or its cache from disk if it hasn't been active since the app started.
+    ///
+    /// Returns whether `name` had a cache to put back. If not, it needs to be loaded from Cloudflare.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if there is no such profile or the profile list can't be saved.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the profile lock or the cache is poisoned.
+    pub fn switch(&self, name: &str, managed_cache: &ManagedCache) -> Result<bool, AppError> {
+        #[allow(clippy::unwrap_used)]
+        let mut state = self.state.lock().unwrap();
+        if !state.names.contains(name) {
+            return Err(unknown_profile(name));
+        }
+        if state.active == name {
+            #[allow(clippy::unwrap_used)]
+            let cached = !managed_cache.zones.lock().unwrap().is_empty();
+            return Ok(cached);
+        }
+
+        let previous = std::mem::replace(&mut state.active, name.to_string());
+        let stashed = managed_cache.take();
+        if state.names.contains(&previous) {
+            state.caches.insert(previous, stashed);
+        }
+        let cache = state
+            .caches
+            .remove(name)
+            .or_else(|| self.load_from_disk(name))
+            .unwrap_or_default();
+        let cached = !cache.zones.is_empty();
+        managed_cache.restore(cache);
+
+        let result = self.save(&state);
+        drop(state);
+        result.map(|()| cached)
+    }
+
+    /// Load a profile's cache from disk, logging rather than failing if the file can't be read.
+    fn load_from_disk(&self, name: &str) -> Option<ProfileCache> {
+        self.disk_cache.load(name).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load the cache from disk: {e}");
+            None
+        })
+    }
+
+    /// Fill the cache with the active profile's cache from disk, as saved by the last run of the app.
+    ///
+    /// Returns whether there was anything to restore.
+    #[must_use]
+    pub fn restore_from_disk(&self, managed_cache: &ManagedCache) -> bool {
+        self.load_from_disk(&self.active())
+            .map(|cache| managed_cache.restore(cache))
+            .is_some()
+    }
+
+    /// Run `write` if `profile` is still active, returning what it returns. Commands capture the active profile
+    /// before awaiting Cloudflare and write their results to the cache with this, so results for a profile that
+    /// has been switched away from are discarded rather than mixed into another profile's cache. No switch can
+    /// happen while `write` runs.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the profile lock is poisoned.
+    pub fn while_active<T>(&self, profile: &str, write: impl FnOnce() -> T) -> Option<T> {
+        #[allow(clippy::unwrap_used)]
+        let state = self.state.lock().unwrap();
+        let result = (state.active == profile).then(write);
+        drop(state);
+        result
+    }
+
+    /// Save the cache to disk as `profile`'s, unless another profile has been switched to since. Failures are
+    /// logged rather than returned: the cache in memory is still correct, and the file is only used to show
+    /// something quickly at the next start.
+    pub fn persist(&self, profile: &str, managed_cache: &ManagedCache) {
+        let Some(cache) = self.while_active(profile, || managed_cache.snapshot()) else {
+            return;
+        };
+        if let Err(e) = self.disk_cache.save(profile, cache) {
+            tracing::warn!("Failed to save the cache to disk: {e}");
+        }
+    }
+
+    /// Store freshly fetched data for a profile and save it to disk. If the profile is still active it replaces
+    /// the managed cache, except for the records of zones written since `writes` was taken with
+    /// [`ManagedCache::zone_writes`]; if another profile was switched to while fetching, it replaces the
+    /// stashed cache.
+    ///
+    /// Returns whether the profile was still active.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the profile lock or the cache is poisoned.
+    pub fn store_refresh(
+        &self,
+        name: &str,
+        cache: ProfileCache,
+        writes: &HashMap<String, u64>,
+        managed_cache: &ManagedCache,
+    ) -> bool {
+        #[allow(clippy::unwrap_used)]
+        let mut state = self.state.lock().unwrap();
+        let active = state.active == name;
+        let cache = if active {
+            managed_cache.restore_fetched(cache, writes)
+        } else {
+            state.caches.insert(name.to_string(), cache.clone());
+            cache
+        };
+        if let Err(e) = self.disk_cache.save(name, cache) {
+            tracing::warn!("Failed to save the cache to disk: {e}");
+        }
+        drop(state);
+        active
+    }
+}
@@ -0,0 +1,506 @@
+//! Checking whether a record is live: asking DNS servers for it and comparing their answers with the cache.
+//!
+//! [`check`] asks each of the zone's Cloudflare name servers, which should answer with the record as soon as
+//! it is saved, and the recursive resolvers in [`PropagationSettings`], which answer from their caches until
+//! the old answer's TTL runs out. The resolvers are saved in `resolvers.json` in the app data directory.
+
+use std::{
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use hickory_resolver::{
+    config::{NameServerConfig, NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    error::ResolveErrorKind,
+    proto::rr::{RData, RecordType},
+    Name, TokioAsyncResolver,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cloudflare::{CloudflareListZonesResponse, DNSRecord},
+    error::{AppError, FieldError},
+    records::DNSContent,
+    validation::txt_chunks,
+    zonefile,
+};
+
+/// The file the resolvers are saved in.
+const RESOLVERS_FILE: &str = "resolvers.json";
+
+/// How long to wait for each server to answer.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The port DNS servers listen on.
+const DNS_PORT: u16 = 53;
+
+/// Whether a server answers for the zone or looks answers up.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolverKind {
+    /// One of the zone's Cloudflare name servers.
+    Authoritative,
+    /// A public or local resolver, answering from its cache.
+    Recursive,
+}
+
+/// A recursive resolver to ask.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct NamedResolver {
+    /// The name to show, e.g. `Google`.
+    pub name: String,
+    /// The address, with or without a port, e.g. `8.8.8.8` or `[2001:4860:4860::8888]:53`.
+    pub address: String,
+}
+
+impl NamedResolver {
+    /// A resolver listening on the DNS port of `address`.
+    fn new(name: &str, address: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            address: address.to_string(),
+        }
+    }
+}
+
+/// The servers a propagation check asks.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct PropagationSettings {
+    /// The recursive resolvers to ask. They also look up the addresses of the zone's name servers.
+    pub resolvers: Vec<NamedResolver>,
+    /// The port the zone's name servers are asked on. Only a local test server needs anything other than 53.
+    #[serde(default = "default_name_server_port")]
+    pub name_server_port: u16,
+}
+
+/// The port the zone's name servers are asked on by default.
+const fn default_name_server_port() -> u16 {
+    DNS_PORT
+}
+
+impl Default for PropagationSettings {
+    fn default() -> Self {
+        Self {
+            resolvers: vec![
+                NamedResolver::new("Cloudflare", "1.1.1.1"),
+                NamedResolver::new("Google", "8.8.8.8"),
+                NamedResolver::new("Quad9", "9.9.9.9"),
+                NamedResolver::new("OpenDNS", "208.67.222.222"),
+            ],
+            name_server_port: DNS_PORT,
+        }
+    }
+}
+
+impl PropagationSettings {
+    /// A resolver that asks the recursive resolvers. Ones whose address can't be read are left out.
+    #[must_use]
+    pub fn resolver(&self) -> TokioAsyncResolver {
+        resolver(
+            &self
+                .resolvers
+                .iter()
+                .filter_map(|r| socket_addr(&r.address, DNS_PORT))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+/// Read a server address, with or without a port. Without one, `default_port` is used.
+#[must_use]
+pub fn socket_addr(address: &str, default_port: u16) -> Option<SocketAddr> {
+    let address = address.trim();
+    address.parse().ok().or_else(|| {
+        address
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, default_port))
+    })
+}
+
+/// The resolvers file in the app data directory. This is held in Tauri managed state.
+#[derive(Debug)]
+pub struct Propagation {
+    /// Where the settings are saved.
+    path: PathBuf,
+    /// The settings, as last saved.
+    settings: Mutex<PropagationSettings>,
+}
+
+impl Propagation {
+    /// The settings saved in `data_dir`. A missing or unreadable file gives the default resolvers.
+    #[must_use]
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(RESOLVERS_FILE);
+        let settings = fs::read(&path)
+            .ok()
+            .and_then(|data| {
+                serde_json::from_slice(&data)
+                    .map_err(|e| tracing::warn!("Ignoring unreadable resolver settings: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    /// A copy of the settings.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the settings lock is poisoned.
+    #[must_use]
+    pub fn settings(&self) -> PropagationSettings {
+        #[allow(clippy::unwrap_used)]
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Check and save new settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if a resolver has no name or an address that can't be read, or the file can't
+    /// be written.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the settings lock is poisoned.
+    pub fn set(&self, settings: PropagationSettings) -> Result<PropagationSettings, AppError> {
+        let errors: Vec<FieldError> = settings
+            .resolvers
+            .iter()
+            .enumerate()
+            .flat_map(|(i, resolver)| {
+                let mut errors = Vec::new();
+                if resolver.name.trim().is_empty() {
+                    errors.push(FieldError::new(
+                        &format!("resolvers[{i}].name"),
+                        "A resolver needs a name",
+                    ));
+                }
+                if socket_addr(&resolver.address, DNS_PORT).is_none() {
+                    errors.push(FieldError::new(
+                        &format!("resolvers[{i}].address"),
+                        format!("{} isn't an IP address", resolver.address),
+                    ));
+                }
+                errors
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(AppError::validation(errors));
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&settings)?)?;
+        #[allow(clippy::unwrap_used)]
+        self.settings.lock().unwrap().clone_from(&settings);
+        Ok(settings)
+    }
+}
+
+/// How a server's answer compares with the cached record.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerStatus {
+    /// The answer includes the record.
+    Agrees,
+    /// The answer has records of the type, but not this one, e.g. the old content.
+    Differs,
+    /// There are no records of the type at the name.
+    Missing,
+    /// The server didn't answer.
+    Failed,
+}
+
+/// One server's answer.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ResolverAnswer {
+    /// The server's name, e.g. `Google` or `bob.ns.cloudflare.com`.
+    pub resolver: String,
+    /// Whether the server answers for the zone or looks answers up.
+    pub kind: ResolverKind,
+    /// The address the server was asked at, if it could be found.
+    pub address: Option<String>,
+    /// How the answer compares with the cached record.
+    pub status: AnswerStatus,
+    /// The records in the answer, written the way a zone file writes them.
+    pub answers: Vec<String>,
+    /// How many more seconds the server will give this answer, if it had records.
+    pub ttl: Option<u32>,
+    /// Why the server didn't answer.
+    pub error: Option<String>,
+}
+
+/// Whether a record is live, and where.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct PropagationReport {
+    /// The zone identifier.
+    pub zone_id: String,
+    /// Identifier for the record.
+    pub record_id: String,
+    /// The record name.
+    pub name: String,
+    /// The record type that was asked for.
+    pub record_type: String,
+    /// The cached content the answers were compared with. Proxied records resolve to Cloudflare's own
+    /// addresses, so for those any answer agrees.
+    pub expected: String,
+    /// When the servers were asked.
+    pub checked_at: DateTime<Utc>,
+    /// Each server's answer, the zone's name servers first.
+    pub answers: Vec<ResolverAnswer>,
+    /// The percentage of the servers that answered whose answer includes the record, from 0 to 100.
+    pub propagation: f64,
+    /// Whether every one of the zone's name servers answers with the record.
+    pub consistent: bool,
+}
+
+/// A resolver that asks only the servers at `addresses`, and remembers nothing between lookups.
+fn resolver(addresses: &[SocketAddr]) -> TokioAsyncResolver {
+    let servers: Vec<NameServerConfig> = addresses
+        .iter()
+        .map(|address| NameServerConfig::new(*address, hickory_resolver::config::Protocol::Udp))
+        .collect();
+    let mut options = ResolverOpts::default();
+    options.timeout = QUERY_TIMEOUT;
+    options.attempts = 1;
+    options.cache_size = 0;
+    options.use_hosts_file = false;
+    TokioAsyncResolver::tokio(
+        ResolverConfig::from_parts(None, Vec::new(), NameServerConfigGroup::from(servers)),
+        options,
+    )
+}
+
+/// What to ask for, and the text an answer that includes the record has. `None` for a proxied record, which
+/// resolves to Cloudflare's addresses instead.
+fn question(record: &DNSRecord) -> (RecordType, Option<String>) {
+    let proxied = record.proxied.unwrap_or_default();
+    match &record.content {
+        DNSContent::Cname { .. } | DNSContent::A { .. } if proxied => (RecordType::A, None),
+        DNSContent::Aaaa { .. } if proxied => (RecordType::AAAA, None),
+        DNSContent::Txt { content } => (RecordType::TXT, Some(txt_chunks(content).concat())),
+        content => (
+            RecordType::from_str(content.record_type()).unwrap_or(RecordType::Unknown(0)),
+            Some(normalize(&zonefile::rdata(content))),
+        ),
+    }
+}
+
+/// Record data as text that compares equal however it was written: lowercase, without quotes or the dots
+/// ending absolute names.
+fn normalize(rdata: &str) -> String {
+    rdata
+        .split_whitespace()
+        .map(|word| {
+            word.trim_matches('"')
+                .trim_end_matches('.')
+                .to_ascii_lowercase()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The text of record data in an answer, comparable with [`question`]'s.
+fn answer_text(rdata: &RData) -> String {
+    match rdata {
+        RData::TXT(txt) => txt
+            .iter()
+            .map(|chunk| String::from_utf8_lossy(chunk))
+            .collect(),
+        rdata => normalize(&rdata.to_string()),
+    }
+}
+
+/// Ask one server for `name`, and compare its answer with `expected`.
+async fn ask(
+    resolver: String,
+    kind: ResolverKind,
+    address: SocketAddr,
+    name: Name,
+    record_type: RecordType,
+    expected: Option<&str>,
+) -> ResolverAnswer {
+    let mut answer = ResolverAnswer {
+        resolver,
+        kind,
+        address: Some(address.to_string()),
+        status: AnswerStatus::Failed,
+        answers: Vec::new(),
+        ttl: None,
+        error: None,
+    };
+    match self::resolver(&[address]).lookup(name, record_type).await {
+        Ok(lookup) => {
+            let records: Vec<_> = lookup
+                .record_iter()
+                .filter(|r| r.record_type() == record_type)
+                .collect();
+            answer.ttl = records.iter().map(|r| r.ttl()).min();
+            answer.answers = records
+                .iter()
+                .filter_map(|r| r.data())
+                .map(answer_text)
+                .collect();
+            answer.status = if answer.answers.is_empty() {
+                AnswerStatus::Missing
+            } else if expected.is_none_or(|expected| answer.answers.iter().any(|a| a == expected)) {
+                AnswerStatus::Agrees
+            } else {
+                AnswerStatus::Differs
+            };
+        }
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+            answer.status = AnswerStatus::Missing;
+        }
+        Err(e) => answer.error = Some(e.to_string()),
+    }
+    answer
+}
+
+/// Find the address of one of the zone's name servers through the recursive resolvers.
+async fn name_server_address(
+    resolvers: &TokioAsyncResolver,
+    name_server: &str,
+    port: u16,
+) -> Result<SocketAddr, String> {
+    let lookup = resolvers
+        .lookup_ip(format!("{}.", name_server.trim_end_matches('.')))
+        .await
+        .map_err(|e| format!("Can't find the address of {name_server}: {e}"))?;
+    let ip = lookup
+        .iter()
+        .find(IpAddr::is_ipv4)
+        .or_else(|| lookup.iter().next())
+        .ok_or_else(|| format!("{name_server} has no address"))?;
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// Ask the zone's name servers and the recursive resolvers for a record, and compare their answers with it.
+///
+/// The servers are asked at the same time, and a server that doesn't answer is reported rather than failing
+/// the whole check. Servers that don't answer are left out of the propagation percentage.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the record's name isn't a valid domain name.
+pub async fn check(
+    settings: &PropagationSettings,
+    zone: &CloudflareListZonesResponse,
+    record: &DNSRecord,
+) -> Result<PropagationReport, AppError> {
+    let name = Name::from_str(&format!("{}.", record.name.trim_end_matches('.'))).map_err(|e| {
+        AppError::validation(vec![FieldError::new(
+            "name",
+            format!("{} isn't a valid domain name: {e}", record.name),
+        )])
+    })?;
+    let (record_type, expected) = question(record);
+    let recursive: Vec<(String, SocketAddr)> = settings
+        .resolvers
+        .iter()
+        .filter_map(|r| socket_addr(&r.address, DNS_PORT).map(|address| (r.name.clone(), address)))
+        .collect();
+
+    let lookups = resolver(&recursive.iter().map(|(_, a)| *a).collect::<Vec<_>>());
+    let name_servers = join_all(zone.name_servers.iter().map(|name_server| {
+        let lookups = &lookups;
+        async move {
+            (
+                name_server.clone(),
+                name_server_address(lookups, name_server, settings.name_server_port).await,
+            )
+        }
+    }))
+    .await;
+
+    let mut failed = Vec::new();
+    let mut queries = Vec::new();
+    for (name_server, address) in name_servers {
+        match address {
+            Ok(address) => queries.push((name_server, ResolverKind::Authoritative, address)),
+            Err(error) => failed.push(ResolverAnswer {
+                resolver: name_server,
+                kind: ResolverKind::Authoritative,
+                address: None,
+                status: AnswerStatus::Failed,
+                answers: Vec::new(),
+                ttl: None,
+                error: Some(error),
+            }),
+        }
+    }
+    queries.extend(
+        recursive
+            .into_iter()
+            .map(|(resolver, address)| (resolver, ResolverKind::Recursive, address)),
+    );
+    let mut answers = join_all(queries.into_iter().map(|(resolver, kind, address)| {
+        ask(
+            resolver,
+            kind,
+            address,
+            name.clone(),
+            record_type,
+            expected.as_deref(),
+        )
+    }))
+    .await;
+    answers.extend(failed);
+    // Name servers first, in the zone's order, then the resolvers in the order they were set up.
+    answers.sort_by_key(|a| {
+        (
+            a.kind != ResolverKind::Authoritative,
+            zone.name_servers.iter().position(|n| *n == a.resolver),
+        )
+    });
+
+    let answered: Vec<&ResolverAnswer> = answers
+        .iter()
+        .filter(|a| a.status != AnswerStatus::Failed)
+        .collect();
+    let agreeing = answered
+        .iter()
+        .filter(|a| a.status == AnswerStatus::Agrees)
+        .count();
+    let propagation = if answered.is_empty() {
+        0.0
+    } else {
+        agreeing as f64 * 100.0 / answered.len() as f64
+    };
+    let consistent = answers
+        .iter()
+        .filter(|a| a.kind == ResolverKind::Authoritative)
+        .all(|a| a.status == AnswerStatus::Agrees);
+
+    Ok(PropagationReport {
+        zone_id: zone.id.clone(),
+        record_id: record.id.clone(),
+        name: record.name.clone(),
+        record_type: record_type.to_string(),
+        expected: expected.unwrap_or_else(|| "any address (proxied)".to_string()),
+        checked_at: Utc::now(),
+        answers,
+        propagation,
+        consistent,
+    })
+}
@@ -0,0 +1,248 @@
+//! Keeping requests within Cloudflare's rate limit, and retrying the ones that fail anyway.
+//!
+//! Cloudflare allows 1200 requests per five minutes per user. [`TokenBucket`] spends that budget on the
+//! client side, so a large initialization waits rather than being throttled. When Cloudflare responds
+//! with 429 anyway (other tools may share the budget), its `Retry-After` header pauses every request.
+//! [`RetryPolicy`] decides how often and how long to wait before retrying a failed `GET`.
+
+use std::{
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::error::{AppError, ErrorCategory};
+
+/// How many requests may be made in a period of time.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    /// How many requests may be made in each period.
+    pub requests: u32,
+    /// The length of the period.
+    pub per: Duration,
+}
+
+impl Default for RateLimit {
+    /// Cloudflare's global limit: 1200 requests per five minutes.
+    fn default() -> Self {
+        Self {
+            requests: 1200,
+            per: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// How failed requests are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// How many times a request is retried before its error is returned.
+    pub max_retries: u32,
+    /// The backoff before the first retry. It doubles with each retry after that.
+    pub base_delay: Duration,
+    /// The longest backoff between two attempts.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The backoff before retry number `attempt` (starting at 1): a random time between zero and the
+    /// exponential delay ("full jitter"), so clients that failed together don't retry together.
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        exponential.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// Why a request is waiting before it is sent.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleReason {
+    /// The client-side budget is spent, so the request waits for it to refill.
+    Budget,
+    /// Cloudflare responded with 429.
+    RateLimited,
+    /// Cloudflare failed to handle the request (HTTP 5xx).
+    Server,
+    /// The request never got a response.
+    Network,
+}
+
+/// The payload of the `api-throttled` event, emitted whenever a request has to wait before it is sent.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ThrottleEvent {
+    /// Why the request is waiting.
+    pub reason: ThrottleReason,
+    /// Which retry this is, or 0 if the request hasn't been sent yet.
+    pub attempt: u32,
+    /// How long the request will wait, in milliseconds.
+    #[ts(type = "number")]
+    pub retry_in_ms: u64,
+}
+
+impl ThrottleEvent {
+    /// An event for a request waiting `delay`.
+    #[must_use]
+    pub fn new(reason: ThrottleReason, attempt: u32, delay: Duration) -> Self {
+        Self {
+            reason,
+            attempt,
+            retry_in_ms: u64::try_from(delay.as_millis()).unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Why a request that failed with `error` is retried, or `None` if the error isn't one that is retried.
+    #[must_use]
+    pub const fn reason_for(error: &AppError) -> Option<ThrottleReason> {
+        match error.category {
+            ErrorCategory::RateLimited => Some(ThrottleReason::RateLimited),
+            ErrorCategory::Server => Some(ThrottleReason::Server),
+            ErrorCategory::Network => Some(ThrottleReason::Network),
+            _ => None,
+        }
+    }
+}
+
+/// A callback told about every request that has to wait, so the app can show that it is being throttled.
+pub struct ThrottleListener(Box<dyn Fn(&ThrottleEvent) + Send + Sync>);
+
+impl ThrottleListener {
+    /// A listener calling `f`.
+    pub fn new(f: impl Fn(&ThrottleEvent) + Send + Sync + 'static) -> Self {
+        Self(Box::new(f))
+    }
+
+    /// Tell the listener about a waiting request.
+    pub fn notify(&self, event: &ThrottleEvent) {
+        (self.0)(event);
+    }
+}
+
+impl fmt::Debug for ThrottleListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ThrottleListener")
+    }
+}
+
+/// The state of a [`TokenBucket`].
+#[derive(Debug)]
+struct Bucket {
+    /// How many requests can be made right now. Fractional, as the bucket refills continuously.
+    tokens: f64,
+    /// When `tokens` was last brought up to date.
+    refilled_at: Instant,
+    /// No request may be sent before this, after Cloudflare asked to wait with `Retry-After`.
+    paused_until: Option<Instant>,
+}
+
+/// A client-side rate limiter. Each request takes a token; tokens refill evenly over the period, up to the
+/// number of requests allowed in one period.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// The most tokens the bucket holds.
+    capacity: f64,
+    /// How many tokens are added per second.
+    refill_per_second: f64,
+    /// The current state.
+    bucket: Mutex<Bucket>,
+}
+
+impl TokenBucket {
+    /// A full bucket for the given limit.
+    #[must_use]
+    pub fn new(limit: RateLimit) -> Self {
+        let capacity = f64::from(limit.requests.max(1));
+        Self {
+            capacity,
+            refill_per_second: capacity / limit.per.as_secs_f64().max(f64::EPSILON),
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Take a token if one is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns how long to wait before trying again if there is no token, or requests are paused.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the bucket lock is poisoned.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let now = Instant::now();
+        #[allow(clippy::unwrap_used)]
+        let mut bucket = self.bucket.lock().unwrap();
+        if let Some(paused_until) = bucket.paused_until {
+            if paused_until > now {
+                return Err(paused_until - now);
+            }
+            bucket.paused_until = None;
+        }
+
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = elapsed
+            .mul_add(self.refill_per_second, bucket.tokens)
+            .min(self.capacity);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            drop(bucket);
+            return Ok(());
+        }
+        let missing = 1.0 - bucket.tokens;
+        drop(bucket);
+        Err(Duration::from_secs_f64(missing / self.refill_per_second))
+    }
+
+    /// Hold every request until `delay` has passed, e.g. when Cloudflare responds with `Retry-After`.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the bucket lock is poisoned.
+    pub fn pause_for(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        #[allow(clippy::unwrap_used)]
+        let mut bucket = self.bucket.lock().unwrap();
+        if bucket.paused_until.is_none_or(|paused| paused < until) {
+            bucket.paused_until = Some(until);
+        }
+        drop(bucket);
+    }
+}
+
+/// Read the `Retry-After` header: either a number of seconds, or an HTTP date.
+#[must_use]
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+        .or(Some(Duration::ZERO))
+}
@@ -0,0 +1,353 @@
+//! The content of a DNS record, typed by record type.
+//!
+//! Cloudflare sends a record's type as `type`, and its content either as a `content` string (A, AAAA, CNAME,
+//! MX, NS, OPENPGPKEY, PTR, TXT) or as a structured `data` object (every other type). MX and URI records
+//! also carry a top-level `priority`. [`DNSContent`] is tagged by `type` and flattened into the record, so it
+//! reads and writes exactly that shape.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// The type and content of a DNS record.
+///
+/// Records that Cloudflare describes with `data` only keep the `data`: the `content` string Cloudflare also
+/// sends for them is derived from it, and is ignored when creating or updating a record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "UPPERCASE")]
+pub enum DNSContent {
+    /// An IPv4 address.
+    A {
+        /// The address.
+        content: Ipv4Addr,
+    },
+    /// An IPv6 address.
+    Aaaa {
+        /// The address.
+        content: Ipv6Addr,
+    },
+    /// A certification authority allowed to issue certificates for the name.
+    Caa {
+        /// The CAA record fields.
+        data: CAAData,
+    },
+    /// A certificate or certificate revocation list.
+    Cert {
+        /// The CERT record fields.
+        data: CERTData,
+    },
+    /// An alias for another name.
+    Cname {
+        /// The name this is an alias for.
+        content: String,
+    },
+    /// A public key used for DNSSEC.
+    Dnskey {
+        /// The DNSKEY record fields.
+        data: DNSKEYData,
+    },
+    /// A delegation signer, linking a child zone's DNSSEC key to its parent.
+    Ds {
+        /// The DS record fields.
+        data: DSData,
+    },
+    /// Service binding for HTTPS.
+    Https {
+        /// The HTTPS record fields.
+        data: SVCBData,
+    },
+    /// A geographical location.
+    Loc {
+        /// The LOC record fields.
+        data: LOCData,
+    },
+    /// A mail server for the name.
+    Mx {
+        /// The mail server's name.
+        content: String,
+        /// Lower values are preferred.
+        priority: u16,
+    },
+    /// A rule rewriting the name into a URI or another name.
+    Naptr {
+        /// The NAPTR record fields.
+        data: NAPTRData,
+    },
+    /// A name server for the name.
+    Ns {
+        /// The name server's name.
+        content: String,
+    },
+    /// An `OpenPGP` public key.
+    Openpgpkey {
+        /// The key, base64 encoded.
+        content: String,
+    },
+    /// A pointer to another name, usually for reverse lookups.
+    Ptr {
+        /// The name pointed to.
+        content: String,
+    },
+    /// An S/MIME certificate association.
+    Smimea {
+        /// The SMIMEA record fields.
+        data: TLSAData,
+    },
+    /// The location of a service.
+    Srv {
+        /// The SRV record fields.
+        data: SRVData,
+    },
+    /// An SSH public key fingerprint.
+    Sshfp {
+        /// The SSHFP record fields.
+        data: SSHFPData,
+    },
+    /// General service binding.
+    Svcb {
+        /// The SVCB record fields.
+        data: SVCBData,
+    },
+    /// A TLS certificate association (DANE).
+    Tlsa {
+        /// The TLSA record fields.
+        data: TLSAData,
+    },
+    /// Free-form text.
+    Txt {
+        /// The text.
+        content: String,
+    },
+    /// A URI for the name.
+    Uri {
+        /// The URI record fields.
+        data: URIData,
+        /// Lower values are preferred.
+        priority: u16,
+    },
+}
+
+impl DNSContent {
+    /// Whether records of this type can be proxied through Cloudflare: only A, AAAA and CNAME records can.
+    #[must_use]
+    pub const fn is_proxiable(&self) -> bool {
+        matches!(
+            self,
+            Self::A { .. } | Self::Aaaa { .. } | Self::Cname { .. }
+        )
+    }
+
+    /// The record type, as Cloudflare names it (e.g. `AAAA`).
+    #[must_use]
+    pub const fn record_type(&self) -> &'static str {
+        match self {
+            Self::A { .. } => "A",
+            Self::Aaaa { .. } => "AAAA",
+            Self::Caa { .. } => "CAA",
+            Self::Cert { .. } => "CERT",
+            Self::Cname { .. } => "CNAME",
+            Self::Dnskey { .. } => "DNSKEY",
+            Self::Ds { .. } => "DS",
+            Self::Https { .. } => "HTTPS",
+            Self::Loc { .. } => "LOC",
+            Self::Mx { .. } => "MX",
+            Self::Naptr { .. } => "NAPTR",
+            Self::Ns { .. } => "NS",
+            Self::Openpgpkey { .. } => "OPENPGPKEY",
+            Self::Ptr { .. } => "PTR",
+            Self::Smimea { .. } => "SMIMEA",
+            Self::Srv { .. } => "SRV",
+            Self::Sshfp { .. } => "SSHFP",
+            Self::Svcb { .. } => "SVCB",
+            Self::Tlsa { .. } => "TLSA",
+            Self::Txt { .. } => "TXT",
+            Self::Uri { .. } => "URI",
+        }
+    }
+}
+
+/// The fields of a CAA record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct CAAData {
+    /// Flags for the record. 128 marks the tag as critical.
+    pub flags: u8,
+    /// The property, e.g. `issue`, `issuewild` or `iodef`.
+    pub tag: String,
+    /// The value of the property, e.g. `letsencrypt.org`.
+    pub value: String,
+}
+
+/// The fields of a CERT record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct CERTData {
+    /// The algorithm of the key.
+    pub algorithm: u8,
+    /// The certificate, base64 encoded.
+    pub certificate: String,
+    /// The key tag.
+    pub key_tag: u16,
+    /// The certificate type.
+    pub r#type: u16,
+}
+
+/// The fields of a DNSKEY record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct DNSKEYData {
+    /// The algorithm of the key.
+    pub algorithm: u8,
+    /// Flags for the key. 257 marks a key-signing key, 256 a zone-signing key.
+    pub flags: u16,
+    /// The protocol. Always 3.
+    pub protocol: u8,
+    /// The public key, base64 encoded.
+    pub public_key: String,
+}
+
+/// The fields of a DS record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct DSData {
+    /// The algorithm of the key.
+    pub algorithm: u8,
+    /// The digest of the key, hex encoded.
+    pub digest: String,
+    /// The digest algorithm.
+    pub digest_type: u8,
+    /// The key tag.
+    pub key_tag: u16,
+}
+
+/// The fields of an HTTPS or SVCB record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct SVCBData {
+    /// 0 for alias mode, otherwise lower values are preferred.
+    pub priority: u16,
+    /// The target name, or `.` for the owner name.
+    pub target: String,
+    /// The service parameters, e.g. `alpn="h3,h2"`.
+    pub value: String,
+}
+
+/// The hemisphere of a LOC record's latitude.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum LatitudeDirection {
+    /// North.
+    N,
+    /// South.
+    S,
+}
+
+/// The hemisphere of a LOC record's longitude.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+pub enum LongitudeDirection {
+    /// East.
+    E,
+    /// West.
+    W,
+}
+
+/// The fields of a LOC record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct LOCData {
+    /// Altitude in meters.
+    pub altitude: f64,
+    /// Degrees of latitude, 0 - 90.
+    pub lat_degrees: u8,
+    /// Hemisphere of the latitude.
+    pub lat_direction: LatitudeDirection,
+    /// Minutes of latitude, 0 - 59.
+    pub lat_minutes: u8,
+    /// Seconds of latitude, 0 - 59.999.
+    pub lat_seconds: f64,
+    /// Degrees of longitude, 0 - 180.
+    pub long_degrees: u8,
+    /// Hemisphere of the longitude.
+    pub long_direction: LongitudeDirection,
+    /// Minutes of longitude, 0 - 59.
+    pub long_minutes: u8,
+    /// Seconds of longitude, 0 - 59.999.
+    pub long_seconds: f64,
+    /// Horizontal precision in meters.
+    pub precision_horz: f64,
+    /// Vertical precision in meters.
+    pub precision_vert: f64,
+    /// Size of the location in meters.
+    pub size: f64,
+}
+
+/// The fields of a NAPTR record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct NAPTRData {
+    /// Flags controlling how the rule is applied, e.g. `U` or `S`.
+    pub flags: String,
+    /// The order rules are processed in. Lower values come first.
+    pub order: u16,
+    /// The preference between rules with the same order. Lower values are preferred.
+    pub preference: u16,
+    /// The substitution expression applied to the name.
+    pub regex: String,
+    /// The name to query next.
+    pub replacement: String,
+    /// The service, e.g. `E2U+sip`.
+    pub service: String,
+}
+
+/// The fields of a TLSA or SMIMEA record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct TLSAData {
+    /// The certificate association data, hex encoded.
+    pub certificate: String,
+    /// How the data is matched against the certificate.
+    pub matching_type: u8,
+    /// Which part of the certificate is matched.
+    pub selector: u8,
+    /// How the certificate is used.
+    pub usage: u8,
+}
+
+/// The fields of an SRV record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct SRVData {
+    /// The port the service listens on.
+    pub port: u16,
+    /// Lower values are preferred.
+    pub priority: u16,
+    /// The name of the host providing the service.
+    pub target: String,
+    /// The relative weight of targets with the same priority.
+    pub weight: u16,
+}
+
+/// The fields of an SSHFP record.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct SSHFPData {
+    /// The algorithm of the key.
+    pub algorithm: u8,
+    /// The fingerprint, hex encoded.
+    pub fingerprint: String,
+    /// The fingerprint type.
+    pub r#type: u8,
+}
+
+/// The fields of a URI record. Its priority is kept alongside the data, as Cloudflare does.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct URIData {
+    /// The URI.
+    pub target: String,
+    /// The relative weight of URIs with the same priority.
+    pub weight: u16,
+}
@@ -0,0 +1,246 @@
+//! Named snapshots of a zone's records, to compare with each other or the live zone, and to restore.
+//!
+//! Each snapshot is saved as its own JSON file in the `snapshots` directory of the app data directory, so
+//! taking one never rewrites another. A snapshot is restored by planning the changes that turn the live
+//! records back into the snapshot's; the plan is applied like any other, so each change is journaled.
+
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cloudflare::{DNSRecord, DNSRecordRequest},
+    error::{AppError, ErrorCategory, FieldError},
+    plan::{diff, ChangePlan, ConflictResolution, PlanOptions},
+    validation::ZoneContext,
+};
+
+/// The directory snapshots are saved in.
+const SNAPSHOTS_DIR: &str = "snapshots";
+
+/// A zone's records as they were at one point in time.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ZoneSnapshot {
+    /// Identifier for the snapshot.
+    pub id: String,
+    /// The name the snapshot was given, e.g. `before migration`.
+    pub name: String,
+    /// When the records were fetched.
+    pub taken_at: DateTime<Utc>,
+    /// The profile the snapshot was taken with.
+    pub profile: String,
+    /// The zone identifier.
+    pub zone_id: String,
+    /// The zone name.
+    pub zone_name: String,
+    /// The zone's records.
+    pub records: Vec<DNSRecord>,
+}
+
+/// A snapshot without its records, for listing.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct SnapshotSummary {
+    /// Identifier for the snapshot.
+    pub id: String,
+    /// The name the snapshot was given.
+    pub name: String,
+    /// When the records were fetched.
+    pub taken_at: DateTime<Utc>,
+    /// The profile the snapshot was taken with.
+    pub profile: String,
+    /// The zone identifier.
+    pub zone_id: String,
+    /// The zone name.
+    pub zone_name: String,
+    /// How many records the snapshot holds.
+    pub record_count: usize,
+}
+
+impl ZoneSnapshot {
+    /// A snapshot of records just fetched from Cloudflare.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the name is empty.
+    pub fn new(
+        name: &str,
+        profile: &str,
+        zone_id: &str,
+        zone_name: &str,
+        records: Vec<DNSRecord>,
+    ) -> Result<Self, AppError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::validation(vec![FieldError::new(
+                "name",
+                "A snapshot needs a name",
+            )]));
+        }
+        Ok(Self {
+            id: format!("{:032x}", rand::random::<u128>()),
+            name: name.to_string(),
+            taken_at: Utc::now(),
+            profile: profile.to_string(),
+            zone_id: zone_id.to_string(),
+            zone_name: zone_name.to_string(),
+            records,
+        })
+    }
+
+    /// The snapshot without its records.
+    #[must_use]
+    pub fn summary(&self) -> SnapshotSummary {
+        SnapshotSummary {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            taken_at: self.taken_at,
+            profile: self.profile.clone(),
+            zone_id: self.zone_id.clone(),
+            zone_name: self.zone_name.clone(),
+            record_count: self.records.len(),
+        }
+    }
+
+    /// The changes that return a zone with `records` to this snapshot.
+    #[must_use]
+    pub fn restore_plan(&self, records: &[DNSRecord]) -> ChangePlan {
+        changes_between(&self.zone_name, records, &self.records)
+    }
+}
+
+/// The changes that turn the records `from` into the records `to`, both of the zone `zone_name`.
+///
+/// Records Cloudflare manages itself (locked records) can't be changed, so they are left out on both sides.
+#[must_use]
+pub fn changes_between(zone_name: &str, from: &[DNSRecord], to: &[DNSRecord]) -> ChangePlan {
+    let from: Vec<DNSRecord> = from.iter().filter(|r| !r.locked).cloned().collect();
+    let zone = ZoneContext {
+        zone_name,
+        records: &from,
+    };
+    let wanted: Vec<DNSRecordRequest> = to
+        .iter()
+        .filter(|r| !r.locked)
+        .map(DNSRecordRequest::from)
+        .collect();
+    diff(
+        &zone,
+        &wanted,
+        PlanOptions {
+            on_conflict: ConflictResolution::Overwrite,
+            delete_missing: true,
+        },
+    )
+}
+
+/// The snapshots directory in the app data directory. This is held in Tauri managed state.
+#[derive(Debug)]
+pub struct SnapshotStore {
+    /// Where the snapshots are saved.
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    /// The snapshots directory in `data_dir`.
+    #[must_use]
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.join(SNAPSHOTS_DIR),
+        }
+    }
+
+    /// The file a snapshot is saved in. Identifiers are only ever hex, so they can't escape the directory.
+    fn path(&self, snapshot_id: &str) -> Result<PathBuf, AppError> {
+        if snapshot_id.is_empty() || !snapshot_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(not_found(snapshot_id));
+        }
+        Ok(self.dir.join(format!("{snapshot_id}.json")))
+    }
+
+    /// Save a snapshot, writing it in one step so a crash can't leave half a file behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the file can't be written.
+    pub fn save(&self, snapshot: &ZoneSnapshot) -> Result<(), AppError> {
+        let path = self.path(&snapshot.id)?;
+        fs::create_dir_all(&self.dir)?;
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec(snapshot)?)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
+    /// Load a snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if there is no such snapshot, or its file can't be read.
+    pub fn load(&self, snapshot_id: &str) -> Result<ZoneSnapshot, AppError> {
+        let path = self.path(snapshot_id)?;
+        if !path.exists() {
+            return Err(not_found(snapshot_id));
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// The saved snapshots, of every zone or just one, newest first. Files that can't be read are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if the directory exists but can't be read.
+    pub fn list(&self, zone_id: Option<&str>) -> Result<Vec<SnapshotSummary>, AppError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut summaries = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let snapshot = fs::read(&path)
+                .map_err(AppError::from)
+                .and_then(|data| Ok(serde_json::from_slice::<ZoneSnapshot>(&data)?));
+            match snapshot {
+                Ok(snapshot) if zone_id.is_none_or(|id| snapshot.zone_id == id) => {
+                    summaries.push(snapshot.summary());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Skipping snapshot {}: {e}", path.display()),
+            }
+        }
+        summaries.sort_by_key(|summary| Reverse(summary.taken_at));
+        Ok(summaries)
+    }
+
+    /// Delete a snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AppError`] if there is no such snapshot, or its file can't be deleted.
+    pub fn remove(&self, snapshot_id: &str) -> Result<(), AppError> {
+        let path = self.path(snapshot_id)?;
+        if !path.exists() {
+            return Err(not_found(snapshot_id));
+        }
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
+
+/// Create an error for a snapshot that doesn't exist.
+fn not_found(snapshot_id: &str) -> AppError {
+    AppError::new(
+        ErrorCategory::NotFound,
+        format!("There is no snapshot {snapshot_id}"),
+    )
+}
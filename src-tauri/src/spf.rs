@@ -0,0 +1,1070 @@
+//! Reading SPF records and checking what it costs receivers to evaluate them.
+//!
+//! [`SpfRecord::parse`] reads a `v=spf1` TXT record into its terms. [`analyze`] follows a domain's record
+//! through its `include:`, `redirect=`, `a` and `mx` terms, counting the DNS lookups a receiver makes against
+//! the limits of [RFC 7208](https://www.rfc-editor.org/rfc/rfc7208#section-4.6.4), and proposes a flattened
+//! record with the addresses those terms resolve to. Names are looked up through an [`SpfLookup`], which
+//! [`CachedLookup`] answers from the cached zones where it can.
+
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use futures::future::BoxFuture;
+use hickory_resolver::{
+    error::{ResolveError, ResolveErrorKind},
+    proto::op::ResponseCode,
+    TokioAsyncResolver,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cloudflare::DNSRecord,
+    error::{AppError, ErrorCategory},
+    models::ManagedCache,
+    propagation::PropagationSettings,
+    records::DNSContent,
+    validation::{txt_chunks, ZoneContext},
+};
+
+/// The most terms causing DNS lookups a receiver evaluates before giving up.
+pub const MAX_LOOKUPS: usize = 10;
+
+/// The most lookups finding nothing a receiver allows before giving up.
+pub const MAX_VOID_LOOKUPS: usize = 2;
+
+/// The most mail servers an `mx` term may have. Their addresses don't count as lookups.
+pub const MAX_MX_NAMES: usize = 10;
+
+/// The longest a record should be, so the answer to its lookup fits in a 512-byte UDP response (RFC 7208,
+/// section 3.4).
+pub const MAX_RECORD_LENGTH: usize = 450;
+
+/// How deep `include:` and `redirect=` are followed. Far more than the lookup limit allows.
+const MAX_DEPTH: usize = 20;
+
+/// What a term's result is when it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qualifier {
+    /// `+`, the default: the sender is allowed.
+    Pass,
+    /// `-`: the sender isn't allowed.
+    Fail,
+    /// `~`: the sender probably isn't allowed.
+    SoftFail,
+    /// `?`: nothing is said about the sender.
+    Neutral,
+}
+
+impl Qualifier {
+    /// The qualifier written before a term, or nothing for [`Qualifier::Pass`].
+    #[must_use]
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Self::Pass => "",
+            Self::Fail => "-",
+            Self::SoftFail => "~",
+            Self::Neutral => "?",
+        }
+    }
+}
+
+/// A term matching the sender's address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mechanism {
+    /// Matches every sender.
+    All,
+    /// Matches if the other domain's record passes.
+    Include(String),
+    /// Matches the addresses of a domain, the current one if there is none.
+    A {
+        /// The domain whose addresses match.
+        domain: Option<String>,
+        /// The length of the IPv4 networks around them.
+        ip4_prefix: Option<u8>,
+        /// The length of the IPv6 networks around them.
+        ip6_prefix: Option<u8>,
+    },
+    /// Matches the addresses of a domain's mail servers, the current one's if there is none.
+    Mx {
+        /// The domain whose mail servers match.
+        domain: Option<String>,
+        /// The length of the IPv4 networks around them.
+        ip4_prefix: Option<u8>,
+        /// The length of the IPv6 networks around them.
+        ip6_prefix: Option<u8>,
+    },
+    /// Matches if the sender's reverse DNS name is under a domain. Deprecated, as it is slow.
+    Ptr(Option<String>),
+    /// Matches an IPv4 network, written as it is in the record.
+    Ip4(String),
+    /// Matches an IPv6 network, written as it is in the record.
+    Ip6(String),
+    /// Matches if a domain, usually built from macros, has an address.
+    Exists(String),
+}
+
+impl Mechanism {
+    /// Whether a receiver makes a DNS lookup to evaluate this.
+    #[must_use]
+    pub const fn looks_up(&self) -> bool {
+        !matches!(self, Self::All | Self::Ip4(_) | Self::Ip6(_))
+    }
+}
+
+/// Write a domain and networks the way `a` and `mx` terms do.
+fn write_target(
+    f: &mut fmt::Formatter<'_>,
+    domain: Option<&String>,
+    ip4_prefix: Option<u8>,
+    ip6_prefix: Option<u8>,
+) -> fmt::Result {
+    if let Some(domain) = domain {
+        write!(f, ":{domain}")?;
+    }
+    if let Some(prefix) = ip4_prefix {
+        write!(f, "/{prefix}")?;
+    }
+    if let Some(prefix) = ip6_prefix {
+        write!(f, "//{prefix}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Mechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => f.write_str("all"),
+            Self::Include(domain) => write!(f, "include:{domain}"),
+            Self::A {
+                domain,
+                ip4_prefix,
+                ip6_prefix,
+            } => {
+                f.write_str("a")?;
+                write_target(f, domain.as_ref(), *ip4_prefix, *ip6_prefix)
+            }
+            Self::Mx {
+                domain,
+                ip4_prefix,
+                ip6_prefix,
+            } => {
+                f.write_str("mx")?;
+                write_target(f, domain.as_ref(), *ip4_prefix, *ip6_prefix)
+            }
+            Self::Ptr(domain) => {
+                f.write_str("ptr")?;
+                write_target(f, domain.as_ref(), None, None)
+            }
+            Self::Ip4(network) => write!(f, "ip4:{network}"),
+            Self::Ip6(network) => write!(f, "ip6:{network}"),
+            Self::Exists(domain) => write!(f, "exists:{domain}"),
+        }
+    }
+}
+
+/// One term of an SPF record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    /// A mechanism, matched against the sender in order.
+    Mechanism {
+        /// The result if it matches.
+        qualifier: Qualifier,
+        /// What it matches.
+        mechanism: Mechanism,
+    },
+    /// A `name=value` modifier, e.g. `redirect=_spf.example.com`.
+    Modifier {
+        /// The modifier name, lowercase.
+        name: String,
+        /// The value.
+        value: String,
+    },
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mechanism {
+                qualifier,
+                mechanism,
+            } => write!(f, "{}{mechanism}", qualifier.prefix()),
+            Self::Modifier { name, value } => write!(f, "{name}={value}"),
+        }
+    }
+}
+
+/// A parsed `v=spf1` record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpfRecord {
+    /// The terms after the version, in order.
+    pub terms: Vec<Term>,
+}
+
+/// Whether TXT record text is an SPF record.
+#[must_use]
+pub fn is_spf(text: &str) -> bool {
+    let text = text.trim_start();
+    text.get(..6)
+        .is_some_and(|version| version.eq_ignore_ascii_case("v=spf1"))
+        && text[6..].chars().next().is_none_or(|c| c == ' ')
+}
+
+/// An error reading an SPF record.
+fn syntax_error(message: impl Into<String>) -> AppError {
+    AppError::new(ErrorCategory::Parse, message)
+}
+
+/// Read a domain name, which may contain macros.
+fn domain_spec(term: &str, domain: &str) -> Result<String, AppError> {
+    if domain.is_empty() || domain.contains(char::is_whitespace) {
+        return Err(syntax_error(format!("{term} needs a domain")));
+    }
+    Ok(domain.to_string())
+}
+
+/// Read a network length of at most `max`.
+fn prefix(term: &str, text: &str, max: u8) -> Result<u8, AppError> {
+    text.parse()
+        .ok()
+        .filter(|prefix| *prefix <= max)
+        .ok_or_else(|| syntax_error(format!("{term} has a network length that isn't 0 to {max}")))
+}
+
+/// The optional domain, IPv4 network length and IPv6 network length of an `a` or `mx` term.
+type Target = (Option<String>, Option<u8>, Option<u8>);
+
+/// Read what follows `a` or `mx`.
+fn target(term: &str, rest: &str) -> Result<Target, AppError> {
+    let (domain, cidr) = rest.find('/').map_or((rest, ""), |i| rest.split_at(i));
+    let domain = match domain.strip_prefix(':') {
+        Some(domain) => Some(domain_spec(term, domain)?),
+        None if domain.is_empty() => None,
+        None => return Err(syntax_error(format!("{term} isn't a mechanism SPF has"))),
+    };
+    let (ip4, ip6) = match cidr.split_once("//") {
+        Some((ip4, ip6)) => (ip4, Some(ip6)),
+        None => (cidr, None),
+    };
+    let ip4_prefix = ip4
+        .strip_prefix('/')
+        .map(|p| prefix(term, p, 32))
+        .transpose()?;
+    if ip4_prefix.is_none() && !ip4.is_empty() {
+        return Err(syntax_error(format!("{term} isn't a mechanism SPF has")));
+    }
+    let ip6_prefix = ip6.map(|p| prefix(term, p, 128)).transpose()?;
+    Ok((domain, ip4_prefix, ip6_prefix))
+}
+
+/// Read an `ip4` or `ip6` network.
+fn network<T: std::str::FromStr>(term: &str, text: &str, max: u8) -> Result<String, AppError> {
+    let (address, length) = text
+        .split_once('/')
+        .map_or((text, None), |(a, l)| (a, Some(l)));
+    if address.parse::<T>().is_err() {
+        return Err(syntax_error(format!("{term} doesn't have a valid address")));
+    }
+    if let Some(length) = length {
+        prefix(term, length, max)?;
+    }
+    Ok(text.to_string())
+}
+
+/// Read one mechanism, without its qualifier.
+fn mechanism(term: &str, text: &str) -> Result<Mechanism, AppError> {
+    let split = text.find([':', '/']).unwrap_or(text.len());
+    let (name, rest) = text.split_at(split);
+    let argument = rest.strip_prefix(':');
+    Ok(match (name.to_ascii_lowercase().as_str(), argument) {
+        ("all", None) if rest.is_empty() => Mechanism::All,
+        ("include", Some(domain)) => Mechanism::Include(domain_spec(term, domain)?),
+        ("exists", Some(domain)) => Mechanism::Exists(domain_spec(term, domain)?),
+        ("ip4", Some(text)) => Mechanism::Ip4(network::<Ipv4Addr>(term, text, 32)?),
+        ("ip6", Some(text)) => Mechanism::Ip6(network::<Ipv6Addr>(term, text, 128)?),
+        ("a", _) => {
+            let (domain, ip4_prefix, ip6_prefix) = target(term, rest)?;
+            Mechanism::A {
+                domain,
+                ip4_prefix,
+                ip6_prefix,
+            }
+        }
+        ("mx", _) => {
+            let (domain, ip4_prefix, ip6_prefix) = target(term, rest)?;
+            Mechanism::Mx {
+                domain,
+                ip4_prefix,
+                ip6_prefix,
+            }
+        }
+        ("ptr", None) if rest.is_empty() => Mechanism::Ptr(None),
+        ("ptr", Some(domain)) => Mechanism::Ptr(Some(domain_spec(term, domain)?)),
+        ("include" | "exists" | "ip4" | "ip6", None) => {
+            return Err(syntax_error(format!("{term} needs a value after a colon")))
+        }
+        _ => return Err(syntax_error(format!("{term} isn't a mechanism SPF has"))),
+    })
+}
+
+impl SpfRecord {
+    /// Read an SPF record from TXT record text, with or without its quotes.
+    ///
+    /// # Errors
+    ///
+    /// This will return an [`AppError`] if the text isn't a `v=spf1` record, or a term in it is invalid.
+    pub fn parse(text: &str) -> Result<Self, AppError> {
+        let text = txt_chunks(text).concat();
+        if !is_spf(&text) {
+            return Err(syntax_error("SPF records start with v=spf1"));
+        }
+        let mut terms = Vec::new();
+        for term in text.split_ascii_whitespace().skip(1) {
+            let modifier = term.split_once('=').filter(|(name, _)| {
+                name.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+            });
+            if let Some((name, value)) = modifier {
+                let name = name.to_ascii_lowercase();
+                if (name == "redirect" || name == "exp")
+                    && terms
+                        .iter()
+                        .any(|t| matches!(t, Term::Modifier { name: n, .. } if *n == name))
+                {
+                    return Err(syntax_error(format!("{name}= can only be given once")));
+                }
+                terms.push(Term::Modifier {
+                    value: domain_spec(&name, value)?,
+                    name,
+                });
+                continue;
+            }
+            let (qualifier, mechanism_text) = match term.chars().next() {
+                Some('+') => (Qualifier::Pass, &term[1..]),
+                Some('-') => (Qualifier::Fail, &term[1..]),
+                Some('~') => (Qualifier::SoftFail, &term[1..]),
+                Some('?') => (Qualifier::Neutral, &term[1..]),
+                _ => (Qualifier::Pass, term),
+            };
+            terms.push(Term::Mechanism {
+                qualifier,
+                mechanism: mechanism(term, mechanism_text)?,
+            });
+        }
+        Ok(Self { terms })
+    }
+
+    /// The domain in the `redirect=` modifier, if there is one.
+    #[must_use]
+    pub fn redirect(&self) -> Option<&str> {
+        self.modifier("redirect")
+    }
+
+    /// The value of the modifier `name`, if there is one.
+    #[must_use]
+    pub fn modifier(&self, name: &str) -> Option<&str> {
+        self.terms.iter().find_map(|term| match term {
+            Term::Modifier { name: n, value } if n == name => Some(value.as_str()),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for SpfRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("v=spf1")?;
+        for term in &self.terms {
+            write!(f, " {term}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The kinds of answer an SPF check looks up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Query {
+    /// TXT records, each with its character strings joined.
+    Txt,
+    /// IPv4 addresses.
+    A,
+    /// IPv6 addresses.
+    Aaaa,
+    /// Mail server names, most preferred first.
+    Mx,
+}
+
+/// Something that can look up the records an SPF check needs.
+pub trait SpfLookup: fmt::Debug + Send + Sync {
+    /// The answers of `query`'s kind at `name`, written as text. Empty if there are none, or the name doesn't
+    /// exist.
+    fn lookup(&self, name: &str, query: Query) -> BoxFuture<'_, Result<Vec<String>, AppError>>;
+}
+
+/// Looks records up through the recursive resolvers in the [`PropagationSettings`].
+#[derive(Debug)]
+pub struct DnsLookup {
+    /// The resolver asking them.
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsLookup {
+    /// A lookup asking the recursive resolvers in `settings`.
+    #[must_use]
+    pub fn new(settings: &PropagationSettings) -> Self {
+        Self {
+            resolver: settings.resolver(),
+        }
+    }
+}
+
+/// The answers of a lookup, with no answers and names that don't exist both giving none.
+fn answers<T>(
+    name: &str,
+    result: Result<T, ResolveError>,
+    text: impl FnOnce(T) -> Vec<String>,
+) -> Result<Vec<String>, AppError> {
+    match result {
+        Ok(lookup) => Ok(text(lookup)),
+        Err(e) => match e.kind() {
+            ResolveErrorKind::NoRecordsFound {
+                response_code: ResponseCode::NXDomain | ResponseCode::NoError,
+                ..
+            } => Ok(Vec::new()),
+            _ => Err(AppError::new(
+                ErrorCategory::Network,
+                format!("Can't look up {name}: {e}"),
+            )),
+        },
+    }
+}
+
+impl SpfLookup for DnsLookup {
+    fn lookup(&self, name: &str, query: Query) -> BoxFuture<'_, Result<Vec<String>, AppError>> {
+        let name = format!("{}.", name.trim_end_matches('.'));
+        Box::pin(async move {
+            let resolver = &self.resolver;
+            match query {
+                Query::Txt => answers(&name, resolver.txt_lookup(name.as_str()).await, |lookup| {
+                    lookup
+                        .iter()
+                        .map(|txt| {
+                            txt.txt_data()
+                                .iter()
+                                .map(|chunk| String::from_utf8_lossy(chunk))
+                                .collect()
+                        })
+                        .collect()
+                }),
+                Query::A => answers(&name, resolver.ipv4_lookup(name.as_str()).await, |lookup| {
+                    lookup.iter().map(ToString::to_string).collect()
+                }),
+                Query::Aaaa => {
+                    answers(&name, resolver.ipv6_lookup(name.as_str()).await, |lookup| {
+                        lookup.iter().map(ToString::to_string).collect()
+                    })
+                }
+                Query::Mx => answers(&name, resolver.mx_lookup(name.as_str()).await, |lookup| {
+                    let mut servers: Vec<_> = lookup.iter().collect();
+                    servers.sort_by_key(|mx| mx.preference());
+                    servers
+                        .iter()
+                        .map(|mx| mx.exchange().to_string().trim_end_matches('.').to_string())
+                        .collect()
+                }),
+            }
+        })
+    }
+}
+
+/// Answers from the cached records of the zone a name is in, and through another lookup for other names.
+///
+/// Proxied records resolve to Cloudflare's addresses rather than their content, so names
+/// with proxied address records are looked up too.
+#[derive(Debug)]
+pub struct CachedLookup<'a> {
+    /// The cached zones.
+    pub cache: &'a ManagedCache,
+    /// The lookup for everything else.
+    pub fallback: &'a dyn SpfLookup,
+}
+
+impl CachedLookup<'_> {
+    /// The answers from the cache, or `None` if the name isn't in a cached zone or can't be answered from it.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the cache is poisoned.
+    fn cached(&self, name: &str, query: Query) -> Option<Vec<String>> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        // The most specific zone, in case one is cached as well as its parent.
+        #[allow(clippy::unwrap_used)]
+        let zone_id = self
+            .cache
+            .zones
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|zone| {
+                ZoneContext {
+                    zone_name: &zone.name,
+                    records: &[],
+                }
+                .qualify(&name)
+                .is_some()
+            })
+            .max_by_key(|zone| zone.name.len())
+            .map(|zone| zone.id.clone())?;
+        #[allow(clippy::unwrap_used)]
+        let at_name: Vec<DNSRecord> = self
+            .cache
+            .zone_dns
+            .lock()
+            .unwrap()
+            .get(&zone_id)?
+            .iter()
+            .filter(|r| r.name.trim_end_matches('.').eq_ignore_ascii_case(&name))
+            .cloned()
+            .collect();
+        let mut answers = Vec::new();
+        let mut servers = Vec::new();
+        for record in &at_name {
+            match (&record.content, query) {
+                (DNSContent::Txt { content }, Query::Txt) => {
+                    answers.push(txt_chunks(content).concat());
+                }
+                (DNSContent::A { .. } | DNSContent::Aaaa { .. }, Query::A | Query::Aaaa)
+                    if record.proxied.unwrap_or_default() =>
+                {
+                    return None;
+                }
+                (DNSContent::A { content }, Query::A) => answers.push(content.to_string()),
+                (DNSContent::Aaaa { content }, Query::Aaaa) => answers.push(content.to_string()),
+                (DNSContent::Mx { content, priority }, Query::Mx) => servers.push((
+                    *priority,
+                    content.trim_end_matches('.').to_ascii_lowercase(),
+                )),
+                // The name is an alias; where it points may not be cached.
+                (DNSContent::Cname { .. }, _) => return None,
+                _ => {}
+            }
+        }
+        servers.sort();
+        answers.extend(servers.into_iter().map(|(_, server)| server));
+        Some(answers)
+    }
+}
+
+impl SpfLookup for CachedLookup<'_> {
+    fn lookup(&self, name: &str, query: Query) -> BoxFuture<'_, Result<Vec<String>, AppError>> {
+        match self.cached(name, query) {
+            Some(answers) => Box::pin(async move { Ok(answers) }),
+            None => self.fallback.lookup(name, query),
+        }
+    }
+}
+
+/// The kinds of problem an SPF check finds.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum SpfProblemKind {
+    /// A domain has no SPF record.
+    NoRecord,
+    /// A domain has more than one SPF record, so receivers treat it as having a broken one.
+    MultipleRecords,
+    /// A record can't be read.
+    Syntax,
+    /// Evaluating the record takes more than [`MAX_LOOKUPS`] lookups, so receivers give up.
+    TooManyLookups,
+    /// More than [`MAX_VOID_LOOKUPS`] lookups find nothing, so receivers give up.
+    TooManyVoidLookups,
+    /// A lookup found nothing.
+    VoidLookup,
+    /// The record ends with `+all`, allowing anyone to send mail as the domain.
+    PassAll,
+    /// Records include each other.
+    Loop,
+    /// An `mx` term has more than [`MAX_MX_NAMES`] mail servers.
+    TooManyMxNames,
+    /// A `ptr` term, which RFC 7208 says not to use.
+    Ptr,
+    /// A lookup failed.
+    LookupFailed,
+    /// The flattened record is longer than [`MAX_RECORD_LENGTH`], so it can't be published as one record.
+    FlattenedTooLong,
+}
+
+/// A problem found in a domain's SPF record, or one it includes.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct SpfProblem {
+    /// What kind of problem it is.
+    pub kind: SpfProblemKind,
+    /// The domain whose record has the problem.
+    pub domain: String,
+    /// What the problem is.
+    pub message: String,
+}
+
+/// A record followed to from another.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export)]
+pub struct SpfInclude {
+    /// The domain followed to.
+    pub domain: String,
+    /// How deep it is: 1 for the record's own includes, 2 for theirs, and so on.
+    pub depth: usize,
+    /// Whether it was followed by `include:` or `redirect=`.
+    pub redirect: bool,
+    /// The lookups evaluating its record takes, not counting the term that leads to it.
+    pub lookups: usize,
+}
+
+/// What evaluating a domain's SPF record involves.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct SpfReport {
+    /// The domain checked.
+    pub domain: String,
+    /// The SPF records at the domain. There should be exactly one.
+    pub records: Vec<String>,
+    /// The lookups evaluating the record takes, at most [`MAX_LOOKUPS`] for receivers to accept it.
+    pub lookups: usize,
+    /// The lookups finding nothing, at most [`MAX_VOID_LOOKUPS`] for receivers to accept it.
+    pub void_lookups: usize,
+    /// The records followed to, in the order they are evaluated.
+    pub includes: Vec<SpfInclude>,
+    /// The problems found.
+    pub problems: Vec<SpfProblem>,
+    /// The record with `include:`, `redirect=`, `a` and `mx` replaced by the addresses they currently
+    /// resolve to, so it takes no lookups for them. The addresses need updating when the providers' change.
+    ///
+    /// `None` if a record couldn't be read or a lookup failed, since the addresses would be incomplete.
+    pub flattened: Option<String>,
+}
+
+/// A term of the flattened record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Flat {
+    /// The result if it matches.
+    qualifier: Qualifier,
+    /// The term without its qualifier, e.g. `ip4:192.0.2.1`.
+    term: String,
+}
+
+/// Follows records, counting lookups and noting problems.
+struct Walker<'a> {
+    /// Where records are looked up.
+    lookup: &'a dyn SpfLookup,
+    /// The lookups counted against [`MAX_LOOKUPS`].
+    lookups: usize,
+    /// The lookups that found nothing.
+    void_lookups: usize,
+    /// The records followed to.
+    includes: Vec<SpfInclude>,
+    /// The problems found.
+    problems: Vec<SpfProblem>,
+    /// The domains whose records are being evaluated, outermost first.
+    chain: Vec<String>,
+}
+
+impl Walker<'_> {
+    /// Note a problem with `domain`'s record.
+    fn problem(&mut self, kind: SpfProblemKind, domain: &str, message: impl Into<String>) {
+        self.problems.push(SpfProblem {
+            kind,
+            domain: domain.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Look something up for `domain`'s record, noting failures and void lookups. `None` if the lookup failed.
+    async fn query(&mut self, domain: &str, name: &str, query: Query) -> Option<Vec<String>> {
+        match self.lookup.lookup(name, query).await {
+            Ok(answers) => Some(answers),
+            Err(e) => {
+                self.problem(SpfProblemKind::LookupFailed, domain, e.message);
+                None
+            }
+        }
+    }
+
+    /// Note a lookup for `term` that found nothing.
+    fn void(&mut self, domain: &str, term: &str) {
+        self.void_lookups += 1;
+        self.problem(
+            SpfProblemKind::VoidLookup,
+            domain,
+            format!("{term} finds nothing"),
+        );
+    }
+
+    /// The addresses of `name` as `ip4` and `ip6` terms. Empty if it has none, and `None` if a lookup failed.
+    async fn addresses(
+        &mut self,
+        domain: &str,
+        name: &str,
+        ip4_prefix: Option<u8>,
+        ip6_prefix: Option<u8>,
+    ) -> Option<Vec<String>> {
+        let with_prefix = |kind: &str, address: String, prefix: Option<u8>| {
+            let prefix = prefix.map(|p| format!("/{p}")).unwrap_or_default();
+            format!("{kind}:{address}{prefix}")
+        };
+        let ip4 = self.query(domain, name, Query::A).await;
+        let ip6 = self.query(domain, name, Query::Aaaa).await;
+        let terms = ip4?
+            .into_iter()
+            .map(|address| with_prefix("ip4", address, ip4_prefix))
+            .chain(
+                ip6?.into_iter()
+                    .map(|address| with_prefix("ip6", address, ip6_prefix)),
+            )
+            .collect();
+        Some(terms)
+    }
+
+    /// The SPF record of `domain`, reached from another record, noting problems with it. `None` if there
+    /// isn't one that can be read.
+    async fn record(&mut self, domain: &str, term: &str) -> Option<SpfRecord> {
+        let txt = self.query(domain, domain, Query::Txt).await?;
+        let records: Vec<&String> = txt.iter().filter(|text| is_spf(text)).collect();
+        let Some(first) = records.first() else {
+            if txt.is_empty() {
+                self.void(domain, term);
+            }
+            self.problem(
+                SpfProblemKind::NoRecord,
+                domain,
+                format!("{term} points at a domain with no SPF record, so receivers reject the whole record"),
+            );
+            return None;
+        };
+        if records.len() > 1 {
+            self.problem(
+                SpfProblemKind::MultipleRecords,
+                domain,
+                format!(
+                    "{domain} has {} SPF records; receivers reject them all",
+                    records.len()
+                ),
+            );
+        }
+        match SpfRecord::parse(first) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                self.problem(SpfProblemKind::Syntax, domain, e.message);
+                None
+            }
+        }
+    }
+
+    /// Follow `include:` or `redirect=` to `target` from the record of `domain`, returning the flattened
+    /// terms of its record.
+    async fn follow(&mut self, domain: &str, target: &str, redirect: bool) -> Option<Vec<Flat>> {
+        let term = if redirect {
+            format!("redirect={target}")
+        } else {
+            format!("include:{target}")
+        };
+        let target = target.trim_end_matches('.').to_ascii_lowercase();
+        if self.chain.contains(&target) || self.chain.len() > MAX_DEPTH {
+            self.problem(
+                SpfProblemKind::Loop,
+                domain,
+                format!("{term} leads back to a record already being evaluated"),
+            );
+            return None;
+        }
+        let before = self.lookups;
+        let index = self.includes.len();
+        self.includes.push(SpfInclude {
+            domain: target.clone(),
+            depth: self.chain.len(),
+            redirect,
+            lookups: 0,
+        });
+        let flat = match self.record(&target, &term).await {
+            Some(record) => {
+                self.chain.push(target.clone());
+                let flat = self.walk(&target, &record).await;
+                self.chain.pop();
+                Some(flat)
+            }
+            None => None,
+        };
+        self.includes[index].lookups = self.lookups - before;
+        flat
+    }
+
+    /// Evaluate `domain`'s record, returning its terms with lookups replaced by the addresses they find.
+    fn walk<'s>(&'s mut self, domain: &'s str, record: &'s SpfRecord) -> BoxFuture<'s, Vec<Flat>> {
+        Box::pin(async move {
+            let mut flat = Vec::new();
+            let mut ends_with_all = false;
+            for term in &record.terms {
+                let Term::Mechanism {
+                    qualifier,
+                    mechanism,
+                } = term
+                else {
+                    continue;
+                };
+                let qualifier = *qualifier;
+                if mechanism.looks_up() {
+                    self.lookups += 1;
+                }
+                let written = term.to_string();
+                // Terms with macros depend on the sender, so can't be looked up ahead of time.
+                let terms = if written.contains('%') {
+                    vec![mechanism.to_string()]
+                } else {
+                    self.expand(domain, &written, qualifier, mechanism).await
+                };
+                flat.extend(terms.into_iter().map(|term| Flat { qualifier, term }));
+                if *mechanism == Mechanism::All {
+                    // Receivers never get past it.
+                    ends_with_all = true;
+                    break;
+                }
+            }
+            if let (false, Some(target)) = (ends_with_all, record.redirect()) {
+                self.lookups += 1;
+                if target.contains('%') {
+                    flat.push(Flat {
+                        qualifier: Qualifier::Pass,
+                        term: format!("redirect={target}"),
+                    });
+                } else if let Some(redirected) = self.follow(domain, target, true).await {
+                    flat.extend(redirected);
+                }
+            }
+            flat
+        })
+    }
+
+    /// The flattened terms of one mechanism in `domain`'s record.
+    async fn expand(
+        &mut self,
+        domain: &str,
+        written: &str,
+        qualifier: Qualifier,
+        mechanism: &Mechanism,
+    ) -> Vec<String> {
+        match mechanism {
+            Mechanism::All => {
+                if qualifier == Qualifier::Pass {
+                    self.problem(
+                        SpfProblemKind::PassAll,
+                        domain,
+                        format!("{written} lets anyone send mail as {domain}"),
+                    );
+                }
+                vec![mechanism.to_string()]
+            }
+            Mechanism::Include(target) => self
+                .follow(domain, target, false)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                // Only the included record passing matches; its other results don't.
+                .filter(|flat| flat.qualifier == Qualifier::Pass)
+                .map(|flat| flat.term)
+                .collect(),
+            Mechanism::A {
+                domain: target,
+                ip4_prefix,
+                ip6_prefix,
+            } => {
+                let name = target.as_deref().unwrap_or(domain);
+                let terms = self.addresses(domain, name, *ip4_prefix, *ip6_prefix).await;
+                if terms.as_ref().is_some_and(Vec::is_empty) {
+                    self.void(domain, written);
+                }
+                terms.unwrap_or_default()
+            }
+            Mechanism::Mx {
+                domain: target,
+                ip4_prefix,
+                ip6_prefix,
+            } => {
+                let name = target.as_deref().unwrap_or(domain);
+                let Some(servers) = self.query(domain, name, Query::Mx).await else {
+                    return Vec::new();
+                };
+                if servers.is_empty() {
+                    self.void(domain, written);
+                }
+                if servers.len() > MAX_MX_NAMES {
+                    self.problem(
+                        SpfProblemKind::TooManyMxNames,
+                        domain,
+                        format!(
+                            "{written} has {} mail servers; receivers give up after {MAX_MX_NAMES}",
+                            servers.len()
+                        ),
+                    );
+                }
+                let mut terms = Vec::new();
+                for server in servers.iter().take(MAX_MX_NAMES) {
+                    terms.extend(
+                        self.addresses(domain, server, *ip4_prefix, *ip6_prefix)
+                            .await
+                            .unwrap_or_default(),
+                    );
+                }
+                terms
+            }
+            Mechanism::Ptr(_) => {
+                self.problem(
+                    SpfProblemKind::Ptr,
+                    domain,
+                    format!("{written} is slow and unreliable; RFC 7208 says not to use it"),
+                );
+                vec![mechanism.to_string()]
+            }
+            Mechanism::Ip4(_) | Mechanism::Ip6(_) | Mechanism::Exists(_) => {
+                vec![mechanism.to_string()]
+            }
+        }
+    }
+
+    /// Note the limits the walk went past, and write the flattened record of `domain`'s `record`, unless a
+    /// record couldn't be followed.
+    fn check_limits(
+        &mut self,
+        domain: &str,
+        flat: Option<&[Flat]>,
+        record: Option<&SpfRecord>,
+    ) -> Option<String> {
+        if self.lookups > MAX_LOOKUPS {
+            self.problem(
+                SpfProblemKind::TooManyLookups,
+                domain,
+                format!(
+                    "Evaluating the record takes {} lookups; receivers give up after {MAX_LOOKUPS}",
+                    self.lookups
+                ),
+            );
+        }
+        if self.void_lookups > MAX_VOID_LOOKUPS {
+            self.problem(
+                SpfProblemKind::TooManyVoidLookups,
+                domain,
+                format!(
+                    "{} lookups find nothing; receivers give up after {MAX_VOID_LOOKUPS}",
+                    self.void_lookups
+                ),
+            );
+        }
+        // Publishing a record missing the addresses of a term that couldn't be followed would reject mail.
+        let incomplete = self.problems.iter().any(|problem| {
+            matches!(
+                problem.kind,
+                SpfProblemKind::LookupFailed
+                    | SpfProblemKind::NoRecord
+                    | SpfProblemKind::Syntax
+                    | SpfProblemKind::Loop
+            )
+        });
+        let flattened = flatten(flat.filter(|_| !incomplete)?, record?.modifier("exp"));
+        if flattened.len() > MAX_RECORD_LENGTH {
+            self.problem(
+                SpfProblemKind::FlattenedTooLong,
+                domain,
+                format!(
+                    "The flattened record is {} characters; keep it under {MAX_RECORD_LENGTH} so its \
+                     lookup fits in one response, or split it across includes",
+                    flattened.len()
+                ),
+            );
+        }
+        Some(flattened)
+    }
+}
+
+/// Look up `domain`'s SPF record and follow it, counting the lookups a receiver makes and noting problems.
+///
+/// The lookup of the record itself doesn't count towards the limit. Everything is followed even past the
+/// limits, so the report shows the full cost.
+pub async fn analyze(lookup: &dyn SpfLookup, domain: &str) -> SpfReport {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    let mut walker = Walker {
+        lookup,
+        lookups: 0,
+        void_lookups: 0,
+        includes: Vec::new(),
+        problems: Vec::new(),
+        chain: vec![domain.clone()],
+    };
+    let records: Vec<String> = walker
+        .query(&domain, &domain, Query::Txt)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|text| is_spf(text))
+        .collect();
+    let record = match records.as_slice() {
+        [] => {
+            walker.problem(
+                SpfProblemKind::NoRecord,
+                &domain,
+                format!("{domain} has no SPF record"),
+            );
+            None
+        }
+        [first, rest @ ..] => {
+            if !rest.is_empty() {
+                walker.problem(
+                    SpfProblemKind::MultipleRecords,
+                    &domain,
+                    format!(
+                        "{domain} has {} SPF records; receivers reject them all",
+                        records.len()
+                    ),
+                );
+            }
+            SpfRecord::parse(first)
+                .map_err(|e| walker.problem(SpfProblemKind::Syntax, &domain, e.message))
+                .ok()
+        }
+    };
+
+    let flat = match &record {
+        Some(record) => Some(walker.walk(&domain, record).await),
+        None => None,
+    };
+    let flattened = walker.check_limits(&domain, flat.as_deref(), record.as_ref());
+
+    SpfReport {
+        domain,
+        records,
+        lookups: walker.lookups,
+        void_lookups: walker.void_lookups,
+        includes: walker.includes,
+        problems: walker.problems,
+        flattened,
+    }
+}
+
+/// Write flattened terms as a record, each once, keeping `exp=`.
+fn flatten(flat: &[Flat], exp: Option<&str>) -> String {
+    let mut terms: Vec<String> = Vec::new();
+    for Flat { qualifier, term } in flat {
+        let written = format!("{}{term}", qualifier.prefix());
+        if !terms.contains(&written) {
+            terms.push(written);
+        }
+    }
+    if let Some(exp) = exp {
+        terms.push(format!("exp={exp}"));
+    }
+    std::iter::once("v=spf1".to_string())
+        .chain(terms)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
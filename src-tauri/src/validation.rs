@@ -0,0 +1,436 @@
+//! Checks on DNS records before changes are sent to Cloudflare.
+//!
+//! Cloudflare rejects most invalid records itself, but only one problem at a time and with messages that
+//! don't say which field is wrong. These checks run against the cached zone first and report every problem
+//! as a [`FieldError`], keyed by the path of the field in [`DNSRecordRequest`], so the UI can highlight it.
+
+use std::net::IpAddr;
+
+use crate::{
+    cloudflare::{DNSRecord, DNSRecordRequest},
+    error::{AppError, FieldError},
+    records::DNSContent,
+};
+
+/// The TTL value meaning "automatic".
+pub const AUTO_TTL: u32 = 1;
+
+/// The shortest TTL Cloudflare accepts, other than [`AUTO_TTL`].
+pub const MIN_TTL: u32 = 60;
+
+/// The longest TTL Cloudflare accepts.
+pub const MAX_TTL: u32 = 86400;
+
+/// The longest a full name may be, in characters.
+const MAX_NAME_LENGTH: usize = 253;
+
+/// The longest a single label of a name may be, in characters.
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// The longest a single TXT character string may be, in bytes.
+const MAX_TXT_CHUNK_LENGTH: usize = 255;
+
+/// The longest content Cloudflare accepts for a TXT record, in characters.
+const MAX_TXT_LENGTH: usize = 2048;
+
+/// The zone a record is checked against.
+#[derive(Debug, Clone, Copy)]
+pub struct ZoneContext<'a> {
+    /// The zone's domain name, e.g. `example.com`.
+    pub zone_name: &'a str,
+    /// The records already in the zone.
+    pub records: &'a [DNSRecord],
+}
+
+impl ZoneContext<'_> {
+    /// The fully qualified, lowercase form of `name` if it is in the zone. `@` stands for the zone apex.
+    #[must_use]
+    pub fn qualify(&self, name: &str) -> Option<String> {
+        let zone = self.zone_name.trim_end_matches('.').to_ascii_lowercase();
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        if name == "@" || name == zone {
+            return Some(zone);
+        }
+        name.strip_suffix(&zone)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+            .then_some(name)
+    }
+
+    /// The fully qualified, lowercase form of a name written relative to the zone, unless it ends with a dot
+    /// or already ends with the zone's name. `@` stands for the zone apex.
+    #[must_use]
+    pub fn qualify_relative(&self, name: &str) -> String {
+        let name = name.to_ascii_lowercase();
+        if let Some(absolute) = name.strip_suffix('.') {
+            return absolute.to_string();
+        }
+        self.qualify(&name).unwrap_or_else(|| {
+            format!(
+                "{name}.{}",
+                self.zone_name.trim_end_matches('.').to_ascii_lowercase()
+            )
+        })
+    }
+
+    /// The records at `name` (fully qualified and lowercase), other than the one with `except_id`.
+    fn records_at<'b>(
+        &'b self,
+        name: &'b str,
+        except_id: Option<&'b str>,
+    ) -> impl Iterator<Item = &'b DNSRecord> {
+        self.records.iter().filter(move |r| {
+            Some(r.id.as_str()) != except_id && self.qualify(&r.name).as_deref() == Some(name)
+        })
+    }
+
+    /// Whether there is a CNAME record at `target`, if `target` is in the zone.
+    fn is_alias(&self, target: &str) -> bool {
+        self.qualify(target).is_some_and(|target| {
+            self.records_at(&target, None)
+                .any(|r| matches!(r.content, DNSContent::Cname { .. }))
+        })
+    }
+}
+
+/// Check a record about to be created, or about to replace `existing`.
+///
+/// Returns every problem found; an empty list means the record can be sent.
+#[must_use]
+pub fn validate_record(
+    request: &DNSRecordRequest,
+    zone: &ZoneContext<'_>,
+    existing: Option<&DNSRecord>,
+) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if let Some(existing) = existing {
+        errors.extend(check_locked(existing));
+    }
+    errors.extend(check_name(request, zone));
+    errors.extend(check_ttl(request.ttl));
+    errors.extend(check_proxied(request, existing));
+    errors.extend(check_content(request, zone));
+    if let Some(name) = zone.qualify(&request.name) {
+        errors.extend(check_cname_exclusivity(
+            &request.content,
+            &name,
+            zone,
+            existing.map(|r| r.id.as_str()),
+        ));
+    }
+    errors
+}
+
+/// Check a record can be deleted.
+#[must_use]
+pub fn validate_deletion(existing: &DNSRecord) -> Vec<FieldError> {
+    check_locked(existing).into_iter().collect()
+}
+
+/// Turn the problems found into an [`AppError`], if there are any.
+///
+/// # Errors
+///
+/// Returns an [`ErrorCategory::Validation`](crate::error::ErrorCategory::Validation) error listing
+/// `errors` if it isn't empty.
+pub fn ensure_valid(errors: Vec<FieldError>) -> Result<(), AppError> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::validation(errors))
+    }
+}
+
+/// Locked records are managed by Cloudflare and can't be changed.
+fn check_locked(existing: &DNSRecord) -> Option<FieldError> {
+    existing.locked.then(|| {
+        FieldError::input(format!(
+            "{} is managed by Cloudflare and can't be changed",
+            existing.name
+        ))
+    })
+}
+
+/// What is wrong with a single label of a name, if anything.
+fn label_problem(label: &str) -> Option<String> {
+    if label.is_empty() {
+        return Some("contains an empty label".to_string());
+    }
+    if label.len() > MAX_LABEL_LENGTH {
+        return Some(format!(
+            "\"{label}\" is longer than {MAX_LABEL_LENGTH} characters"
+        ));
+    }
+    if !label.is_ascii() {
+        return Some(format!("\"{label}\" must be written in Punycode (xn--...)"));
+    }
+    if let Some(c) = label
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '-' && *c != '_')
+    {
+        return Some(format!("\"{label}\" contains \"{c}\""));
+    }
+    if label.starts_with('-') || label.ends_with('-') {
+        return Some(format!("\"{label}\" starts or ends with a hyphen"));
+    }
+    None
+}
+
+/// What is wrong with the syntax of a name, if anything. A leading `*` label is allowed if `wildcard` is set.
+fn name_problem(name: &str, wildcard: bool) -> Option<String> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        return Some("can't be empty".to_string());
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Some(format!("is longer than {MAX_NAME_LENGTH} characters"));
+    }
+    name.split('.')
+        .enumerate()
+        .filter(|(i, label)| !(wildcard && *i == 0 && *label == "*"))
+        .find_map(|(_, label)| label_problem(label))
+}
+
+/// The record's name must be a valid name within the zone.
+fn check_name(request: &DNSRecordRequest, zone: &ZoneContext<'_>) -> Option<FieldError> {
+    let name = request.name.trim();
+    if name == "@" {
+        return None;
+    }
+    if let Some(problem) = name_problem(name, true) {
+        return Some(FieldError::new("name", format!("The name {problem}")));
+    }
+    let Some(qualified) = zone.qualify(name) else {
+        return Some(FieldError::new(
+            "name",
+            format!("{name} is not in the zone {}", zone.zone_name),
+        ));
+    };
+    if let DNSContent::Srv { .. } = request.content {
+        let mut labels = qualified.split('.');
+        let service = labels.next().unwrap_or_default();
+        let protocol = labels.next().unwrap_or_default();
+        if !service.starts_with('_') || !protocol.starts_with('_') {
+            return Some(FieldError::new(
+                "name",
+                "SRV record names start with the service and protocol, e.g. _sip._tcp.example.com",
+            ));
+        }
+    }
+    None
+}
+
+/// The TTL must be automatic, or between [`MIN_TTL`] and [`MAX_TTL`] seconds.
+fn check_ttl(ttl: u32) -> Option<FieldError> {
+    (ttl != AUTO_TTL && !(MIN_TTL..=MAX_TTL).contains(&ttl)).then(|| {
+        FieldError::new(
+            "ttl",
+            format!(
+                "The TTL must be {AUTO_TTL} (automatic) or between {MIN_TTL} and {MAX_TTL} seconds"
+            ),
+        )
+    })
+}
+
+/// Only A, AAAA and CNAME records can be proxied, and only if Cloudflare says the record is proxiable.
+fn check_proxied(request: &DNSRecordRequest, existing: Option<&DNSRecord>) -> Option<FieldError> {
+    if request.proxied != Some(true) {
+        return None;
+    }
+    if !request.content.is_proxiable() {
+        return Some(FieldError::new(
+            "proxied",
+            format!("{} records can't be proxied", request.content.record_type()),
+        ));
+    }
+    existing
+        .filter(|r| r.content.record_type() == request.content.record_type() && !r.proxiable)
+        .map(|_| FieldError::new("proxied", "Cloudflare can't proxy this record"))
+}
+
+/// A target name (of a CNAME, MX, NS or SRV record) must be a host name, not an IP address.
+fn check_target(field: &str, target: &str) -> Option<FieldError> {
+    if target.parse::<IpAddr>().is_ok() {
+        return Some(FieldError::new(
+            field,
+            "The target must be a host name, not an IP address",
+        ));
+    }
+    name_problem(target, false)
+        .map(|problem| FieldError::new(field, format!("The target {problem}")))
+}
+
+/// A target that other records point at must not be an alias (RFC 2181, section 10.3).
+fn check_not_alias(field: &str, target: &str, zone: &ZoneContext<'_>) -> Option<FieldError> {
+    zone.is_alias(target).then(|| {
+        FieldError::new(
+            field,
+            format!("{target} is a CNAME; point at the name it is an alias for instead"),
+        )
+    })
+}
+
+/// Type-specific checks of the content.
+fn check_content(request: &DNSRecordRequest, zone: &ZoneContext<'_>) -> Vec<FieldError> {
+    match &request.content {
+        DNSContent::A { content } => {
+            let problem = if content.is_unspecified() {
+                Some("0.0.0.0 is not a usable address")
+            } else if content.is_broadcast() {
+                Some("The broadcast address is not a usable address")
+            } else if content.is_multicast() {
+                Some("A multicast address is not a usable address")
+            } else {
+                None
+            };
+            problem
+                .map(|problem| FieldError::new("content", problem))
+                .into_iter()
+                .collect()
+        }
+        DNSContent::Aaaa { content } => {
+            let problem = if content.is_unspecified() {
+                Some(":: is not a usable address")
+            } else if content.is_multicast() {
+                Some("A multicast address is not a usable address")
+            } else if content.to_ipv4_mapped().is_some() {
+                Some("IPv4-mapped addresses belong in an A record")
+            } else {
+                None
+            };
+            problem
+                .map(|problem| FieldError::new("content", problem))
+                .into_iter()
+                .collect()
+        }
+        DNSContent::Cname { content } => {
+            let mut errors: Vec<_> = check_target("content", content).into_iter().collect();
+            if zone.qualify(content).is_some()
+                && zone.qualify(content) == zone.qualify(&request.name)
+            {
+                errors.push(FieldError::new(
+                    "content",
+                    "A CNAME record can't point at itself",
+                ));
+            }
+            errors
+        }
+        DNSContent::Ns { content } => check_target("content", content).into_iter().collect(),
+        DNSContent::Mx { content, priority } => {
+            // A null MX (RFC 7505) says the domain accepts no mail.
+            if content.trim_end_matches('.').is_empty() {
+                return (*priority != 0)
+                    .then(|| FieldError::new("priority", "A null MX record must have priority 0"))
+                    .into_iter()
+                    .collect();
+            }
+            check_target("content", content)
+                .or_else(|| check_not_alias("content", content, zone))
+                .into_iter()
+                .collect()
+        }
+        DNSContent::Srv { data } => {
+            // A target of "." says the service isn't available.
+            if data.target.trim_end_matches('.').is_empty() {
+                return Vec::new();
+            }
+            let mut errors: Vec<_> = check_target("data.target", &data.target)
+                .or_else(|| check_not_alias("data.target", &data.target, zone))
+                .into_iter()
+                .collect();
+            if data.port == 0 {
+                errors.push(FieldError::new("data.port", "The port can't be 0"));
+            }
+            errors
+        }
+        DNSContent::Txt { content } => check_txt(content).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// TXT content is made of character strings of at most 255 bytes each. Content written as quoted strings
+/// (`"v=DKIM1; " "p=..."`) is checked string by string; unquoted content is a single string.
+fn check_txt(content: &str) -> Option<FieldError> {
+    if content.chars().count() > MAX_TXT_LENGTH {
+        return Some(FieldError::new(
+            "content",
+            format!("TXT content can't be longer than {MAX_TXT_LENGTH} characters"),
+        ));
+    }
+    let chunks = txt_chunks(content);
+    chunks
+        .iter()
+        .position(|chunk| chunk.len() > MAX_TXT_CHUNK_LENGTH)
+        .map(|i| {
+            let message = if chunks.len() == 1 {
+                format!(
+                    "TXT content longer than {MAX_TXT_CHUNK_LENGTH} bytes must be split into quoted strings"
+                )
+            } else {
+                format!(
+                    "Quoted string {} is longer than {MAX_TXT_CHUNK_LENGTH} bytes",
+                    i + 1
+                )
+            };
+            FieldError::new("content", message)
+        })
+}
+
+/// Split TXT content into its character strings, without quotes or escapes.
+#[must_use]
+pub fn txt_chunks(content: &str) -> Vec<String> {
+    let trimmed = content.trim();
+    if !trimmed.starts_with('"') {
+        return vec![trimmed.to_string()];
+    }
+    let mut chunks = Vec::new();
+    let mut chars = trimmed.chars();
+    while let Some(c) = chars.next() {
+        if c != '"' {
+            continue;
+        }
+        let mut chunk = String::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => chunk.extend(chars.next()),
+                c => chunk.push(c),
+            }
+        }
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// A CNAME record can't share its name with any other record.
+fn check_cname_exclusivity(
+    content: &DNSContent,
+    name: &str,
+    zone: &ZoneContext<'_>,
+    except_id: Option<&str>,
+) -> Option<FieldError> {
+    let others: Vec<&DNSRecord> = zone.records_at(name, except_id).collect();
+    if matches!(content, DNSContent::Cname { .. }) {
+        if others.is_empty() {
+            return None;
+        }
+        let mut types: Vec<&str> = others.iter().map(|r| r.content.record_type()).collect();
+        types.sort_unstable();
+        types.dedup();
+        return Some(FieldError::new(
+            "name",
+            format!(
+                "{name} already has {} records, which can't share a name with a CNAME record",
+                types.join(", ")
+            ),
+        ));
+    }
+    others
+        .iter()
+        .any(|r| matches!(r.content, DNSContent::Cname { .. }))
+        .then(|| {
+            FieldError::new(
+                "name",
+                format!("{name} has a CNAME record, which can't share a name with other records"),
+            )
+        })
+}
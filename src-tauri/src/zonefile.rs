@@ -0,0 +1,940 @@
+//! Reading and writing zones as RFC 1035 zone files, for backups and for moving zones between providers.
+//!
+//! Owner names are written relative to `$ORIGIN`, and targets as absolute names. Cloudflare's own metadata
+//! goes in a trailing comment the way Cloudflare's export does it (`; comment cf_tags=cf-proxied:true,tag`),
+//! so an exported file can also be imported through Cloudflare's dashboard or API. The SOA record and the
+//! name servers of the zone itself are managed by Cloudflare, so they aren't written, and are skipped when a
+//! file is read.
+
+use std::{collections::HashMap, fmt::Write, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    cloudflare::{DNSRecord, DNSRecordRequest},
+    error::{AppError, FieldError},
+    plan::ChangePlan,
+    records::{
+        CAAData, CERTData, DNSContent, DNSKEYData, DSData, LOCData, LatitudeDirection,
+        LongitudeDirection, NAPTRData, SRVData, SSHFPData, SVCBData, TLSAData, URIData,
+    },
+    validation::{txt_chunks, ZoneContext, AUTO_TTL},
+};
+
+/// The `$TTL` written for a zone with no records.
+const DEFAULT_TTL: u32 = 3600;
+
+/// The longest character string a TXT record can hold, in bytes.
+const MAX_CHARACTER_STRING: usize = 255;
+
+/// Render a zone's records as a zone file.
+///
+/// The `$TTL` is the most common TTL in the zone, so most records don't need their own. NS records at the
+/// apex are left out.
+#[must_use]
+pub fn render(zone_name: &str, records: &[DNSRecord], exported_at: DateTime<Utc>) -> String {
+    let origin = absolute(zone_name);
+    let mut lines: Vec<(String, &DNSRecord)> = records
+        .iter()
+        .map(|record| (relative(&record.name, &origin), record))
+        .filter(|(owner, record)| {
+            !(owner == "@" && matches!(record.content, DNSContent::Ns { .. }))
+        })
+        .collect();
+    let default_ttl = most_common_ttl(lines.iter().map(|(_, record)| *record));
+
+    // The apex first, then by name, so the file diffs well between exports.
+    lines.sort_by(|(a_name, a), (b_name, b)| {
+        (a_name != "@", a_name, a.content.record_type()).cmp(&(
+            b_name != "@",
+            b_name,
+            b.content.record_type(),
+        ))
+    });
+
+    let mut out = format!(
+        ";; Zone: {zone_name}\n;; Exported: {}\n;; The SOA and apex NS records are managed by Cloudflare.\n\n$ORIGIN {origin}\n$TTL {default_ttl}\n\n",
+        exported_at.to_rfc3339()
+    );
+    for (owner, record) in lines {
+        out.push_str(&owner);
+        out.push('\t');
+        if record.ttl != default_ttl {
+            out.push_str(&record.ttl.to_string());
+        }
+        out.push_str("\tIN\t");
+        out.push_str(record.content.record_type());
+        out.push('\t');
+        out.push_str(&rdata(&record.content));
+        if let Some(comment) = metadata_comment(record) {
+            out.push_str(" ; ");
+            out.push_str(&comment);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The most common TTL among the records, preferring the shorter one on a tie. Automatic TTLs count too,
+/// as they are written as `1` like Cloudflare does.
+fn most_common_ttl<'a>(records: impl Iterator<Item = &'a DNSRecord>) -> u32 {
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    for record in records {
+        *counts.entry(record.ttl).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|(a_ttl, a_count), (b_ttl, b_count)| a_count.cmp(b_count).then(b_ttl.cmp(a_ttl)))
+        .map_or(DEFAULT_TTL, |(ttl, _)| ttl)
+}
+
+/// A name with a trailing dot.
+fn absolute(name: &str) -> String {
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{name}.")
+    }
+}
+
+/// An owner name relative to `origin` (which ends with a dot), `@` for the origin itself, or the absolute
+/// name if it is outside the origin.
+fn relative(name: &str, origin: &str) -> String {
+    let name = absolute(name);
+    if name.eq_ignore_ascii_case(origin) {
+        return "@".to_string();
+    }
+    if name.len() > origin.len() {
+        let (prefix, suffix) = name.split_at(name.len() - origin.len());
+        if suffix.eq_ignore_ascii_case(origin) {
+            if let Some(prefix) = prefix.strip_suffix('.') {
+                return prefix.to_string();
+            }
+        }
+    }
+    name
+}
+
+/// A character string in quotes, with quotes and backslashes escaped, and unprintable bytes written as `\DDD`.
+fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for byte in text.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(char::from(byte));
+            }
+            0x20..=0x7e => out.push(char::from(byte)),
+            _ => {
+                let _ = write!(out, "\\{byte:03}");
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The character strings of TXT content, each quoted. Content that isn't already split into quoted strings
+/// is split every 255 bytes (on character boundaries).
+#[must_use]
+pub fn quote_txt(content: &str) -> String {
+    let chunks = if content.trim_start().starts_with('"') {
+        txt_chunks(content)
+    } else {
+        let mut chunks = vec![String::new()];
+        for c in content.chars() {
+            if chunks
+                .last()
+                .is_some_and(|chunk| chunk.len() + c.len_utf8() > MAX_CHARACTER_STRING)
+            {
+                chunks.push(String::new());
+            }
+            if let Some(chunk) = chunks.last_mut() {
+                chunk.push(c);
+            }
+        }
+        chunks
+    };
+    chunks
+        .iter()
+        .map(|chunk| quote(chunk))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The location in the `d m s N d m s E alt size hp vp` form of RFC 1876.
+fn loc(data: &LOCData) -> String {
+    format!(
+        "{} {} {:.3} {:?} {} {} {:.3} {:?} {:.2}m {:.2}m {:.2}m {:.2}m",
+        data.lat_degrees,
+        data.lat_minutes,
+        data.lat_seconds,
+        data.lat_direction,
+        data.long_degrees,
+        data.long_minutes,
+        data.long_seconds,
+        data.long_direction,
+        data.altitude,
+        data.size,
+        data.precision_horz,
+        data.precision_vert,
+    )
+}
+
+/// The record data as written in a zone file, e.g. `10 mail.example.com.` for an MX record.
+#[must_use]
+pub fn rdata(content: &DNSContent) -> String {
+    match content {
+        DNSContent::A { content } => content.to_string(),
+        DNSContent::Aaaa { content } => content.to_string(),
+        DNSContent::Cname { content }
+        | DNSContent::Ns { content }
+        | DNSContent::Ptr { content } => absolute(content),
+        DNSContent::Mx { content, priority } => format!("{priority} {}", absolute(content)),
+        DNSContent::Txt { content } => quote_txt(content),
+        DNSContent::Openpgpkey { content } => content.clone(),
+        DNSContent::Caa { data } => format!("{} {} {}", data.flags, data.tag, quote(&data.value)),
+        DNSContent::Cert { data } => format!(
+            "{} {} {} {}",
+            data.r#type, data.key_tag, data.algorithm, data.certificate
+        ),
+        DNSContent::Dnskey { data } => format!(
+            "{} {} {} {}",
+            data.flags, data.protocol, data.algorithm, data.public_key
+        ),
+        DNSContent::Ds { data } => format!(
+            "{} {} {} {}",
+            data.key_tag, data.algorithm, data.digest_type, data.digest
+        ),
+        DNSContent::Https { data } | DNSContent::Svcb { data } => format!(
+            "{} {} {}",
+            data.priority,
+            absolute(&data.target),
+            data.value
+        )
+        .trim_end()
+        .to_string(),
+        DNSContent::Loc { data } => loc(data),
+        DNSContent::Naptr { data } => format!(
+            "{} {} {} {} {} {}",
+            data.order,
+            data.preference,
+            quote(&data.flags),
+            quote(&data.service),
+            quote(&data.regex),
+            absolute(&data.replacement)
+        ),
+        DNSContent::Smimea { data } | DNSContent::Tlsa { data } => format!(
+            "{} {} {} {}",
+            data.usage, data.selector, data.matching_type, data.certificate
+        ),
+        DNSContent::Srv { data } => format!(
+            "{} {} {} {}",
+            data.priority,
+            data.weight,
+            data.port,
+            absolute(&data.target)
+        ),
+        DNSContent::Sshfp { data } => {
+            format!("{} {} {}", data.algorithm, data.r#type, data.fingerprint)
+        }
+        DNSContent::Uri { data, priority } => {
+            format!("{priority} {} {}", data.weight, quote(&data.target))
+        }
+    }
+}
+
+/// The trailing comment carrying Cloudflare's metadata: the record's comment, then `cf_tags=` with whether it
+/// is proxied and its tags. `None` if there is nothing to write.
+fn metadata_comment(record: &DNSRecord) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(comment) = record.comment.as_deref().filter(|c| !c.trim().is_empty()) {
+        parts.push(comment.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    let mut tags = Vec::new();
+    if record.proxiable {
+        tags.push(format!("cf-proxied:{}", record.proxied.unwrap_or(false)));
+    }
+    tags.extend(record.tags.iter().cloned());
+    if !tags.is_empty() {
+        parts.push(format!("cf_tags={}", tags.join(",")));
+    }
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// A zone file read into records, ready to be compared with the zone.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedZone {
+    /// The records in the file.
+    pub records: Vec<DNSRecordRequest>,
+    /// The records in the file that are left out, such as the SOA record, each with its line number.
+    pub skipped: Vec<String>,
+}
+
+/// What importing a zone file would change, shown before anything is applied.
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[ts(export)]
+pub struct ImportPreview {
+    /// The changes that make the zone match the file.
+    pub plan: ChangePlan,
+    /// The records in the file that are left out, such as the SOA record, each with its line number.
+    pub skipped: Vec<String>,
+}
+
+/// A directive or a record of a zone file, which may span lines in parentheses.
+struct Entry {
+    /// The line it starts on.
+    line: usize,
+    /// Whether it starts with whitespace, leaving out the owner name.
+    blank_owner: bool,
+    /// Its words, with quotes and escapes decoded.
+    tokens: Vec<String>,
+    /// The text of its comments.
+    comment: String,
+}
+
+/// Read a zone file into records for the zone `zone_name`.
+///
+/// Names are relative to `$ORIGIN` (the zone itself by default), and records without a TTL get the `$TTL`,
+/// or an automatic TTL if there is none. Cloudflare's metadata is read back from `cf_tags=` comments;
+/// proxiable records without a `cf-proxied` tag are proxied if `proxied_default` is set. The SOA record and
+/// the name servers of the zone itself are skipped.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] with a problem for each line that can't be read: bad syntax, a record
+/// outside the zone, a record type Cloudflare doesn't support, or a directive other than `$ORIGIN` or `$TTL`.
+pub fn parse(text: &str, zone_name: &str, proxied_default: bool) -> Result<ParsedZone, AppError> {
+    let entries = entries(text).map_err(|e| AppError::validation(vec![e]))?;
+    let zone = ZoneContext {
+        zone_name,
+        records: &[],
+    };
+    let zone_name = zone_name.trim_end_matches('.').to_ascii_lowercase();
+
+    let mut origin = zone_name.clone();
+    let mut default_ttl = AUTO_TTL;
+    let mut previous_owner: Option<String> = None;
+    let mut parsed = ParsedZone::default();
+    let mut errors = Vec::new();
+    for entry in entries {
+        let line = entry.line;
+        let result = if !entry.blank_owner && entry.tokens[0].starts_with('$') {
+            directive(&entry.tokens, &mut origin, &mut default_ttl)
+        } else {
+            record(&entry, &origin, previous_owner.as_deref()).and_then(|(owner, ttl, content)| {
+                previous_owner = Some(owner.clone());
+                let Some(name) = zone.qualify(&owner) else {
+                    return Err(format!("{owner} is outside the zone {zone_name}"));
+                };
+                let record_type = content.as_ref().map_or("SOA", DNSContent::record_type);
+                match content {
+                    Some(content) if !(name == zone_name && record_type == "NS") => {
+                        parsed.records.push(request(
+                            name,
+                            ttl.unwrap_or(default_ttl),
+                            content,
+                            &entry.comment,
+                            proxied_default,
+                        ));
+                    }
+                    _ => parsed.skipped.push(format!(
+                        "line {line}: {record_type} record for {name} (managed by Cloudflare)"
+                    )),
+                }
+                Ok(())
+            })
+        };
+        if let Err(message) = result {
+            errors.push(FieldError::new(&format!("line {line}"), message));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(AppError::validation(errors))
+    }
+}
+
+/// Read the record data of one record of the zone `zone_name`, e.g. `10 mail.example.com` for an MX record.
+///
+/// The data is written the way a zone file writes it, but names are taken the way Cloudflare's API takes
+/// them: absolute, with or without a trailing dot, and `@` for the zone itself.
+///
+/// # Errors
+///
+/// This will return an [`AppError`] if the data can't be read, or the record type isn't one Cloudflare
+/// supports.
+pub fn parse_rdata(record_type: &str, text: &str, zone_name: &str) -> Result<DNSContent, AppError> {
+    let problem = |message: String| AppError::validation(vec![FieldError::new("content", message)]);
+    let entries = entries(text).map_err(|e| problem(e.message))?;
+    let origin = zone_name.trim_end_matches('.').to_ascii_lowercase();
+    let record_type = record_type.to_ascii_uppercase();
+    let mut rdata = Rdata {
+        tokens: entries.iter().flat_map(|entry| &entry.tokens).collect(),
+        next: 0,
+        origin: &origin,
+        absolute_names: true,
+    };
+    let content = content(&record_type, &mut rdata)
+        .map_err(problem)?
+        .ok_or_else(|| problem("SOA records are managed by Cloudflare".to_string()))?;
+    rdata.finish().map_err(problem)?;
+    Ok(content)
+}
+
+/// Split a zone file into entries, joining lines in parentheses and decoding quotes and escapes.
+///
+/// # Errors
+///
+/// This will return a [`FieldError`] for the first line with unbalanced parentheses or quotes.
+fn entries(text: &str) -> Result<Vec<Entry>, FieldError> {
+    let line_error = |line: usize, message: &str| FieldError::new(&format!("line {line}"), message);
+
+    let mut entries = Vec::new();
+    let mut open: Option<Entry> = None;
+    let mut depth = 0_usize;
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let mut entry = open.take().unwrap_or_else(|| Entry {
+            line: number,
+            blank_owner: line.starts_with([' ', '\t']),
+            tokens: Vec::new(),
+            comment: String::new(),
+        });
+
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b' ' | b'\t' => i += 1,
+                b';' => {
+                    if !entry.comment.is_empty() {
+                        entry.comment.push(' ');
+                    }
+                    entry.comment.push_str(line[i + 1..].trim());
+                    break;
+                }
+                b'(' => {
+                    depth += 1;
+                    i += 1;
+                }
+                b')' => {
+                    depth = depth
+                        .checked_sub(1)
+                        .ok_or_else(|| line_error(number, "has a `)` without a matching `(`"))?;
+                    i += 1;
+                }
+                byte => {
+                    let quoted = byte == b'"';
+                    let (text, end) = word(bytes, if quoted { i + 1 } else { i }, quoted)
+                        .map_err(|message| line_error(number, message))?;
+                    entry.tokens.push(text);
+                    i = end;
+                }
+            }
+        }
+
+        if depth > 0 {
+            open = Some(entry);
+        } else if !entry.tokens.is_empty() {
+            entries.push(entry);
+        }
+    }
+    match open {
+        Some(entry) => Err(line_error(entry.line, "has a `(` without a matching `)`")),
+        None => Ok(entries),
+    }
+}
+
+/// Read one word from `start`, decoding `\X` and `\DDD` escapes. A quoted word runs to the closing quote;
+/// any other word runs to whitespace, a quote, a parenthesis or a comment.
+///
+/// Returns the word and the index just after it.
+///
+/// # Errors
+///
+/// This will return a message if a quote isn't closed on the same line, or an escape is malformed.
+fn word(bytes: &[u8], start: usize, quoted: bool) -> Result<(String, usize), &'static str> {
+    let mut out = Vec::new();
+    let mut i = start;
+    while let Some(&byte) = bytes.get(i) {
+        match byte {
+            b'"' if quoted => return Ok((String::from_utf8_lossy(&out).into_owned(), i + 1)),
+            b' ' | b'\t' | b';' | b'(' | b')' | b'"' if !quoted => break,
+            b'\\' => {
+                match bytes.get(i + 1..i + 4) {
+                    Some(digits) if digits.iter().all(u8::is_ascii_digit) => {
+                        let value = digits
+                            .iter()
+                            .fold(0_u16, |n, d| n * 10 + u16::from(d - b'0'));
+                        out.push(u8::try_from(value).map_err(|_| "has an escape above \\255")?);
+                        i += 4;
+                    }
+                    _ => {
+                        out.push(*bytes.get(i + 1).ok_or("ends with a `\\`")?);
+                        i += 2;
+                    }
+                }
+                continue;
+            }
+            _ => out.push(byte),
+        }
+        i += 1;
+    }
+    if quoted {
+        Err("has a quoted string without a closing `\"`")
+    } else {
+        Ok((String::from_utf8_lossy(&out).into_owned(), i))
+    }
+}
+
+/// Apply a `$ORIGIN` or `$TTL` directive.
+///
+/// # Errors
+///
+/// This will return a message for other directives, which aren't supported, or a directive without a value.
+fn directive(tokens: &[String], origin: &mut String, default_ttl: &mut u32) -> Result<(), String> {
+    let name = tokens[0].to_ascii_uppercase();
+    let [_, value] = tokens else {
+        return Err(format!("{name} takes exactly one value"));
+    };
+    match name.as_str() {
+        "$ORIGIN" => *origin = qualify(value, origin),
+        "$TTL" => *default_ttl = ttl(value).ok_or("$TTL must be a time to live")?,
+        _ => return Err(format!("the {name} directive isn't supported")),
+    }
+    Ok(())
+}
+
+/// The fully qualified form of `name` (without the trailing dot), relative to `origin` unless it ends with a dot.
+/// The root is kept as `.`.
+fn qualify(name: &str, origin: &str) -> String {
+    if name == "@" {
+        origin.to_string()
+    } else if name == "." {
+        name.to_string()
+    } else if let Some(name) = name.strip_suffix('.') {
+        name.to_string()
+    } else {
+        format!("{name}.{origin}")
+    }
+}
+
+/// A time to live in seconds, written as a number or with units, e.g. `3600` or `1h30m`.
+fn ttl(text: &str) -> Option<u32> {
+    if let Ok(seconds) = text.parse() {
+        return Some(seconds);
+    }
+    let mut total: u32 = 0;
+    let mut number: Option<u32> = None;
+    for c in text.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            number = Some(number.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604_800,
+            _ => return None,
+        };
+        total = total.checked_add(number.take()?.checked_mul(unit)?)?;
+    }
+    number.is_none().then_some(total)
+}
+
+/// Read the owner, TTL and content of a record. The content is `None` for an SOA record.
+///
+/// # Errors
+///
+/// This will return a message if the record can't be read.
+fn record(
+    entry: &Entry,
+    origin: &str,
+    previous_owner: Option<&str>,
+) -> Result<(String, Option<u32>, Option<DNSContent>), String> {
+    let mut tokens = entry.tokens.iter().peekable();
+    let owner = if entry.blank_owner {
+        previous_owner
+            .ok_or("the first record must have an owner name")?
+            .to_string()
+    } else {
+        tokens
+            .next()
+            .map_or_else(String::new, |t| qualify(t, origin))
+    };
+
+    // The TTL and class may come in either order, and both may be left out.
+    let mut record_ttl = None;
+    for _ in 0..2 {
+        let Some(token) = tokens.peek() else { break };
+        if token.eq_ignore_ascii_case("IN") {
+            tokens.next();
+        } else if ["CH", "HS", "CS", "ANY"]
+            .iter()
+            .any(|c| token.eq_ignore_ascii_case(c))
+        {
+            return Err(format!("only class IN is supported, not {token}"));
+        } else if token.starts_with(|c: char| c.is_ascii_digit()) {
+            record_ttl = Some(ttl(token).ok_or_else(|| format!("{token} isn't a TTL"))?);
+            tokens.next();
+        }
+    }
+
+    let record_type = tokens
+        .next()
+        .ok_or("the record has no type")?
+        .to_ascii_uppercase();
+    let mut rdata = Rdata {
+        tokens: tokens.collect(),
+        next: 0,
+        origin,
+        absolute_names: false,
+    };
+    let content = content(&record_type, &mut rdata)?;
+    rdata.finish()?;
+    Ok((owner, record_ttl, content))
+}
+
+/// The record data of a record being read.
+struct Rdata<'a> {
+    /// The words after the record type.
+    tokens: Vec<&'a String>,
+    /// The index of the next word to read.
+    next: usize,
+    /// The origin relative names are qualified with.
+    origin: &'a str,
+    /// Whether names are absolute even without a trailing dot, the way Cloudflare's API takes them.
+    absolute_names: bool,
+}
+
+impl Rdata<'_> {
+    /// The next word.
+    fn word(&mut self, what: &str) -> Result<&str, String> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .ok_or_else(|| format!("the {what} is missing"))?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    /// The next word, as a number.
+    fn number<T: FromStr>(&mut self, what: &str) -> Result<T, String> {
+        let word = self.word(what)?;
+        word.parse()
+            .map_err(|_| format!("the {what} must be a number, not {word}"))
+    }
+
+    /// The next word, as a domain name.
+    fn name(&mut self, what: &str) -> Result<String, String> {
+        let origin = self.origin;
+        let absolute_names = self.absolute_names;
+        self.word(what).map(|name| {
+            if absolute_names && name != "@" && !name.ends_with('.') {
+                qualify(&format!("{name}."), origin)
+            } else {
+                qualify(name, origin)
+            }
+        })
+    }
+
+    /// The next word if it isn't one of `stop`.
+    fn until(&self, stop: &[&str]) -> Option<&str> {
+        self.tokens
+            .get(self.next)
+            .map(|t| t.as_str())
+            .filter(|text| !stop.iter().any(|s| text.eq_ignore_ascii_case(s)))
+    }
+
+    /// The rest of the words, joined with `separator`.
+    fn rest(&mut self, separator: &str, what: &str) -> Result<String, String> {
+        if self.next >= self.tokens.len() {
+            return Err(format!("the {what} is missing"));
+        }
+        let rest = self.tokens[self.next..]
+            .iter()
+            .map(|t| t.as_str())
+            .collect::<Vec<_>>()
+            .join(separator);
+        self.next = self.tokens.len();
+        Ok(rest)
+    }
+
+    /// Check every word was read.
+    fn finish(&self) -> Result<(), String> {
+        self.tokens.get(self.next).map_or(Ok(()), |extra| {
+            Err(format!("unexpected {extra} after the record data"))
+        })
+    }
+}
+
+/// Read the record data of a record type. Returns `None` for an SOA record, which is left out.
+///
+/// # Errors
+///
+/// This will return a message if the data can't be read or the type isn't supported by Cloudflare.
+fn content(record_type: &str, rdata: &mut Rdata<'_>) -> Result<Option<DNSContent>, String> {
+    let content = match record_type {
+        "SOA" => {
+            rdata.next = rdata.tokens.len();
+            return Ok(None);
+        }
+        "A" => {
+            let address = rdata.word("address")?;
+            DNSContent::A {
+                content: address
+                    .parse()
+                    .map_err(|_| format!("{address} isn't an IPv4 address"))?,
+            }
+        }
+        "AAAA" => {
+            let address = rdata.word("address")?;
+            DNSContent::Aaaa {
+                content: address
+                    .parse()
+                    .map_err(|_| format!("{address} isn't an IPv6 address"))?,
+            }
+        }
+        "CNAME" => DNSContent::Cname {
+            content: rdata.name("target")?,
+        },
+        "NS" => DNSContent::Ns {
+            content: rdata.name("name server")?,
+        },
+        "PTR" => DNSContent::Ptr {
+            content: rdata.name("target")?,
+        },
+        "MX" => DNSContent::Mx {
+            priority: rdata.number("priority")?,
+            content: rdata.name("mail server")?,
+        },
+        "TXT" => {
+            let content = match rdata.tokens.as_slice() {
+                [] => return Err("the text is missing".to_string()),
+                [text] => (*text).clone(),
+                // Several character strings are kept quoted, the way Cloudflare stores them.
+                strings => strings
+                    .iter()
+                    .map(|text| quote(text))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            };
+            rdata.next = rdata.tokens.len();
+            DNSContent::Txt { content }
+        }
+        "OPENPGPKEY" => DNSContent::Openpgpkey {
+            content: rdata.rest("", "key")?,
+        },
+        _ => data_content(record_type, rdata)?,
+    };
+    Ok(Some(content))
+}
+
+/// Read the record data of the record types Cloudflare describes with a `data` object.
+///
+/// # Errors
+///
+/// This will return a message if the data can't be read or the type isn't supported by Cloudflare.
+fn data_content(record_type: &str, rdata: &mut Rdata<'_>) -> Result<DNSContent, String> {
+    Ok(match record_type {
+        "CAA" => DNSContent::Caa {
+            data: CAAData {
+                flags: rdata.number("flags")?,
+                tag: rdata.word("tag")?.to_string(),
+                value: rdata.rest(" ", "value")?,
+            },
+        },
+        "CERT" => DNSContent::Cert {
+            data: CERTData {
+                r#type: rdata.number("certificate type")?,
+                key_tag: rdata.number("key tag")?,
+                algorithm: rdata.number("algorithm")?,
+                certificate: rdata.rest("", "certificate")?,
+            },
+        },
+        "DNSKEY" => DNSContent::Dnskey {
+            data: DNSKEYData {
+                flags: rdata.number("flags")?,
+                protocol: rdata.number("protocol")?,
+                algorithm: rdata.number("algorithm")?,
+                public_key: rdata.rest("", "public key")?,
+            },
+        },
+        "DS" => DNSContent::Ds {
+            data: DSData {
+                key_tag: rdata.number("key tag")?,
+                algorithm: rdata.number("algorithm")?,
+                digest_type: rdata.number("digest type")?,
+                digest: rdata.rest("", "digest")?,
+            },
+        },
+        "HTTPS" | "SVCB" => {
+            let data = SVCBData {
+                priority: rdata.number("priority")?,
+                target: rdata.name("target")?,
+                value: rdata.rest(" ", "value").unwrap_or_default(),
+            };
+            if record_type == "HTTPS" {
+                DNSContent::Https { data }
+            } else {
+                DNSContent::Svcb { data }
+            }
+        }
+        "LOC" => DNSContent::Loc {
+            data: location(rdata)?,
+        },
+        "NAPTR" => DNSContent::Naptr {
+            data: NAPTRData {
+                order: rdata.number("order")?,
+                preference: rdata.number("preference")?,
+                flags: rdata.word("flags")?.to_string(),
+                service: rdata.word("service")?.to_string(),
+                regex: rdata.word("regular expression")?.to_string(),
+                replacement: rdata.name("replacement")?,
+            },
+        },
+        "SMIMEA" | "TLSA" => {
+            let data = TLSAData {
+                usage: rdata.number("usage")?,
+                selector: rdata.number("selector")?,
+                matching_type: rdata.number("matching type")?,
+                certificate: rdata.rest("", "certificate")?,
+            };
+            if record_type == "SMIMEA" {
+                DNSContent::Smimea { data }
+            } else {
+                DNSContent::Tlsa { data }
+            }
+        }
+        "SRV" => DNSContent::Srv {
+            data: SRVData {
+                priority: rdata.number("priority")?,
+                weight: rdata.number("weight")?,
+                port: rdata.number("port")?,
+                target: rdata.name("target")?,
+            },
+        },
+        "SSHFP" => DNSContent::Sshfp {
+            data: SSHFPData {
+                algorithm: rdata.number("algorithm")?,
+                r#type: rdata.number("fingerprint type")?,
+                fingerprint: rdata.rest("", "fingerprint")?,
+            },
+        },
+        "URI" => DNSContent::Uri {
+            priority: rdata.number("priority")?,
+            data: URIData {
+                weight: rdata.number("weight")?,
+                target: rdata.word("target")?.to_string(),
+            },
+        },
+        _ => return Err(format!("Cloudflare doesn't support {record_type} records")),
+    })
+}
+
+/// Read a location in the `d [m [s]] N d [m [s]] E alt[m] [size[m] [hp[m] [vp[m]]]]` form of RFC 1876.
+///
+/// # Errors
+///
+/// This will return a message if the location can't be read.
+fn location(rdata: &mut Rdata<'_>) -> Result<LOCData, String> {
+    /// A distance in metres, with or without the `m`.
+    fn metres(rdata: &mut Rdata<'_>, what: &str) -> Result<f64, String> {
+        let word = rdata.word(what)?;
+        word.trim_end_matches(['m', 'M'])
+            .parse()
+            .map_err(|_| format!("the {what} must be a distance, not {word}"))
+    }
+    /// The degrees, minutes and seconds of a coordinate, up to its direction.
+    fn coordinate(rdata: &mut Rdata<'_>, directions: &[&str]) -> Result<(u8, u8, f64), String> {
+        let degrees = rdata.number("degrees")?;
+        let mut minutes = 0;
+        let mut seconds = 0.0;
+        if rdata.until(directions).is_some() {
+            minutes = rdata.number("minutes")?;
+            if rdata.until(directions).is_some() {
+                seconds = rdata.number("seconds")?;
+            }
+        }
+        Ok((degrees, minutes, seconds))
+    }
+
+    let (lat_degrees, lat_minutes, lat_seconds) = coordinate(rdata, &["N", "S"])?;
+    let lat_direction = match rdata.word("latitude direction")? {
+        "N" | "n" => LatitudeDirection::N,
+        "S" | "s" => LatitudeDirection::S,
+        other => return Err(format!("the latitude must be N or S, not {other}")),
+    };
+    let (long_degrees, long_minutes, long_seconds) = coordinate(rdata, &["E", "W"])?;
+    let long_direction = match rdata.word("longitude direction")? {
+        "E" | "e" => LongitudeDirection::E,
+        "W" | "w" => LongitudeDirection::W,
+        other => return Err(format!("the longitude must be E or W, not {other}")),
+    };
+    let altitude = metres(rdata, "altitude")?;
+    let mut optional = |what: &str, default: f64| {
+        if rdata.until(&[]).is_some() {
+            metres(rdata, what)
+        } else {
+            Ok(default)
+        }
+    };
+    // The defaults are those of RFC 1876.
+    let size = optional("size", 1.0)?;
+    let precision_horz = optional("horizontal precision", 10000.0)?;
+    let precision_vert = optional("vertical precision", 10.0)?;
+    Ok(LOCData {
+        altitude,
+        lat_degrees,
+        lat_direction,
+        lat_minutes,
+        lat_seconds,
+        long_degrees,
+        long_direction,
+        long_minutes,
+        long_seconds,
+        precision_horz,
+        precision_vert,
+        size,
+    })
+}
+
+/// A request for a record read from a zone file, with the metadata from its `cf_tags=` comment.
+fn request(
+    name: String,
+    ttl: u32,
+    content: DNSContent,
+    comment: &str,
+    proxied_default: bool,
+) -> DNSRecordRequest {
+    let (comment, tags) = comment
+        .split_once("cf_tags=")
+        .map_or((comment, ""), |(comment, tags)| {
+            (comment, tags.split_whitespace().next().unwrap_or_default())
+        });
+    let mut proxied = None;
+    let mut record_tags = Vec::new();
+    for tag in tags.split(',').filter(|t| !t.is_empty()) {
+        match tag.strip_prefix("cf-proxied:") {
+            Some(value) => proxied = Some(value.eq_ignore_ascii_case("true")),
+            None => record_tags.push(tag.to_string()),
+        }
+    }
+    let proxiable = content.is_proxiable();
+    DNSRecordRequest {
+        content,
+        name,
+        proxied: proxiable.then(|| proxied.unwrap_or(proxied_default)),
+        comment: Some(comment.trim().to_string()).filter(|c| !c.is_empty()),
+        tags: record_tags,
+        ttl,
+    }
+}
@@ -0,0 +1,253 @@
+//! Tests of the dangling record audit, with a stub in place of DNS lookups.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use cloudflare_dns_gui::{
+    audit::{audit, audit_zone, DnsResolver, Resolution, Severity, TargetResolver, TargetStatus},
+    cloudflare::{CloudflareListZonesResponse, DNSRecord},
+    mock::MockDns,
+    models::ManagedCache,
+    propagation::{NamedResolver, PropagationSettings},
+};
+use futures::future::BoxFuture;
+use hickory_resolver::proto::rr::{
+    rdata::{A, MX},
+    Name, RData, Record,
+};
+
+mod common;
+
+use common::block_on;
+
+/// The zone ID of example.com.
+const EXAMPLE_COM: &str = "023e105f4ecef8ad9ca31a8372d0c353";
+
+/// Answers with fixed resolutions, and with an address for any other name, noting the names asked for.
+#[derive(Debug, Default)]
+struct StubResolver {
+    /// The resolutions of the names that don't simply resolve.
+    resolutions: HashMap<String, Resolution>,
+    /// The names looked up, in order.
+    asked: Mutex<Vec<String>>,
+}
+
+impl StubResolver {
+    /// A resolver answering `resolutions`.
+    fn new(resolutions: &[(&str, Resolution)]) -> Self {
+        Self {
+            resolutions: resolutions
+                .iter()
+                .map(|(name, resolution)| ((*name).to_string(), resolution.clone()))
+                .collect(),
+            ..Self::default()
+        }
+    }
+}
+
+impl TargetResolver for StubResolver {
+    fn resolve(&self, name: &str) -> BoxFuture<'_, Resolution> {
+        self.asked
+            .lock()
+            .expect("stub is not poisoned")
+            .push(name.to_string());
+        let resolution = self
+            .resolutions
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| resolves(&[]));
+        Box::pin(async move { resolution })
+    }
+}
+
+/// A resolution to a documentation address, through `aliases`.
+fn resolves(aliases: &[&str]) -> Resolution {
+    Resolution::Resolves {
+        addresses: vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))],
+        aliases: aliases.iter().map(ToString::to_string).collect(),
+    }
+}
+
+/// The fixture zones, and the records of example.com.
+fn fixtures() -> (
+    Vec<CloudflareListZonesResponse>,
+    HashMap<String, Vec<DNSRecord>>,
+) {
+    let zones =
+        serde_json::from_str(include_str!("../fixtures/zones.json")).expect("fixtures deserialize");
+    let records = serde_json::from_str(include_str!("../fixtures/dns_records.json"))
+        .expect("fixtures deserialize");
+    (zones, records)
+}
+
+/// A copy of `base` with another ID, name and content.
+fn record(base: &DNSRecord, id: &str, name: &str, content: serde_json::Value) -> DNSRecord {
+    let mut record = base.clone();
+    id.clone_into(&mut record.id);
+    name.clone_into(&mut record.name);
+    record.content = serde_json::from_value(content).expect("content deserializes");
+    record
+}
+
+#[test]
+fn dangling_targets_are_found_and_ranked() {
+    let (_, mut records) = fixtures();
+    let mut records = records
+        .remove(EXAMPLE_COM)
+        .expect("example.com has records");
+    let base = records[0].clone();
+    records.extend([
+        record(
+            &base,
+            "heroku",
+            "old.example.com",
+            serde_json::json!({"type": "CNAME", "content": "gone-app.herokuapp.com"}),
+        ),
+        record(
+            &base,
+            "pages",
+            "docs.example.com",
+            serde_json::json!({"type": "CNAME", "content": "docs.example.org"}),
+        ),
+        record(
+            &base,
+            "lapsed",
+            "legacy.example.com",
+            serde_json::json!({"type": "CNAME", "content": "old.example.org."}),
+        ),
+        record(
+            &base,
+            "typo",
+            "typo.example.com",
+            serde_json::json!({"type": "CNAME", "content": "missing.example.com"}),
+        ),
+        record(
+            &base,
+            "mail",
+            "example.com",
+            serde_json::json!({"type": "MX", "content": "mx.lapsed.example", "priority": 20}),
+        ),
+        record(
+            &base,
+            "route53",
+            "dev.example.com",
+            serde_json::json!({"type": "NS", "content": "ns-1.awsdns-01.org"}),
+        ),
+        record(
+            &base,
+            "apex",
+            "example.com",
+            serde_json::json!({"type": "NS", "content": "bob.ns.cloudflare.com"}),
+        ),
+    ]);
+    let resolver = StubResolver::new(&[
+        ("gone-app.herokuapp.com", Resolution::NxDomain),
+        ("docs.example.org", resolves(&["example.github.io"])),
+        ("old.example.org", Resolution::NxDomain),
+        ("missing.example.com", Resolution::NxDomain),
+        ("mx.lapsed.example", Resolution::NxDomain),
+        ("dev.example.com", Resolution::ServFail),
+        ("bob.ns.cloudflare.com", Resolution::NxDomain),
+    ]);
+
+    let report = block_on(audit_zone(&resolver, EXAMPLE_COM, "example.com", &records));
+    assert_eq!(
+        report.records_checked, 8,
+        "www, the two MX records, four more CNAMEs and the delegation"
+    );
+    assert_eq!(report.severity, Some(Severity::Critical));
+    let found: Vec<(&str, Severity)> = report
+        .findings
+        .iter()
+        .map(|f| (f.record_id.as_str(), f.severity))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("route53", Severity::Critical),
+            ("heroku", Severity::Critical),
+            ("mail", Severity::High),
+            ("lapsed", Severity::High),
+            ("typo", Severity::Low),
+            ("pages", Severity::Info),
+        ]
+    );
+    let heroku = &report.findings[1];
+    assert_eq!(heroku.provider.as_deref(), Some("Heroku"));
+    assert_eq!(heroku.status, TargetStatus::NxDomain);
+    assert_eq!(
+        report.findings[0].provider.as_deref(),
+        Some("Amazon Route 53")
+    );
+    assert_eq!(report.findings[3].target, "old.example.org");
+    assert_eq!(report.findings[5].provider.as_deref(), Some("GitHub Pages"));
+
+    let mut asked = resolver.asked.lock().expect("stub is not poisoned").clone();
+    assert!(!asked.contains(&"bob.ns.cloudflare.com".to_string()));
+    let count = asked.len();
+    asked.sort();
+    asked.dedup();
+    assert_eq!(asked.len(), count, "each name is looked up once");
+}
+
+#[test]
+fn every_cached_zone_is_audited() {
+    let (zones, records) = fixtures();
+    let cache = ManagedCache::default();
+    *cache.zones.lock().expect("cache is not poisoned") = zones;
+    for (zone_id, records) in records {
+        cache.put_zone_records(&zone_id, records);
+    }
+    let resolver = StubResolver::new(&[("mail.example.com", Resolution::NoAddresses)]);
+
+    let audits = block_on(audit(&resolver, &cache));
+    let names: Vec<&str> = audits.iter().map(|a| a.zone_name.as_str()).collect();
+    assert_eq!(names, vec!["example.com", "example.net"]);
+    assert_eq!(audits[0].findings.len(), 1);
+    assert_eq!(audits[0].findings[0].record_type, "MX");
+    assert_eq!(audits[0].severity, Some(Severity::Medium));
+    assert_eq!(
+        audits[1].records_checked, 0,
+        "example.net only has an A record"
+    );
+    assert!(audits[1].severity.is_none());
+}
+
+#[test]
+fn lookups_are_classified() {
+    let name = |name: &str| Name::from_str(name).expect("name parses");
+    let server = block_on(MockDns::start(vec![
+        Record::from_rdata(
+            name("app.example.org."),
+            300,
+            RData::A(A(Ipv4Addr::new(192, 0, 2, 1))),
+        ),
+        Record::from_rdata(
+            name("example.org."),
+            300,
+            RData::MX(MX::new(10, name("app.example.org."))),
+        ),
+    ]))
+    .expect("DNS server starts");
+    let resolver = DnsResolver::new(&PropagationSettings {
+        resolvers: vec![NamedResolver {
+            name: "Stub".to_string(),
+            address: server.address().to_string(),
+        }],
+        ..PropagationSettings::default()
+    });
+
+    assert_eq!(block_on(resolver.resolve("app.example.org")), resolves(&[]));
+    assert_eq!(
+        block_on(resolver.resolve("example.org")),
+        Resolution::NoAddresses
+    );
+    assert_eq!(
+        block_on(resolver.resolve("gone.example.org")),
+        Resolution::NxDomain
+    );
+}
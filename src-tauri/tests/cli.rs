@@ -0,0 +1,165 @@
+//! Tests of the `cfdns` command-line tool against the mock Cloudflare API.
+
+use std::{
+    path::Path,
+    process::{Command, Output},
+};
+
+use cloudflare_dns_gui::mock::{MockCloudflare, MOCK_TOKEN};
+use serde_json::Value;
+
+mod common;
+
+use common::block_on;
+
+/// Start a mock server with the fixtures.
+fn start_mock() -> MockCloudflare {
+    block_on(MockCloudflare::start()).expect("mock server starts")
+}
+
+/// Write a config file in `dir` pointing at `server` with `token`, and return its path.
+fn write_config(server: &MockCloudflare, token: &str, dir: &Path) -> String {
+    let path = dir.join("config.yaml");
+    std::fs::write(
+        &path,
+        format!("token: {token}\nbase_url: {}\n", server.base_url()),
+    )
+    .expect("config is written");
+    path.display().to_string()
+}
+
+/// Run `cfdns` with the config file at `config` and `args`.
+fn cfdns(config: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cfdns"))
+        .env_remove("CLOUDFLARE_API_TOKEN")
+        .env_remove("CFDNS_CONFIG")
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .output()
+        .expect("cfdns runs")
+}
+
+/// Run `cfdns` with `--json`, check it succeeded, and parse what it printed.
+fn cfdns_json(config: &str, args: &[&str]) -> Value {
+    let output = cfdns(config, &[args, &["--json"]].concat());
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).expect("cfdns prints JSON")
+}
+
+#[test]
+fn zones_and_records_are_listed_as_tables_and_json() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let config = write_config(&server, MOCK_TOKEN, dir.path());
+
+    let output = cfdns(&config, &["zones"]);
+    assert!(output.status.success());
+    let table = String::from_utf8(output.stdout).expect("output is UTF-8");
+    let lines: Vec<&str> = table.lines().collect();
+    assert!(lines[0].starts_with("ID"), "{table}");
+    assert_eq!(lines.len(), 3, "a header and a row per zone: {table}");
+    assert!(table.contains("example.net"));
+
+    let zones = cfdns_json(&config, &["zones"]);
+    assert_eq!(zones.as_array().map(Vec::len), Some(2));
+
+    let mx = cfdns_json(&config, &["records", "list", "example.com", "--type", "mx"]);
+    assert_eq!(mx.as_array().map(Vec::len), Some(1));
+    assert_eq!(mx[0]["type"], "MX");
+    let www = cfdns_json(
+        &config,
+        &["records", "list", "example.com", "--name", "www"],
+    );
+    assert_eq!(www[0]["name"], "www.example.com");
+
+    let exported = cfdns(&config, &["export", "023e105f4ecef8ad9ca31a8372d0c353"]);
+    let zone_file = String::from_utf8(exported.stdout).expect("output is UTF-8");
+    assert!(zone_file.contains("$ORIGIN example.com."), "{zone_file}");
+}
+
+#[test]
+fn records_are_created_updated_and_deleted() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let config = write_config(&server, MOCK_TOKEN, dir.path());
+
+    let created = cfdns_json(
+        &config,
+        &[
+            "records",
+            "create",
+            "example.com",
+            "api",
+            "A",
+            "192.0.2.8",
+            "--ttl",
+            "300",
+        ],
+    );
+    assert_eq!(created[0]["name"], "api.example.com");
+    assert_eq!(created[0]["content"], "192.0.2.8");
+    let id = created[0]["id"].as_str().expect("the record has an ID");
+
+    let updated = cfdns_json(
+        &config,
+        &[
+            "records",
+            "update",
+            "example.com",
+            id,
+            "--content",
+            "192.0.2.9",
+        ],
+    );
+    assert_eq!(updated[0]["content"], "192.0.2.9");
+    assert_eq!(updated[0]["ttl"], 300, "settings not given are kept");
+
+    let deleted = cfdns_json(&config, &["records", "delete", "example.com", id]);
+    assert_eq!(deleted["id"], id);
+    let listed = cfdns_json(&config, &["records", "list", "example.com"]);
+    assert_eq!(listed.as_array().map(Vec::len), Some(5));
+}
+
+#[test]
+fn problems_are_reported() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let config = write_config(&server, MOCK_TOKEN, dir.path());
+
+    // www is a CNAME, so nothing else can be at that name.
+    let output = cfdns(
+        &config,
+        &["records", "create", "example.com", "www", "A", "192.0.2.8"],
+    );
+    assert!(!output.status.success());
+    let output = cfdns(
+        &config,
+        &[
+            "records",
+            "create",
+            "example.com",
+            "api",
+            "A",
+            "not-an-address",
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("isn't an IPv4 address"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cfdns"))
+        .env_remove("CLOUDFLARE_API_TOKEN")
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg("zones")
+        .output()
+        .expect("cfdns runs");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No API token"));
+
+    let unauthorized = write_config(&server, "wrong-token", dir.path());
+    assert!(!cfdns(&unauthorized, &["zones"]).status.success());
+}
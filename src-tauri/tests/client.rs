@@ -0,0 +1,162 @@
+//! Tests of the request layer's rate limiting and retries against the mock Cloudflare API.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use cloudflare_dns_gui::{
+    api,
+    client::{ClientConfig, CloudflareClient},
+    cloudflare::BearerAuthorizer,
+    error::ErrorCategory,
+    mock::{MockCloudflare, MockFailure, MOCK_TOKEN},
+    rate_limit::{retry_after, RateLimit, RetryPolicy, ThrottleListener, ThrottleReason},
+};
+use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+
+mod common;
+
+use common::block_on;
+
+/// Retries quickly, so the tests don't wait for real backoff delays.
+const FAST_RETRY: RetryPolicy = RetryPolicy {
+    max_retries: 3,
+    base_delay: Duration::from_millis(1),
+    max_delay: Duration::from_millis(10),
+};
+
+/// A client pointed at `server` with the mock token, recording the reason of every throttle event.
+fn client(
+    server: &MockCloudflare,
+    config: ClientConfig,
+) -> (CloudflareClient, Arc<Mutex<Vec<ThrottleReason>>>) {
+    let client = CloudflareClient::new(ClientConfig {
+        base_url: server.base_url(),
+        ..config
+    })
+    .expect("client builds");
+    client.set_authorizer(Box::new(BearerAuthorizer {
+        token: MOCK_TOKEN.to_string(),
+    }));
+    let reasons = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&reasons);
+    client.set_throttle_listener(ThrottleListener::new(move |event| {
+        recorded
+            .lock()
+            .expect("reasons are not poisoned")
+            .push(event.reason);
+    }));
+    (client, reasons)
+}
+
+/// Queue failures for the next requests to the mock server.
+fn fail_next(server: &MockCloudflare, failures: &[MockFailure]) {
+    let state = server.state();
+    state
+        .lock()
+        .expect("mock state is not poisoned")
+        .failures
+        .extend(failures);
+}
+
+/// How many requests the mock server has received.
+fn requests(server: &MockCloudflare) -> usize {
+    server
+        .state()
+        .lock()
+        .expect("mock state is not poisoned")
+        .requests
+}
+
+#[test]
+fn get_is_retried_after_rate_limiting_and_server_errors() {
+    let server = block_on(MockCloudflare::start()).expect("mock server starts");
+    let (client, reasons) = client(
+        &server,
+        ClientConfig {
+            retry: FAST_RETRY,
+            ..ClientConfig::default()
+        },
+    );
+    fail_next(
+        &server,
+        &[
+            MockFailure::rate_limited(Some(0)),
+            MockFailure::server_error(503),
+        ],
+    );
+
+    let zones = block_on(api::get_zones(&client)).expect("the third attempt succeeds");
+    assert_eq!(zones.len(), 2);
+    assert_eq!(requests(&server), 3);
+    assert_eq!(
+        *reasons.lock().expect("reasons are not poisoned"),
+        vec![ThrottleReason::RateLimited, ThrottleReason::Server]
+    );
+}
+
+#[test]
+fn get_gives_up_after_the_last_retry() {
+    let server = block_on(MockCloudflare::start()).expect("mock server starts");
+    let (client, _) = client(
+        &server,
+        ClientConfig {
+            retry: RetryPolicy {
+                max_retries: 2,
+                ..FAST_RETRY
+            },
+            ..ClientConfig::default()
+        },
+    );
+    fail_next(&server, &[MockFailure::server_error(502); 5]);
+
+    let error = block_on(api::get_zones(&client)).expect_err("every attempt fails");
+    assert_eq!(error.category, ErrorCategory::Server);
+    assert_eq!(error.status, Some(502));
+    assert_eq!(requests(&server), 3);
+}
+
+#[test]
+fn requests_wait_for_the_budget_to_refill() {
+    let server = block_on(MockCloudflare::start()).expect("mock server starts");
+    let (client, reasons) = client(
+        &server,
+        ClientConfig {
+            rate_limit: RateLimit {
+                requests: 2,
+                per: Duration::from_millis(500),
+            },
+            ..ClientConfig::default()
+        },
+    );
+
+    let started = Instant::now();
+    for _ in 0..3 {
+        block_on(api::check_api_key(&client)).expect("request succeeds");
+    }
+    // The third request has to wait for a quarter of the period.
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    assert!(reasons
+        .lock()
+        .expect("reasons are not poisoned")
+        .contains(&ThrottleReason::Budget));
+}
+
+#[test]
+fn retry_after_accepts_seconds_and_dates() {
+    let mut headers = HeaderMap::new();
+    assert_eq!(retry_after(&headers), None);
+
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("90"));
+    assert_eq!(retry_after(&headers), Some(Duration::from_secs(90)));
+
+    headers.insert(
+        RETRY_AFTER,
+        HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+    );
+    assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+    assert_eq!(retry_after(&headers), None);
+}
@@ -0,0 +1,1638 @@
+//! Integration tests of the Tauri commands against the mock Cloudflare API.
+
+use cloudflare_dns_gui::{
+    client::{ClientConfig, CloudflareClient},
+    cloudflare::{BearerAuthorizer, Credential, DNSRecord, DNSRecordPatch, DNSRecordRequest},
+    commands,
+    credentials::SecretStorage,
+    ddns::Ddns,
+    disk_cache::DiskCache,
+    error::{AppError, ErrorCategory},
+    journal::{Journal, JournalAction},
+    mock::{MockCloudflare, MOCK_GLOBAL_API_KEY, MOCK_TOKEN},
+    models::ManagedCache,
+    plan::{Change, ConflictResolution, PlanOptions, Writer},
+    profiles::Profiles,
+    rate_limit::RetryPolicy,
+    records::DNSContent,
+    snapshots::SnapshotStore,
+};
+use std::{path::Path, time::Duration};
+
+use serde_json::Value;
+use tauri::{async_runtime::block_on, test::MockRuntime, App, Manager};
+
+/// Start a mock server with the fixtures.
+fn start_mock() -> MockCloudflare {
+    block_on(MockCloudflare::start()).expect("mock server starts")
+}
+
+/// A mock app with an empty cache and the client pointed at `server`, using `token`. Credentials, profiles
+/// and the disk cache are kept in `dir`.
+fn mock_app(server: &MockCloudflare, token: &str, dir: &Path) -> App<MockRuntime> {
+    let app = tauri::test::mock_app();
+    app.manage(ManagedCache::default());
+    app.manage(SecretStorage::file_only(dir));
+    app.manage(Profiles::load(dir));
+    app.manage(Journal::new(dir));
+    app.manage(SnapshotStore::new(dir));
+    app.manage(Ddns::load(dir));
+    let client = CloudflareClient::new(ClientConfig {
+        base_url: server.base_url(),
+        // Retry quickly, so failing requests don't wait for real backoff delays.
+        retry: RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+            ..RetryPolicy::default()
+        },
+        ..ClientConfig::default()
+    })
+    .expect("client builds");
+    client.set_authorizer(Box::new(BearerAuthorizer {
+        token: token.to_string(),
+    }));
+    app.manage(client);
+    app
+}
+
+#[test]
+fn initialize_cf_loads_user_zones_and_records() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+
+    let ready = block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    assert!(ready);
+
+    let cache = app.state::<ManagedCache>();
+    let user_details = cache
+        .user_details
+        .lock()
+        .expect("cache is not poisoned")
+        .clone()
+        .expect("user details are cached");
+    assert_eq!(user_details.email, "user@example.com");
+    assert_eq!(user_details.organizations, vec!["Cloudflare, Inc."]);
+
+    let zones = cache.zones.lock().expect("cache is not poisoned").clone();
+    let zone_names: Vec<&str> = zones.iter().map(|z| z.name.as_str()).collect();
+    assert_eq!(zone_names, vec!["example.com", "example.net"]);
+
+    let zone_dns = cache
+        .zone_dns
+        .lock()
+        .expect("cache is not poisoned")
+        .clone();
+    assert_eq!(zone_dns.len(), 2);
+    assert_eq!(zone_dns["023e105f4ecef8ad9ca31a8372d0c353"].len(), 5);
+    assert_eq!(zone_dns["9a7806061c88ada191ed06f989cc3dac"].len(), 1);
+}
+
+#[test]
+fn initialize_cf_follows_every_page() {
+    let server = start_mock();
+    {
+        let state = server.state();
+        let mut state = state.lock().expect("mock state is not poisoned");
+        let template = state.zones[0].clone();
+        state.zones = (0..120)
+            .map(|i| {
+                let mut zone = template.clone();
+                zone["id"] = Value::String(format!("zone{i:03}"));
+                zone["name"] = Value::String(format!("example{i:03}.com"));
+                zone
+            })
+            .collect();
+        let record = state.dns_records["023e105f4ecef8ad9ca31a8372d0c353"][0].clone();
+        state.dns_records = state
+            .zones
+            .iter()
+            .map(|zone| {
+                let zone_id = zone["id"].as_str().unwrap_or_default().to_string();
+                (zone_id, vec![record.clone(); 3])
+            })
+            .collect();
+    }
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    let cache = app.state::<ManagedCache>();
+    assert_eq!(
+        cache.zones.lock().expect("cache is not poisoned").len(),
+        120
+    );
+    let zone_dns = cache
+        .zone_dns
+        .lock()
+        .expect("cache is not poisoned")
+        .clone();
+    assert_eq!(zone_dns.len(), 120);
+    assert!(zone_dns.values().all(|records| records.len() == 3));
+}
+
+#[test]
+fn initialize_cf_reports_invalid_token() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, "not-the-right-token", dir.path());
+
+    let error = block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("initialization fails");
+    assert_eq!(error.category, ErrorCategory::Unauthorized);
+    assert_eq!(error.status, Some(403));
+    assert_eq!(error.errors[0].code, 9109);
+
+    let cache = app.state::<ManagedCache>();
+    assert!(cache
+        .user_details
+        .lock()
+        .expect("cache is not poisoned")
+        .is_none());
+    assert!(cache
+        .zones
+        .lock()
+        .expect("cache is not poisoned")
+        .is_empty());
+}
+
+#[test]
+fn initialize_cf_reports_unreachable_api() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    drop(server);
+
+    let error = block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("initialization fails");
+    assert_eq!(error.category, ErrorCategory::Network);
+    assert_eq!(error.status, None);
+}
+
+#[test]
+fn set_api_token_accepts_global_api_key() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, "", dir.path());
+
+    let credential = Credential::GlobalApiKey {
+        email: "user@example.com".to_string(),
+        key: MOCK_GLOBAL_API_KEY.to_string(),
+    };
+    block_on(commands::set_api_token(
+        credential,
+        app.state(),
+        app.state(),
+    ))
+    .expect("global API key is accepted");
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    let cache = app.state::<ManagedCache>();
+    assert_eq!(cache.zones.lock().expect("cache is not poisoned").len(), 2);
+}
+
+#[test]
+fn set_api_token_rejects_global_api_key_with_wrong_email() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, "", dir.path());
+
+    let credential = Credential::GlobalApiKey {
+        email: "someone-else@example.com".to_string(),
+        key: MOCK_GLOBAL_API_KEY.to_string(),
+    };
+    let error = block_on(commands::set_api_token(
+        credential,
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("wrong email is rejected");
+    assert_eq!(error.category, ErrorCategory::Unauthorized);
+    assert_eq!(error.errors[0].code, 9103);
+}
+
+#[test]
+fn saved_credentials_are_restored_and_forgotten() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, "", dir.path());
+
+    let credential = Credential::BearerToken {
+        token: MOCK_TOKEN.to_string(),
+    };
+    block_on(commands::save_credentials(
+        credential,
+        app.state(),
+        app.state(),
+    ))
+    .expect("credentials are saved");
+
+    let restored = block_on(commands::load_credentials(
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("saved credentials are valid");
+    assert!(restored);
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    block_on(commands::forget_credentials(
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("credentials are deleted");
+    let error = block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("credentials were cleared");
+    assert_eq!(error.category, ErrorCategory::MissingCredentials);
+    let restored = block_on(commands::load_credentials(
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("store is readable");
+    assert!(!restored);
+}
+
+#[test]
+fn profiles_keep_their_own_credentials_and_cache() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+
+    // The default profile uses the API token and sees both zones.
+    let token = Credential::BearerToken {
+        token: MOCK_TOKEN.to_string(),
+    };
+    block_on(commands::save_credentials(token, app.state(), app.state()))
+        .expect("credentials are saved");
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    // A second profile uses the global API key and, by the time it is loaded, only sees one zone.
+    let global_key = Credential::GlobalApiKey {
+        email: "user@example.com".to_string(),
+        key: MOCK_GLOBAL_API_KEY.to_string(),
+    };
+    let added = block_on(commands::add_profile(
+        " work ".to_string(),
+        global_key,
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("profile is added");
+    assert_eq!(added.name, "work");
+    assert_eq!(added.email.as_deref(), Some("user@example.com"));
+    assert!(!added.active);
+
+    let cached = block_on(commands::switch_profile(
+        "work".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("switches to the new profile");
+    assert!(!cached);
+    {
+        let state = server.state();
+        state
+            .lock()
+            .expect("mock state is not poisoned")
+            .zones
+            .truncate(1);
+    }
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    // Switching back restores the default profile's cache without asking Cloudflare.
+    drop(server);
+    let cached = block_on(commands::switch_profile(
+        "default".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("switches back");
+    assert!(cached);
+    let cache = app.state::<ManagedCache>();
+    assert_eq!(cache.zones.lock().expect("cache is not poisoned").len(), 2);
+
+    let profiles =
+        block_on(commands::list_profiles(app.state(), app.state())).expect("profiles are listed");
+    let summary: Vec<(&str, bool, usize)> = profiles
+        .iter()
+        .map(|p| (p.name.as_str(), p.active, p.zone_count))
+        .collect();
+    assert_eq!(summary, vec![("default", true, 2), ("work", false, 1)]);
+
+    // The profile list survives a restart.
+    assert_eq!(Profiles::load(dir.path()).active(), "default");
+
+    block_on(commands::remove_profile(
+        "work".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("profile is removed");
+    let error = block_on(commands::switch_profile(
+        "work".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("removed profile can't be switched to");
+    assert_eq!(error.category, ErrorCategory::MissingCredentials);
+}
+
+#[test]
+fn results_for_a_profile_switched_away_from_are_discarded() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let global_key = Credential::GlobalApiKey {
+        email: "user@example.com".to_string(),
+        key: MOCK_GLOBAL_API_KEY.to_string(),
+    };
+    block_on(commands::add_profile(
+        "work".to_string(),
+        global_key,
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("profile is added");
+    let example_com = "023e105f4ecef8ad9ca31a8372d0c353";
+    let cache = app.state::<ManagedCache>();
+    let (_, records) = cache.zone(example_com).expect("example.com is cached");
+
+    // A command starts with the default profile, and the work profile is switched to while it waits.
+    let client = app.state::<CloudflareClient>();
+    let journal = app.state::<Journal>();
+    let profiles = app.state::<Profiles>();
+    let writer = Writer::new(&client, &cache, &journal, &profiles);
+    block_on(commands::switch_profile(
+        "work".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("switches to the work profile");
+
+    writer.put_record(example_com, records[0].clone());
+    assert!(
+        cache.zone(example_com).is_err(),
+        "the default profile's record isn't put in the work profile's cache"
+    );
+    let error = block_on(writer.create_record(example_com, &api_record()))
+        .expect_err("no more changes are made with the default profile");
+    assert_eq!(error.category, ErrorCategory::Validation);
+    profiles.persist(&writer.profile, &cache);
+    let saved = DiskCache::new(dir.path())
+        .load("default")
+        .expect("the disk cache is readable")
+        .expect("the default profile's cache is saved");
+    assert_eq!(
+        saved.zones.len(),
+        2,
+        "the work profile's cache isn't saved as the default profile's"
+    );
+
+    // The profile list was saved without leaving its temporary file behind.
+    assert_eq!(Profiles::load(dir.path()).active(), "work");
+    assert!(!dir.path().join("profiles.json.tmp").exists());
+}
+
+#[test]
+fn add_profile_rejects_invalid_credentials() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+
+    let credential = Credential::BearerToken {
+        token: "not-the-right-token".to_string(),
+    };
+    let error = block_on(commands::add_profile(
+        "work".to_string(),
+        credential,
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("invalid token is rejected");
+    assert_eq!(error.category, ErrorCategory::Unauthorized);
+
+    let profiles =
+        block_on(commands::list_profiles(app.state(), app.state())).expect("profiles are listed");
+    assert!(profiles.is_empty());
+}
+
+#[test]
+fn initialize_cf_saves_the_cache_to_disk() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    // The next run of the app restores the cache without asking Cloudflare.
+    drop(server);
+    let restarted = ManagedCache::default();
+    assert!(Profiles::load(dir.path()).restore_from_disk(&restarted));
+    assert_eq!(
+        restarted.zones.lock().expect("cache is not poisoned").len(),
+        2
+    );
+    let zone_dns = restarted
+        .zone_dns
+        .lock()
+        .expect("cache is not poisoned")
+        .clone();
+    assert_eq!(zone_dns["023e105f4ecef8ad9ca31a8372d0c353"].len(), 5);
+    let fetched_at = restarted
+        .fetched_at
+        .lock()
+        .expect("cache is not poisoned")
+        .expect("fetch time is saved");
+    let zone_fetched_at = restarted
+        .zone_fetched_at
+        .lock()
+        .expect("cache is not poisoned")
+        .clone();
+    assert_eq!(zone_fetched_at.len(), 2);
+    assert!(zone_fetched_at.values().all(|at| *at >= fetched_at));
+}
+
+#[test]
+fn cache_from_another_version_is_ignored() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    std::fs::write(
+        dir.path().join("cache.json"),
+        r#"{"version": 0, "profiles": {"default": {"zones": "an older layout"}}}"#,
+    )
+    .expect("cache file is written");
+
+    let cache = ManagedCache::default();
+    assert!(!Profiles::load(dir.path()).restore_from_disk(&cache));
+}
+
+#[test]
+fn failed_refresh_keeps_the_cached_data() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    app.state::<CloudflareClient>()
+        .set_authorizer(Box::new(BearerAuthorizer {
+            token: "revoked-token".to_string(),
+        }));
+    let error = block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("refresh fails");
+    assert_eq!(error.category, ErrorCategory::Unauthorized);
+
+    let cache = app.state::<ManagedCache>();
+    assert_eq!(cache.zones.lock().expect("cache is not poisoned").len(), 2);
+    assert!(cache
+        .user_details
+        .lock()
+        .expect("cache is not poisoned")
+        .is_some());
+}
+
+#[test]
+fn refresh_keeps_records_written_while_it_ran() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let (example_com, example_net) = (
+        "023e105f4ecef8ad9ca31a8372d0c353",
+        "9a7806061c88ada191ed06f989cc3dac",
+    );
+    let cache = app.state::<ManagedCache>();
+    let profiles = app.state::<Profiles>();
+    let mut fetched = cache.snapshot();
+    let writes = cache.zone_writes();
+
+    // A record is created through the app after the refresh fetched the zone's records.
+    let (_, records) = cache.zone(example_com).expect("example.com is cached");
+    let mut created = records[0].clone();
+    "created".clone_into(&mut created.id);
+    cache.put_record(example_com, created);
+    fetched.zone_dns.insert(example_net.to_string(), Vec::new());
+
+    assert!(profiles.store_refresh(&profiles.active(), fetched, &writes, &cache));
+    let (_, records) = cache.zone(example_com).expect("example.com is cached");
+    assert!(
+        records.iter().any(|r| r.id == "created"),
+        "the record written during the refresh is kept"
+    );
+    let (_, records) = cache.zone(example_net).expect("example.net is cached");
+    assert!(
+        records.is_empty(),
+        "zones that weren't written are replaced"
+    );
+}
+
+#[test]
+fn initialize_cf_records_failing_zones_and_loads_the_rest() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    // The second zone's records now fail to load.
+    {
+        let state = server.state();
+        state
+            .lock()
+            .expect("mock state is not poisoned")
+            .dns_records
+            .remove("9a7806061c88ada191ed06f989cc3dac");
+    }
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("a failing zone doesn't fail the refresh");
+
+    let status = block_on(commands::get_cache_status(app.state())).expect("status is read");
+    assert_eq!(status.zone_errors.len(), 1);
+    let error = &status.zone_errors["9a7806061c88ada191ed06f989cc3dac"];
+    assert_eq!(error.category, ErrorCategory::NotFound);
+    assert_eq!(error.errors[0].code, 7003);
+
+    // The failing zone keeps the records cached before.
+    let cache = app.state::<ManagedCache>();
+    let zone_dns = cache
+        .zone_dns
+        .lock()
+        .expect("cache is not poisoned")
+        .clone();
+    assert_eq!(zone_dns["023e105f4ecef8ad9ca31a8372d0c353"].len(), 5);
+    assert_eq!(zone_dns["9a7806061c88ada191ed06f989cc3dac"].len(), 1);
+}
+
+#[test]
+fn invalid_changes_are_rejected_before_reaching_cloudflare() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let state = server.state();
+    let requests = state.lock().expect("mock state is not poisoned").requests;
+
+    let record = DNSRecordRequest {
+        content: DNSContent::A {
+            content: "198.51.100.7".parse().expect("address parses"),
+        },
+        name: "api.example.net".to_string(),
+        proxied: None,
+        comment: None,
+        tags: vec![],
+        ttl: 30,
+    };
+    let error = block_on(commands::create_dns_record(
+        "023e105f4ecef8ad9ca31a8372d0c353".to_string(),
+        record.clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("the record is invalid");
+    assert_eq!(error.category, ErrorCategory::Validation);
+    let fields: Vec<_> = error
+        .field_errors
+        .iter()
+        .map(|e| e.field.as_deref())
+        .collect();
+    assert_eq!(fields, [Some("name"), Some("ttl")]);
+
+    let field_errors = block_on(commands::validate_dns_record(
+        "023e105f4ecef8ad9ca31a8372d0c353".to_string(),
+        record,
+        None,
+        app.state(),
+    ))
+    .expect("the zone is cached");
+    assert_eq!(field_errors, error.field_errors);
+
+    // Patches are checked as the record they would produce.
+    let error = block_on(commands::patch_dns_record(
+        "023e105f4ecef8ad9ca31a8372d0c353".to_string(),
+        "d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8".to_string(),
+        DNSRecordPatch {
+            proxied: Some(true),
+            ..DNSRecordPatch::default()
+        },
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("TXT records can't be proxied");
+    assert_eq!(error.field_errors[0].field.as_deref(), Some("proxied"));
+
+    let error = block_on(commands::delete_dns_record(
+        "023e105f4ecef8ad9ca31a8372d0c353".to_string(),
+        "not-a-cached-record".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("the record isn't cached");
+    assert_eq!(error.category, ErrorCategory::NotFound);
+
+    assert_eq!(
+        state.lock().expect("mock state is not poisoned").requests,
+        requests,
+        "nothing was sent to Cloudflare"
+    );
+}
+
+#[test]
+fn export_zone_file_writes_the_cached_zone() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    let path = dir.path().join("example.com.zone");
+    block_on(commands::export_zone_file(
+        "023e105f4ecef8ad9ca31a8372d0c353".to_string(),
+        path.to_string_lossy().into_owned(),
+        app.state(),
+    ))
+    .expect("the zone is exported");
+    let zone_file = std::fs::read_to_string(&path).expect("the zone file is written");
+    assert!(zone_file.contains("$ORIGIN example.com.\n"));
+    assert_eq!(
+        zone_file.lines().filter(|l| l.contains("\tIN\t")).count(),
+        5
+    );
+
+    let error = block_on(commands::export_zone_file(
+        "not-a-zone".to_string(),
+        path.to_string_lossy().into_owned(),
+        app.state(),
+    ))
+    .expect_err("the zone isn't cached");
+    assert_eq!(error.category, ErrorCategory::NotFound);
+}
+
+#[test]
+fn zone_file_import_previews_then_applies() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let state = server.state();
+    let requests = state.lock().expect("mock state is not poisoned").requests;
+
+    // The A record moves, the TXT record is gone, and there is a new record at `api`.
+    let path = dir.path().join("example.com.zone");
+    std::fs::write(
+        &path,
+        "$ORIGIN example.com.
+$TTL 1
+@\tIN\tSOA\tns1.example.com. admin.example.com. 1 7200 3600 1209600 3600
+@\tIN\tA\t198.51.100.5 ; Web server cf_tags=cf-proxied:true
+@\tIN\tAAAA\t2001:db8::4 ; cf_tags=cf-proxied:true
+@\t3600\tIN\tMX\t10 mail.example.com.
+www\tIN\tCNAME\texample.com. ; cf_tags=cf-proxied:true
+api\tIN\tA\t198.51.100.7
+",
+    )
+    .expect("the zone file is written");
+    let preview = block_on(commands::preview_zone_import(
+        "023e105f4ecef8ad9ca31a8372d0c353".to_string(),
+        path.to_string_lossy().into_owned(),
+        false,
+        PlanOptions {
+            on_conflict: ConflictResolution::Overwrite,
+            delete_missing: true,
+        },
+        app.state(),
+    ))
+    .expect("the import is previewed");
+    let actions: Vec<_> = preview
+        .plan
+        .changes
+        .iter()
+        .map(|change| match change {
+            Change::Create { .. } => "create",
+            Change::Update { .. } => "update",
+            Change::Delete { .. } => "delete",
+        })
+        .collect();
+    assert_eq!(actions, ["delete", "update", "create"]);
+    assert_eq!(preview.plan.unchanged, 3);
+    assert_eq!(preview.skipped.len(), 1);
+    assert_eq!(
+        state.lock().expect("mock state is not poisoned").requests,
+        requests,
+        "previewing sends nothing"
+    );
+
+    let results = block_on(commands::apply_zone_plan(
+        "023e105f4ecef8ad9ca31a8372d0c353".to_string(),
+        preview.plan,
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the plan is applied");
+    assert!(results.iter().all(|r| r.error.is_none()), "{results:?}");
+
+    let on_cloudflare = state
+        .lock()
+        .expect("mock state is not poisoned")
+        .dns_records["023e105f4ecef8ad9ca31a8372d0c353"]
+        .clone();
+    let cached = app
+        .state::<ManagedCache>()
+        .zone_dns
+        .lock()
+        .expect("cache is not poisoned")["023e105f4ecef8ad9ca31a8372d0c353"]
+        .clone();
+    assert_eq!(on_cloudflare.len(), 5);
+    assert_eq!(cached.len(), 5);
+    assert!(cached
+        .iter()
+        .any(|r| r.name == "api.example.com" && r.proxied == Some(false)));
+    assert!(!cached.iter().any(|r| r.content.record_type() == "TXT"));
+}
+
+#[test]
+fn planned_changes_leave_records_changed_since_alone() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let zone_id = "023e105f4ecef8ad9ca31a8372d0c353";
+
+    // The A record moves and the TXT record is gone.
+    let path = dir.path().join("example.com.zone");
+    std::fs::write(
+        &path,
+        "$ORIGIN example.com.
+$TTL 1
+@\tIN\tA\t198.51.100.5 ; Web server cf_tags=cf-proxied:true
+@\tIN\tAAAA\t2001:db8::4 ; cf_tags=cf-proxied:true
+@\t3600\tIN\tMX\t10 mail.example.com.
+www\tIN\tCNAME\texample.com. ; cf_tags=cf-proxied:true
+",
+    )
+    .expect("the zone file is written");
+    let preview = block_on(commands::preview_zone_import(
+        zone_id.to_string(),
+        path.to_string_lossy().into_owned(),
+        false,
+        PlanOptions {
+            on_conflict: ConflictResolution::Overwrite,
+            delete_missing: true,
+        },
+        app.state(),
+    ))
+    .expect("the import is previewed");
+
+    // Someone changes both records outside the app before the plan is applied.
+    let state = server.state();
+    let mut guard = state.lock().expect("mock state is not poisoned");
+    for record in guard
+        .dns_records
+        .get_mut(zone_id)
+        .expect("the zone is on Cloudflare")
+        .iter_mut()
+        .filter(|r| r["type"] == "A" || r["type"] == "TXT")
+    {
+        record["ttl"] = Value::from(600);
+    }
+    drop(guard);
+
+    let results = block_on(commands::apply_zone_plan(
+        zone_id.to_string(),
+        preview.plan,
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the plan is applied");
+    assert_eq!(results.len(), 2);
+    for result in &results {
+        let error = result.error.as_ref().expect("the change is refused");
+        assert_eq!(error.category, ErrorCategory::Validation);
+    }
+    let on_cloudflare = state
+        .lock()
+        .expect("mock state is not poisoned")
+        .dns_records[zone_id]
+        .clone();
+    assert_eq!(on_cloudflare.len(), 5, "nothing is deleted");
+    assert!(on_cloudflare
+        .iter()
+        .any(|r| r["type"] == "A" && r["content"] == "198.51.100.4"));
+    let (_, cached) = app
+        .state::<ManagedCache>()
+        .zone(zone_id)
+        .expect("the zone is cached");
+    assert_eq!(
+        cached.iter().filter(|r| r.ttl == 600).count(),
+        2,
+        "the changed records are refreshed in the cache"
+    );
+}
+
+#[test]
+fn zone_files_can_be_imported_by_cloudflare() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let zone_id = "023e105f4ecef8ad9ca31a8372d0c353";
+
+    // Someone adds a record outside the app, so the cache is missing it.
+    let state = server.state();
+    let mut guard = state.lock().expect("mock state is not poisoned");
+    let records = guard
+        .dns_records
+        .get_mut(zone_id)
+        .expect("the zone is on Cloudflare");
+    let mut outside = records
+        .iter()
+        .find(|r| r["type"] == "A")
+        .expect("the zone has an A record")
+        .clone();
+    outside["id"] = Value::from("added-outside");
+    outside["name"] = Value::from("outside.example.com");
+    records.push(outside);
+    drop(guard);
+    let writes = app.state::<ManagedCache>().zone_writes();
+
+    let path = dir.path().join("example.com.zone");
+    std::fs::write(
+        &path,
+        "www IN CNAME example.com.\napi IN A 198.51.100.7\nmail IN A 198.51.100.8 ; cf_tags=cf-proxied:false\n",
+    )
+    .expect("the zone file is written");
+    let imported = block_on(commands::import_zone_file_via_cloudflare(
+        zone_id.to_string(),
+        path.to_string_lossy().into_owned(),
+        true,
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the zone file is imported");
+    assert_eq!(imported.total_records_parsed, 3);
+    assert_eq!(imported.recs_added, 2, "the existing CNAME is skipped");
+
+    let cached = app
+        .state::<ManagedCache>()
+        .zone_dns
+        .lock()
+        .expect("cache is not poisoned")[zone_id]
+        .clone();
+    assert_eq!(cached.len(), 8);
+    assert_ne!(
+        app.state::<ManagedCache>().zone_writes().get(zone_id),
+        writes.get(zone_id),
+        "the import counts as the app's own write"
+    );
+    let proxied = |name: &str| {
+        cached
+            .iter()
+            .find(|r| r.name == name)
+            .and_then(|r| r.proxied)
+    };
+    assert_eq!(proxied("api.example.com"), Some(true));
+    assert_eq!(proxied("mail.example.com"), Some(false));
+
+    let history = block_on(commands::list_history(
+        zone_id.to_string(),
+        None,
+        app.state(),
+    ))
+    .expect("the history is read");
+    assert_eq!(
+        history.len(),
+        2,
+        "the imported records are journaled, but not the one added outside the app"
+    );
+    assert!(history.iter().all(|e| e.action == JournalAction::Create));
+}
+
+#[test]
+fn desired_state_is_planned_and_applied() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+
+    // example.net has a single A record at its apex, which the file moves and owns.
+    let path = dir.path().join("example.net.yaml");
+    std::fs::write(
+        &path,
+        "zone: example.net
+ownership: { mode: all }
+records:
+  - { name: '@', type: A, content: 198.51.100.9 }
+  - { name: www, type: CNAME, content: example.net, proxied: true }
+",
+    )
+    .expect("the desired state is written");
+
+    let planned = block_on(commands::plan_desired_state(
+        path.to_string_lossy().into_owned(),
+        app.state(),
+    ))
+    .expect("the desired state is planned");
+    assert_eq!(planned.zone_id, "9a7806061c88ada191ed06f989cc3dac");
+    assert_eq!(planned.plan.changes.len(), 2);
+
+    let results = block_on(commands::apply_desired_state(
+        path.to_string_lossy().into_owned(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the desired state is applied");
+    assert!(results.iter().all(|r| r.error.is_none()), "{results:?}");
+
+    let replanned = block_on(commands::plan_desired_state(
+        path.to_string_lossy().into_owned(),
+        app.state(),
+    ))
+    .expect("the desired state is planned again");
+    assert!(replanned.plan.changes.is_empty(), "{:?}", replanned.plan);
+}
+
+/// A new A record at `api.example.com`.
+fn api_record() -> DNSRecordRequest {
+    DNSRecordRequest {
+        content: DNSContent::A {
+            content: "198.51.100.7".parse().expect("address parses"),
+        },
+        name: "api.example.com".to_string(),
+        proxied: Some(false),
+        comment: None,
+        tags: vec![],
+        ttl: 1,
+    }
+}
+
+/// Undo a journaled change.
+fn undo(app: &App<MockRuntime>, entry_id: &str) -> Result<Option<DNSRecord>, AppError> {
+    block_on(commands::undo_change(
+        entry_id.to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+}
+
+#[test]
+fn changes_are_journaled_and_undone() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let zone_id = "023e105f4ecef8ad9ca31a8372d0c353";
+    let history = |record_id: Option<&str>| {
+        block_on(commands::list_history(
+            zone_id.to_string(),
+            record_id.map(ToString::to_string),
+            app.state(),
+        ))
+        .expect("the history is read")
+    };
+
+    let created = block_on(commands::create_dns_record(
+        zone_id.to_string(),
+        api_record(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the record is created");
+    block_on(commands::update_dns_record(
+        zone_id.to_string(),
+        created.id.clone(),
+        DNSRecordRequest {
+            ttl: 300,
+            ..api_record()
+        },
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the record is updated");
+    block_on(commands::delete_dns_record(
+        zone_id.to_string(),
+        "d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the TXT record is deleted");
+
+    let entries = history(None);
+    let actions: Vec<_> = entries.iter().map(|e| e.action).collect();
+    assert_eq!(
+        actions,
+        [
+            JournalAction::Delete,
+            JournalAction::Update,
+            JournalAction::Create
+        ],
+        "newest first"
+    );
+    assert_eq!(history(Some(&created.id)).len(), 2);
+    assert_eq!(entries[1].before.as_ref().map(|r| r.ttl), Some(1));
+    assert_eq!(entries[1].after.as_ref().map(|r| r.ttl), Some(300));
+
+    // Undoing the update puts the TTL back, and the undo is journaled too.
+    let restored = undo(&app, &entries[1].id).expect("the update is undone");
+    assert_eq!(restored.map(|r| r.ttl), Some(1));
+    assert_eq!(
+        history(None)[0].undoes.as_deref(),
+        Some(entries[1].id.as_str())
+    );
+    let error = undo(&app, &entries[1].id).expect_err("an undo can't be repeated");
+    assert_eq!(error.category, ErrorCategory::Validation);
+
+    // Undoing the deletion creates the TXT record again.
+    let recreated = undo(&app, &entries[0].id)
+        .expect("the deletion is undone")
+        .expect("the record is recreated");
+    assert_eq!(recreated.content.record_type(), "TXT");
+    assert_eq!(recreated.tags, ["owner:mail"]);
+}
+
+#[test]
+fn undo_is_refused_once_the_record_has_changed() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let zone_id = "023e105f4ecef8ad9ca31a8372d0c353";
+    let created = block_on(commands::create_dns_record(
+        zone_id.to_string(),
+        api_record(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the record is created");
+    let entries = block_on(commands::list_history(
+        zone_id.to_string(),
+        Some(created.id.clone()),
+        app.state(),
+    ))
+    .expect("the history is read");
+
+    // Someone changes the record outside the app.
+    let state = server.state();
+    let mut guard = state.lock().expect("mock state is not poisoned");
+    let live = guard
+        .dns_records
+        .get_mut(zone_id)
+        .and_then(|records| records.iter_mut().find(|r| r["id"] == created.id.as_str()))
+        .expect("the record is on Cloudflare");
+    live["ttl"] = Value::from(600);
+    drop(guard);
+
+    let error = undo(&app, &entries[0].id).expect_err("the record has changed");
+    assert_eq!(error.category, ErrorCategory::Validation);
+    assert!(
+        app.state::<ManagedCache>()
+            .zone(zone_id)
+            .expect("the zone is cached")
+            .1
+            .iter()
+            .any(|r| r.id == created.id),
+        "the record isn't deleted"
+    );
+}
+
+#[test]
+fn undoing_a_deletion_is_refused_once_the_name_is_taken() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let zone_id = "023e105f4ecef8ad9ca31a8372d0c353";
+    let www = "b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6";
+    block_on(commands::delete_dns_record(
+        zone_id.to_string(),
+        www.to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the CNAME record is deleted");
+    let entries = block_on(commands::list_history(
+        zone_id.to_string(),
+        Some(www.to_string()),
+        app.state(),
+    ))
+    .expect("the history is read");
+
+    // Someone puts an A record at www.example.com outside the app, so the CNAME can't come back.
+    let state = server.state();
+    let mut guard = state.lock().expect("mock state is not poisoned");
+    let records = guard
+        .dns_records
+        .get_mut(zone_id)
+        .expect("the zone is on Cloudflare");
+    let mut address = records
+        .iter()
+        .find(|r| r["type"] == "A")
+        .expect("the zone has an A record")
+        .clone();
+    address["id"] = Value::from("added-outside");
+    address["name"] = Value::from("www.example.com");
+    records.push(address);
+    drop(guard);
+
+    let error = undo(&app, &entries[0].id).expect_err("www.example.com is taken");
+    assert_eq!(error.category, ErrorCategory::Validation);
+    let (_, cached) = app
+        .state::<ManagedCache>()
+        .zone(zone_id)
+        .expect("the zone is cached");
+    assert!(
+        cached.iter().any(|r| r.id == "added-outside"),
+        "the live records at the name are cached"
+    );
+    assert!(
+        !cached
+            .iter()
+            .any(|r| r.content.record_type() == "CNAME" && r.name == "www.example.com"),
+        "the CNAME isn't recreated"
+    );
+}
+
+#[test]
+fn undo_is_refused_for_another_profiles_changes() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let zone_id = "023e105f4ecef8ad9ca31a8372d0c353";
+    let created = block_on(commands::create_dns_record(
+        zone_id.to_string(),
+        api_record(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the record is created");
+    let entries = block_on(commands::list_history(
+        zone_id.to_string(),
+        Some(created.id.clone()),
+        app.state(),
+    ))
+    .expect("the history is read");
+
+    let global_key = Credential::GlobalApiKey {
+        email: "user@example.com".to_string(),
+        key: MOCK_GLOBAL_API_KEY.to_string(),
+    };
+    block_on(commands::add_profile(
+        "work".to_string(),
+        global_key,
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("profile is added");
+    block_on(commands::switch_profile(
+        "work".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("switches to the new profile");
+
+    let error = undo(&app, &entries[0].id).expect_err("the change was made with another profile");
+    assert_eq!(error.category, ErrorCategory::Validation);
+    assert!(
+        server
+            .state()
+            .lock()
+            .expect("mock state is not poisoned")
+            .dns_records[zone_id]
+            .iter()
+            .any(|r| r["id"] == created.id.as_str()),
+        "the record isn't deleted"
+    );
+}
+
+#[test]
+fn snapshots_are_compared_and_restored() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let zone_id = "023e105f4ecef8ad9ca31a8372d0c353";
+    let take = |name: &str| {
+        block_on(commands::take_snapshot(
+            zone_id.to_string(),
+            name.to_string(),
+            app.state(),
+            app.state(),
+            app.state(),
+            app.state(),
+        ))
+        .expect("the snapshot is taken")
+    };
+    let compare = |from_id: &str, to_id: Option<&str>| {
+        block_on(commands::compare_snapshots(
+            from_id.to_string(),
+            to_id.map(ToString::to_string),
+            app.state(),
+            app.state(),
+        ))
+        .expect("the snapshots are compared")
+    };
+
+    let before = take("before");
+    assert_eq!(before.record_count, 5);
+    block_on(commands::delete_dns_record(
+        zone_id.to_string(),
+        "d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8".to_string(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the TXT record is deleted");
+    let after = take("after");
+
+    assert_eq!(compare(&before.id, Some(&after.id)).changes.len(), 1);
+    let since = compare(&before.id, None);
+    assert!(matches!(since.changes[..], [Change::Delete { .. }]));
+
+    let listed = block_on(commands::list_snapshots(
+        Some(zone_id.to_string()),
+        app.state(),
+    ))
+    .expect("the snapshots are listed");
+    assert_eq!(listed.len(), 2);
+
+    let plan = block_on(commands::plan_snapshot_restore(
+        before.id.clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the restore is planned");
+    assert!(matches!(plan.changes[..], [Change::Create { .. }]));
+    let results = block_on(commands::apply_zone_plan(
+        zone_id.to_string(),
+        plan,
+        app.state(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the plan is applied");
+    assert!(results.iter().all(|r| r.error.is_none()), "{results:?}");
+    assert!(compare(&before.id, None).changes.is_empty());
+
+    block_on(commands::delete_snapshot(after.id.clone(), app.state()))
+        .expect("the snapshot is deleted");
+    let error = block_on(commands::compare_snapshots(
+        before.id,
+        Some(after.id),
+        app.state(),
+        app.state(),
+    ))
+    .expect_err("the snapshot is gone");
+    assert_eq!(error.category, ErrorCategory::NotFound);
+}
+
+#[test]
+fn changes_made_outside_the_app_are_noticed() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    let check = || {
+        block_on(commands::check_drift(
+            app.handle().clone(),
+            app.state(),
+            app.state(),
+            app.state(),
+        ))
+        .expect("drift is checked")
+    };
+    assert!(check().is_empty(), "nothing is cached yet");
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    assert!(check().is_empty());
+
+    // Someone edits example.com in the dashboard, which moves its modified_on.
+    let state = server.state();
+    let mut guard = state.lock().expect("mock state is not poisoned");
+    guard.zones[0]["modified_on"] = Value::from("2024-06-01T00:00:00Z");
+    let records = guard
+        .dns_records
+        .get_mut("023e105f4ecef8ad9ca31a8372d0c353")
+        .expect("example.com has records");
+    records[0]["ttl"] = Value::from(300);
+    records.remove(4);
+    let requests = guard.requests;
+    drop(guard);
+
+    let drifted = check();
+    assert_eq!(drifted.len(), 1);
+    assert_eq!(drifted[0].zone_name, "example.com");
+    assert_eq!(drifted[0].changed.len(), 1);
+    assert_eq!(drifted[0].removed.len(), 1);
+    assert!(drifted[0].added.is_empty());
+    assert_eq!(
+        state.lock().expect("mock state is not poisoned").requests,
+        requests + 2,
+        "only the zone list and example.com's records are fetched"
+    );
+    let cached = app
+        .state::<ManagedCache>()
+        .zone("023e105f4ecef8ad9ca31a8372d0c353")
+        .expect("the zone is cached")
+        .1;
+    assert_eq!(cached.len(), 4);
+    assert!(check().is_empty(), "the cache is up to date");
+}
+
+#[test]
+fn dynamic_records_follow_the_public_address() {
+    let server = start_mock();
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let app = mock_app(&server, MOCK_TOKEN, dir.path());
+    block_on(commands::initialize_cf(
+        app.handle().clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("initialization succeeds");
+    let zone_id = "9a7806061c88ada191ed06f989cc3dac";
+    let record_id = app
+        .state::<ManagedCache>()
+        .zone(zone_id)
+        .expect("example.net is cached")
+        .1[0]
+        .id
+        .clone();
+    block_on(commands::set_ddns_source(
+        server.ip_source(),
+        5,
+        app.state(),
+    ))
+    .expect("the source is set");
+    block_on(commands::set_record_dynamic(
+        zone_id.to_string(),
+        record_id.clone(),
+        true,
+        app.state(),
+        app.state(),
+        app.state(),
+    ))
+    .expect("the A record becomes dynamic");
+    let run = || {
+        block_on(commands::run_ddns(
+            app.state(),
+            app.state(),
+            app.state(),
+            app.state(),
+            app.state(),
+        ))
+        .expect("the records are updated")
+    };
+
+    let records = run();
+    assert_eq!(records.len(), 1);
+    let status = &records[0].status;
+    assert!(status.error.is_none(), "{status:?}");
+    assert_eq!(
+        status.address,
+        Some("203.0.113.77".parse().expect("address parses"))
+    );
+    assert!(status.last_changed.is_some());
+    let live = server
+        .state()
+        .lock()
+        .expect("mock state is not poisoned")
+        .dns_records[zone_id][0]
+        .clone();
+    assert_eq!(live["content"], "203.0.113.77");
+    let history = block_on(commands::list_history(
+        zone_id.to_string(),
+        Some(record_id),
+        app.state(),
+    ))
+    .expect("history is listed");
+    assert_eq!(history.len(), 1, "the change is journaled");
+
+    let requests = server
+        .state()
+        .lock()
+        .expect("mock state is not poisoned")
+        .requests;
+    let records = run();
+    assert_eq!(records[0].status.last_changed, status.last_changed);
+    assert_eq!(
+        server
+            .state()
+            .lock()
+            .expect("mock state is not poisoned")
+            .requests,
+        requests,
+        "a record already pointing at the address isn't touched"
+    );
+}
@@ -0,0 +1,31 @@
+//! Fixtures and helpers shared by the integration tests.
+
+// Each test crate only uses some of these.
+#![allow(dead_code)]
+
+use std::{collections::HashMap, future::Future, sync::OnceLock};
+
+use cloudflare_dns_gui::cloudflare::DNSRecord;
+use tokio::runtime::Runtime;
+
+/// The zone ID of example.com.
+pub const EXAMPLE_COM: &str = "023e105f4ecef8ad9ca31a8372d0c353";
+
+/// The fixture records of `example.com`: A, AAAA, MX and TXT records at the apex, and a CNAME at `www`.
+pub fn example_com() -> Vec<DNSRecord> {
+    let mut zones: HashMap<String, Vec<DNSRecord>> =
+        serde_json::from_str(include_str!("../../fixtures/dns_records.json"))
+            .expect("fixtures deserialize");
+    zones
+        .remove(EXAMPLE_COM)
+        .expect("example.com is in the fixtures")
+}
+
+/// Run a future to completion on a runtime shared by the whole test crate, so tasks it spawns, like a mock
+/// server, keep running after it returns.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| Runtime::new().expect("the runtime starts"))
+        .block_on(future)
+}
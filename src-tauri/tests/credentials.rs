@@ -0,0 +1,115 @@
+//! Tests of the encrypted-file credential store, which is what headless machines fall back to.
+
+use cloudflare_dns_gui::{
+    cloudflare::Credential,
+    credentials::{CredentialStore, EncryptedFileStore, SecretStorage},
+    error::ErrorCategory,
+};
+
+/// A bearer token credential.
+fn token(token: &str) -> Credential {
+    Credential::BearerToken {
+        token: token.to_string(),
+    }
+}
+
+#[test]
+fn encrypted_file_store_saves_loads_and_forgets() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let store = EncryptedFileStore::new(dir.path());
+
+    assert!(store.load("default").expect("store is readable").is_none());
+
+    store
+        .save("default", &token("first-token"))
+        .expect("credentials are saved");
+    let global_key = Credential::GlobalApiKey {
+        email: "user@example.com".to_string(),
+        key: "global-key".to_string(),
+    };
+    store
+        .save("work", &global_key)
+        .expect("credentials are saved");
+    store
+        .save("default", &token("second-token"))
+        .expect("credentials are replaced");
+
+    // A new store over the same directory sees what the first one saved.
+    let store = EncryptedFileStore::new(dir.path());
+    match &store.load("default").expect("store is readable") {
+        Some(Credential::BearerToken { token }) => assert_eq!(token, "second-token"),
+        other => panic!("expected the second token, found {other:?}"),
+    }
+    assert!(matches!(
+        store.load("work").expect("store is readable"),
+        Some(Credential::GlobalApiKey { .. })
+    ));
+
+    store.forget("default").expect("credentials are deleted");
+    assert!(store.load("default").expect("store is readable").is_none());
+    assert!(store.load("work").expect("store is readable").is_some());
+}
+
+#[test]
+fn encrypted_file_store_keeps_concurrent_saves() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let store = EncryptedFileStore::new(dir.path());
+    std::thread::scope(|scope| {
+        for i in 0..8 {
+            let store = &store;
+            scope.spawn(move || {
+                store
+                    .save(&format!("profile-{i}"), &token(&format!("token-{i}")))
+                    .expect("credentials are saved");
+            });
+        }
+    });
+    for i in 0..8 {
+        assert!(
+            store
+                .load(&format!("profile-{i}"))
+                .expect("store is readable")
+                .is_some(),
+            "profile-{i} was overwritten"
+        );
+    }
+    let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+        .expect("dir is readable")
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+}
+
+#[test]
+fn encrypted_file_store_does_not_write_the_secret_in_plain_text() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let store = SecretStorage::file_only(dir.path());
+    store
+        .save("default", &token("a-very-secret-token"))
+        .expect("credentials are saved");
+
+    let data = std::fs::read(dir.path().join("credentials.enc")).expect("data file exists");
+    let data = String::from_utf8_lossy(&data);
+    assert!(!data.contains("a-very-secret-token"));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let key = std::fs::metadata(dir.path().join("credentials.key")).expect("key file exists");
+        assert_eq!(key.permissions().mode() & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn encrypted_file_store_rejects_a_different_key() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let store = EncryptedFileStore::new(dir.path());
+    store
+        .save("default", &token("token"))
+        .expect("credentials are saved");
+
+    std::fs::write(dir.path().join("credentials.key"), [7u8; 32]).expect("key is replaced");
+    let error = store.load("default").expect_err("decryption fails");
+    assert_eq!(error.category, ErrorCategory::CredentialStorage);
+}
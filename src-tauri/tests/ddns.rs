@@ -0,0 +1,241 @@
+//! Tests of the dynamic DNS settings and updates, with a stub in place of address detection.
+
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use chrono::Utc;
+use cloudflare_dns_gui::{
+    client::{ClientConfig, CloudflareClient},
+    cloudflare::{CloudflareListZonesResponse, DNSRecord},
+    ddns::{self, Ddns, IpDetector, IpFamily, IpSource},
+    error::{AppError, ErrorCategory},
+    journal::Journal,
+    models::ManagedCache,
+    plan::Writer,
+    profiles::Profiles,
+};
+use futures::future::BoxFuture;
+
+mod common;
+
+use common::block_on;
+
+/// The zone ID of example.net, whose one A record points at 203.0.113.10.
+const EXAMPLE_NET: &str = "9a7806061c88ada191ed06f989cc3dac";
+
+/// Detects a fixed address, or fails if there is none, counting how often it is asked.
+#[derive(Debug, Default)]
+struct StubDetector {
+    /// The address to detect.
+    address: Mutex<Option<IpAddr>>,
+    /// How many times an address was detected.
+    calls: AtomicUsize,
+}
+
+impl IpDetector for StubDetector {
+    fn detect(&self, _family: IpFamily) -> BoxFuture<'_, Result<IpAddr, AppError>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let address = *self.address.lock().expect("stub is not poisoned");
+        Box::pin(async move {
+            address.ok_or_else(|| {
+                AppError::new(ErrorCategory::Network, "No route to the echo service")
+            })
+        })
+    }
+}
+
+/// A cache holding the fixture zones and records of example.net.
+fn cache() -> ManagedCache {
+    let cache = ManagedCache::default();
+    let zones: Vec<CloudflareListZonesResponse> =
+        serde_json::from_str(include_str!("../fixtures/zones.json")).expect("fixtures deserialize");
+    *cache.zones.lock().expect("cache is not poisoned") = zones;
+    let mut records: HashMap<String, Vec<DNSRecord>> =
+        serde_json::from_str(include_str!("../fixtures/dns_records.json"))
+            .expect("fixtures deserialize");
+    cache.put_zone_records(
+        EXAMPLE_NET,
+        records
+            .remove(EXAMPLE_NET)
+            .expect("example.net is in the fixtures"),
+    );
+    cache
+}
+
+/// Mark example.net's A record as dynamic for the `default` profile.
+fn mark_dynamic(ddns: &Ddns, cache: &ManagedCache) {
+    let (_, records) = cache.zone(EXAMPLE_NET).expect("example.net is cached");
+    ddns.set_dynamic("default", EXAMPLE_NET, &records[0], true)
+        .expect("A records can be dynamic");
+}
+
+/// Run the updater against `cache` with a client that isn't pointed at anything reachable.
+fn run(dir: &Path, cache: &ManagedCache, detector: &StubDetector, ddns: &Ddns, force: bool) {
+    let client = CloudflareClient::new(ClientConfig {
+        base_url: "http://127.0.0.1:9".to_string(),
+        ..ClientConfig::default()
+    })
+    .expect("client builds");
+    let journal = Journal::new(dir);
+    let profiles = Profiles::load(dir);
+    let writer = Writer::new(&client, cache, &journal, &profiles);
+    block_on(ddns::run(&writer, detector, ddns, force)).expect("statuses are saved");
+}
+
+#[test]
+fn only_address_records_can_be_dynamic_and_settings_are_saved() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let cache = cache();
+    let ddns = Ddns::load(dir.path());
+    let mut mx = cache.zone(EXAMPLE_NET).expect("example.net is cached").1[0].clone();
+    mx.content = serde_json::from_value(serde_json::json!({
+        "type": "MX",
+        "content": "mail.example.net",
+        "priority": 10
+    }))
+    .expect("MX content deserializes");
+    let error = ddns
+        .set_dynamic("default", EXAMPLE_NET, &mx, true)
+        .expect_err("MX records can't be dynamic");
+    assert_eq!(error.category, ErrorCategory::Validation);
+
+    mark_dynamic(&ddns, &cache);
+    mark_dynamic(&ddns, &cache);
+    ddns.set_source(IpSource::Interface, 15)
+        .expect("the source is set");
+    assert!(ddns.set_source(IpSource::Interface, 0).is_err());
+
+    let reloaded = Ddns::load(dir.path()).settings();
+    assert_eq!(reloaded.source, IpSource::Interface);
+    assert_eq!(reloaded.interval_minutes, 15);
+    assert_eq!(reloaded.records.len(), 1, "marking twice keeps one entry");
+    assert_eq!(reloaded.records[0].family, IpFamily::V4);
+
+    let (_, records) = cache.zone(EXAMPLE_NET).expect("example.net is cached");
+    let settings = ddns
+        .set_dynamic("default", EXAMPLE_NET, &records[0], false)
+        .expect("the record stops being dynamic");
+    assert!(settings.records.is_empty());
+}
+
+#[test]
+fn up_to_date_records_are_left_alone() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let cache = cache();
+    let ddns = Ddns::load(dir.path());
+    mark_dynamic(&ddns, &cache);
+    let detector = StubDetector {
+        address: Mutex::new(Some("203.0.113.10".parse().expect("address parses"))),
+        ..StubDetector::default()
+    };
+
+    run(dir.path(), &cache, &detector, &ddns, false);
+    let status = ddns.settings().records[0].status.clone();
+    assert!(status.error.is_none(), "no request was needed: {status:?}");
+    assert_eq!(
+        status.address,
+        detector
+            .address
+            .lock()
+            .expect("stub is not poisoned")
+            .to_owned()
+    );
+    assert!(status.last_changed.is_none());
+    assert!(status.next_run.is_some_and(|at| at > Utc::now()));
+
+    run(dir.path(), &cache, &detector, &ddns, false);
+    assert_eq!(
+        detector.calls.load(Ordering::SeqCst),
+        1,
+        "the record isn't due again yet"
+    );
+    run(dir.path(), &cache, &detector, &ddns, true);
+    assert_eq!(
+        detector.calls.load(Ordering::SeqCst),
+        2,
+        "forcing runs it anyway"
+    );
+}
+
+#[test]
+fn failures_back_off() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let cache = cache();
+    let ddns = Ddns::load(dir.path());
+    mark_dynamic(&ddns, &cache);
+    let detector = StubDetector::default();
+
+    run(dir.path(), &cache, &detector, &ddns, true);
+    let first = ddns.settings().records[0].status.clone();
+    assert_eq!(first.failures, 1);
+    assert_eq!(
+        first.error.as_ref().map(|e| e.category),
+        Some(ErrorCategory::Network)
+    );
+    assert!(first.last_run.is_some());
+
+    run(dir.path(), &cache, &detector, &ddns, false);
+    assert_eq!(detector.calls.load(Ordering::SeqCst), 1, "the retry waits");
+    run(dir.path(), &cache, &detector, &ddns, true);
+    let second = ddns.settings().records[0].status.clone();
+    assert_eq!(second.failures, 2);
+    assert!(
+        second.next_run.expect("a retry is scheduled") - second.last_run.expect("it ran")
+            > first.next_run.expect("a retry is scheduled") - first.last_run.expect("it ran"),
+        "the backoff grows"
+    );
+
+    *detector.address.lock().expect("stub is not poisoned") =
+        Some("203.0.113.10".parse().expect("address parses"));
+    run(dir.path(), &cache, &detector, &ddns, true);
+    let recovered = ddns.settings().records[0].status.clone();
+    assert_eq!(recovered.failures, 0);
+    assert!(recovered.error.is_none());
+}
+
+#[test]
+fn records_of_other_profiles_are_paused() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let cache = cache();
+    let ddns = Ddns::load(dir.path());
+    let (_, records) = cache.zone(EXAMPLE_NET).expect("example.net is cached");
+    ddns.set_dynamic("work", EXAMPLE_NET, &records[0], true)
+        .expect("A records can be dynamic");
+    let detector = StubDetector::default();
+
+    run(dir.path(), &cache, &detector, &ddns, true);
+    let status = ddns.settings().records[0].status.clone();
+    assert!(status.paused);
+    assert!(status.last_run.is_none());
+    assert_eq!(
+        detector.calls.load(Ordering::SeqCst),
+        0,
+        "the default profile's credentials aren't used for it"
+    );
+
+    // Nothing changed, so the settings aren't saved again.
+    fs::remove_file(dir.path().join("ddns.json")).expect("the settings were saved");
+    run(dir.path(), &cache, &detector, &ddns, true);
+    assert!(!dir.path().join("ddns.json").exists());
+}
+
+#[test]
+fn the_detector_is_kept_until_the_source_changes() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let ddns = Ddns::load(dir.path());
+    let first = ddns.detector().expect("the detector is created");
+    let again = ddns.detector().expect("the detector is reused");
+    assert!(Arc::ptr_eq(&first, &again));
+    ddns.set_source(IpSource::Interface, 5)
+        .expect("the source is set");
+    let changed = ddns.detector().expect("the detector is created");
+    assert!(!Arc::ptr_eq(&first, &changed));
+}
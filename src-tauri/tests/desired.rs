@@ -0,0 +1,207 @@
+//! Tests of reading desired state files and planning the changes they call for.
+
+use cloudflare_dns_gui::{
+    desired::{DesiredState, Ownership},
+    plan::{Change, ChangePlan},
+    records::{DNSContent, SRVData},
+};
+
+mod common;
+
+use common::example_com;
+
+/// `example.com` as it is in the fixtures, except for a new `api` record, with the given ownership.
+fn desired(ownership: &str) -> DesiredState {
+    DesiredState::from_yaml(&format!(
+        r#"
+zone: example.com
+ownership: {ownership}
+records:
+  - {{ name: "@", type: A, content: 198.51.100.4, proxied: true, comment: Web server }}
+  - {{ name: "@", type: AAAA, content: "2001:db8::4", proxied: true }}
+  - {{ name: www, type: CNAME, content: example.com, proxied: true }}
+  - {{ name: "@", type: MX, content: mail.example.com, priority: 10, ttl: 3600 }}
+  - {{ name: api, type: A, content: 198.51.100.7 }}
+"#
+    ))
+    .expect("the desired state parses")
+}
+
+/// The action and record name of each change.
+fn summary(plan: &ChangePlan) -> Vec<(&'static str, &str)> {
+    plan.changes
+        .iter()
+        .map(|change| match change {
+            Change::Create { record } => ("create", record.name.as_str()),
+            Change::Update { before, .. } => ("update", before.name.as_str()),
+            Change::Delete { before } => ("delete", before.name.as_str()),
+        })
+        .collect()
+}
+
+#[test]
+fn yaml_and_json_files_are_read() {
+    let yaml = DesiredState::from_yaml(
+        r"
+zone: example.com
+defaults:
+  ttl: 300
+  proxied: true
+records:
+  - name: _sip._tcp
+    type: SRV
+    data: { port: 5060, priority: 10, target: sip.example.com, weight: 5 }
+  - name: www.example.com
+    type: CNAME
+    content: example.com
+  - name: other.example.net.
+    type: TXT
+    content: hello
+    ttl: 60
+",
+    )
+    .expect("the YAML parses");
+    assert_eq!(yaml.ownership, Ownership::Listed);
+    let requests = yaml.requests();
+    assert_eq!(requests[0].name, "_sip._tcp.example.com");
+    assert_eq!(
+        requests[0].content,
+        DNSContent::Srv {
+            data: SRVData {
+                port: 5060,
+                priority: 10,
+                target: "sip.example.com".to_string(),
+                weight: 5,
+            },
+        }
+    );
+    assert_eq!(requests[0].ttl, 300);
+    assert_eq!(requests[0].proxied, None, "SRV records can't be proxied");
+    assert_eq!(requests[1].name, "www.example.com");
+    assert_eq!(requests[1].proxied, Some(true));
+    assert_eq!(requests[2].name, "other.example.net");
+    assert_eq!(requests[2].ttl, 60);
+
+    let json = DesiredState::from_json(
+        r#"{
+            "zone": "example.com",
+            "ownership": { "mode": "tagged", "tag": "managed-by:cfdns" },
+            "records": [{ "name": "@", "type": "A", "content": "198.51.100.4" }]
+        }"#,
+    )
+    .expect("the JSON parses");
+    assert_eq!(
+        json.requests()[0].tags,
+        ["managed-by:cfdns"],
+        "records get the ownership tag"
+    );
+    assert_eq!(json.requests()[0].ttl, 1, "the TTL is automatic by default");
+}
+
+#[test]
+fn errors_point_at_their_line() {
+    let error = DesiredState::from_yaml(
+        "zone: example.com\nrecords:\n  - name: api\n    type: A\n    content: not-an-address\n",
+    )
+    .expect_err("the address is invalid");
+    assert!(error.field_errors[0]
+        .field
+        .as_deref()
+        .is_some_and(|field| field.starts_with("line ")));
+
+    let error = DesiredState::from_json("{\n  \"zone\": 1\n}").expect_err("the zone isn't a name");
+    assert_eq!(error.field_errors[0].field.as_deref(), Some("line 2"));
+}
+
+#[test]
+fn only_owned_records_are_deleted() {
+    let records = example_com();
+
+    // The TXT record isn't in the file.
+    let listed = desired("{ mode: listed }").plan("example.com", &records);
+    assert_eq!(summary(&listed), [("create", "api.example.com")]);
+    assert_eq!(listed.kept, 1);
+
+    let all = desired("{ mode: all }").plan("example.com", &records);
+    assert_eq!(
+        summary(&all),
+        [("delete", "example.com"), ("create", "api.example.com")]
+    );
+
+    // Only the TXT record has the `owner:mail` tag, so tagging every record in the file updates them all.
+    let tagged = desired("{ mode: tagged, tag: \"owner:mail\" }").plan("example.com", &records);
+    assert_eq!(
+        summary(&tagged),
+        [
+            ("delete", "example.com"),
+            ("update", "example.com"),
+            ("update", "example.com"),
+            ("update", "example.com"),
+            ("update", "www.example.com"),
+            ("create", "api.example.com"),
+        ]
+    );
+    let tagged =
+        desired("{ mode: tagged, tag: \"managed-by:cfdns\" }").plan("example.com", &records);
+    assert!(
+        !tagged
+            .changes
+            .iter()
+            .any(|change| matches!(change, Change::Delete { .. })),
+        "records without the tag aren't deleted"
+    );
+}
+
+#[test]
+fn ignored_records_are_left_alone() {
+    let records = example_com();
+    let mut state = desired("{ mode: all }");
+    state.ignore = vec!["*.example.com".to_string()];
+    let plan = state.plan("example.com", &records);
+    // `www` and `api` are ignored, in the zone and in the file alike.
+    assert_eq!(summary(&plan), [("delete", "example.com")]);
+    state.ignore.push("EXAMPLE.com".to_string());
+    assert!(state.plan("example.com", &records).changes.is_empty());
+    assert!(state.ignores("_acme-challenge.www.example.com"));
+    assert!(!state.ignores("example.net"));
+}
+
+#[test]
+fn tagged_files_leave_hand_made_records_alone() {
+    let mut records = example_com();
+    let record = |id: &str, content: &str, tags: &[&str]| {
+        let mut record = records[0].clone();
+        id.clone_into(&mut record.id);
+        "api.example.com".clone_into(&mut record.name);
+        record.content = DNSContent::A {
+            content: content.parse().expect("the address parses"),
+        };
+        record.tags = tags.iter().map(ToString::to_string).collect();
+        record
+    };
+    let hand_made = record("hand-made", "198.51.100.9", &[]);
+    let owned = record("owned", "198.51.100.8", &["managed-by:cfdns"]);
+    records.extend([hand_made, owned]);
+
+    let plan = desired("{ mode: tagged, tag: \"managed-by:cfdns\" }").plan("example.com", &records);
+    let api: Vec<(&str, &str)> = plan
+        .changes
+        .iter()
+        .filter_map(|change| match change {
+            Change::Update { before, .. } if before.name == "api.example.com" => {
+                Some(("update", before.id.as_str()))
+            }
+            Change::Delete { before } => Some(("delete", before.id.as_str())),
+            Change::Create { record } if record.name == "api.example.com" => {
+                Some(("create", record.name.as_str()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        api,
+        [("update", "owned")],
+        "the owned record is updated, and the hand-made one is neither overwritten nor deleted"
+    );
+    assert_eq!(plan.kept, 2, "the TXT record and the hand-made A record");
+}
@@ -0,0 +1,109 @@
+//! Tests of comparing cached records with the records on Cloudflare.
+
+use std::collections::HashMap;
+
+use cloudflare_dns_gui::{
+    cloudflare::CloudflareListZonesResponse,
+    drift::{compare, DriftCheck},
+    models::ManagedCache,
+};
+
+mod common;
+
+use common::{example_com, EXAMPLE_COM};
+
+/// The zone ID of example.net.
+const EXAMPLE_NET: &str = "9a7806061c88ada191ed06f989cc3dac";
+
+#[test]
+fn unchanged_records_are_not_drift() {
+    let cached = example_com();
+    let mut live = example_com();
+    live.reverse();
+    // Only the timestamp and the order of the tags moved.
+    live[0].modified_on = Some("2024-01-01T00:00:00Z".to_string());
+    live[0].tags.push("extra".to_string());
+    live[0].tags.reverse();
+    let mut cached_with_tag = cached.clone();
+    cached_with_tag[4].tags.push("extra".to_string());
+    let drift = compare("zone", "example.com", &cached_with_tag, &live);
+    assert!(drift.is_empty(), "{drift:?}");
+    assert!(!compare("zone", "example.com", &cached, &live).is_empty());
+}
+
+#[test]
+fn records_are_matched_by_id() {
+    let cached = example_com();
+    let mut live = example_com();
+    live[0].ttl = 300;
+    let removed = live.remove(4);
+    let mut added = live[1].clone();
+    added.id = "new".to_string();
+    added.name = "new.example.com".to_string();
+    live.push(added);
+
+    let drift = compare("zone", "example.com", &cached, &live);
+    assert_eq!(drift.zone_name, "example.com");
+    assert_eq!(drift.changed.len(), 1);
+    assert_eq!(drift.changed[0].before.ttl, 1);
+    assert_eq!(drift.changed[0].after.ttl, 300);
+    assert_eq!(drift.removed.len(), 1);
+    assert_eq!(drift.removed[0].id, removed.id);
+    assert_eq!(drift.added.len(), 1);
+    assert_eq!(drift.added[0].name, "new.example.com");
+}
+
+#[test]
+fn zones_written_during_a_check_keep_their_records() {
+    let zones: Vec<CloudflareListZonesResponse> =
+        serde_json::from_str(include_str!("../fixtures/zones.json")).expect("fixtures deserialize");
+    let cache = ManagedCache::default();
+    *cache.zones.lock().expect("cache is not poisoned") = zones.clone();
+    cache.put_zone_records(EXAMPLE_COM, example_com());
+    cache.put_zone_records(EXAMPLE_NET, example_com());
+    cache.put_zone_records("gone", Vec::new());
+    let writes = cache.zone_writes();
+
+    // A record is created through the app while the zones are fetched, after example.com's records were.
+    let live = example_com();
+    let mut created = live[0].clone();
+    "created".clone_into(&mut created.id);
+    cache.put_record(EXAMPLE_COM, created);
+    let mut changed = live.clone();
+    changed[0].ttl = 300;
+    let check = DriftCheck {
+        zones,
+        zone_dns: HashMap::from([
+            (EXAMPLE_COM.to_string(), changed.clone()),
+            (EXAMPLE_NET.to_string(), Vec::new()),
+        ]),
+        drifted: vec![
+            compare(EXAMPLE_COM, "example.com", &live, &changed),
+            compare(EXAMPLE_NET, "example.net", &live, &[]),
+        ],
+    };
+
+    let drifted = check.store(&cache, &writes);
+    let drifted: Vec<&str> = drifted.iter().map(|d| d.zone_id.as_str()).collect();
+    assert_eq!(
+        drifted,
+        [EXAMPLE_NET],
+        "example.com's drift may be the app's own write"
+    );
+    let (_, records) = cache.zone(EXAMPLE_COM).expect("example.com is cached");
+    assert!(records.iter().any(|r| r.id == "created"));
+    assert_eq!(records[0].ttl, 1, "the fetched records weren't stored");
+    let (_, records) = cache.zone(EXAMPLE_NET).expect("example.net is cached");
+    assert!(
+        records.is_empty(),
+        "example.net wasn't written, so its fetched records replace the cached ones"
+    );
+    assert!(
+        !cache
+            .zone_fetched_at
+            .lock()
+            .expect("cache is not poisoned")
+            .contains_key("gone"),
+        "zones that are gone are dropped"
+    );
+}
@@ -0,0 +1,346 @@
+//! Tests of checking and writing mail authentication records.
+
+use cloudflare_dns_gui::{
+    cloudflare::DNSRecord,
+    email_auth::{
+        check_record, check_zone, generate, DkimKeyType, DmarcPolicy, MailRecordKind,
+        MailRecordOptions, MtaStsMode, SpfAll,
+    },
+    error::ErrorCategory,
+    records::DNSContent,
+    validation::txt_chunks,
+};
+
+mod common;
+
+use common::{example_com, EXAMPLE_COM};
+
+/// A 2048-bit RSA key's worth of base64.
+fn rsa_key() -> String {
+    format!(
+        "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA{}AQAB",
+        "x".repeat(344)
+    )
+}
+
+/// A TXT record like `base` at `name`, with `content`.
+fn txt(base: &DNSRecord, id: &str, name: &str, content: &str) -> DNSRecord {
+    let mut record = base.clone();
+    id.clone_into(&mut record.id);
+    name.clone_into(&mut record.name);
+    record.content = DNSContent::Txt {
+        content: content.to_string(),
+    };
+    record
+}
+
+#[test]
+fn records_are_checked_against_their_specifications() {
+    let valid = [
+        (MailRecordKind::Spf, "v=spf1 mx -all".to_string()),
+        (
+            MailRecordKind::Dmarc,
+            "v=DMARC1; p=reject; rua=mailto:dmarc@example.com!10m; adkim=s; fo=1:d".to_string(),
+        ),
+        (
+            MailRecordKind::Dkim,
+            format!("v=DKIM1; k=rsa; p={}", rsa_key()),
+        ),
+        (
+            MailRecordKind::MtaSts,
+            "v=STSv1; id=20240101T000000".to_string(),
+        ),
+        (
+            MailRecordKind::TlsRpt,
+            "v=TLSRPTv1; rua=mailto:tls@example.com,https://reports.example.com/tls".to_string(),
+        ),
+        (
+            MailRecordKind::Bimi,
+            "v=BIMI1; l=https://example.com/logo.svg; a=https://example.com/vmc.pem".to_string(),
+        ),
+    ];
+    for (kind, text) in valid {
+        assert_eq!(check_record(kind, &text), (vec![], vec![]), "{text}");
+    }
+
+    let invalid = [
+        (MailRecordKind::Spf, "v=spf1 +all"),
+        (MailRecordKind::Spf, "v=spf1 ip4:192.0.2.300 -all"),
+        (MailRecordKind::Dmarc, "p=reject; v=DMARC1"),
+        (MailRecordKind::Dmarc, "v=DMARC1; p=block"),
+        (MailRecordKind::Dmarc, "v=DMARC1; p=none; p=reject"),
+        (MailRecordKind::Dmarc, "v=DMARC1; p=reject; pct=150"),
+        (
+            MailRecordKind::Dmarc,
+            "v=DMARC1; p=reject; rua=dmarc@example.com",
+        ),
+        (MailRecordKind::Dkim, "v=DKIM1; k=dsa; p=AAAA"),
+        (MailRecordKind::Dkim, "v=DKIM1; k=rsa"),
+        (MailRecordKind::Dkim, "v=DKIM1; p=not base64!"),
+        (MailRecordKind::Dkim, "v=DKIM1; k=ed25519; p=AAAA"),
+        (MailRecordKind::MtaSts, "v=STSv1; id=2024-01-01"),
+        (MailRecordKind::TlsRpt, "v=TLSRPTv1; rua=tls@example.com"),
+        (
+            MailRecordKind::Bimi,
+            "v=BIMI1; l=http://example.com/logo.svg",
+        ),
+    ];
+    for (kind, text) in invalid {
+        let (errors, _) = check_record(kind, text);
+        assert!(!errors.is_empty(), "{text}");
+    }
+
+    let weak = [
+        (MailRecordKind::Spf, "v=spf1 mx ?all"),
+        (
+            MailRecordKind::Dmarc,
+            "v=DMARC1; p=none; rua=mailto:dmarc@example.com",
+        ),
+        (MailRecordKind::Dmarc, "v=DMARC1; p=reject"),
+        (
+            MailRecordKind::Dkim,
+            "v=DKIM1; p=MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQCx",
+        ),
+        (MailRecordKind::Dkim, "v=DKIM1; p="),
+    ];
+    for (kind, text) in weak {
+        let (errors, warnings) = check_record(kind, text);
+        assert!(errors.is_empty(), "{text}: {errors:?}");
+        assert!(!warnings.is_empty(), "{text}");
+    }
+}
+
+#[test]
+fn the_zone_is_scored() {
+    let records = example_com();
+    let posture = check_zone(EXAMPLE_COM, "example.com", &records);
+    assert_eq!(posture.records.len(), 1, "only the SPF record is set up");
+    assert_eq!(posture.records[0].kind, MailRecordKind::Spf);
+    assert_eq!(posture.score, 25);
+    assert_eq!(
+        posture.missing,
+        vec![
+            MailRecordKind::Dmarc,
+            MailRecordKind::Dkim,
+            MailRecordKind::MtaSts,
+            MailRecordKind::TlsRpt,
+            MailRecordKind::Bimi,
+        ]
+    );
+    assert_eq!(posture.recommendations.len(), 5);
+
+    let base = records[4].clone();
+    let mut records = records;
+    records.extend([
+        txt(
+            &base,
+            "dmarc",
+            "_dmarc.example.com",
+            "v=DMARC1; p=quarantine; pct=50; rua=mailto:dmarc@example.com",
+        ),
+        txt(
+            &base,
+            "dkim",
+            "google._domainkey.example.com",
+            &format!("v=DKIM1; k=rsa; p={}", rsa_key()),
+        ),
+        txt(&base, "sts", "_mta-sts.example.com", "v=STSv1; id=1"),
+        txt(&base, "sts-old", "_mta-sts.example.com", "v=STSv1; id=2"),
+        txt(
+            &base,
+            "bimi",
+            "default._bimi.example.com",
+            "v=BIMI1; l=https://example.com/logo.svg; a=https://example.com/vmc.pem",
+        ),
+        txt(&base, "other", "_dmarc.example.net", "v=DMARC1; p=reject"),
+    ]);
+    let posture = check_zone(EXAMPLE_COM, "example.com", &records);
+    let found: Vec<(&str, bool)> = posture
+        .records
+        .iter()
+        .map(|r| (r.record_id.as_str(), r.valid()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (base.id.as_str(), true),
+            ("dmarc", true),
+            ("dkim", true),
+            ("sts", false),
+            ("sts-old", false),
+            ("bimi", true),
+        ],
+        "two MTA-STS records cancel each other out"
+    );
+    // SPF 25, DMARC quarantining half the mail 25, DKIM 20; BIMI needs full enforcement.
+    assert_eq!(posture.score, 70);
+    assert_eq!(
+        posture.missing,
+        vec![MailRecordKind::MtaSts, MailRecordKind::TlsRpt]
+    );
+    assert!(posture
+        .recommendations
+        .iter()
+        .any(|r| r.contains("pct=100")));
+}
+
+#[test]
+fn generated_records_pass_their_own_checks() {
+    let records = example_com();
+    let options = [
+        MailRecordOptions::Spf {
+            mx: true,
+            a: false,
+            networks: vec!["192.0.2.0/24".to_string(), "2001:db8::1".to_string()],
+            includes: vec!["_spf.google.com.".to_string()],
+            all: SpfAll::SoftFail,
+        },
+        MailRecordOptions::Dmarc {
+            policy: DmarcPolicy::Quarantine,
+            subdomain_policy: Some(DmarcPolicy::Reject),
+            percent: Some(25),
+            aggregate_reports: vec!["dmarc@example.com".to_string()],
+            failure_reports: vec![],
+            strict_dkim: true,
+            strict_spf: false,
+        },
+        MailRecordOptions::Dkim {
+            selector: "Selector1".to_string(),
+            key_type: DkimKeyType::Rsa,
+            public_key: format!(
+                "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----",
+                rsa_key()
+            ),
+            testing: false,
+        },
+        MailRecordOptions::MtaSts {
+            mode: MtaStsMode::Enforce,
+            mx: vec![],
+            max_age_days: 7,
+        },
+        MailRecordOptions::TlsRpt {
+            reports: vec!["tls@example.com".to_string()],
+        },
+        MailRecordOptions::Bimi {
+            selector: None,
+            logo_url: "https://example.com/logo.svg".to_string(),
+            certificate_url: None,
+        },
+    ];
+    let mut generated = Vec::new();
+    for options in &options {
+        let record = generate("example.com", &records, options).expect("the options are valid");
+        let DNSContent::Txt { content } = &record.record.content else {
+            panic!("{options:?} generated a {:?}", record.record.content);
+        };
+        let text = txt_chunks(content).concat();
+        assert!(check_record(record.kind, &text).0.is_empty(), "{text}");
+        assert!(
+            txt_chunks(content).iter().all(|chunk| chunk.len() <= 255),
+            "{content}"
+        );
+        assert_eq!(record.record.ttl, 1);
+        generated.push((record.record.name.clone(), text, record));
+    }
+
+    assert_eq!(
+        generated[0].1,
+        "v=spf1 mx ip4:192.0.2.0/24 ip6:2001:db8::1 include:_spf.google.com ~all"
+    );
+    assert_eq!(
+        generated[0].2.replaces.as_deref(),
+        Some(records[4].id.as_str()),
+        "the SPF record replaces the zone's"
+    );
+    assert_eq!(generated[1].0, "_dmarc.example.com");
+    assert_eq!(
+        generated[1].1,
+        "v=DMARC1; p=quarantine; sp=reject; pct=25; rua=mailto:dmarc@example.com; adkim=s"
+    );
+    assert!(generated[1].2.replaces.is_none());
+    assert_eq!(generated[2].0, "selector1._domainkey.example.com");
+    assert_eq!(generated[2].1, format!("v=DKIM1; k=rsa; p={}", rsa_key()));
+    assert_eq!(generated[3].0, "_mta-sts.example.com");
+    assert_eq!(
+        generated[3].2.policy.as_deref(),
+        Some("version: STSv1\nmode: enforce\nmx: mail.example.com\nmax_age: 604800\n"),
+        "the policy lists the zone's MX records"
+    );
+    assert_eq!(generated[4].0, "_smtp._tls.example.com");
+    assert_eq!(generated[4].1, "v=TLSRPTv1; rua=mailto:tls@example.com");
+    assert_eq!(generated[5].0, "default._bimi.example.com");
+}
+
+#[test]
+fn invalid_options_are_reported_by_field() {
+    let records = example_com();
+    let fields = |options: MailRecordOptions| {
+        let error =
+            generate("example.com", &records, &options).expect_err("the options are invalid");
+        assert_eq!(error.category, ErrorCategory::Validation);
+        error
+            .field_errors
+            .into_iter()
+            .filter_map(|e| e.field)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        fields(MailRecordOptions::Dmarc {
+            policy: DmarcPolicy::Reject,
+            subdomain_policy: None,
+            percent: Some(101),
+            aggregate_reports: vec!["dmarc@example.com".to_string(), "nobody".to_string()],
+            failure_reports: vec![],
+            strict_dkim: false,
+            strict_spf: false,
+        }),
+        vec!["percent", "aggregate_reports[1]"]
+    );
+    assert_eq!(
+        fields(MailRecordOptions::Spf {
+            mx: false,
+            a: false,
+            networks: vec!["mail.example.com".to_string()],
+            includes: vec![],
+            all: SpfAll::Fail,
+        }),
+        vec!["networks[0]"]
+    );
+    assert_eq!(
+        fields(MailRecordOptions::Dkim {
+            selector: "bad selector".to_string(),
+            key_type: DkimKeyType::Ed25519,
+            public_key: "AAAA".to_string(),
+            testing: false,
+        }),
+        vec!["selector"]
+    );
+    assert_eq!(
+        fields(MailRecordOptions::Dkim {
+            selector: "ed".to_string(),
+            key_type: DkimKeyType::Ed25519,
+            public_key: "AAAA".to_string(),
+            testing: false,
+        }),
+        vec!["content"],
+        "the key is checked once the record is written"
+    );
+
+    let error = generate(
+        "example.net",
+        &[],
+        &MailRecordOptions::MtaSts {
+            mode: MtaStsMode::Testing,
+            mx: vec![],
+            max_age_days: 0,
+        },
+    )
+    .expect_err("the zone has no MX records");
+    let fields: Vec<_> = error
+        .field_errors
+        .into_iter()
+        .filter_map(|e| e.field)
+        .collect();
+    assert_eq!(fields, vec!["mx", "max_age_days"]);
+}
@@ -0,0 +1,109 @@
+//! Tests of the change journal.
+
+use cloudflare_dns_gui::journal::{Journal, JournalAction, JournalEntry};
+
+mod common;
+
+use common::example_com;
+
+#[test]
+fn entries_are_appended_and_listed_newest_first() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let journal = Journal::new(dir.path());
+    assert!(journal
+        .entries()
+        .expect("a missing journal is empty")
+        .is_empty());
+
+    let records = example_com();
+    let mut moved = records[0].clone();
+    moved.ttl = 300;
+    let zone_id = "023e105f4ecef8ad9ca31a8372d0c353";
+    let entries = [
+        JournalEntry::new(
+            "default",
+            zone_id,
+            "example.com",
+            None,
+            Some(records[1].clone()),
+        ),
+        JournalEntry::new(
+            "default",
+            zone_id,
+            "example.com",
+            Some(records[0].clone()),
+            Some(moved),
+        ),
+        JournalEntry::new(
+            "default",
+            zone_id,
+            "example.com",
+            Some(records[4].clone()),
+            None,
+        ),
+        JournalEntry::new(
+            "work",
+            "another-zone",
+            "example.org",
+            Some(records[2].clone()),
+            None,
+        ),
+    ];
+    for entry in &entries {
+        journal.append(entry).expect("the entry is appended");
+    }
+
+    let actions: Vec<_> = entries.iter().map(|e| e.action).collect();
+    assert_eq!(
+        actions,
+        [
+            JournalAction::Create,
+            JournalAction::Update,
+            JournalAction::Delete,
+            JournalAction::Delete
+        ]
+    );
+    let history = journal.history(zone_id, None).expect("the journal is read");
+    let ids: Vec<_> = history.iter().map(|e| e.id.as_str()).collect();
+    assert_eq!(ids, [&entries[2].id, &entries[1].id, &entries[0].id]);
+    let history = journal
+        .history(zone_id, Some(&records[0].id))
+        .expect("the journal is read");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].record_id, records[0].id);
+
+    let found = journal.find(&entries[3].id).expect("the entry is found");
+    assert_eq!(found.profile, "work");
+    assert!(journal.find("missing").is_err());
+}
+
+#[test]
+fn unreadable_lines_are_skipped() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let journal = Journal::new(dir.path());
+    let record = example_com().remove(0);
+    journal
+        .append(&JournalEntry::new(
+            "default",
+            "zone",
+            "example.com",
+            None,
+            Some(record),
+        ))
+        .expect("the entry is appended");
+    let path = dir.path().join("journal.jsonl");
+    let mut text = std::fs::read_to_string(&path).expect("the journal is written");
+    text.push_str("{not json\n");
+    std::fs::write(&path, text).expect("the journal is rewritten");
+    journal
+        .append(&JournalEntry::new(
+            "default",
+            "zone",
+            "example.com",
+            None,
+            None,
+        ))
+        .expect("the entry is appended after the bad line");
+
+    assert_eq!(journal.entries().expect("the journal is read").len(), 2);
+}
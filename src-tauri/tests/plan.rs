@@ -0,0 +1,131 @@
+//! Tests of working out the changes that bring a zone to the wanted records.
+
+use cloudflare_dns_gui::{
+    cloudflare::{DNSRecord, DNSRecordRequest},
+    plan::{diff, Change, ChangePlan, ConflictResolution, PlanOptions},
+    records::DNSContent,
+    validation::ZoneContext,
+};
+
+mod common;
+
+use common::example_com;
+
+/// The request that leaves a record as it is.
+fn unchanged(record: &DNSRecord) -> DNSRecordRequest {
+    DNSRecordRequest {
+        content: record.content.clone(),
+        name: record.name.clone(),
+        proxied: record.proxiable.then_some(record.proxied.unwrap_or(false)),
+        comment: record.comment.clone(),
+        tags: record.tags.clone(),
+        ttl: record.ttl,
+    }
+}
+
+/// Plan the changes to `example.com`.
+fn plan(wanted: &[DNSRecordRequest], options: PlanOptions) -> ChangePlan {
+    let records = example_com();
+    let zone = ZoneContext {
+        zone_name: "example.com",
+        records: &records,
+    };
+    diff(&zone, wanted, options)
+}
+
+/// The action and record name of each change.
+fn summary(plan: &ChangePlan) -> Vec<(&'static str, &str)> {
+    plan.changes
+        .iter()
+        .map(|change| match change {
+            Change::Create { record } => ("create", record.name.as_str()),
+            Change::Update { before, .. } => ("update", before.name.as_str()),
+            Change::Delete { before } => ("delete", before.name.as_str()),
+        })
+        .collect()
+}
+
+#[test]
+fn matching_records_are_unchanged() {
+    let wanted: Vec<_> = example_com().iter().map(unchanged).collect();
+    let plan = plan(&wanted, PlanOptions::default());
+    assert!(plan.changes.is_empty());
+    assert_eq!(plan.unchanged, 5);
+    assert_eq!(plan.kept, 0);
+}
+
+#[test]
+fn changes_are_ordered_deletes_updates_creates() {
+    let records = example_com();
+    let mut wanted: Vec<_> = records.iter().map(unchanged).collect();
+    // Move the A record, drop the TXT record, and add a new one.
+    wanted[0].content = DNSContent::A {
+        content: "198.51.100.5".parse().expect("address parses"),
+    };
+    wanted.retain(|r| r.content.record_type() != "TXT");
+    wanted.push(DNSRecordRequest {
+        name: "api.example.com".to_string(),
+        ..wanted[0].clone()
+    });
+
+    let plan_with_deletes = plan(
+        &wanted,
+        PlanOptions {
+            delete_missing: true,
+            ..PlanOptions::default()
+        },
+    );
+    assert_eq!(
+        summary(&plan_with_deletes),
+        [
+            ("delete", "example.com"),
+            ("update", "example.com"),
+            ("create", "api.example.com")
+        ]
+    );
+    assert_eq!(plan_with_deletes.unchanged, 3);
+
+    let plan_without_deletes = plan(&wanted, PlanOptions::default());
+    assert_eq!(
+        summary(&plan_without_deletes),
+        [("update", "example.com"), ("create", "api.example.com")]
+    );
+    assert_eq!(plan_without_deletes.kept, 1, "the TXT record is left alone");
+}
+
+#[test]
+fn conflicts_can_be_kept() {
+    let mut wanted: Vec<_> = example_com().iter().map(unchanged).collect();
+    // A different TTL on the MX record, and a different A address.
+    wanted[3].ttl = 300;
+    wanted[0].content = DNSContent::A {
+        content: "198.51.100.5".parse().expect("address parses"),
+    };
+
+    let overwrite = plan(&wanted, PlanOptions::default());
+    assert_eq!(
+        summary(&overwrite),
+        [("update", "example.com"), ("update", "example.com")]
+    );
+
+    let keep = plan(
+        &wanted,
+        PlanOptions {
+            on_conflict: ConflictResolution::Keep,
+            delete_missing: false,
+        },
+    );
+    assert!(keep.changes.is_empty());
+    assert_eq!(keep.kept, 2);
+    assert_eq!(keep.unchanged, 3);
+}
+
+#[test]
+fn names_are_compared_fully_qualified() {
+    let records = example_com();
+    let mut wanted: Vec<_> = records.iter().map(unchanged).collect();
+    wanted[0].name = "@".to_string();
+    wanted[2].name = "WWW.example.com.".to_string();
+    let plan = plan(&wanted, PlanOptions::default());
+    assert!(plan.changes.is_empty(), "{:?}", summary(&plan));
+}
@@ -0,0 +1,194 @@
+//! Tests of checking record propagation against stand-in DNS servers.
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    str::FromStr,
+};
+
+use cloudflare_dns_gui::{
+    cloudflare::{CloudflareListZonesResponse, DNSRecord},
+    error::ErrorCategory,
+    mock::MockDns,
+    propagation::{
+        check, socket_addr, AnswerStatus, NamedResolver, Propagation, PropagationSettings,
+        ResolverKind,
+    },
+};
+use hickory_resolver::proto::rr::{
+    rdata::{A, MX, TXT},
+    Name, RData, Record,
+};
+
+mod common;
+
+use common::{block_on, EXAMPLE_COM};
+
+/// The fixture zone example.com, whose name servers are bob and lola.ns.cloudflare.com.
+fn example_com() -> (CloudflareListZonesResponse, Vec<DNSRecord>) {
+    let zones: Vec<CloudflareListZonesResponse> =
+        serde_json::from_str(include_str!("../fixtures/zones.json")).expect("fixtures deserialize");
+    let zone = zones[0].clone();
+    assert_eq!(zone.id, EXAMPLE_COM);
+    (zone, common::example_com())
+}
+
+/// A record for a stand-in server.
+fn record(name: &str, ttl: u32, rdata: RData) -> Record {
+    Record::from_rdata(Name::from_str(name).expect("name parses"), ttl, rdata)
+}
+
+/// An MX record for example.com.
+fn mx(ttl: u32, exchange: &str) -> Record {
+    record(
+        "example.com.",
+        ttl,
+        RData::MX(MX::new(10, Name::from_str(exchange).expect("name parses"))),
+    )
+}
+
+/// A stand-in recursive resolver that knows where the name servers are, answering with `records`, and a
+/// stand-in for the name servers, answering with the `live` records. Returns the settings that ask them.
+fn servers(records: Vec<Record>, live: Vec<Record>) -> (MockDns, MockDns, PropagationSettings) {
+    let mut cached = vec![
+        record(
+            "bob.ns.cloudflare.com.",
+            300,
+            RData::A(A(Ipv4Addr::LOCALHOST)),
+        ),
+        record(
+            "lola.ns.cloudflare.com.",
+            300,
+            RData::A(A(Ipv4Addr::LOCALHOST)),
+        ),
+    ];
+    cached.extend(records);
+    let recursive = block_on(MockDns::start(cached)).expect("DNS server starts");
+    let authoritative = block_on(MockDns::start(live)).expect("DNS server starts");
+    let settings = PropagationSettings {
+        resolvers: vec![NamedResolver {
+            name: "Stub".to_string(),
+            address: recursive.address().to_string(),
+        }],
+        name_server_port: authoritative.address().port(),
+    };
+    (recursive, authoritative, settings)
+}
+
+#[test]
+fn answers_are_compared_with_the_cached_record() {
+    let (zone, records) = example_com();
+    let live = vec![
+        mx(3600, "mail.example.com."),
+        record(
+            "example.com.",
+            3600,
+            RData::TXT(TXT::new(vec!["v=spf1 mx -all".to_string()])),
+        ),
+        record(
+            "example.com.",
+            300,
+            RData::A(A(Ipv4Addr::new(104, 16, 0, 1))),
+        ),
+    ];
+    let (_recursive, _authoritative, settings) =
+        servers(vec![mx(120, "oldmail.example.com.")], live);
+
+    let report = block_on(check(&settings, &zone, &records[3])).expect("the check runs");
+    assert_eq!(report.record_type, "MX");
+    assert_eq!(report.answers.len(), 3);
+    let (name_servers, resolvers) = report.answers.split_at(2);
+    assert!(name_servers
+        .iter()
+        .all(|a| a.kind == ResolverKind::Authoritative && a.status == AnswerStatus::Agrees));
+    assert_eq!(resolvers[0].status, AnswerStatus::Differs);
+    assert_eq!(resolvers[0].answers, vec!["10 oldmail.example.com"]);
+    assert_eq!(
+        resolvers[0].ttl,
+        Some(120),
+        "the time left on the old answer"
+    );
+    assert!(report.consistent);
+    assert!((report.propagation - 200.0 / 3.0).abs() < 0.01);
+
+    // TXT records compare their text, and the resolver has none cached.
+    let report = block_on(check(&settings, &zone, &records[4])).expect("the check runs");
+    assert!(report.consistent, "{report:?}");
+    assert_eq!(report.answers[2].status, AnswerStatus::Missing);
+
+    // The proxied A record resolves to Cloudflare's addresses, which count.
+    let report = block_on(check(&settings, &zone, &records[0])).expect("the check runs");
+    assert!(report.consistent, "{report:?}");
+}
+
+#[test]
+fn servers_that_do_not_answer_are_reported_and_left_out() {
+    let (zone, records) = example_com();
+    let (recursive, _authoritative, mut settings) =
+        servers(vec![mx(3600, "mail.example.com.")], Vec::new());
+    // Nothing listens on the discard port.
+    settings.resolvers.push(NamedResolver {
+        name: "Down".to_string(),
+        address: "127.0.0.1:9".to_string(),
+    });
+
+    let report = block_on(check(&settings, &zone, &records[3])).expect("the check runs");
+    let down = report
+        .answers
+        .iter()
+        .find(|a| a.resolver == "Down")
+        .expect("the resolver is reported");
+    assert_eq!(down.status, AnswerStatus::Failed);
+    assert!(down.error.is_some());
+    assert!(
+        !report.consistent,
+        "the name servers don't have the record yet"
+    );
+    // The name servers are missing it, the stub resolver has it.
+    assert!((report.propagation - 100.0 / 3.0).abs() < 0.01);
+
+    settings.resolvers.pop();
+    recursive
+        .records()
+        .lock()
+        .expect("records are not poisoned")
+        .retain(|r| !r.name().to_string().starts_with("bob."));
+    let report = block_on(check(&settings, &zone, &records[3])).expect("the check runs");
+    let bob = &report.answers[0];
+    assert_eq!(bob.resolver, "bob.ns.cloudflare.com");
+    assert_eq!(bob.status, AnswerStatus::Failed);
+    assert!(bob.address.is_none());
+}
+
+#[test]
+fn resolvers_are_checked_and_saved() {
+    assert_eq!(
+        socket_addr("1.1.1.1", 53),
+        Some(SocketAddr::from(([1, 1, 1, 1], 53)))
+    );
+    assert_eq!(
+        socket_addr("[2606:4700:4700::1111]:5353", 53).map(|a| a.port()),
+        Some(5353)
+    );
+    assert!(socket_addr("2606:4700:4700::1111", 53).is_some());
+    assert!(socket_addr("dns.google", 53).is_none());
+
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let propagation = Propagation::load(dir.path());
+    assert_eq!(propagation.settings(), PropagationSettings::default());
+    let mut settings = PropagationSettings::default();
+    settings.resolvers.push(NamedResolver {
+        name: String::new(),
+        address: "dns.google".to_string(),
+    });
+    let error = propagation
+        .set(settings.clone())
+        .expect_err("the resolver is rejected");
+    assert_eq!(error.category, ErrorCategory::Validation);
+    assert_eq!(error.field_errors.len(), 2);
+
+    settings.resolvers.truncate(1);
+    propagation
+        .set(settings.clone())
+        .expect("settings are saved");
+    assert_eq!(Propagation::load(dir.path()).settings(), settings);
+}
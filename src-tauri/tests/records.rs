@@ -0,0 +1,227 @@
+//! Tests of reading and writing typed DNS records in Cloudflare's wire format.
+
+use std::net::Ipv4Addr;
+
+use cloudflare_dns_gui::{
+    cloudflare::{DNSRecord, DNSRecordPatch, DNSRecordRequest},
+    records::{DNSContent, LOCData, LatitudeDirection, LongitudeDirection, SRVData, URIData},
+};
+use serde_json::{json, Value};
+
+/// A record as Cloudflare returns it, with the given type-specific fields.
+fn wire_record(fields: &Value) -> Value {
+    let mut record = json!({
+        "name": "www.example.com",
+        "proxied": false,
+        "comment": null,
+        "created_on": "2014-01-01T05:20:00.12345Z",
+        "id": "023e105f4ecef8ad9ca31a8372d0c353",
+        "locked": false,
+        "meta": { "auto_added": false, "source": "primary" },
+        "modified_on": "2014-01-01T05:20:00.12345Z",
+        "proxiable": true,
+        "tags": [],
+        "ttl": 3600,
+        "zone_id": "023e105f4ecef8ad9ca31a8372d0c353",
+        "zone_name": "example.com",
+    });
+    for (key, value) in fields.as_object().expect("fields are an object") {
+        record[key] = value.clone();
+    }
+    record
+}
+
+/// Read a record, check its content, and check writing it gives the same content back.
+fn assert_round_trip(fields: &Value, expected: &DNSContent) {
+    let record: DNSRecord =
+        serde_json::from_value(wire_record(fields)).expect("record deserializes");
+    assert_eq!(&record.content, expected);
+
+    let written = serde_json::to_value(&record).expect("record serializes");
+    let reread: DNSRecord = serde_json::from_value(written).expect("record deserializes again");
+    assert_eq!(&reread.content, expected);
+    assert_eq!(reread.name, "www.example.com");
+    assert_eq!(reread.ttl, 3600);
+}
+
+#[test]
+fn content_records_round_trip() {
+    assert_round_trip(
+        &json!({ "type": "A", "content": "198.51.100.4" }),
+        &DNSContent::A {
+            content: Ipv4Addr::new(198, 51, 100, 4),
+        },
+    );
+    assert_round_trip(
+        &json!({ "type": "AAAA", "content": "2001:db8::4" }),
+        &DNSContent::Aaaa {
+            content: "2001:db8::4".parse().expect("address parses"),
+        },
+    );
+    assert_round_trip(
+        &json!({ "type": "MX", "content": "mail.example.com", "priority": 10 }),
+        &DNSContent::Mx {
+            content: "mail.example.com".to_string(),
+            priority: 10,
+        },
+    );
+    assert_round_trip(
+        &json!({ "type": "TXT", "content": "v=spf1 mx -all" }),
+        &DNSContent::Txt {
+            content: "v=spf1 mx -all".to_string(),
+        },
+    );
+}
+
+#[test]
+fn data_records_round_trip() {
+    assert_round_trip(
+        &json!({
+            "type": "SRV",
+            "content": "5 8806 sip.example.com",
+            "priority": 10,
+            "data": { "port": 8806, "priority": 10, "target": "sip.example.com", "weight": 5 },
+        }),
+        &DNSContent::Srv {
+            data: SRVData {
+                port: 8806,
+                priority: 10,
+                target: "sip.example.com".to_string(),
+                weight: 5,
+            },
+        },
+    );
+    assert_round_trip(
+        &json!({
+            "type": "URI",
+            "content": "1 \"https://example.com/\"",
+            "priority": 10,
+            "data": { "target": "https://example.com/", "weight": 1 },
+        }),
+        &DNSContent::Uri {
+            data: URIData {
+                target: "https://example.com/".to_string(),
+                weight: 1,
+            },
+            priority: 10,
+        },
+    );
+    assert_round_trip(
+        &json!({
+            "type": "LOC",
+            "content": "37 46 30.000 N 122 23 30.000 W 0.00m 100.00m 0.00m 0.00m",
+            "data": {
+                "altitude": 0, "lat_degrees": 37, "lat_direction": "N", "lat_minutes": 46, "lat_seconds": 30,
+                "long_degrees": 122, "long_direction": "W", "long_minutes": 23, "long_seconds": 30,
+                "precision_horz": 0, "precision_vert": 0, "size": 100,
+            },
+        }),
+        &DNSContent::Loc {
+            data: LOCData {
+                altitude: 0.0,
+                lat_degrees: 37,
+                lat_direction: LatitudeDirection::N,
+                lat_minutes: 46,
+                lat_seconds: 30.0,
+                long_degrees: 122,
+                long_direction: LongitudeDirection::W,
+                long_minutes: 23,
+                long_seconds: 30.0,
+                precision_horz: 0.0,
+                precision_vert: 0.0,
+                size: 100.0,
+            },
+        },
+    );
+}
+
+#[test]
+fn every_record_type_deserializes() {
+    let samples = [
+        json!({ "type": "CAA", "data": { "flags": 0, "tag": "issue", "value": "letsencrypt.org" } }),
+        json!({ "type": "CERT", "data": { "algorithm": 8, "certificate": "AAAA", "key_tag": 1, "type": 1 } }),
+        json!({ "type": "CNAME", "content": "example.com" }),
+        json!({ "type": "DNSKEY", "data": { "algorithm": 13, "flags": 257, "protocol": 3, "public_key": "AAAA" } }),
+        json!({ "type": "DS", "data": { "algorithm": 13, "digest": "ABCD", "digest_type": 2, "key_tag": 2371 } }),
+        json!({ "type": "HTTPS", "data": { "priority": 1, "target": ".", "value": "alpn=\"h3,h2\"" } }),
+        json!({ "type": "NAPTR", "data": {
+            "flags": "U", "order": 100, "preference": 10, "regex": "!^.*$!sip:info@example.com!",
+            "replacement": ".", "service": "E2U+sip",
+        } }),
+        json!({ "type": "NS", "content": "ns1.example.com" }),
+        json!({ "type": "OPENPGPKEY", "content": "AAAA" }),
+        json!({ "type": "PTR", "content": "example.com" }),
+        json!({ "type": "SMIMEA", "data": { "certificate": "ABCD", "matching_type": 1, "selector": 0, "usage": 3 } }),
+        json!({ "type": "SSHFP", "data": { "algorithm": 4, "fingerprint": "ABCD", "type": 2 } }),
+        json!({ "type": "SVCB", "data": { "priority": 1, "target": ".", "value": "port=\"8443\"" } }),
+        json!({ "type": "TLSA", "data": { "certificate": "ABCD", "matching_type": 1, "selector": 1, "usage": 3 } }),
+    ];
+    for sample in samples {
+        let record: DNSRecord = serde_json::from_value(wire_record(&sample))
+            .unwrap_or_else(|e| panic!("{} deserializes: {e}", sample["type"]));
+        assert_eq!(record.content.record_type(), sample["type"]);
+        let written = serde_json::to_value(&record).expect("record serializes");
+        assert_eq!(written["type"], sample["type"]);
+        assert_eq!(written.get("data"), sample.get("data"));
+    }
+}
+
+#[test]
+fn invalid_addresses_are_rejected() {
+    let error = serde_json::from_value::<DNSRecord>(wire_record(
+        &json!({ "type": "A", "content": "2001:db8::4" }),
+    ));
+    assert!(error.is_err());
+    let error = serde_json::from_value::<DNSRecord>(wire_record(
+        &json!({ "type": "MX", "content": "mail.example.com" }),
+    ));
+    assert!(error.is_err(), "MX records need a priority");
+}
+
+#[test]
+fn requests_are_written_in_the_wire_format() {
+    let request = DNSRecordRequest {
+        content: DNSContent::Mx {
+            content: "mail.example.com".to_string(),
+            priority: 10,
+        },
+        name: "example.com".to_string(),
+        proxied: None,
+        comment: None,
+        tags: vec![],
+        ttl: 1,
+    };
+    assert_eq!(
+        serde_json::to_value(&request).expect("request serializes"),
+        json!({
+            "type": "MX",
+            "content": "mail.example.com",
+            "priority": 10,
+            "name": "example.com",
+            "tags": [],
+            "ttl": 1,
+        })
+    );
+}
+
+#[test]
+fn patches_flatten_their_content() {
+    let patch: DNSRecordPatch = serde_json::from_value(json!({
+        "content": { "type": "A", "content": "203.0.113.10" },
+        "ttl": 300,
+    }))
+    .expect("patch deserializes");
+    assert_eq!(
+        serde_json::to_value(&patch).expect("patch serializes"),
+        json!({ "type": "A", "content": "203.0.113.10", "ttl": 300 })
+    );
+
+    let patch = DNSRecordPatch {
+        proxied: Some(true),
+        ..DNSRecordPatch::default()
+    };
+    assert_eq!(
+        serde_json::to_value(&patch).expect("patch serializes"),
+        json!({ "proxied": true })
+    );
+}
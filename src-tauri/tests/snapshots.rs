@@ -0,0 +1,114 @@
+//! Tests of saving zone snapshots and working out how to get back to them.
+
+use cloudflare_dns_gui::{
+    plan::Change,
+    records::DNSContent,
+    snapshots::{changes_between, SnapshotStore, ZoneSnapshot},
+};
+
+mod common;
+
+use common::example_com;
+
+/// A snapshot of `example.com` as it is in the fixtures.
+fn snapshot(name: &str) -> ZoneSnapshot {
+    ZoneSnapshot::new(
+        name,
+        "default",
+        "023e105f4ecef8ad9ca31a8372d0c353",
+        "example.com",
+        example_com(),
+    )
+    .expect("the snapshot has a name")
+}
+
+#[test]
+fn snapshots_are_saved_listed_and_removed() {
+    let dir = tempfile::tempdir().expect("temp dir is created");
+    let store = SnapshotStore::new(dir.path());
+    assert!(store.list(None).expect("no snapshots yet").is_empty());
+
+    let first = snapshot("before");
+    let mut second = snapshot("after");
+    second.taken_at = first.taken_at + chrono::Duration::minutes(5);
+    second.zone_id = "another-zone".to_string();
+    store.save(&first).expect("the snapshot is saved");
+    store.save(&second).expect("the snapshot is saved");
+
+    let names: Vec<_> = store
+        .list(None)
+        .expect("the snapshots are listed")
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    assert_eq!(names, ["after", "before"], "newest first");
+    let listed = store
+        .list(Some("023e105f4ecef8ad9ca31a8372d0c353"))
+        .expect("the snapshots are listed");
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].record_count, 5);
+    assert_eq!(
+        store
+            .load(&first.id)
+            .expect("the snapshot loads")
+            .records
+            .len(),
+        5
+    );
+
+    store.remove(&first.id).expect("the snapshot is removed");
+    assert!(store.load(&first.id).is_err());
+    assert!(
+        store.load("../cache").is_err(),
+        "only snapshot IDs are loaded"
+    );
+    assert!(ZoneSnapshot::new(" ", "default", "zone", "example.com", vec![]).is_err());
+}
+
+#[test]
+fn restoring_plans_the_fewest_changes() {
+    let snapshot = snapshot("before");
+    let mut live = example_com();
+    // Since the snapshot: the A record moved, the TXT record was deleted, and a record was added.
+    live[0].content = DNSContent::A {
+        content: "198.51.100.5".parse().expect("address parses"),
+    };
+    let mut added = live.remove(4);
+    added.id = "new".to_string();
+    added.name = "new.example.com".to_string();
+    live.push(added);
+
+    let plan = snapshot.restore_plan(&live);
+    let actions: Vec<_> = plan
+        .changes
+        .iter()
+        .map(|change| match change {
+            Change::Create { record } => ("create", record.name.as_str()),
+            Change::Update { before, .. } => ("update", before.name.as_str()),
+            Change::Delete { before } => ("delete", before.name.as_str()),
+        })
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            ("delete", "new.example.com"),
+            ("update", "example.com"),
+            ("create", "example.com"),
+        ]
+    );
+    assert_eq!(plan.unchanged, 3);
+
+    assert!(
+        changes_between("example.com", &snapshot.records, &example_com())
+            .changes
+            .is_empty()
+    );
+}
+
+#[test]
+fn locked_records_are_left_out() {
+    let mut live = example_com();
+    live[4].locked = true;
+    let plan = changes_between("example.com", &live, &example_com()[..4]);
+    assert!(plan.changes.is_empty(), "{:?}", plan.changes);
+}
@@ -0,0 +1,351 @@
+//! Tests of reading SPF records and following them, with a stub in place of DNS lookups.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use cloudflare_dns_gui::{
+    cloudflare::{CloudflareListZonesResponse, DNSRecord},
+    error::{AppError, ErrorCategory},
+    models::ManagedCache,
+    spf::{
+        analyze, is_spf, CachedLookup, Mechanism, Qualifier, Query, SpfLookup, SpfProblemKind,
+        SpfRecord, SpfReport, Term, MAX_RECORD_LENGTH,
+    },
+};
+use futures::future::BoxFuture;
+
+mod common;
+
+use common::block_on;
+
+/// Answers from fixed records, failing for names listed as down, and noting what was asked.
+#[derive(Debug, Default)]
+struct StubLookup {
+    /// The answers, by name and kind.
+    answers: HashMap<(String, Query), Vec<String>>,
+    /// Names whose lookups fail.
+    down: Vec<String>,
+    /// The lookups made, in order.
+    asked: Mutex<Vec<(String, Query)>>,
+}
+
+impl StubLookup {
+    /// A lookup answering `answers`.
+    fn new(answers: &[(&str, Query, &[&str])]) -> Self {
+        Self {
+            answers: answers
+                .iter()
+                .map(|(name, query, answers)| {
+                    (
+                        ((*name).to_string(), *query),
+                        answers.iter().map(ToString::to_string).collect(),
+                    )
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+}
+
+impl SpfLookup for StubLookup {
+    fn lookup(&self, name: &str, query: Query) -> BoxFuture<'_, Result<Vec<String>, AppError>> {
+        self.asked
+            .lock()
+            .expect("stub is not poisoned")
+            .push((name.to_string(), query));
+        let result = if self.down.iter().any(|down| down == name) {
+            Err(AppError::new(
+                ErrorCategory::Network,
+                format!("Can't look up {name}"),
+            ))
+        } else {
+            Ok(self
+                .answers
+                .get(&(name.to_string(), query))
+                .cloned()
+                .unwrap_or_default())
+        };
+        Box::pin(async move { result })
+    }
+}
+
+/// The kinds of the problems in `report`.
+fn kinds(report: &SpfReport) -> Vec<SpfProblemKind> {
+    report.problems.iter().map(|p| p.kind).collect()
+}
+
+#[test]
+fn records_are_parsed_and_written_back() {
+    let record = SpfRecord::parse(
+        r#""v=spf1 +a mx:mail.example.com/24//64 ~ip4:192.0.2.0/24 " "ip6:2001:db8::/32 include:_spf.example.net ?exists:%{i}.spf.example.com redirect=_spf.example.com""#,
+    )
+    .expect("the record parses");
+    assert_eq!(
+        record.terms[1],
+        Term::Mechanism {
+            qualifier: Qualifier::Pass,
+            mechanism: Mechanism::Mx {
+                domain: Some("mail.example.com".to_string()),
+                ip4_prefix: Some(24),
+                ip6_prefix: Some(64),
+            },
+        }
+    );
+    assert_eq!(record.redirect(), Some("_spf.example.com"));
+    assert_eq!(
+        record.to_string(),
+        "v=spf1 a mx:mail.example.com/24//64 ~ip4:192.0.2.0/24 ip6:2001:db8::/32 include:_spf.example.net ?exists:%{i}.spf.example.com redirect=_spf.example.com",
+        "quoted strings are joined"
+    );
+
+    assert!(is_spf("v=spf1 -all"));
+    assert!(is_spf("V=SPF1"));
+    assert!(!is_spf("v=spf10 -all"));
+    assert!(!is_spf("google-site-verification=abc"));
+
+    for invalid in [
+        "v=spf1 ip4:192.0.2.300",
+        "v=spf1 ip4:192.0.2.0/33",
+        "v=spf1 include",
+        "v=spf1 a:",
+        "v=spf1 mx/24/",
+        "v=spf1 allow",
+        "v=spf1 redirect=a.example redirect=b.example",
+        "spf1 -all",
+    ] {
+        let error = SpfRecord::parse(invalid).expect_err(invalid);
+        assert_eq!(error.category, ErrorCategory::Parse, "{invalid}");
+    }
+}
+
+#[test]
+fn lookups_are_counted_across_includes() {
+    let lookup = StubLookup::new(&[
+        (
+            "example.com",
+            Query::Txt,
+            &[
+                "v=spf1 a mx include:_spf.provider.example include:_spf.example.net ip4:192.0.2.1 -all",
+                "google-site-verification=abc",
+            ],
+        ),
+        ("example.com", Query::A, &["198.51.100.4"]),
+        ("example.com", Query::Mx, &["mail.example.com"]),
+        ("mail.example.com", Query::A, &["198.51.100.25"]),
+        (
+            "_spf.provider.example",
+            Query::Txt,
+            &["v=spf1 include:a.provider.example include:b.provider.example include:c.provider.example ~all"],
+        ),
+        ("a.provider.example", Query::Txt, &["v=spf1 ip4:203.0.113.0/26 a:x.provider.example a:y.provider.example ~all"]),
+        ("x.provider.example", Query::A, &["203.0.113.100"]),
+        ("y.provider.example", Query::A, &["203.0.113.101"]),
+        ("b.provider.example", Query::Txt, &["v=spf1 ip6:2001:db8:b::/48 mx:b.provider.example ~all"]),
+        ("b.provider.example", Query::Mx, &["mx1.provider.example"]),
+        ("mx1.provider.example", Query::Aaaa, &["2001:db8:b::25"]),
+        ("c.provider.example", Query::Txt, &["v=spf1 exists:%{i}._ip.provider.example -all"]),
+        ("_spf.example.net", Query::Txt, &["v=spf1 redirect=_spf.example.org"]),
+        ("_spf.example.org", Query::Txt, &["v=spf1 ip4:198.51.100.0/28 +all"]),
+    ]);
+
+    let report = block_on(analyze(&lookup, "Example.com."));
+    assert_eq!(report.domain, "example.com");
+    assert_eq!(report.records.len(), 1, "other TXT records are ignored");
+    // a, mx, two includes; the provider's three includes, two a and an mx, and an exists; a redirect.
+    assert_eq!(report.lookups, 12);
+    assert_eq!(report.void_lookups, 0);
+    let includes: Vec<(&str, usize, usize)> = report
+        .includes
+        .iter()
+        .map(|i| (i.domain.as_str(), i.depth, i.lookups))
+        .collect();
+    assert_eq!(
+        includes,
+        vec![
+            ("_spf.provider.example", 1, 7),
+            ("a.provider.example", 2, 2),
+            ("b.provider.example", 2, 1),
+            ("c.provider.example", 2, 1),
+            ("_spf.example.net", 1, 1),
+            ("_spf.example.org", 2, 0),
+        ]
+    );
+    assert!(report.includes[5].redirect);
+    assert_eq!(
+        kinds(&report),
+        vec![SpfProblemKind::PassAll, SpfProblemKind::TooManyLookups]
+    );
+    assert_eq!(report.problems[0].domain, "_spf.example.org");
+}
+
+#[test]
+fn broken_records_are_reported() {
+    let mut lookup = StubLookup::new(&[
+        (
+            "example.com",
+            Query::Txt,
+            &[
+                "v=spf1 include:loop.example.com a:missing.example.com mx:nomail.example.com include:gone.example include:down.example ptr -all",
+                "v=spf1 -all",
+            ],
+        ),
+        ("loop.example.com", Query::Txt, &["v=spf1 include:example.com -all"]),
+    ]);
+    lookup.down.push("down.example".to_string());
+
+    let report = block_on(analyze(&lookup, "example.com"));
+    assert_eq!(report.records.len(), 2);
+    assert_eq!(report.void_lookups, 3, "a, mx and the include find nothing");
+    assert_eq!(
+        kinds(&report),
+        vec![
+            SpfProblemKind::MultipleRecords,
+            SpfProblemKind::Loop,
+            SpfProblemKind::VoidLookup,
+            SpfProblemKind::VoidLookup,
+            SpfProblemKind::VoidLookup,
+            SpfProblemKind::NoRecord,
+            SpfProblemKind::LookupFailed,
+            SpfProblemKind::Ptr,
+            SpfProblemKind::TooManyVoidLookups,
+        ]
+    );
+
+    assert!(
+        report.flattened.is_none(),
+        "the record can't be flattened without the includes"
+    );
+
+    let report = block_on(analyze(&lookup, "nothing.example"));
+    assert_eq!(kinds(&report), vec![SpfProblemKind::NoRecord]);
+    assert!(report.flattened.is_none());
+}
+
+#[test]
+fn flattened_records_list_the_addresses() {
+    let lookup = StubLookup::new(&[
+        (
+            "example.com",
+            Query::Txt,
+            &["v=spf1 ~a/24 include:_spf.provider.example include:_spf.provider.example ip4:192.0.2.1 exists:%{i}.example.com -all exp=explain.example.com"],
+        ),
+        ("example.com", Query::A, &["198.51.100.4"]),
+        ("example.com", Query::Aaaa, &["2001:db8::4"]),
+        (
+            "_spf.provider.example",
+            Query::Txt,
+            &["v=spf1 -ip4:203.0.113.66 ip4:203.0.113.0/24 mx ?all"],
+        ),
+        ("_spf.provider.example", Query::Mx, &["mx.provider.example"]),
+        ("mx.provider.example", Query::A, &["192.0.2.1"]),
+    ]);
+
+    let report = block_on(analyze(&lookup, "example.com"));
+    assert_eq!(
+        report.flattened.as_deref(),
+        Some("v=spf1 ~ip4:198.51.100.4/24 ~ip6:2001:db8::4 ip4:203.0.113.0/24 ip4:192.0.2.1 exists:%{i}.example.com -all exp=explain.example.com"),
+    );
+    let flattened = SpfRecord::parse(report.flattened.as_deref().unwrap_or_default())
+        .expect("the flattened record parses");
+    assert_eq!(
+        flattened
+            .terms
+            .iter()
+            .filter(|t| matches!(t, Term::Mechanism { mechanism, .. } if mechanism.looks_up()))
+            .count(),
+        1,
+        "only exists is left to look up"
+    );
+}
+
+#[test]
+fn failed_lookups_are_not_void() {
+    let mut lookup = StubLookup::new(&[
+        (
+            "example.com",
+            Query::Txt,
+            &["v=spf1 a:web.example.com mx:mail.example.net include:_spf.example.net -all"],
+        ),
+        ("web.example.com", Query::A, &["198.51.100.4"]),
+    ]);
+    lookup.down = vec![
+        "mail.example.net".to_string(),
+        "_spf.example.net".to_string(),
+    ];
+
+    let report = block_on(analyze(&lookup, "example.com"));
+    assert_eq!(report.void_lookups, 0, "{:?}", report.problems);
+    assert_eq!(
+        kinds(&report),
+        vec![SpfProblemKind::LookupFailed, SpfProblemKind::LookupFailed]
+    );
+    assert!(
+        report.flattened.is_none(),
+        "a flattened record without the mail servers or the include would reject their mail"
+    );
+}
+
+#[test]
+fn flattened_records_too_long_to_publish_are_flagged() {
+    let addresses: Vec<String> = (0..40).map(|i| format!("198.51.100.{i}")).collect();
+    let addresses: Vec<&str> = addresses.iter().map(String::as_str).collect();
+    let lookup = StubLookup::new(&[
+        (
+            "example.com",
+            Query::Txt,
+            &["v=spf1 a:web.example.com -all"],
+        ),
+        ("web.example.com", Query::A, &addresses),
+    ]);
+
+    let report = block_on(analyze(&lookup, "example.com"));
+    assert_eq!(kinds(&report), vec![SpfProblemKind::FlattenedTooLong]);
+    assert!(report
+        .flattened
+        .is_some_and(|flattened| flattened.len() > MAX_RECORD_LENGTH));
+}
+
+#[test]
+fn cached_zones_are_answered_from_the_cache() {
+    let cache = ManagedCache::default();
+    let zones: Vec<CloudflareListZonesResponse> =
+        serde_json::from_str(include_str!("../fixtures/zones.json")).expect("fixtures deserialize");
+    *cache.zones.lock().expect("cache is not poisoned") = zones;
+    let records: HashMap<String, Vec<DNSRecord>> =
+        serde_json::from_str(include_str!("../fixtures/dns_records.json"))
+            .expect("fixtures deserialize");
+    for (zone_id, records) in records {
+        cache.put_zone_records(&zone_id, records);
+    }
+    let fallback = StubLookup::new(&[("example.com", Query::A, &["104.16.0.1"])]);
+    let lookup = CachedLookup {
+        cache: &cache,
+        fallback: &fallback,
+    };
+
+    // The fixture record is "v=spf1 mx -all", and mail.example.com has no addresses in the zone.
+    let report = block_on(analyze(&lookup, "example.com"));
+    assert_eq!(report.records, vec!["v=spf1 mx -all"]);
+    assert_eq!(report.lookups, 1);
+    assert!(report.problems.is_empty(), "{:?}", report.problems);
+    assert_eq!(report.flattened.as_deref(), Some("v=spf1 -all"));
+    assert!(
+        fallback
+            .asked
+            .lock()
+            .expect("stub is not poisoned")
+            .is_empty(),
+        "everything was in the cache"
+    );
+
+    // example.com's A record is proxied, so its address is looked up.
+    let answers = block_on(lookup.lookup("example.com", Query::A)).expect("the lookup works");
+    assert_eq!(answers, vec!["104.16.0.1"]);
+    let answers = block_on(lookup.lookup("example.net", Query::A)).expect("the lookup works");
+    assert_eq!(answers, vec!["203.0.113.10"]);
+    block_on(lookup.lookup("_spf.example.org", Query::Txt)).expect("the lookup works");
+    assert_eq!(
+        fallback.asked.lock().expect("stub is not poisoned").len(),
+        2,
+        "only the proxied name and the name outside the cache are looked up"
+    );
+}
@@ -0,0 +1,291 @@
+//! Tests of the checks run on DNS records before changes are sent to Cloudflare.
+
+use cloudflare_dns_gui::{
+    cloudflare::DNSRecordRequest,
+    error::FieldError,
+    records::{DNSContent, SRVData},
+    validation::{txt_chunks, validate_deletion, validate_record, ZoneContext},
+};
+
+mod common;
+
+use common::example_com;
+
+/// A request for a record with the given name and content, a TTL of 300 and no proxying.
+fn request(name: &str, content: DNSContent) -> DNSRecordRequest {
+    DNSRecordRequest {
+        content,
+        name: name.to_string(),
+        proxied: None,
+        comment: None,
+        tags: vec![],
+        ttl: 300,
+    }
+}
+
+/// Validate a new record in `example.com`.
+fn validate_new(request: &DNSRecordRequest) -> Vec<FieldError> {
+    let records = example_com();
+    let zone = ZoneContext {
+        zone_name: "example.com",
+        records: &records,
+    };
+    validate_record(request, &zone, None)
+}
+
+/// The fields with problems.
+fn fields(errors: &[FieldError]) -> Vec<Option<&str>> {
+    errors.iter().map(|e| e.field.as_deref()).collect()
+}
+
+/// An A record with the given address.
+fn a(address: &str) -> DNSContent {
+    DNSContent::A {
+        content: address.parse().expect("address parses"),
+    }
+}
+
+#[test]
+fn valid_records_pass() {
+    assert_eq!(
+        validate_new(&request("api.example.com", a("198.51.100.7"))),
+        []
+    );
+    assert_eq!(
+        validate_new(&request("*.example.com", a("198.51.100.7"))),
+        []
+    );
+    assert_eq!(validate_new(&request("@", a("198.51.100.7"))), []);
+    assert_eq!(
+        validate_new(&request(
+            "_sip._tcp.example.com",
+            DNSContent::Srv {
+                data: SRVData {
+                    port: 5060,
+                    priority: 10,
+                    target: "sip.example.com".to_string(),
+                    weight: 5,
+                },
+            },
+        )),
+        []
+    );
+}
+
+#[test]
+fn ttl_must_be_automatic_or_in_range() {
+    for ttl in [1, 60, 86400] {
+        let record = DNSRecordRequest {
+            ttl,
+            ..request("api.example.com", a("198.51.100.7"))
+        };
+        assert_eq!(validate_new(&record), [], "TTL {ttl} is valid");
+    }
+    for ttl in [0, 30, 86401] {
+        let record = DNSRecordRequest {
+            ttl,
+            ..request("api.example.com", a("198.51.100.7"))
+        };
+        assert_eq!(
+            fields(&validate_new(&record)),
+            [Some("ttl")],
+            "TTL {ttl} is invalid"
+        );
+    }
+}
+
+#[test]
+fn names_must_be_valid_and_in_the_zone() {
+    for name in [
+        "example.net",
+        "notexample.com",
+        "api..example.com",
+        "-api.example.com",
+        "api example.com",
+        "bücher.example.com",
+        "www.*.example.com",
+    ] {
+        let errors = validate_new(&request(name, a("198.51.100.7")));
+        assert_eq!(fields(&errors), [Some("name")], "{name} is invalid");
+    }
+}
+
+#[test]
+fn only_proxiable_records_can_be_proxied() {
+    let txt = DNSRecordRequest {
+        proxied: Some(true),
+        ..request(
+            "example.com",
+            DNSContent::Txt {
+                content: "hello".to_string(),
+            },
+        )
+    };
+    assert_eq!(fields(&validate_new(&txt)), [Some("proxied")]);
+
+    let records = example_com();
+    let zone = ZoneContext {
+        zone_name: "example.com",
+        records: &records,
+    };
+    let mut unproxiable = records[0].clone();
+    unproxiable.proxiable = false;
+    let proxied = DNSRecordRequest {
+        proxied: Some(true),
+        ..request("example.com", a("198.51.100.4"))
+    };
+    assert_eq!(
+        fields(&validate_record(&proxied, &zone, Some(&unproxiable))),
+        [Some("proxied")]
+    );
+}
+
+#[test]
+fn cnames_cannot_share_a_name() {
+    // www.example.com already has a CNAME record.
+    let errors = validate_new(&request("www.example.com", a("198.51.100.7")));
+    assert_eq!(fields(&errors), [Some("name")]);
+
+    // The apex already has other records.
+    let cname = DNSContent::Cname {
+        content: "other.example.net".to_string(),
+    };
+    let errors = validate_new(&request("example.com", cname.clone()));
+    assert_eq!(fields(&errors), [Some("name")]);
+    assert!(errors[0].message.contains("A, AAAA, MX, TXT"));
+
+    // Replacing the CNAME itself is fine.
+    let records = example_com();
+    let zone = ZoneContext {
+        zone_name: "example.com",
+        records: &records,
+    };
+    let existing = records
+        .iter()
+        .find(|r| r.name == "www.example.com")
+        .expect("www is in the fixtures");
+    assert_eq!(
+        validate_record(&request("www.example.com", cname), &zone, Some(existing)),
+        []
+    );
+}
+
+#[test]
+fn addresses_must_be_usable() {
+    for address in ["0.0.0.0", "255.255.255.255", "224.0.0.1"] {
+        let errors = validate_new(&request("api.example.com", a(address)));
+        assert_eq!(fields(&errors), [Some("content")], "{address} is rejected");
+    }
+    for address in ["::", "ff02::1", "::ffff:198.51.100.7"] {
+        let aaaa = DNSContent::Aaaa {
+            content: address.parse().expect("address parses"),
+        };
+        let errors = validate_new(&request("api.example.com", aaaa));
+        assert_eq!(fields(&errors), [Some("content")], "{address} is rejected");
+    }
+}
+
+#[test]
+fn txt_strings_must_fit_in_255_bytes() {
+    let txt = |content: String| request("example.com", DNSContent::Txt { content });
+
+    let long = "a".repeat(300);
+    assert_eq!(fields(&validate_new(&txt(long.clone()))), [Some("content")]);
+
+    let split = format!("\"{}\" \"{}\"", &long[..200], &long[200..]);
+    assert_eq!(validate_new(&txt(split)), []);
+
+    let too_long_chunk = format!("\"{}\" \"b\"", "a".repeat(256));
+    assert_eq!(
+        fields(&validate_new(&txt(too_long_chunk))),
+        [Some("content")]
+    );
+
+    assert_eq!(
+        txt_chunks(r#""v=DKIM1; " "p=\"quoted\"""#),
+        ["v=DKIM1; ", "p=\"quoted\""]
+    );
+}
+
+#[test]
+fn mail_and_service_targets_must_be_host_names() {
+    let mx = |content: &str, priority| {
+        request(
+            "example.com",
+            DNSContent::Mx {
+                content: content.to_string(),
+                priority,
+            },
+        )
+    };
+    assert_eq!(validate_new(&mx("mx2.example.net", 20)), []);
+    assert_eq!(validate_new(&mx(".", 0)), [], "null MX");
+    assert_eq!(fields(&validate_new(&mx(".", 10))), [Some("priority")]);
+    assert_eq!(
+        fields(&validate_new(&mx("198.51.100.4", 10))),
+        [Some("content")]
+    );
+    // www.example.com is a CNAME.
+    assert_eq!(
+        fields(&validate_new(&mx("www.example.com", 10))),
+        [Some("content")]
+    );
+
+    let srv = |name: &str, port, target: &str| {
+        request(
+            name,
+            DNSContent::Srv {
+                data: SRVData {
+                    port,
+                    priority: 10,
+                    target: target.to_string(),
+                    weight: 5,
+                },
+            },
+        )
+    };
+    assert_eq!(
+        fields(&validate_new(&srv(
+            "sip.example.com",
+            5060,
+            "sip.example.com"
+        ))),
+        [Some("name")]
+    );
+    assert_eq!(
+        fields(&validate_new(&srv(
+            "_sip._tcp.example.com",
+            0,
+            "sip.example.com"
+        ))),
+        [Some("data.port")]
+    );
+    assert_eq!(
+        fields(&validate_new(&srv(
+            "_sip._tcp.example.com",
+            5060,
+            "www.example.com"
+        ))),
+        [Some("data.target")]
+    );
+}
+
+#[test]
+fn locked_records_cannot_be_changed() {
+    let records = example_com();
+    let zone = ZoneContext {
+        zone_name: "example.com",
+        records: &records,
+    };
+    let mut locked = records[0].clone();
+    locked.locked = true;
+
+    let errors = validate_record(
+        &request("example.com", a("198.51.100.5")),
+        &zone,
+        Some(&locked),
+    );
+    assert_eq!(fields(&errors), [None]);
+    assert_eq!(fields(&validate_deletion(&locked)), [None]);
+    assert_eq!(validate_deletion(&records[0]), []);
+}
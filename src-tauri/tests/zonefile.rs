@@ -0,0 +1,269 @@
+//! Tests of reading and writing zones as zone files.
+
+use chrono::{TimeZone, Utc};
+use cloudflare_dns_gui::{
+    records::{DNSContent, SRVData},
+    zonefile,
+};
+
+mod common;
+
+use common::example_com;
+
+/// The rdata of a TXT record with the given content.
+fn txt(content: &str) -> String {
+    zonefile::rdata(&DNSContent::Txt {
+        content: content.to_string(),
+    })
+}
+
+#[test]
+fn fixture_zone_renders() {
+    let exported_at = Utc
+        .with_ymd_and_hms(2024, 5, 1, 12, 0, 0)
+        .single()
+        .expect("the date is valid");
+    assert_eq!(
+        zonefile::render("example.com", &example_com(), exported_at),
+        ";; Zone: example.com
+;; Exported: 2024-05-01T12:00:00+00:00
+;; The SOA and apex NS records are managed by Cloudflare.
+
+$ORIGIN example.com.
+$TTL 1
+
+@\t\tIN\tA\t198.51.100.4 ; Web server cf_tags=cf-proxied:true
+@\t\tIN\tAAAA\t2001:db8::4 ; cf_tags=cf-proxied:true
+@\t3600\tIN\tMX\t10 mail.example.com.
+@\t3600\tIN\tTXT\t\"v=spf1 mx -all\" ; cf_tags=owner:mail
+www\t\tIN\tCNAME\texample.com. ; cf_tags=cf-proxied:true
+"
+    );
+}
+
+#[test]
+fn apex_name_servers_are_left_out() {
+    let mut records = example_com();
+    for (i, name) in ["example.com", "sub.example.com"].into_iter().enumerate() {
+        let mut ns = records[0].clone();
+        ns.id = format!("ns-{i}");
+        ns.name = name.to_string();
+        ns.ttl = 86400;
+        ns.content = DNSContent::Ns {
+            content: "ns1.example.net".to_string(),
+        };
+        records.push(ns);
+    }
+    let rendered = zonefile::render("example.com", &records, Utc::now());
+    assert!(!rendered.contains("@\t86400\tIN\tNS"), "{rendered}");
+    assert!(
+        rendered.contains("\nsub\t86400\tIN\tNS\tns1.example.net. ;"),
+        "{rendered}"
+    );
+}
+
+#[test]
+fn names_outside_the_zone_stay_absolute() {
+    let mut records = example_com();
+    records[0].name = "deep.sub.example.com".to_string();
+    records[1].name = "example.com.evil.net".to_string();
+    let rendered = zonefile::render("example.com", &records, Utc::now());
+    assert!(rendered.contains("\ndeep.sub\t"));
+    assert!(rendered.contains("\nexample.com.evil.net.\t"));
+}
+
+#[test]
+fn txt_strings_are_quoted_and_split() {
+    assert_eq!(txt(r#"say "hi" \ bye"#), r#""say \"hi\" \\ bye""#);
+    assert_eq!(txt("tab\there"), r#""tab\009here""#);
+    assert_eq!(
+        txt(r#""v=DKIM1; " "p=abc""#),
+        r#""v=DKIM1; " "p=abc""#,
+        "already quoted strings are kept"
+    );
+
+    let long = "a".repeat(300);
+    assert_eq!(
+        txt(&long),
+        format!("\"{}\" \"{}\"", "a".repeat(255), "a".repeat(45))
+    );
+}
+
+#[test]
+fn structured_records_render() {
+    let srv: DNSContent = serde_json::from_value(serde_json::json!({
+        "type": "SRV",
+        "data": { "port": 5060, "priority": 10, "target": "sip.example.com", "weight": 5 },
+    }))
+    .expect("SRV deserializes");
+    assert_eq!(zonefile::rdata(&srv), "10 5 5060 sip.example.com.");
+
+    let caa: DNSContent = serde_json::from_value(serde_json::json!({
+        "type": "CAA",
+        "data": { "flags": 0, "tag": "issue", "value": "letsencrypt.org" },
+    }))
+    .expect("CAA deserializes");
+    assert_eq!(zonefile::rdata(&caa), "0 issue \"letsencrypt.org\"");
+
+    let loc: DNSContent = serde_json::from_value(serde_json::json!({
+        "type": "LOC",
+        "data": {
+            "altitude": 10, "lat_degrees": 37, "lat_direction": "N", "lat_minutes": 46, "lat_seconds": 30,
+            "long_degrees": 122, "long_direction": "W", "long_minutes": 23, "long_seconds": 30.5,
+            "precision_horz": 10000, "precision_vert": 10, "size": 1,
+        },
+    }))
+    .expect("LOC deserializes");
+    assert_eq!(
+        zonefile::rdata(&loc),
+        "37 46 30.000 N 122 23 30.500 W 10.00m 1.00m 10000.00m 10.00m"
+    );
+}
+
+#[test]
+fn rendered_zones_read_back() {
+    let records = example_com();
+    let parsed = zonefile::parse(
+        &zonefile::render("example.com", &records, Utc::now()),
+        "example.com",
+        false,
+    )
+    .expect("the rendered zone parses");
+    assert_eq!(parsed.records.len(), records.len());
+    assert!(parsed.skipped.is_empty());
+    for record in &records {
+        let read = parsed
+            .records
+            .iter()
+            .find(|r| r.content == record.content)
+            .unwrap_or_else(|| panic!("{} is read back", record.content.record_type()));
+        assert_eq!(read.name, record.name);
+        assert_eq!(read.ttl, record.ttl);
+        assert_eq!(read.tags, record.tags);
+        assert_eq!(read.comment, record.comment);
+        if record.proxiable {
+            assert_eq!(read.proxied, record.proxied);
+        }
+    }
+}
+
+#[test]
+fn zone_file_syntax_is_read() {
+    let parsed = zonefile::parse(
+        r#"$ORIGIN example.com.
+$TTL 1h
+@   IN  SOA ns1.example.com. admin.example.com. (
+            2024050101 ; serial
+            7200 3600 1209600 3600 )
+    IN  NS  ns1.example.com.
+api 300 A   198.51.100.7 ; cf_tags=cf-proxied:true,team:api
+    IN  AAAA 2001:db8::7
+_sip._tcp 1d IN SRV 10 5 5060 sip
+txt IN  TXT "v=DKIM1; " "p=\"quoted\"" ; DKIM key
+$ORIGIN sub.example.com.
+www CNAME @
+"#,
+        "example.com",
+        true,
+    )
+    .expect("the zone parses");
+
+    let records: Vec<_> = parsed
+        .records
+        .iter()
+        .map(|r| (r.name.as_str(), r.ttl, r.content.clone(), r.proxied))
+        .collect();
+    assert_eq!(
+        records,
+        [
+            (
+                "api.example.com",
+                300,
+                DNSContent::A {
+                    content: "198.51.100.7".parse().expect("address parses")
+                },
+                Some(true)
+            ),
+            (
+                "api.example.com",
+                3600,
+                DNSContent::Aaaa {
+                    content: "2001:db8::7".parse().expect("address parses")
+                },
+                Some(true)
+            ),
+            (
+                "_sip._tcp.example.com",
+                86400,
+                DNSContent::Srv {
+                    data: SRVData {
+                        port: 5060,
+                        priority: 10,
+                        target: "sip.example.com".to_string(),
+                        weight: 5,
+                    }
+                },
+                None
+            ),
+            (
+                "txt.example.com",
+                3600,
+                DNSContent::Txt {
+                    content: r#""v=DKIM1; " "p=\"quoted\"""#.to_string()
+                },
+                None
+            ),
+            (
+                "www.sub.example.com",
+                3600,
+                DNSContent::Cname {
+                    content: "sub.example.com".to_string()
+                },
+                Some(true)
+            ),
+        ]
+    );
+    assert_eq!(parsed.records[0].tags, ["team:api"]);
+    assert_eq!(parsed.records[3].comment.as_deref(), Some("DKIM key"));
+    assert_eq!(parsed.skipped.len(), 2, "the SOA and apex NS are skipped");
+    assert!(parsed.skipped[0].starts_with("line 3: SOA"));
+}
+
+#[test]
+fn zone_file_errors_name_their_lines() {
+    let error = zonefile::parse(
+        "$INCLUDE other.zone
+api IN A 198.51.100.300
+api.example.net. IN A 198.51.100.7
+api CH A 198.51.100.7
+api IN WKS 198.51.100.7 tcp smtp
+api IN MX 10
+",
+        "example.com",
+        false,
+    )
+    .expect_err("the zone is invalid");
+    let fields: Vec<_> = error
+        .field_errors
+        .iter()
+        .map(|e| e.field.as_deref())
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            Some("line 1"),
+            Some("line 2"),
+            Some("line 3"),
+            Some("line 4"),
+            Some("line 5"),
+            Some("line 6")
+        ]
+    );
+
+    let error = zonefile::parse("api IN TXT \"unclosed\n", "example.com", false)
+        .expect_err("the quote isn't closed");
+    assert_eq!(error.field_errors[0].field.as_deref(), Some("line 1"));
+    let error = zonefile::parse("@ IN SOA (\n1 2 3\n", "example.com", false)
+        .expect_err("the parenthesis isn't closed");
+    assert_eq!(error.field_errors[0].field.as_deref(), Some("line 1"));
+}
@@ -1,11 +1,40 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { createEffect, createSignal, For, type JSX, Show } from "solid-js";
+import type { AppError } from "../src-tauri/bindings/AppError";
+import type { Change } from "../src-tauri/bindings/Change";
+import type { ChangePlan } from "../src-tauri/bindings/ChangePlan";
+import type { ChangeResult } from "../src-tauri/bindings/ChangeResult";
+import type { DdnsSettings } from "../src-tauri/bindings/DdnsSettings";
+import type { DesiredStatePlan } from "../src-tauri/bindings/DesiredStatePlan";
+import type { DNSRecordImportResponse } from "../src-tauri/bindings/DNSRecordImportResponse";
+import type { DynamicRecord } from "../src-tauri/bindings/DynamicRecord";
+import type { EmailPosture } from "../src-tauri/bindings/EmailPosture";
+import type { GeneratedRecord } from "../src-tauri/bindings/GeneratedRecord";
+import type { ImportPreview } from "../src-tauri/bindings/ImportPreview";
+import type { JournalEntry } from "../src-tauri/bindings/JournalEntry";
+import type { PlanOptions } from "../src-tauri/bindings/PlanOptions";
+import type { PropagationReport } from "../src-tauri/bindings/PropagationReport";
+import type { SnapshotSummary } from "../src-tauri/bindings/SnapshotSummary";
+import type { SpfReport } from "../src-tauri/bindings/SpfReport";
+import type { ThrottleEvent } from "../src-tauri/bindings/ThrottleEvent";
+import type { ThrottleReason } from "../src-tauri/bindings/ThrottleReason";
+import type { ZoneAudit } from "../src-tauri/bindings/ZoneAudit";
+import type { ZoneDrift } from "../src-tauri/bindings/ZoneDrift";
 import Navbar from "./Navbar";
 import { useTokenProvider } from "./TokenProvider";
 import { useZone } from "./ZoneProvider";
 
+const THROTTLE_MESSAGES: Record<ThrottleReason, string> = {
+	budget: "Slowing down to stay within Cloudflare's rate limit",
+	rate_limited: "Cloudflare is throttling requests",
+	server: "Cloudflare failed to respond",
+	network: "Cloudflare couldn't be reached",
+};
+
 function App() {
 	const { apiToken, userDetails } = useTokenProvider();
-	const { zones, zoneDns } = useZone();
+	const { zones, zoneDns, refetchZoneDns, progress } = useZone();
 
 	const [toast, setToast] = createSignal<JSX.Element>(<div />);
 	const putToast = (token: JSX.Element) => {
@@ -15,6 +44,353 @@ function App() {
 		}, 5_000);
 	};
 
+	listen<ThrottleEvent>("api-throttled", (event) => {
+		const { reason, attempt, retry_in_ms } = event.payload;
+		putToast(
+			<div class="alert alert-warning">
+				<div>
+					{THROTTLE_MESSAGES[reason]}, retrying in {Math.ceil(retry_in_ms / 1000)}s
+					{attempt > 0 ? ` (attempt ${attempt})` : ""}.
+				</div>
+			</div>,
+		);
+	});
+
+	listen<ZoneDrift>("zone-drift", (event) => {
+		const { zone_name, added, removed, changed } = event.payload;
+		putToast(
+			<div class="alert alert-info">
+				<div>
+					{zone_name} was changed outside the app: {added.length} added, {changed.length} changed,{" "}
+					{removed.length} removed.
+				</div>
+			</div>,
+		);
+	});
+
+	listen<DynamicRecord[]>("ddns-updated", () => {
+		refetchZoneDns();
+	});
+
+	const exportZone = async (zoneId: string, zoneName: string) => {
+		const path = prompt(`Save the zone file for ${zoneName} to:`, `${zoneName}.zone`);
+		if (!path) return;
+		try {
+			await invoke("export_zone_file", { zoneId, path });
+			putToast(<div class="alert alert-success">Exported {zoneName} to {path}.</div>);
+		} catch (error) {
+			putToast(<div class="alert alert-error">Export failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	// Show what a plan changes, and apply it if the user agrees
+	const confirmAndApply = async (zoneId: string, zoneName: string, plan: ChangePlan, notes: string[] = []) => {
+		if (plan.changes.length === 0) {
+			putToast(<div class="alert alert-info">{zoneName} is already up to date.</div>);
+			return;
+		}
+		const count = (action: Change["action"]) => plan.changes.filter((change) => change.action === action).length;
+		const summary = [
+			`${count("create")} to create, ${count("update")} to update, ${count("delete")} to delete`,
+			`${plan.unchanged} unchanged, ${plan.kept} left as they are`,
+			...notes,
+		].join("\n");
+		if (!confirm(`Apply these changes to ${zoneName}?\n\n${summary}`)) return;
+
+		const results = await invoke<ChangeResult[]>("apply_zone_plan", { zoneId, plan });
+		refetchZoneDns();
+		const failed = results.filter((result) => result.error);
+		putToast(
+			<div class={failed.length > 0 ? "alert alert-warning" : "alert alert-success"}>
+				<div class="flex flex-col gap-1">
+					<div>
+						Applied {results.length - failed.length} of {results.length} changes to {zoneName}.
+					</div>
+					<For each={failed}>{(result) => <div>{result.error?.message}</div>}</For>
+				</div>
+			</div>,
+		);
+	};
+
+	const importZone = async (zoneId: string, zoneName: string) => {
+		const path = prompt(`Import a zone file into ${zoneName} from:`, `${zoneName}.zone`);
+		if (!path) return;
+		const proxiedDefault = confirm("Proxy A, AAAA and CNAME records that don't say whether they are proxied?");
+		try {
+			if (confirm("Let Cloudflare import the file? It only adds records that don't exist yet.")) {
+				const imported = await invoke<DNSRecordImportResponse>("import_zone_file_via_cloudflare", {
+					zoneId,
+					path,
+					proxied: proxiedDefault,
+				});
+				refetchZoneDns();
+				putToast(
+					<div class="alert alert-success">
+						Cloudflare added {imported.recs_added} of {imported.total_records_parsed} records to {zoneName}.
+					</div>,
+				);
+				return;
+			}
+
+			const options: PlanOptions = {
+				on_conflict: confirm("Overwrite records that differ from the file?") ? "overwrite" : "keep",
+				delete_missing: confirm("Delete records that aren't in the file?"),
+			};
+			const preview = await invoke<ImportPreview>("preview_zone_import", { zoneId, path, proxiedDefault, options });
+			await confirmAndApply(
+				zoneId,
+				zoneName,
+				preview.plan,
+				preview.skipped.map((skipped) => `Skipped ${skipped}`),
+			);
+		} catch (error) {
+			putToast(<div class="alert alert-error">Import failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	const applyDesiredState = async () => {
+		const path = prompt("Plan the changes from the desired state file at:", "dns.yaml");
+		if (!path) return;
+		try {
+			const planned = await invoke<DesiredStatePlan>("plan_desired_state", { path });
+			await confirmAndApply(planned.zone_id, planned.zone_name, planned.plan);
+		} catch (error) {
+			putToast(<div class="alert alert-error">Planning failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	// List the recent changes to a zone, and undo the one the user picks
+	const showHistory = async (zoneId: string, zoneName: string) => {
+		try {
+			const history = (await invoke<JournalEntry[]>("list_history", { zoneId })).slice(0, 10);
+			if (history.length === 0) {
+				putToast(<div class="alert alert-info">No changes to {zoneName} have been made here yet.</div>);
+				return;
+			}
+			const lines = history.map((entry, i) => {
+				const record = entry.after ?? entry.before;
+				const undo = entry.undoes ? " (undo)" : "";
+				return `${i + 1}. ${new Date(entry.at).toLocaleString()} ${entry.action}${undo} ${record?.type} ${record?.name}`;
+			});
+			const picked = prompt(`Recent changes to ${zoneName}:\n\n${lines.join("\n")}\n\nUndo which change?`);
+			const entry = picked ? history[Number(picked) - 1] : undefined;
+			if (!entry) return;
+			await invoke("undo_change", { entryId: entry.id });
+			refetchZoneDns();
+			putToast(<div class="alert alert-success">Undid the change to {zoneName}.</div>);
+		} catch (error) {
+			putToast(<div class="alert alert-error">Undo failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	// Take a new snapshot of a zone, or compare the zone with an earlier one and restore it
+	const manageSnapshots = async (zoneId: string, zoneName: string) => {
+		try {
+			const snapshots = await invoke<SnapshotSummary[]>("list_snapshots", { zoneId });
+			const lines = snapshots.map(
+				(snapshot, i) =>
+					`${i + 1}. ${snapshot.name} (${new Date(snapshot.taken_at).toLocaleString()}, ${snapshot.record_count} records)`,
+			);
+			const picked = prompt(
+				`Snapshots of ${zoneName}:\n\n${lines.join("\n") || "None yet"}\n\nEnter a number to restore that snapshot, or a name to take a new one:`,
+			);
+			if (!picked) return;
+			const snapshot = snapshots[Number(picked) - 1];
+			if (!snapshot) {
+				const taken = await invoke<SnapshotSummary>("take_snapshot", { zoneId, name: picked });
+				putToast(
+					<div class="alert alert-success">
+						Saved {taken.record_count} records of {zoneName} as {taken.name}.
+					</div>,
+				);
+				return;
+			}
+			const plan = await invoke<ChangePlan>("plan_snapshot_restore", { snapshotId: snapshot.id });
+			refetchZoneDns();
+			await confirmAndApply(zoneId, zoneName, plan, [`Restores ${snapshot.name}`]);
+		} catch (error) {
+			putToast(<div class="alert alert-error">Snapshot failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	const manageDdns = async (zoneId: string, zoneName: string) => {
+		try {
+			const settings = await invoke<DdnsSettings>("get_ddns_settings");
+			const records = (zoneDns.latest[zoneId] ?? []).flatMap((r) =>
+				r.type === "A" || r.type === "AAAA" ? [{ id: r.id, name: r.name, type: r.type, content: r.content }] : [],
+			);
+			const lines = records.map((record, i) => {
+				const dynamic = settings.records.find((d) => d.zone_id === zoneId && d.record_id === record.id);
+				const status = dynamic
+					? dynamic.status.error
+						? ` - dynamic, failing: ${dynamic.status.error.message}`
+						: ` - dynamic${dynamic.status.last_run ? `, checked ${new Date(dynamic.status.last_run).toLocaleString()}` : ""}`
+					: "";
+				return `${i + 1}. ${record.type} ${record.name} ${record.content}${status}`;
+			});
+			const picked = prompt(
+				`Address records of ${zoneName}:\n\n${lines.join("\n") || "None"}\n\nEnter a number to start or stop keeping that record pointed at this network's public address, or "run" to update the dynamic records now:`,
+			);
+			if (!picked) return;
+			if (picked.trim().toLowerCase() === "run") {
+				const updated = await invoke<DynamicRecord[]>("run_ddns");
+				const failed = updated.filter((d) => d.status.error);
+				refetchZoneDns();
+				putToast(
+					<div class={failed.length > 0 ? "alert alert-warning" : "alert alert-success"}>
+						Checked {updated.length} dynamic records{failed.length > 0 ? `, ${failed.length} failed` : ""}.
+					</div>,
+				);
+				return;
+			}
+			const record = records[Number(picked) - 1];
+			if (!record) return;
+			const dynamic = !settings.records.some((d) => d.zone_id === zoneId && d.record_id === record.id);
+			await invoke<DdnsSettings>("set_record_dynamic", { zoneId, recordId: record.id, dynamic });
+			putToast(
+				<div class="alert alert-success">
+					{record.name} {dynamic ? "now follows the public address" : "no longer follows the public address"}.
+				</div>,
+			);
+		} catch (error) {
+			putToast(<div class="alert alert-error">DDNS failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	const checkPropagation = async (zoneId: string, zoneName: string) => {
+		const records = zoneDns.latest[zoneId] ?? [];
+		const picked = prompt(
+			`Records of ${zoneName}:\n\n${records.map((r, i) => `${i + 1}. ${r.type} ${r.name} ${r.content}`).join("\n") || "None"}\n\nEnter a number to check whether that record has reached the name servers and public resolvers:`,
+		);
+		const record = records[Number(picked) - 1];
+		if (!record) return;
+		try {
+			const report = await invoke<PropagationReport>("check_propagation", { zoneId, recordId: record.id });
+			putToast(
+				<div class={report.consistent ? "alert alert-success" : "alert alert-warning"}>
+					<div class="flex flex-col gap-1">
+						<div>
+							{report.record_type} {report.name}: {Math.round(report.propagation)}% propagated
+							{report.consistent ? "" : ", the name servers disagree"}.
+						</div>
+						<For each={report.answers}>
+							{(answer) => (
+								<div>
+									{answer.resolver}: {answer.status}
+									{answer.answers.length > 0 ? ` (${answer.answers.join(", ")})` : ""}
+									{answer.ttl !== null && answer.status === "differs" ? `, expires in ${answer.ttl}s` : ""}
+									{answer.error ? ` - ${answer.error}` : ""}
+								</div>
+							)}
+						</For>
+					</div>
+				</div>,
+			);
+		} catch (error) {
+			putToast(<div class="alert alert-error">Propagation check failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	const analyzeSpf = async (zoneId: string, zoneName: string) => {
+		const name = prompt(`Name in ${zoneName} whose SPF record to check:`, zoneName);
+		if (!name) return;
+		try {
+			const report = await invoke<SpfReport>("analyze_spf", { zoneId, name });
+			putToast(
+				<div class={report.problems.length > 0 ? "alert alert-warning" : "alert alert-success"}>
+					<div class="flex flex-col gap-1">
+						<div>
+							SPF for {report.domain}: {report.lookups} of 10 lookups, {report.void_lookups} finding nothing.
+						</div>
+						<For each={report.includes}>
+							{(include) => (
+								<div>
+									{"  ".repeat(include.depth)}
+									{include.redirect ? "redirect" : "include"} {include.domain}: {include.lookups} lookups
+								</div>
+							)}
+						</For>
+						<For each={report.problems}>{(problem) => <div>{problem.message}</div>}</For>
+						<Show when={report.flattened}>
+							<div>Flattened: {report.flattened}</div>
+						</Show>
+					</div>
+				</div>,
+			);
+		} catch (error) {
+			putToast(<div class="alert alert-error">SPF check failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	const checkEmailAuth = async (zoneId: string) => {
+		try {
+			const posture = await invoke<EmailPosture>("check_email_auth", { zoneId });
+			putToast(
+				<div class={posture.score < 60 ? "alert alert-warning" : "alert alert-success"}>
+					<div class="flex flex-col gap-1">
+						<div>
+							Email authentication for {posture.zone_name}: {posture.score} of 100.
+						</div>
+						<For each={posture.records}>
+							{(record) => (
+								<div>
+									{record.name} ({record.kind}): {[...record.errors, ...record.warnings].join("; ") || "ok"}
+								</div>
+							)}
+						</For>
+						<For each={posture.recommendations}>{(recommendation) => <div>{recommendation}</div>}</For>
+					</div>
+				</div>,
+			);
+			if (!posture.missing.includes("dmarc")) return;
+			const reports = prompt(`${posture.zone_name} has no DMARC record. Address to send DMARC reports to:`);
+			if (!reports) return;
+			const generated = await invoke<GeneratedRecord>("generate_email_record", {
+				zoneId,
+				options: {
+					kind: "dmarc",
+					policy: "none",
+					subdomain_policy: null,
+					percent: null,
+					aggregate_reports: [reports],
+					failure_reports: [],
+					strict_dkim: false,
+					strict_spf: false,
+				},
+			});
+			await invoke("create_dns_record", { zoneId, record: generated.record });
+			putToast(<div class="alert alert-success">Created the DMARC record for {posture.zone_name}.</div>);
+		} catch (error) {
+			putToast(<div class="alert alert-error">Email check failed: {(error as AppError).message}</div>);
+		}
+	};
+
+	const auditZones = async () => {
+		try {
+			const audits = await invoke<ZoneAudit[]>("audit_zones");
+			const flagged = audits.filter((audit) => audit.findings.some((f) => f.severity !== "info"));
+			putToast(
+				<div class={flagged.length > 0 ? "alert alert-warning" : "alert alert-success"}>
+					<div class="flex flex-col gap-1">
+						<div>
+							Checked {audits.reduce((sum, audit) => sum + audit.records_checked, 0)} records in {audits.length}{" "}
+							zones; {flagged.length > 0 ? `${flagged.length} have records to look at.` : "none point at missing names."}
+						</div>
+						<For each={audits.flatMap((audit) => audit.findings.map((finding) => ({ zone: audit.zone_name, finding })))}>
+							{({ zone, finding }) => (
+								<div>
+									[{finding.severity}] {zone}: {finding.record_type} {finding.name} {"->"} {finding.target} - {finding.problem}
+								</div>
+							)}
+						</For>
+					</div>
+				</div>,
+			);
+		} catch (error) {
+			putToast(<div class="alert alert-error">Audit failed: {(error as AppError).message}</div>);
+		}
+	};
+
 	createEffect(() => {
 		if (apiToken().length > 0 && userDetails.latest.email.length > 0) {
 			putToast(
@@ -35,6 +411,19 @@ function App() {
 
 			<div class="container mx-auto">
 				<div>
+					<Show when={progress() && (progress()?.loaded ?? 0) + (progress()?.failed ?? 0) < (progress()?.total ?? 0)}>
+						<progress
+							class="progress progress-info w-96"
+							value={(progress()?.loaded ?? 0) + (progress()?.failed ?? 0)}
+							max={progress()?.total}
+						/>
+					</Show>
+					<button type="button" class="btn btn-sm btn-outline my-2" onClick={applyDesiredState}>
+						Plan from file
+					</button>
+					<button type="button" class="btn btn-sm btn-outline my-2 ml-2" onClick={auditZones}>
+						Audit records
+					</button>
 					<div class="flex flex-col gap-3 my-2 w-96">
 						<For each={zones.latest}>
 							{(zone) => (
@@ -43,6 +432,30 @@ function App() {
 									<Show when={zoneDns.latest[zone.id]} fallback={<div class="text-rose-700">Cache miss?</div>}>
 										<div class="text-cyan-400">{zoneDns.latest[zone.id].length} DNS records</div>
 									</Show>
+									<button type="button" class="btn btn-xs btn-ghost" onClick={() => exportZone(zone.id, zone.name)}>
+										Export
+									</button>
+									<button type="button" class="btn btn-xs btn-ghost" onClick={() => importZone(zone.id, zone.name)}>
+										Import
+									</button>
+									<button type="button" class="btn btn-xs btn-ghost" onClick={() => showHistory(zone.id, zone.name)}>
+										History
+									</button>
+									<button type="button" class="btn btn-xs btn-ghost" onClick={() => manageSnapshots(zone.id, zone.name)}>
+										Snapshots
+									</button>
+									<button type="button" class="btn btn-xs btn-ghost" onClick={() => manageDdns(zone.id, zone.name)}>
+										DDNS
+									</button>
+									<button type="button" class="btn btn-xs btn-ghost" onClick={() => checkPropagation(zone.id, zone.name)}>
+										Propagation
+									</button>
+									<button type="button" class="btn btn-xs btn-ghost" onClick={() => analyzeSpf(zone.id, zone.name)}>
+										SPF
+									</button>
+									<button type="button" class="btn btn-xs btn-ghost" onClick={() => checkEmailAuth(zone.id)}>
+										Email
+									</button>
 								</div>
 							)}
 						</For>
@@ -1,15 +1,28 @@
-import { createSignal } from "solid-js";
+import { For, createResource, createSignal } from "solid-js";
 import Lib from "./lib";
 import { invoke } from "@tauri-apps/api/core";
+import type { Credential } from "../src-tauri/bindings/Credential";
+import type { ProfileSummary } from "../src-tauri/bindings/ProfileSummary";
+
+function promptCredential(): Credential | undefined {
+	const key = prompt("Enter your Cloudflare API token (or global API key):");
+	if (!key) return;
+	const email = prompt("Email address for a global API key (leave empty for an API token):");
+	return email ? { type: "global_api_key", email, key } : { type: "bearer_token", token: key };
+}
 
 function Navbar() {
 	const [statusButtonText, setStatusButtonText] = createSignal(statusButtonTextGen());
+	const [profiles, { refetch: refetchProfiles }] = createResource(
+		async () => (await invoke("list_profiles")) as ProfileSummary[],
+		{ initialValue: [] },
+	);
 	function successIconGen() {
 		if (localStorage.getItem(Lib.API_READY)) return "✅";
 		return "🙅";
 	}
 	function statusButtonTextGen() {
-		return `API: ${localStorage.getItem(Lib.CLOUDFLARE_API_KEY) ? successIconGen() : "❌"}`;
+		return `API: ${localStorage.getItem(Lib.CREDENTIALS_SAVED) ? successIconGen() : "❌"}`;
 	}
 
 	// Update the token signal when the localStorage changes
@@ -27,15 +40,17 @@ function Navbar() {
 					type="button"
 					class="btn btn-ghost text-xl"
 					onClick={async () => {
-						if (localStorage.getItem(Lib.CLOUDFLARE_API_KEY)) {
-							localStorage.removeItem(Lib.CLOUDFLARE_API_KEY);
+						if (localStorage.getItem(Lib.CREDENTIALS_SAVED)) {
+							await invoke("forget_credentials");
+							localStorage.removeItem(Lib.CREDENTIALS_SAVED);
 							localStorage.removeItem(Lib.API_READY);
 						} else {
-							const key = prompt("Enter your Cloudflare API key:");
-							if (!key) return;
-							localStorage.setItem(Lib.CLOUDFLARE_API_KEY, key);
+							const credential = promptCredential();
+							if (!credential) return;
 							console.log("Setting API token");
-							await invoke("set_api_token", { token: key });
+							await invoke("set_api_token", { credential });
+							await invoke("save_credentials", { credential });
+							localStorage.setItem(Lib.CREDENTIALS_SAVED, "true");
 							console.log("Initializing CF cache");
 							localStorage.setItem(Lib.API_READY, await invoke("initialize_cf", {}));
 						}
@@ -48,8 +63,8 @@ function Navbar() {
 				</button>
 			</div>
 			<div class="navbar-end">
-				<div class="flex-none">
-					<button type="button" class="btn btn-square btn-ghost">
+				<div class="flex-none dropdown dropdown-end">
+					<button type="button" class="btn btn-square btn-ghost" onClick={() => refetchProfiles()}>
 						<svg
 							xmlns="http://www.w3.org/2000/svg"
 							fill="none"
@@ -65,6 +80,55 @@ function Navbar() {
 							/>
 						</svg>
 					</button>
+					<ul class="dropdown-content menu bg-base-100 rounded-box z-[1] w-64 p-2 shadow">
+						<For each={profiles()}>
+							{(profile) => (
+								<li>
+									<button
+										type="button"
+										classList={{ active: profile.active }}
+										onClick={async () => {
+											const cached = await invoke("switch_profile", { name: profile.name });
+											if (!cached) localStorage.setItem(Lib.API_READY, await invoke("initialize_cf", {}));
+											localStorage.setItem(Lib.CREDENTIALS_SAVED, "true");
+											window.location.reload();
+										}}
+									>
+										{profile.name}
+										<span class="text-xs opacity-60">{profile.email ?? ""}</span>
+									</button>
+								</li>
+							)}
+						</For>
+						<li>
+							<button
+								type="button"
+								onClick={async () => {
+									const name = prompt("Profile name:");
+									if (!name) return;
+									const credential = promptCredential();
+									if (!credential) return;
+									await invoke("add_profile", { name, credential });
+									refetchProfiles();
+								}}
+							>
+								Add profile…
+							</button>
+						</li>
+						<li>
+							<button
+								type="button"
+								onClick={async () => {
+									const name = prompt("Name of the profile to remove:");
+									if (!name) return;
+									await invoke("remove_profile", { name });
+									refetchProfiles();
+								}}
+							>
+								Remove profile…
+							</button>
+						</li>
+					</ul>
 				</div>
 			</div>
 		</div>
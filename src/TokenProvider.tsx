@@ -1,12 +1,13 @@
 import { createContextProvider } from "@solid-primitives/context";
 import { createResource, createSignal } from "solid-js";
-import { CLOUDFLARE_API_KEY } from "./lib";
+import { API_READY, CREDENTIALS_SAVED } from "./lib";
+import { listen } from "@tauri-apps/api/event";
 import type { CustomUserDetails } from "../src-tauri/bindings/CustomUserDetails";
 import { invoke } from "@tauri-apps/api/core";
 
 const [TokenProvider, useTokenProvider] = createContextProvider(() => {
 	const [apiToken, setApiToken] = createSignal<string>("");
-	const [userDetails] = createResource<CustomUserDetails, string>(
+	const [userDetails, { refetch: refetchUserDetails }] = createResource<CustomUserDetails, string>(
 		apiToken(),
 		async () => {
 			return (await invoke("get_user_details", {})) as CustomUserDetails;
@@ -22,12 +23,18 @@ const [TokenProvider, useTokenProvider] = createContextProvider(() => {
 	);
 
 	setTimeout(() => {
-		setApiToken(localStorage.getItem(CLOUDFLARE_API_KEY) || "");
+		setApiToken(localStorage.getItem(CREDENTIALS_SAVED) || "");
 	}, 10);
 
 	// Update the token signal when the localStorage changes
 	window.addEventListener("storage", () => {
-		setApiToken(localStorage.getItem(CLOUDFLARE_API_KEY) || "");
+		setApiToken(localStorage.getItem(CREDENTIALS_SAVED) || "");
+	});
+
+	// The backend restores the saved credentials at startup and refreshes the cache in the background
+	listen("cache-refreshed", () => {
+		localStorage.setItem(API_READY, "true");
+		refetchUserDetails();
 	});
 
 	return { apiToken, setApiToken, userDetails };
@@ -2,7 +2,10 @@ import { createContextProvider } from "@solid-primitives/context";
 import type { CloudflareZoneDnsResponse } from "./types";
 import type { CloudflareListZonesResponse } from "../src-tauri/bindings/CloudflareListZonesResponse";
 import { invoke } from "@tauri-apps/api/core";
-import { createResource } from "solid-js";
+import { listen } from "@tauri-apps/api/event";
+import { createResource, createSignal } from "solid-js";
+import type { InitProgress } from "../src-tauri/bindings/InitProgress";
+import type { ZoneDnsLoaded } from "../src-tauri/bindings/ZoneDnsLoaded";
 import { useTokenProvider } from "./TokenProvider";
 
 const [ZoneProvider, useZone] = createContextProvider(() => {
@@ -14,7 +17,7 @@ const [ZoneProvider, useZone] = createContextProvider(() => {
 		},
 		{ initialValue: [] },
 	);
-	const [zoneDns, { refetch: refetchZoneDns }] = createResource<CloudflareZoneDnsResponse, string>(
+	const [zoneDns, { refetch: refetchZoneDns, mutate: mutateZoneDns }] = createResource<CloudflareZoneDnsResponse, string>(
 		apiToken(),
 		async () => {
 			return (await invoke("get_zone_dns", {})) as CloudflareZoneDnsResponse;
@@ -22,7 +25,31 @@ const [ZoneProvider, useZone] = createContextProvider(() => {
 		{ initialValue: {} },
 	);
 
-	return { zones, zoneDns, refetchZones, refetchZoneDns };
+	// Show the refreshed data once the background refresh started at launch finishes
+	listen("cache-refreshed", () => {
+		refetchZones();
+		refetchZoneDns();
+	});
+
+	// The background drift check has already updated the cache with changes made outside the app
+	listen("zone-drift", () => {
+		refetchZones();
+		refetchZoneDns();
+	});
+
+	// Render each zone's records as soon as they load, rather than waiting for every zone
+	const [progress, setProgress] = createSignal<InitProgress>();
+	listen<InitProgress>("init-progress", (event) => setProgress(event.payload));
+	listen<ZoneDnsLoaded>("zone-dns-loaded", (event) => {
+		const { zone_id, records, error } = event.payload;
+		if (error) {
+			console.error(`Failed to load DNS records for ${zone_id}:`, error.message);
+			return;
+		}
+		mutateZoneDns((previous) => ({ ...previous, [zone_id]: records }));
+	});
+
+	return { zones, zoneDns, refetchZones, refetchZoneDns, progress };
 });
 
 export { ZoneProvider, useZone };
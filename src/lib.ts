@@ -1,5 +1,5 @@
-// Const for the CF API key
-export const CLOUDFLARE_API_KEY = "CF-APIKEY";
+// Const for the flag set when credentials are saved (the credentials themselves live in the OS keyring)
+export const CREDENTIALS_SAVED = "CF-CREDENTIALS-SAVED";
 
 // Const for the UserDetails
 export const USER_DETAILS = "USER_DETAILS";
@@ -8,7 +8,7 @@ export const USER_DETAILS = "USER_DETAILS";
 export const API_READY = "API_READY";
 
 export default {
-	CLOUDFLARE_API_KEY,
+	CREDENTIALS_SAVED,
 	USER_DETAILS,
 	API_READY,
 };